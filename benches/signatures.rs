@@ -45,7 +45,7 @@ fn sign_message(c: &mut Criterion) {
         b.iter_batched(
             gen_keypair,
             |d| {
-                let _ = RistrettoSchnorr::sign(d.k, d.r, &d.m.to_vec()).unwrap();
+                let _ = RistrettoSchnorr::sign_raw(d.k, d.r, &d.m.to_vec()).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -59,7 +59,7 @@ fn verify_message(c: &mut Criterion) {
         b.iter_batched(
             || {
                 let d = gen_keypair();
-                let s = RistrettoSchnorr::sign(d.k.clone(), d.r.clone(), &d.m.to_vec()).unwrap();
+                let s = RistrettoSchnorr::sign_raw(d.k.clone(), d.r.clone(), &d.m.to_vec()).unwrap();
                 (d, s)
             },
             |(d, s)| assert!(s.verify(&d.p, &d.m)),
@@ -68,8 +68,31 @@ fn verify_message(c: &mut Criterion) {
     });
 }
 
+fn batch_verify_message(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let mut signatures = Vec::with_capacity(1000);
+    let mut public_keys = Vec::with_capacity(1000);
+    let mut challenges = Vec::with_capacity(1000);
+    for _ in 0..1000 {
+        let d = gen_keypair();
+        signatures.push(RistrettoSchnorr::sign_raw(d.k, d.r, &d.m.to_vec()).unwrap());
+        public_keys.push(d.p);
+        challenges.push(d.m);
+    }
+    c.bench_function("Batch verify 1000 RistrettoSchnorr", move |b| {
+        b.iter(|| {
+            assert!(RistrettoSchnorr::batch_verify(
+                &signatures,
+                &public_keys,
+                &challenges,
+                &mut rng
+            ))
+        });
+    });
+}
+
 criterion_group!(
 name = signatures;
 config = Criterion::default().warm_up_time(Duration::from_millis(500));
-targets = generate_secret_key, native_keypair, sign_message, verify_message
+targets = generate_secret_key, native_keypair, sign_message, verify_message, batch_verify_message
 );
@@ -25,7 +25,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     hash::{Hash, Hasher},
-    ops::{Add, Mul, Sub},
+    ops::{Add, Mul, Neg, Sub},
 };
 
 use tari_utilities::{ByteArray, ByteArrayError};
@@ -59,6 +59,36 @@ where P: PublicKey
     pub fn from_public_key(p: &P) -> HomomorphicCommitment<P> {
         HomomorphicCommitment(p.clone())
     }
+
+    /// Returns true if this is the identity, i.e. a commitment to zero with a zero blinding factor
+    fn is_identity(&self) -> bool {
+        self.0 == P::default()
+    }
+
+    /// Add two commitments, returning `None` if the result is the identity. Commitments that cancel out exactly are
+    /// almost always the result of a bug (or a malicious input).
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self>
+    where for<'a> &'a P: Add<&'a P, Output = P> {
+        Some(self + rhs).filter(|c| !c.is_identity())
+    }
+
+    /// Subtract `rhs` from this commitment, returning `None` if the result is the identity (i.e. the commitments are
+    /// equal)
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self>
+    where for<'a> &'a P: Sub<&'a P, Output = P> {
+        Some(self - rhs).filter(|c| !c.is_identity())
+    }
+
+    /// Multiply the commitment by a scalar, returning `None` if the result is the identity (i.e. the scalar is zero,
+    /// or the commitment is already the identity)
+    pub fn checked_mul<K>(&self, rhs: &K) -> Option<Self>
+    where
+        P: PublicKey<K = K>,
+        K: SecretKey,
+        for<'a, 'b> &'b K: Mul<&'a P, Output = P>,
+    {
+        Some(self * rhs).filter(|c| !c.is_identity())
+    }
 }
 
 impl<P> ByteArray for HomomorphicCommitment<P>
@@ -129,6 +159,19 @@ where
     }
 }
 
+/// Subtract a public key from a commitment. Note! There is no check that the bases are equal.
+impl<'b, P> Sub<&'b P> for &'b HomomorphicCommitment<P>
+where
+    P: PublicKey,
+    &'b P: Sub<&'b P, Output = P>,
+{
+    type Output = HomomorphicCommitment<P>;
+
+    fn sub(self, rhs: &'b P) -> Self::Output {
+        HomomorphicCommitment(&self.0 - rhs)
+    }
+}
+
 /// Multiply the commitment with a private key
 impl<'a, 'b, P, K> Mul<&'b K> for &'a HomomorphicCommitment<P>
 where
@@ -144,6 +187,29 @@ where
     }
 }
 
+/// Negate the commitment, so that _C + (-C)_ is the identity
+impl<'a, P> Neg for &'a HomomorphicCommitment<P>
+where
+    P: PublicKey,
+    &'a P: Neg<Output = P>,
+{
+    type Output = HomomorphicCommitment<P>;
+
+    fn neg(self) -> Self::Output {
+        HomomorphicCommitment(-&self.0)
+    }
+}
+
+impl<P> Neg for HomomorphicCommitment<P>
+where P: PublicKey + Neg<Output = P>
+{
+    type Output = HomomorphicCommitment<P>;
+
+    fn neg(self) -> Self::Output {
+        HomomorphicCommitment(-self.0)
+    }
+}
+
 impl<P: PublicKey> Hash for HomomorphicCommitment<P> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write(self.as_bytes())
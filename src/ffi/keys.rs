@@ -80,7 +80,7 @@ pub unsafe extern "C" fn sign(
         _ => return STR_CONV_ERR,
     };
     let challenge = Blake256::digest(msg.as_bytes()).to_vec();
-    let sig = match RistrettoSchnorr::sign(k, r, &challenge) {
+    let sig = match RistrettoSchnorr::sign_raw(k, r, &challenge) {
         Ok(sig) => sig,
         _ => return SIGNING_ERROR,
     };
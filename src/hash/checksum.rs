@@ -0,0 +1,128 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Short checksums for human-facing encodings such as addresses.
+//!
+//! A checksum only has to catch typos and transcription errors, so a few bytes are enough, but every encoding should
+//! use its own domain so that a string that is valid in one encoding isn't accidentally valid in another. The
+//! checksum length is hashed too, so a 4-byte checksum is not a prefix of an 8-byte one.
+//!
+//! ```edition2018
+//! # use tari_crypto::hash::checksum::DomainSeparatedChecksum;
+//! let checksum = DomainSeparatedChecksum::<4>::new(b"com.example.address");
+//! let encoded = checksum.append(b"payload");
+//! assert_eq!(encoded.len(), 11);
+//! assert_eq!(checksum.strip(&encoded), Some(&b"payload"[..]));
+//! ```
+
+use crate::hash::xof::Shake256Xof;
+use subtle::ConstantTimeEq;
+
+/// Label that separates checksums from every other hash in the crate
+const CHECKSUM_LABEL: &[u8] = b"com.tari.checksum";
+
+/// An `N`-byte checksum in a given domain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainSeparatedChecksum<const N: usize> {
+    domain: Vec<u8>,
+}
+
+impl<const N: usize> DomainSeparatedChecksum<N> {
+    pub fn new(domain: &[u8]) -> Self {
+        DomainSeparatedChecksum {
+            domain: domain.to_vec(),
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> [u8; N] {
+        Shake256Xof::new(CHECKSUM_LABEL)
+            .chain((self.domain.len() as u64).to_le_bytes())
+            .chain(&self.domain)
+            .chain((N as u64).to_le_bytes())
+            .chain(data)
+            .finalize_fixed()
+    }
+
+    pub fn verify(&self, data: &[u8], checksum: &[u8]) -> bool {
+        self.compute(data)[..].ct_eq(checksum).into()
+    }
+
+    /// `data` followed by its checksum
+    pub fn append(&self, data: &[u8]) -> Vec<u8> {
+        [data, &self.compute(data)].concat()
+    }
+
+    /// The data in `encoded` if its trailing checksum is valid
+    pub fn strip<'a>(&self, encoded: &'a [u8]) -> Option<&'a [u8]> {
+        if encoded.len() < N {
+            return None;
+        }
+        let (data, checksum) = encoded.split_at(encoded.len() - N);
+        if self.verify(data, checksum) {
+            Some(data)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DOMAIN: &[u8] = b"com.tari.test.checksum";
+
+    #[test]
+    fn round_trip() {
+        let checksum = DomainSeparatedChecksum::<4>::new(DOMAIN);
+        for data in &[&b""[..], b"a", b"Librarian"] {
+            let encoded = checksum.append(data);
+            assert_eq!(checksum.strip(&encoded), Some(*data));
+            assert!(checksum.verify(data, &encoded[data.len()..]));
+        }
+        assert_eq!(checksum.strip(b"abc"), None);
+    }
+
+    #[test]
+    fn detects_errors() {
+        let checksum = DomainSeparatedChecksum::<4>::new(DOMAIN);
+        let encoded = checksum.append(b"Ook");
+        for i in 0..encoded.len() {
+            let mut corrupted = encoded.clone();
+            corrupted[i] ^= 0x20;
+            assert_eq!(checksum.strip(&corrupted), None);
+        }
+        // Truncated checksums don't verify
+        assert!(!checksum.verify(b"Ook", &encoded[3..6]));
+    }
+
+    #[test]
+    fn separation() {
+        let c4 = DomainSeparatedChecksum::<4>::new(DOMAIN).compute(b"Ook");
+        let c8 = DomainSeparatedChecksum::<8>::new(DOMAIN).compute(b"Ook");
+        assert_ne!(c4[..], c8[..4]);
+        assert_ne!(
+            DomainSeparatedChecksum::<4>::new(b"com.tari.test.other").compute(b"Ook"),
+            c4
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A forward-only hash chain for deriving a sequence of keys, such as per-message keys in a messaging session.
+//!
+//! Each step derives a key from the current state, then replaces the state with its hash and clears the old one.
+//! Someone who later learns the current state can't work backwards to earlier states or keys, so compromising a
+//! session doesn't expose messages that were already processed.
+//!
+//! ```edition2018
+//! # use tari_crypto::hash::hash_chain::{HashChain, MAX_SKIP};
+//! # use tari_crypto::common::Blake256;
+//! let mut sender = HashChain::<Blake256>::new(b"com.example.messages", b"shared session secret");
+//! let mut receiver = HashChain::<Blake256>::new(b"com.example.messages", b"shared session secret");
+//! let first = sender.next_key().unwrap();
+//! let second = sender.next_key().unwrap();
+//! // The receiver missed the first message
+//! receiver.skip_to(1).unwrap();
+//! assert_eq!(receiver.next_key().unwrap().as_bytes(), second.as_bytes());
+//! assert_eq!((first.index(), second.index()), (0, 1));
+//! assert!(receiver.skip_to(0).is_err());
+//! // A peer can't make the receiver do an unbounded amount of work
+//! assert!(receiver.skip_to(2 + MAX_SKIP + 1).is_err());
+//! ```
+
+use clear_on_drop::clear::Clear;
+use digest::{generic_array::GenericArray, Digest};
+use std::fmt;
+use thiserror::Error;
+
+/// Label that separates hash chain hashes from every other hash in the crate
+const HASH_CHAIN_LABEL: &[u8] = b"com.tari.hash_chain";
+const STATE_TAG: u8 = 0x00;
+const KEY_TAG: u8 = 0x01;
+
+/// The most keys that [HashChain::skip_to] will discard in one call. A message index usually comes from the other
+/// party, so without a bound a single message could make the receiver hash indefinitely.
+pub const MAX_SKIP: u64 = 1000;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum HashChainError {
+    #[error("The chain is at index {current} and can't move back to {requested}")]
+    CannotRewind { current: u64, requested: u64 },
+    #[error(
+        "Skipping from index {current} to {requested} would discard more than {} keys",
+        MAX_SKIP
+    )]
+    TooManySkipped { current: u64, requested: u64 },
+    #[error("The chain has derived every key it can index")]
+    Exhausted,
+}
+
+pub struct HashChain<D: Digest> {
+    /// A hasher that has absorbed the label and domain
+    prefix: D,
+    state: GenericArray<u8, D::OutputSize>,
+    index: u64,
+}
+
+impl<D: Digest + Clone> HashChain<D> {
+    /// Start a chain in `domain` from a secret `seed`, e.g. a Diffie-Hellman shared secret
+    pub fn new(domain: &[u8], seed: &[u8]) -> Self {
+        let prefix = D::new()
+            .chain(HASH_CHAIN_LABEL)
+            .chain((domain.len() as u64).to_le_bytes())
+            .chain(domain);
+        let state = prefix.clone().chain([STATE_TAG]).chain(seed).finalize();
+        HashChain {
+            prefix,
+            state,
+            index: 0,
+        }
+    }
+
+    /// The index of the key that [next_key](HashChain::next_key) will return
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Derive the key for the current index and ratchet forward
+    pub fn next_key(&mut self) -> Result<ChainKey, HashChainError> {
+        let mut key = self.prefix.clone().chain([KEY_TAG]).chain(&self.state).finalize();
+        let chain_key = ChainKey {
+            index: self.index,
+            key: key.to_vec(),
+        };
+        key.as_mut_slice().clear();
+        self.advance()?;
+        Ok(chain_key)
+    }
+
+    /// Ratchet forward to `index`, discarding the keys in between. At most [MAX_SKIP] keys can be skipped at once.
+    pub fn skip_to(&mut self, index: u64) -> Result<(), HashChainError> {
+        if index < self.index {
+            return Err(HashChainError::CannotRewind {
+                current: self.index,
+                requested: index,
+            });
+        }
+        if index - self.index > MAX_SKIP {
+            return Err(HashChainError::TooManySkipped {
+                current: self.index,
+                requested: index,
+            });
+        }
+        while self.index < index {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) -> Result<(), HashChainError> {
+        let index = self.index.checked_add(1).ok_or(HashChainError::Exhausted)?;
+        let next = self.prefix.clone().chain([STATE_TAG]).chain(&self.state).finalize();
+        self.state.as_mut_slice().clear();
+        self.state = next;
+        self.index = index;
+        Ok(())
+    }
+}
+
+impl<D: Digest> Drop for HashChain<D> {
+    fn drop(&mut self) {
+        self.state.as_mut_slice().clear();
+    }
+}
+
+impl<D: Digest> fmt::Debug for HashChain<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashChain").field("index", &self.index).finish()
+    }
+}
+
+/// A key derived from a [HashChain], cleared when dropped
+pub struct ChainKey {
+    index: u64,
+    key: Vec<u8>,
+}
+
+impl ChainKey {
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Drop for ChainKey {
+    fn drop(&mut self) {
+        self.key.as_mut_slice().clear();
+    }
+}
+
+impl fmt::Debug for ChainKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainKey").field("index", &self.index).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::blake2::Blake256;
+    use sha2::Sha512;
+
+    #[test]
+    fn keys_are_distinct() {
+        let mut chain = HashChain::<Sha512>::new(b"com.tari.test.chain", b"Wee Free Men");
+        let keys: Vec<ChainKey> = (0..10).map(|_| chain.next_key().unwrap()).collect();
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(k.index(), i as u64);
+            assert_eq!(k.as_bytes().len(), 64);
+            assert!(keys[i + 1..].iter().all(|other| other.as_bytes() != k.as_bytes()));
+        }
+        assert_eq!(chain.index(), 10);
+    }
+
+    #[test]
+    fn domain_and_seed_separation() {
+        let key = |domain: &[u8], seed: &[u8]| {
+            HashChain::<Blake256>::new(domain, seed)
+                .next_key()
+                .unwrap()
+                .as_bytes()
+                .to_vec()
+        };
+        let reference = key(b"com.tari.test.chain", b"Tiffany");
+        assert_eq!(key(b"com.tari.test.chain", b"Tiffany"), reference);
+        assert_ne!(key(b"com.tari.test.other", b"Tiffany"), reference);
+        assert_ne!(key(b"com.tari.test.chain", b"Aching"), reference);
+    }
+
+    #[test]
+    fn skipping() {
+        let mut a = HashChain::<Blake256>::new(b"com.tari.test.chain", b"Rob Anybody");
+        let mut b = HashChain::<Blake256>::new(b"com.tari.test.chain", b"Rob Anybody");
+        for _ in 0..5 {
+            a.next_key().unwrap();
+        }
+        b.skip_to(5).unwrap();
+        assert_eq!(a.next_key().unwrap().as_bytes(), b.next_key().unwrap().as_bytes());
+        // Skipping to the current index is a no-op
+        b.skip_to(6).unwrap();
+        assert_eq!(b.index(), 6);
+        assert_eq!(
+            b.skip_to(2),
+            Err(HashChainError::CannotRewind {
+                current: 6,
+                requested: 2
+            })
+        );
+    }
+
+    #[test]
+    fn bounded_skipping() {
+        let mut chain = HashChain::<Blake256>::new(b"com.tari.test.chain", b"Rob Anybody");
+        chain.skip_to(MAX_SKIP).unwrap();
+        assert_eq!(chain.index(), MAX_SKIP);
+        assert_eq!(
+            chain.skip_to(2 * MAX_SKIP + 1),
+            Err(HashChainError::TooManySkipped {
+                current: MAX_SKIP,
+                requested: 2 * MAX_SKIP + 1
+            })
+        );
+        // A failed skip leaves the chain where it was
+        assert_eq!(chain.index(), MAX_SKIP);
+        assert_eq!(chain.skip_to(u64::MAX).unwrap_err(), HashChainError::TooManySkipped {
+            current: MAX_SKIP,
+            requested: u64::MAX
+        });
+    }
+
+    #[test]
+    fn exhausted_chain() {
+        let mut chain = HashChain::<Blake256>::new(b"com.tari.test.chain", b"Mad Jock");
+        chain.index = u64::MAX;
+        assert_eq!(chain.next_key().unwrap_err(), HashChainError::Exhausted);
+        assert_eq!(chain.index(), u64::MAX);
+    }
+}
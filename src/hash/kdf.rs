@@ -0,0 +1,291 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! HKDF ([RFC 5869](https://tools.ietf.org/html/rfc5869)) with domain separation.
+//!
+//! Raw Diffie-Hellman outputs and other input key material are not uniformly random and should not be used as keys
+//! directly. [DomainSeparatedKdf] extracts a pseudorandom key from them and expands it into as many keys as are
+//! needed. The domain label is mixed into every expansion's `info` parameter, so two protocols that happen to share
+//! input key material still derive unrelated keys.
+//!
+//! ```edition2018
+//! # use tari_crypto::hash::kdf::DomainSeparatedKdf;
+//! # use tari_crypto::ristretto::{RistrettoPublicKey, RistrettoSecretKey};
+//! # use tari_crypto::keys::{DiffieHellmanSharedSecret, PublicKey};
+//! # use tari_utilities::ByteArray;
+//! # use sha2::Sha512;
+//! let mut rng = rand::thread_rng();
+//! let (k_a, p_a) = RistrettoPublicKey::random_keypair(&mut rng);
+//! let (k_b, p_b) = RistrettoPublicKey::random_keypair(&mut rng);
+//! let secret_a = RistrettoPublicKey::shared_secret(&k_a, &p_b);
+//! let secret_b = RistrettoPublicKey::shared_secret(&k_b, &p_a);
+//! let kdf_a = DomainSeparatedKdf::<Sha512>::extract(b"com.example.session", &[], secret_a.as_bytes());
+//! let kdf_b = DomainSeparatedKdf::<Sha512>::extract(b"com.example.session", &[], secret_b.as_bytes());
+//! let key_a: RistrettoSecretKey = kdf_a.derive_key(b"encryption").unwrap();
+//! let key_b: RistrettoSecretKey = kdf_b.derive_key(b"encryption").unwrap();
+//! assert_eq!(key_a, key_b);
+//! ```
+
+use crate::keys::SecretKey;
+use clear_on_drop::clear::Clear;
+use digest::{
+    generic_array::{typenum::Unsigned, GenericArray},
+    BlockInput,
+    Digest,
+};
+use subtle::ConstantTimeEq;
+use tari_utilities::ByteArrayError;
+use thiserror::Error;
+
+/// Every expansion's `info` starts with one of these tags, right after the domain, so plain expansions, confirmed keys
+/// and confirmation tags can never share an `info` input
+const EXPAND_TAG: u8 = 0x00;
+const CONFIRMED_KEY_TAG: u8 = 0x01;
+const CONFIRMATION_TAG: u8 = 0x02;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum KdfError {
+    #[error("Requested output is longer than HKDF can produce with this digest")]
+    OutputTooLong,
+    #[error("Could not construct key from derived bytes: {0}")]
+    InvalidKey(#[from] ByteArrayError),
+    #[error("The key confirmation tag did not match")]
+    ConfirmationFailed,
+}
+
+/// An HKDF instance holding the pseudorandom key produced by the extract step. The key is cleared when this is
+/// dropped.
+pub struct DomainSeparatedKdf<D: Digest + BlockInput> {
+    prk: GenericArray<u8, D::OutputSize>,
+    domain: Vec<u8>,
+}
+
+impl<D: Digest + BlockInput> DomainSeparatedKdf<D> {
+    /// The HKDF extract step. `salt` may be empty, in which case a block of zeroes is used as RFC 5869 specifies.
+    pub fn extract(domain: &[u8], salt: &[u8], ikm: &[u8]) -> Self {
+        DomainSeparatedKdf {
+            prk: hmac::<D>(salt, &[ikm]),
+            domain: domain.to_vec(),
+        }
+    }
+
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    /// The HKDF expand step, filling `out` with key material bound to this KDF's domain and to `info`.
+    pub fn expand(&self, info: &[u8], out: &mut [u8]) -> Result<(), KdfError> {
+        self.expand_tagged(EXPAND_TAG, info, out)
+    }
+
+    /// Expand directly into a secret key. The key is made from [SecretKey::uniform_length] bytes of output with
+    /// [SecretKey::from_uniform_bytes], so for Ristretto 64 bytes are reduced mod _l_ and the key is unbiased.
+    pub fn derive_key<K: SecretKey>(&self, info: &[u8]) -> Result<K, KdfError> {
+        self.derive_tagged_key(EXPAND_TAG, info)
+    }
+
+    /// Derive a key together with a tag that confirms it. The tag reveals nothing about the key, so the deriving
+    /// side can send it to let the other side check, with [derive_and_verify](DomainSeparatedKdf::derive_and_verify),
+    /// that both hold the same input key material and agree on `info`.
+    pub fn derive_confirmed_key<K: SecretKey>(&self, info: &[u8]) -> Result<(K, Vec<u8>), KdfError> {
+        let key = self.derive_tagged_key(CONFIRMED_KEY_TAG, info)?;
+        Ok((key, self.confirmation_tag(info)?))
+    }
+
+    /// Derive the key that [derive_confirmed_key](DomainSeparatedKdf::derive_confirmed_key) would, but only if
+    /// `tag` confirms it. The tag is compared in constant time.
+    pub fn derive_and_verify<K: SecretKey>(&self, info: &[u8], tag: &[u8]) -> Result<K, KdfError> {
+        let expected = self.confirmation_tag(info)?;
+        if !bool::from(expected.ct_eq(tag)) {
+            return Err(KdfError::ConfirmationFailed);
+        }
+        self.derive_tagged_key(CONFIRMED_KEY_TAG, info)
+    }
+
+    fn confirmation_tag(&self, info: &[u8]) -> Result<Vec<u8>, KdfError> {
+        let mut tag = vec![0u8; D::OutputSize::to_usize()];
+        self.expand_tagged(CONFIRMATION_TAG, info, &mut tag)?;
+        Ok(tag)
+    }
+
+    fn derive_tagged_key<K: SecretKey>(&self, tag: u8, info: &[u8]) -> Result<K, KdfError> {
+        let mut bytes = vec![0u8; K::uniform_length()];
+        let key = self
+            .expand_tagged(tag, info, &mut bytes)
+            .and_then(|_| Ok(K::from_uniform_bytes(&bytes)?));
+        bytes.as_mut_slice().clear();
+        key
+    }
+
+    fn expand_tagged(&self, tag: u8, info: &[u8], out: &mut [u8]) -> Result<(), KdfError> {
+        let domain_len = (self.domain.len() as u64).to_le_bytes();
+        expand::<D>(&self.prk, &[&domain_len, &self.domain, &[tag], info], out)
+    }
+}
+
+impl<D: Digest + BlockInput> Drop for DomainSeparatedKdf<D> {
+    fn drop(&mut self) {
+        self.prk.as_mut_slice().clear();
+    }
+}
+
+/// HMAC over the concatenation of `parts`
+fn hmac<D: Digest + BlockInput>(key: &[u8], parts: &[&[u8]]) -> GenericArray<u8, D::OutputSize> {
+    let block_size = D::BlockSize::to_usize();
+    let mut pad = vec![0u8; block_size];
+    if key.len() > block_size {
+        let mut hashed = D::digest(key);
+        pad[..hashed.len()].copy_from_slice(&hashed);
+        hashed.as_mut_slice().clear();
+    } else {
+        pad[..key.len()].copy_from_slice(key);
+    }
+    pad.iter_mut().for_each(|b| *b ^= 0x36);
+    let mut inner = D::new().chain(&pad);
+    for part in parts {
+        inner.update(part);
+    }
+    let mut inner = inner.finalize();
+    // Switch the inner pad to the outer one
+    pad.iter_mut().for_each(|b| *b ^= 0x36 ^ 0x5c);
+    let result = D::new().chain(&pad).chain(&inner).finalize();
+    pad.as_mut_slice().clear();
+    inner.as_mut_slice().clear();
+    result
+}
+
+fn expand<D: Digest + BlockInput>(prk: &[u8], info: &[&[u8]], out: &mut [u8]) -> Result<(), KdfError> {
+    let hash_len = D::OutputSize::to_usize();
+    if out.len() > 255 * hash_len {
+        return Err(KdfError::OutputTooLong);
+    }
+    let mut t = GenericArray::<u8, D::OutputSize>::default();
+    for (i, chunk) in out.chunks_mut(hash_len).enumerate() {
+        let counter = [i as u8 + 1];
+        let previous: &[u8] = if i == 0 { &[] } else { &t };
+        let mut parts = Vec::with_capacity(info.len() + 2);
+        parts.push(previous);
+        parts.extend_from_slice(info);
+        parts.push(&counter);
+        let next = hmac::<D>(prk, &parts);
+        t.as_mut_slice().clear();
+        t = next;
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    t.as_mut_slice().clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ristretto::RistrettoSecretKey;
+    use sha2::{Sha256, Sha512};
+    use tari_utilities::{
+        hex::{from_hex, Hex},
+        ByteArray,
+    };
+
+    #[test]
+    fn rfc5869_case_1() {
+        let ikm = [0x0bu8; 22];
+        let salt = from_hex("000102030405060708090a0b0c").unwrap();
+        let info = from_hex("f0f1f2f3f4f5f6f7f8f9").unwrap();
+        let prk = hmac::<Sha256>(&salt, &[&ikm]);
+        assert_eq!(
+            prk.to_vec().to_hex(),
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+        );
+        let mut okm = [0u8; 42];
+        expand::<Sha256>(&prk, &[&info], &mut okm).unwrap();
+        assert_eq!(
+            okm.to_vec().to_hex(),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+    }
+
+    #[test]
+    fn output_length_limit() {
+        let kdf = DomainSeparatedKdf::<Sha256>::extract(b"test", b"salt", b"ikm");
+        let mut okm = vec![0u8; 255 * 32];
+        assert!(kdf.expand(b"", &mut okm).is_ok());
+        let mut okm = vec![0u8; 255 * 32 + 1];
+        assert_eq!(kdf.expand(b"", &mut okm), Err(KdfError::OutputTooLong));
+    }
+
+    #[test]
+    fn domain_separation() {
+        let a = DomainSeparatedKdf::<Sha512>::extract(b"com.example.a", b"", b"shared");
+        let b = DomainSeparatedKdf::<Sha512>::extract(b"com.example.b", b"", b"shared");
+        let k1: RistrettoSecretKey = a.derive_key(b"key").unwrap();
+        let k2: RistrettoSecretKey = a.derive_key(b"key").unwrap();
+        let k3: RistrettoSecretKey = b.derive_key(b"key").unwrap();
+        let k4: RistrettoSecretKey = a.derive_key(b"other").unwrap();
+        assert_eq!(k1, k2);
+        assert_ne!(k1, k3);
+        assert_ne!(k1, k4);
+    }
+
+    #[test]
+    fn wide_reduction() {
+        let kdf = DomainSeparatedKdf::<Sha256>::extract(b"com.example.wide", b"", b"shared");
+        let mut wide = [0u8; 64];
+        kdf.expand(b"key", &mut wide).unwrap();
+        let key: RistrettoSecretKey = kdf.derive_key(b"key").unwrap();
+        assert_eq!(key, RistrettoSecretKey::from_uniform_bytes(&wide).unwrap());
+        assert_ne!(key, RistrettoSecretKey::from_bytes(&wide[..32]).unwrap());
+    }
+
+    #[test]
+    fn key_confirmation() {
+        let alice = DomainSeparatedKdf::<Sha512>::extract(b"com.tari.test.handshake", b"", b"shared");
+        let bob = DomainSeparatedKdf::<Sha512>::extract(b"com.tari.test.handshake", b"", b"shared");
+        let mallory = DomainSeparatedKdf::<Sha512>::extract(b"com.tari.test.handshake", b"", b"guessed");
+        let (key, tag): (RistrettoSecretKey, _) = alice.derive_confirmed_key(b"transcript").unwrap();
+        assert_eq!(tag.len(), 64);
+        assert_eq!(
+            bob.derive_and_verify::<RistrettoSecretKey>(b"transcript", &tag),
+            Ok(key.clone())
+        );
+        // The confirmed key is unrelated to a plain derivation with the same info, or with the confirmed key's tag
+        assert_ne!(alice.derive_key::<RistrettoSecretKey>(b"transcript").unwrap(), key);
+        let forged_info = [&[CONFIRMED_KEY_TAG][..], b"transcript"].concat();
+        assert_ne!(alice.derive_key::<RistrettoSecretKey>(&forged_info).unwrap(), key);
+        let mut forged_tag = vec![0u8; 64];
+        alice
+            .expand(&[&[CONFIRMATION_TAG][..], b"transcript"].concat(), &mut forged_tag)
+            .unwrap();
+        assert_ne!(forged_tag, tag);
+        assert_eq!(
+            mallory.derive_and_verify::<RistrettoSecretKey>(b"transcript", &tag),
+            Err(KdfError::ConfirmationFailed)
+        );
+        assert_eq!(
+            bob.derive_and_verify::<RistrettoSecretKey>(b"other transcript", &tag),
+            Err(KdfError::ConfirmationFailed)
+        );
+        assert_eq!(
+            bob.derive_and_verify::<RistrettoSecretKey>(b"transcript", &tag[..32]),
+            Err(KdfError::ConfirmationFailed)
+        );
+    }
+}
@@ -21,3 +21,10 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 pub mod blake2;
+pub mod checksum;
+pub mod hash_chain;
+pub mod kdf;
+#[cfg(feature = "poseidon")]
+pub mod poseidon;
+pub mod tree_hash;
+pub mod xof;
@@ -0,0 +1,228 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The Poseidon hash over the Ristretto scalar field.
+//!
+//! Poseidon needs far fewer constraints than a bit-oriented hash when proven inside an arithmetic circuit, which makes
+//! it suitable for commitments and nullifiers that will later be proven in zero knowledge. This instance uses a
+//! width-3 permutation (rate 2, capacity 1) with the _x^5_ S-box, 8 full rounds and 57 partial rounds, which is the
+//! recommended configuration for a 128-bit security level over a field of this size.
+//!
+//! The round constants are derived by hashing a counter under a fixed label and the MDS matrix is the Cauchy matrix
+//! _M[i][j] = 1/(i + j + 3)_. Other Poseidon implementations generate their constants differently, so a circuit
+//! must use these exact parameters to agree with this hash.
+//!
+//! The domain is mapped to a field element that initialises the capacity, so hashers for different domains are
+//! unrelated. Input is padded with a single one element before the final permutation.
+//!
+//! ```edition2018
+//! # use tari_crypto::hash::poseidon::PoseidonHasher;
+//! # use tari_crypto::ristretto::RistrettoSecretKey;
+//! # use tari_crypto::keys::SecretKey;
+//! let mut rng = rand::thread_rng();
+//! let (value, blinding) = (RistrettoSecretKey::random(&mut rng), RistrettoSecretKey::random(&mut rng));
+//! let nullifier = PoseidonHasher::hash(b"com.example.nullifier", &[value.clone(), blinding.clone()]);
+//! let mut hasher = PoseidonHasher::new(b"com.example.nullifier");
+//! hasher.absorb(&value);
+//! hasher.absorb(&blinding);
+//! assert_eq!(hasher.finalize(), nullifier);
+//! ```
+
+use crate::ristretto::RistrettoSecretKey;
+use curve25519_dalek::scalar::Scalar;
+
+pub const POSEIDON_WIDTH: usize = 3;
+const RATE: usize = POSEIDON_WIDTH - 1;
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 57;
+
+const ROUND_CONSTANTS_LABEL: &[u8] = b"com.tari.poseidon.round_constants";
+const DOMAIN_LABEL: &[u8] = b"com.tari.poseidon.domain";
+
+struct Parameters {
+    round_constants: Vec<[Scalar; POSEIDON_WIDTH]>,
+    mds: [[Scalar; POSEIDON_WIDTH]; POSEIDON_WIDTH],
+}
+
+impl Parameters {
+    fn generate() -> Self {
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|round| {
+                let mut constants = [Scalar::zero(); POSEIDON_WIDTH];
+                for (i, c) in constants.iter_mut().enumerate() {
+                    let counter = (round * POSEIDON_WIDTH + i) as u64;
+                    *c = RistrettoSecretKey::from_domain_hash(ROUND_CONSTANTS_LABEL, &counter.to_le_bytes()).0;
+                }
+                constants
+            })
+            .collect();
+        let mut mds = [[Scalar::zero(); POSEIDON_WIDTH]; POSEIDON_WIDTH];
+        for (i, row) in mds.iter_mut().enumerate() {
+            for (j, m) in row.iter_mut().enumerate() {
+                *m = Scalar::from((i + j + POSEIDON_WIDTH) as u64).invert();
+            }
+        }
+        Parameters { round_constants, mds }
+    }
+}
+
+lazy_static! {
+    static ref PARAMETERS: Parameters = Parameters::generate();
+}
+
+/// The Poseidon permutation
+pub fn permute(state: &mut [Scalar; POSEIDON_WIDTH]) {
+    let params = &*PARAMETERS;
+    let half_full = FULL_ROUNDS / 2;
+    for (round, constants) in params.round_constants.iter().enumerate() {
+        for (s, c) in state.iter_mut().zip(constants) {
+            *s += c;
+        }
+        if round < half_full || round >= half_full + PARTIAL_ROUNDS {
+            state.iter_mut().for_each(sbox);
+        } else {
+            sbox(&mut state[0]);
+        }
+        let mut mixed = [Scalar::zero(); POSEIDON_WIDTH];
+        for (m, row) in mixed.iter_mut().zip(params.mds.iter()) {
+            *m = row.iter().zip(state.iter()).map(|(a, b)| a * b).sum();
+        }
+        *state = mixed;
+    }
+}
+
+fn sbox(x: &mut Scalar) {
+    let x2 = *x * *x;
+    *x *= x2 * x2;
+}
+
+/// A Poseidon sponge that absorbs scalars and produces a single scalar
+#[derive(Clone, Debug)]
+pub struct PoseidonHasher {
+    state: [Scalar; POSEIDON_WIDTH],
+    /// How many rate elements have been filled since the last permutation
+    absorbed: usize,
+}
+
+impl PoseidonHasher {
+    pub fn new(domain: &[u8]) -> Self {
+        let mut state = [Scalar::zero(); POSEIDON_WIDTH];
+        state[0] = RistrettoSecretKey::from_domain_hash(DOMAIN_LABEL, domain).0;
+        PoseidonHasher { state, absorbed: 0 }
+    }
+
+    /// Hash `inputs` in one go
+    pub fn hash(domain: &[u8], inputs: &[RistrettoSecretKey]) -> RistrettoSecretKey {
+        let mut hasher = PoseidonHasher::new(domain);
+        inputs.iter().for_each(|k| hasher.absorb(k));
+        hasher.finalize()
+    }
+
+    pub fn absorb(&mut self, input: &RistrettoSecretKey) {
+        self.absorb_scalar(&input.0);
+    }
+
+    fn absorb_scalar(&mut self, input: &Scalar) {
+        self.state[1 + self.absorbed] += input;
+        self.absorbed += 1;
+        if self.absorbed == RATE {
+            permute(&mut self.state);
+            self.absorbed = 0;
+        }
+    }
+
+    pub fn finalize(mut self) -> RistrettoSecretKey {
+        self.absorb_scalar(&Scalar::one());
+        if self.absorbed != 0 {
+            permute(&mut self.state);
+        }
+        RistrettoSecretKey(self.state[1])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::SecretKey;
+
+    const DOMAIN: &[u8] = b"com.tari.test.poseidon";
+
+    fn scalars(n: u64) -> Vec<RistrettoSecretKey> {
+        (0..n).map(|i| RistrettoSecretKey(Scalar::from(i))).collect()
+    }
+
+    #[test]
+    fn mds_is_invertible() {
+        // A Cauchy matrix over distinct points is MDS; check its determinant as a sanity test of the construction
+        let m = &PARAMETERS.mds;
+        let minor = |j: usize, k: usize| m[1][j] * m[2][k] - m[1][k] * m[2][j];
+        let det = m[0][0] * minor(1, 2) - m[0][1] * minor(0, 2) + m[0][2] * minor(0, 1);
+        assert_ne!(det, Scalar::zero());
+        assert_eq!(m[0][0] * Scalar::from(3u64), Scalar::one());
+    }
+
+    #[test]
+    fn permutation() {
+        let mut a = [Scalar::zero(); POSEIDON_WIDTH];
+        let mut b = [Scalar::zero(); POSEIDON_WIDTH];
+        b[2] = Scalar::one();
+        permute(&mut a);
+        permute(&mut b);
+        assert_ne!(a, b);
+        assert!(a.iter().all(|s| *s != Scalar::zero()));
+    }
+
+    #[test]
+    fn incremental_matches_one_shot() {
+        for n in 0..6 {
+            let inputs = scalars(n);
+            let mut hasher = PoseidonHasher::new(DOMAIN);
+            for k in &inputs {
+                hasher.absorb(k);
+            }
+            assert_eq!(hasher.finalize(), PoseidonHasher::hash(DOMAIN, &inputs));
+        }
+    }
+
+    #[test]
+    fn separation() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let reference = PoseidonHasher::hash(DOMAIN, &[k.clone()]);
+        assert_ne!(PoseidonHasher::hash(b"com.tari.test.other", &[k.clone()]), reference);
+        // Padding distinguishes trailing zeroes and the empty input
+        assert_ne!(
+            PoseidonHasher::hash(DOMAIN, &[k.clone(), RistrettoSecretKey::default()]),
+            reference
+        );
+        assert_ne!(
+            PoseidonHasher::hash(DOMAIN, &[]),
+            PoseidonHasher::hash(DOMAIN, &scalars(1))
+        );
+        let outputs: Vec<RistrettoSecretKey> = (0..5).map(|n| PoseidonHasher::hash(DOMAIN, &scalars(n))).collect();
+        for i in 0..outputs.len() {
+            for j in i + 1..outputs.len() {
+                assert_ne!(outputs[i], outputs[j]);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Tree hashing for large inputs.
+//!
+//! The input is split into [TREE_HASH_CHUNK_SIZE] chunks, which become the leaves of a domain-separated
+//! [MerkleTree]. With the `rayon` feature the leaves are hashed in parallel, which is where nearly all of the work is
+//! for multi-megabyte payloads. The result is not the same as hashing the input directly, so both sides must agree to
+//! use tree hashing.
+
+use crate::{
+    hash::blake2::Blake256,
+    merkle::{Hash, MerkleHasher, MerkleTree},
+};
+use digest::Digest;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Label that separates tree hashes from every other hash in the crate
+const TREE_HASH_LABEL: &[u8] = b"com.tari.tree_hash";
+pub const TREE_HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Tree hash `bytes` in `domain` with Blake256
+pub fn hash_large(domain: &[u8], bytes: &[u8]) -> Hash {
+    hash_large_with::<Blake256>(domain, bytes)
+}
+
+/// Tree hash `bytes` in `domain` with any digest
+pub fn hash_large_with<D: Digest + Send + Sync>(domain: &[u8], bytes: &[u8]) -> Hash {
+    let hasher = MerkleHasher::<D>::new(&[TREE_HASH_LABEL, domain].concat());
+    // An empty input is a single empty leaf, so that it has a tree like any other
+    let chunks: Vec<&[u8]> = if bytes.is_empty() {
+        vec![bytes]
+    } else {
+        bytes.chunks(TREE_HASH_CHUNK_SIZE).collect()
+    };
+    #[cfg(feature = "rayon")]
+    let iter = chunks.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let iter = chunks.iter();
+    let leaves = iter.map(|chunk| hasher.hash_leaf(chunk)).collect();
+    let tree = MerkleTree::from_leaf_hashes(hasher, leaves).expect("there is always at least one chunk");
+    tree.root().clone()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    const DOMAIN: &[u8] = b"com.tari.test.tree_hash";
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn matches_sequential_tree() {
+        let label = [TREE_HASH_LABEL, DOMAIN].concat();
+        for &len in &[
+            1,
+            1000,
+            TREE_HASH_CHUNK_SIZE,
+            TREE_HASH_CHUNK_SIZE + 1,
+            5 * TREE_HASH_CHUNK_SIZE + 17,
+        ] {
+            let bytes = data(len);
+            let chunks: Vec<&[u8]> = bytes.chunks(TREE_HASH_CHUNK_SIZE).collect();
+            let expected = MerkleTree::<Blake256>::new(&label, &chunks).unwrap();
+            assert_eq!(&hash_large(DOMAIN, &bytes), expected.root());
+        }
+        let empty = MerkleTree::<Blake256>::new(&label, &[b""]).unwrap();
+        assert_eq!(&hash_large(DOMAIN, b""), empty.root());
+    }
+
+    #[test]
+    fn separation() {
+        let bytes = data(3 * TREE_HASH_CHUNK_SIZE);
+        let reference = hash_large(DOMAIN, &bytes);
+        assert_ne!(hash_large(b"com.tari.test.other", &bytes), reference);
+        assert_ne!(hash_large(DOMAIN, &bytes[1..]), reference);
+        assert_ne!(hash_large_with::<Sha256>(DOMAIN, &bytes), reference);
+        // Tree hashing is its own construction, distinct from a plain leaf hash of the same input
+        let plain = MerkleHasher::<Blake256>::new(&[TREE_HASH_LABEL, DOMAIN].concat()).hash_leaf(&bytes);
+        assert_ne!(plain, reference);
+        let mut flipped = bytes;
+        flipped[2 * TREE_HASH_CHUNK_SIZE] ^= 1;
+        assert_ne!(hash_large(DOMAIN, &flipped), reference);
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Domain-separated extendable-output functions (XOFs), such as SHAKE, for when a fixed digest length won't do: 64
+//! bytes for wide scalar reduction, or arbitrary lengths for key derivation.
+
+use crate::ristretto::{RistrettoPublicKey, RistrettoSecretKey};
+use clear_on_drop::clear::Clear;
+use digest::{ExtendableOutput, Update, XofReader};
+use sha3::{Shake128, Shake256};
+use std::io;
+
+/// An XOF whose output depends on a domain tag as well as the input. The domain is absorbed first, prefixed with its
+/// length as a little-endian u64, so different domains never produce the same stream for any input.
+///
+/// ```edition2018
+/// # use tari_crypto::hash::xof::Shake256Xof;
+/// # use digest::XofReader;
+/// let mut reader = Shake256Xof::new(b"com.example.kdf").chain(b"input key material").finalize_xof();
+/// let mut okm = [0u8; 100];
+/// reader.read(&mut okm);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DomainSeparatedXof<X> {
+    inner: X,
+}
+
+/// SHAKE128 with domain separation
+pub type Shake128Xof = DomainSeparatedXof<Shake128>;
+/// SHAKE256 with domain separation
+pub type Shake256Xof = DomainSeparatedXof<Shake256>;
+
+impl<X> DomainSeparatedXof<X>
+where X: Update + ExtendableOutput + Default
+{
+    pub fn new(domain: &[u8]) -> Self {
+        let mut inner = X::default();
+        inner.update((domain.len() as u64).to_le_bytes());
+        inner.update(domain);
+        DomainSeparatedXof { inner }
+    }
+
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.inner.update(data);
+    }
+
+    pub fn chain(mut self, data: impl AsRef<[u8]>) -> Self {
+        self.update(data);
+        self
+    }
+
+    /// Absorb everything `reader` produces, a chunk at a time, returning the number of bytes read
+    pub fn update_reader<R: io::Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        let mut buffer = [0u8; 8192];
+        let mut total = 0;
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(total),
+                Ok(n) => {
+                    self.update(&buffer[..n]);
+                    total += n as u64;
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Finish absorbing input and return a reader for as much output as is needed
+    pub fn finalize_xof(self) -> X::Reader {
+        self.inner.finalize_xof()
+    }
+
+    /// Fill `out` with output
+    pub fn finalize_into(self, out: &mut [u8]) {
+        self.finalize_xof().read(out);
+    }
+
+    /// Exactly `N` bytes of output, as an array
+    pub fn finalize_fixed<const N: usize>(self) -> [u8; N] {
+        let mut out = [0u8; N];
+        self.finalize_into(&mut out);
+        out
+    }
+
+    /// 64 bytes of output, e.g. for `RistrettoSecretKey::from_uniform_bytes`
+    pub fn finalize_wide(self) -> [u8; 64] {
+        self.finalize_fixed()
+    }
+
+    /// Reduce 64 bytes of output to a scalar, for challenges and other hash-derived secrets
+    pub fn finalize_into_scalar(self) -> RistrettoSecretKey {
+        let mut wide = self.finalize_wide();
+        let k = RistrettoSecretKey::from_uniform_bytes(&wide).expect("64 bytes is the correct length");
+        wide.clear();
+        k
+    }
+
+    /// Map 64 bytes of output to a Ristretto point with no known discrete log, e.g. for deriving generators
+    pub fn finalize_into_point(self) -> RistrettoPublicKey {
+        RistrettoPublicKey::from_uniform_bytes(&self.finalize_wide()).expect("64 bytes is the correct length")
+    }
+}
+
+/// Allows streaming data in with `io::copy` and friends. Writes never fail.
+impl<X: Update> io::Write for DomainSeparatedXof<X> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::PublicKey;
+    use std::io::Write;
+
+    #[test]
+    fn domain_is_length_prefixed() {
+        let mut expected = [0u8; 80];
+        Shake256::default()
+            .chain(6u64.to_le_bytes())
+            .chain(b"domain")
+            .chain(b"data")
+            .finalize_xof()
+            .read(&mut expected);
+        let mut out = [0u8; 80];
+        Shake256Xof::new(b"domain").chain(b"data").finalize_into(&mut out);
+        assert_eq!(out[..], expected[..]);
+        // Moving bytes between the domain and the data changes the output
+        let mut shifted = [0u8; 80];
+        Shake256Xof::new(b"domai").chain(b"ndata").finalize_into(&mut shifted);
+        assert_ne!(out[..], shifted[..]);
+    }
+
+    #[test]
+    fn streaming_output() {
+        let xof = Shake128Xof::new(b"Mort").chain(b"Ysabell");
+        let mut whole = [0u8; 96];
+        xof.clone().finalize_into(&mut whole);
+        let mut reader = xof.finalize_xof();
+        let mut parts = [0u8; 96];
+        reader.read(&mut parts[..10]);
+        reader.read(&mut parts[10..]);
+        assert_eq!(whole[..], parts[..]);
+        // Shorter outputs are prefixes of longer ones
+        let wide = Shake128Xof::new(b"Mort").chain(b"Ysabell").finalize_wide();
+        assert_eq!(wide[..], whole[..64]);
+        assert!(RistrettoSecretKey::from_uniform_bytes(&wide).is_ok());
+        let short: [u8; 16] = Shake128Xof::new(b"Mort").chain(b"Ysabell").finalize_fixed();
+        assert_eq!(short[..], whole[..16]);
+    }
+
+    #[test]
+    fn scalars_and_points() {
+        let xof = Shake256Xof::new(b"Carrot").chain(b"Ironfoundersson");
+        let wide = xof.clone().finalize_wide();
+        let k = xof.clone().finalize_into_scalar();
+        assert_eq!(k, RistrettoSecretKey::from_uniform_bytes(&wide).unwrap());
+        let p = xof.finalize_into_point();
+        assert_eq!(p, RistrettoPublicKey::from_uniform_bytes(&wide).unwrap());
+        assert_ne!(p, RistrettoPublicKey::from_secret_key(&k));
+    }
+
+    #[test]
+    fn streaming_input() {
+        let data = vec![0x5au8; 20_000];
+        let expected = Shake256Xof::new(b"Hex").chain(&data).finalize_wide();
+        let mut xof = Shake256Xof::new(b"Hex");
+        assert_eq!(xof.update_reader(&data[..]).unwrap(), 20_000);
+        assert_eq!(xof.finalize_wide(), expected);
+        let mut xof = Shake256Xof::new(b"Hex");
+        io::copy(&mut io::Cursor::new(&data), &mut xof).unwrap();
+        assert_eq!(xof.finalize_wide(), expected);
+        let mut xof = Shake256Xof::new(b"Hex");
+        xof.write_all(&data[..7]).unwrap();
+        xof.write_all(&data[7..]).unwrap();
+        assert_eq!(xof.finalize_wide(), expected);
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Hash-based one-time signatures (WOTS+).
+//!
+//! Winternitz one-time signatures rely only on the preimage and second-preimage resistance of a hash function, so
+//! they remain secure against quantum adversaries. This makes them suitable as a post-quantum fallback: commit to a
+//! [WotsPublicKey] now (see [WotsPublicKey::commitment]) and use it to authorise a spend later if elliptic curve
+//! signatures are ever broken.
+//!
+//! The construction follows WOTS+ as described in [RFC 8391](https://datatracker.ietf.org/doc/html/rfc8391), section
+//! 3, with every hash call prefixed by a distinct domain label instead of the RFC's address scheme:
+//!
+//! * The message digest, _M = H(label || seed || m)_, of _n_ bytes, is written as _len_1_ base-_w_ digits, followed by
+//!   _len_2_ digits of the checksum, _\sum (w - 1 - M_i)_.
+//! * Each of the _len = len_1 + len_2_ secret key elements is the start of a hash chain of length _w - 1_. A chain step
+//!   is _F(x) = H(label || seed || i || j || (x xor mask_{i,j}))_, where the mask is derived from the public seed.
+//! * The public key is the end of each chain, and the signature for digit _b_i_ is the _b_i_-th element of chain _i_.
+//!
+//! **NB:** A secret key must sign exactly one message. Signing two different messages with the same key lets anyone
+//! forge signatures on other messages; [WotsSecretKey::sign] consumes the key to make reuse harder.
+//!
+//! ```edition2018
+//! # use tari_crypto::hash_sig::{WotsParameters, WotsSecretKey};
+//! # use sha2::Sha256;
+//! let params = WotsParameters::for_digest::<Sha256>(16).unwrap();
+//! let secret = WotsSecretKey::random::<Sha256, _>(params, &mut rand::thread_rng()).unwrap();
+//! let public = secret.public_key::<Sha256>();
+//! let sig = secret.sign::<Sha256>(b"Unseen Academicals").unwrap();
+//! assert!(public.verify::<Sha256>(b"Unseen Academicals", &sig));
+//! ```
+
+use clear_on_drop::{clear::Clear, ClearOnDrop};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const SEED_LENGTH: usize = 32;
+const SECRET_ELEMENT_LABEL: &[u8] = b"com.tari.hash_sig.secret_element";
+const CHAIN_LABEL: &[u8] = b"com.tari.hash_sig.chain";
+const MASK_LABEL: &[u8] = b"com.tari.hash_sig.mask";
+const MESSAGE_LABEL: &[u8] = b"com.tari.hash_sig.message";
+const COMMITMENT_LABEL: &[u8] = b"com.tari.hash_sig.commitment";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum HashSigError {
+    #[error("The Winternitz parameter must be 4, 16 or 256")]
+    UnsupportedWinternitzParameter,
+    #[error("The hash output must be at least 16 bytes long")]
+    HashTooShort,
+    #[error("The hash function does not match the key parameters")]
+    IncompatibleHashFunction,
+    #[error("Seeds must be 32 bytes long")]
+    InvalidSeed,
+}
+
+/// WOTS+ parameters: the Winternitz parameter, _w_, and the hash output length, _n_.
+///
+/// Larger values of _w_ give shorter signatures at the cost of longer hash chains. With a 32 byte hash, _w = 4_ gives
+/// 133 chains of 3 steps, _w = 16_ gives 67 chains of 15 steps and _w = 256_ gives 34 chains of 255 steps. _w = 16_ is
+/// the usual choice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotsParameters {
+    w: u32,
+    n: usize,
+}
+
+impl WotsParameters {
+    pub fn new(w: u32, n: usize) -> Result<Self, HashSigError> {
+        if !matches!(w, 4 | 16 | 256) {
+            return Err(HashSigError::UnsupportedWinternitzParameter);
+        }
+        if n < 16 {
+            return Err(HashSigError::HashTooShort);
+        }
+        Ok(WotsParameters { w, n })
+    }
+
+    /// Parameters for Winternitz parameter `w` and the output length of `D`
+    pub fn for_digest<D: Digest>(w: u32) -> Result<Self, HashSigError> {
+        Self::new(w, D::output_size())
+    }
+
+    pub fn w(&self) -> u32 {
+        self.w
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    fn log_w(&self) -> u32 {
+        self.w.trailing_zeros()
+    }
+
+    /// The number of message digits, _len_1 = ceil(8n / log_2(w))_
+    pub fn len1(&self) -> usize {
+        let log_w = self.log_w() as usize;
+        (8 * self.n + log_w - 1) / log_w
+    }
+
+    /// The number of checksum digits, _len_2 = floor(log_2(len_1 (w - 1)) / log_2(w)) + 1_
+    pub fn len2(&self) -> usize {
+        let max_checksum = (self.len1() * (self.w as usize - 1)) as u64;
+        let log2 = 63 - max_checksum.leading_zeros();
+        (log2 / self.log_w()) as usize + 1
+    }
+
+    /// The number of hash chains, and signature and public key elements
+    pub fn num_chains(&self) -> usize {
+        self.len1() + self.len2()
+    }
+
+    /// The size of a signature in bytes
+    pub fn signature_size(&self) -> usize {
+        self.num_chains() * self.n
+    }
+
+    /// The base-_w_ digits of the message digest followed by those of its checksum
+    fn digits(&self, digest: &[u8]) -> Vec<u32> {
+        let log_w = self.log_w();
+        let mut digits = base_w(digest, log_w, self.len1());
+        let mut checksum: u64 = digits.iter().map(|d| u64::from(self.w - 1 - d)).sum();
+        let checksum_bits = self.len2() * log_w as usize;
+        checksum <<= (8 - checksum_bits % 8) % 8;
+        let checksum_bytes = checksum.to_be_bytes();
+        let num_bytes = (checksum_bits + 7) / 8;
+        digits.extend(base_w(&checksum_bytes[8 - num_bytes..], log_w, self.len2()));
+        digits
+    }
+}
+
+/// A WOTS+ one-time secret key. The secret seed is cleared from memory when the key is dropped, and the chain starts
+/// derived from it are cleared as soon as they have been used.
+pub struct WotsSecretKey {
+    params: WotsParameters,
+    secret_seed: Vec<u8>,
+    public_seed: Vec<u8>,
+}
+
+impl WotsSecretKey {
+    /// Generate a new secret key with random seeds
+    pub fn random<D: Digest, R: Rng + CryptoRng>(params: WotsParameters, rng: &mut R) -> Result<Self, HashSigError> {
+        let mut secret_seed = vec![0u8; SEED_LENGTH];
+        let mut public_seed = vec![0u8; SEED_LENGTH];
+        rng.fill_bytes(&mut secret_seed);
+        rng.fill_bytes(&mut public_seed);
+        Self::from_seeds::<D>(params, secret_seed, public_seed)
+    }
+
+    /// Build a secret key from its 32 byte seeds, e.g. to derive one-time keys deterministically from a master seed
+    pub fn from_seeds<D: Digest>(
+        params: WotsParameters,
+        secret_seed: Vec<u8>,
+        public_seed: Vec<u8>,
+    ) -> Result<Self, HashSigError> {
+        if D::output_size() != params.n {
+            return Err(HashSigError::IncompatibleHashFunction);
+        }
+        if secret_seed.len() != SEED_LENGTH || public_seed.len() != SEED_LENGTH {
+            return Err(HashSigError::InvalidSeed);
+        }
+        Ok(WotsSecretKey {
+            params,
+            secret_seed,
+            public_seed,
+        })
+    }
+
+    pub fn params(&self) -> &WotsParameters {
+        &self.params
+    }
+
+    /// Calculate the public key, the end of every hash chain
+    pub fn public_key<D: Digest>(&self) -> WotsPublicKey {
+        let steps = self.params.w - 1;
+        let elements = (0..self.params.num_chains())
+            .map(|i| chain::<D>(&self.secret_element::<D>(i), 0, steps, &self.public_seed, i))
+            .collect();
+        WotsPublicKey {
+            w: self.params.w,
+            public_seed: self.public_seed.clone(),
+            elements,
+        }
+    }
+
+    /// Sign `message`. The key is consumed, since each key must only ever sign one message.
+    pub fn sign<D: Digest>(self, message: &[u8]) -> Result<WotsSignature, HashSigError> {
+        if D::output_size() != self.params.n {
+            return Err(HashSigError::IncompatibleHashFunction);
+        }
+        let digest = message_digest::<D>(&self.public_seed, message);
+        let elements = self
+            .params
+            .digits(&digest)
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| chain::<D>(&self.secret_element::<D>(i), 0, b, &self.public_seed, i))
+            .collect();
+        Ok(WotsSignature { elements })
+    }
+
+    /// The start of hash chain `i`, which is cleared once it has been hashed forward and dropped
+    fn secret_element<D: Digest>(&self, i: usize) -> ClearOnDrop<Vec<u8>> {
+        let mut digest = D::new()
+            .chain(SECRET_ELEMENT_LABEL)
+            .chain(&self.secret_seed)
+            .chain((i as u32).to_le_bytes())
+            .finalize();
+        let element = ClearOnDrop::new(digest.to_vec());
+        digest.as_mut_slice().clear();
+        element
+    }
+}
+
+impl Drop for WotsSecretKey {
+    fn drop(&mut self) {
+        self.secret_seed.as_mut_slice().clear();
+    }
+}
+
+/// A WOTS+ public key: the Winternitz parameter, the public seed and the end of every hash chain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotsPublicKey {
+    w: u32,
+    public_seed: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+impl WotsPublicKey {
+    /// Verify a signature on `message`
+    pub fn verify<D: Digest>(&self, message: &[u8], signature: &WotsSignature) -> bool {
+        let params = match WotsParameters::for_digest::<D>(self.w) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        let len = params.num_chains();
+        if self.elements.len() != len || signature.elements.len() != len {
+            return false;
+        }
+        if signature.elements.iter().any(|e| e.len() != params.n) {
+            return false;
+        }
+        let digest = message_digest::<D>(&self.public_seed, message);
+        params.digits(&digest).into_iter().enumerate().all(|(i, b)| {
+            chain::<D>(&signature.elements[i], b, params.w - 1 - b, &self.public_seed, i) == self.elements[i]
+        })
+    }
+
+    /// A compact commitment to the public key, _H(label || w || seed || pk_0 || ... || pk_{len-1})_, for publishing
+    /// ahead of time
+    pub fn commitment<D: Digest>(&self) -> Vec<u8> {
+        let mut hasher = D::new()
+            .chain(COMMITMENT_LABEL)
+            .chain(self.w.to_le_bytes())
+            .chain(&self.public_seed);
+        for element in &self.elements {
+            hasher = hasher.chain(element);
+        }
+        hasher.finalize().to_vec()
+    }
+
+    pub fn w(&self) -> u32 {
+        self.w
+    }
+}
+
+/// A WOTS+ signature, one element from the middle of every hash chain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotsSignature {
+    elements: Vec<Vec<u8>>,
+}
+
+impl WotsSignature {
+    /// The signature as a single byte string, for size accounting
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.elements.concat()
+    }
+}
+
+/// Split `bytes` into `out_len` digits of `log_w` bits each, most significant first
+fn base_w(bytes: &[u8], log_w: u32, out_len: usize) -> Vec<u32> {
+    let mask = (1u32 << log_w) - 1;
+    let mut digits = Vec::with_capacity(out_len);
+    let mut bytes = bytes.iter();
+    let mut total = 0u32;
+    let mut bits = 0u32;
+    for _ in 0..out_len {
+        if bits == 0 {
+            total = u32::from(*bytes.next().expect("base_w input is too short"));
+            bits = 8;
+        }
+        bits -= log_w;
+        digits.push((total >> bits) & mask);
+    }
+    digits
+}
+
+/// Apply `steps` steps of hash chain `i` to `x`, starting at position `start`. When signing, the intermediate values
+/// are secret, so each is cleared once the next has been computed.
+fn chain<D: Digest>(x: &[u8], start: u32, steps: u32, public_seed: &[u8], i: usize) -> Vec<u8> {
+    let mut out = x.to_vec();
+    for j in start..start + steps {
+        let mask = D::new()
+            .chain(MASK_LABEL)
+            .chain(public_seed)
+            .chain((i as u32).to_le_bytes())
+            .chain(j.to_le_bytes())
+            .finalize();
+        let mut masked: Vec<u8> = out.iter().zip(mask.iter()).map(|(a, b)| a ^ b).collect();
+        let mut next = D::new()
+            .chain(CHAIN_LABEL)
+            .chain(public_seed)
+            .chain((i as u32).to_le_bytes())
+            .chain(j.to_le_bytes())
+            .chain(&masked)
+            .finalize();
+        masked.as_mut_slice().clear();
+        out.as_mut_slice().clear();
+        out = next.to_vec();
+        next.as_mut_slice().clear();
+    }
+    out
+}
+
+fn message_digest<D: Digest>(public_seed: &[u8], message: &[u8]) -> Vec<u8> {
+    D::new()
+        .chain(MESSAGE_LABEL)
+        .chain(public_seed)
+        .chain(message)
+        .finalize()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+    use sha2::Sha512;
+
+    #[test]
+    fn parameters() {
+        let p = WotsParameters::new(16, 32).unwrap();
+        assert_eq!((p.len1(), p.len2(), p.num_chains()), (64, 3, 67));
+        assert_eq!(p.signature_size(), 67 * 32);
+        let p = WotsParameters::new(4, 32).unwrap();
+        assert_eq!((p.len1(), p.len2()), (128, 5));
+        let p = WotsParameters::new(256, 32).unwrap();
+        assert_eq!((p.len1(), p.len2()), (32, 2));
+        let p = WotsParameters::for_digest::<Sha512>(16).unwrap();
+        assert_eq!((p.len1(), p.len2()), (128, 3));
+        assert_eq!(
+            WotsParameters::new(8, 32).unwrap_err(),
+            HashSigError::UnsupportedWinternitzParameter
+        );
+        assert_eq!(WotsParameters::new(16, 8).unwrap_err(), HashSigError::HashTooShort);
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut rng = rand::thread_rng();
+        for &w in &[4, 16, 256] {
+            let params = WotsParameters::for_digest::<Blake256>(w).unwrap();
+            let secret = WotsSecretKey::random::<Blake256, _>(params, &mut rng).unwrap();
+            let public = secret.public_key::<Blake256>();
+            let sig = secret.sign::<Blake256>(b"Snuff").unwrap();
+            assert_eq!(sig.to_vec().len(), params.signature_size());
+            assert!(public.verify::<Blake256>(b"Snuff", &sig));
+            assert!(!public.verify::<Blake256>(b"Raising Steam", &sig));
+            assert!(!public.verify::<Sha512>(b"Snuff", &sig));
+        }
+    }
+
+    #[test]
+    fn tampered_signature() {
+        let mut rng = rand::thread_rng();
+        let params = WotsParameters::for_digest::<Blake256>(16).unwrap();
+        let secret = WotsSecretKey::random::<Blake256, _>(params, &mut rng).unwrap();
+        let public = secret.public_key::<Blake256>();
+        let mut sig = secret.sign::<Blake256>(b"Snuff").unwrap();
+        sig.elements[3][0] ^= 1;
+        assert!(!public.verify::<Blake256>(b"Snuff", &sig));
+        sig.elements.pop();
+        assert!(!public.verify::<Blake256>(b"Snuff", &sig));
+    }
+
+    #[test]
+    fn deterministic_keys() {
+        let params = WotsParameters::for_digest::<Blake256>(16).unwrap();
+        let key = || WotsSecretKey::from_seeds::<Blake256>(params, vec![1u8; 32], vec![2u8; 32]).unwrap();
+        let public = key().public_key::<Blake256>();
+        assert_eq!(public, key().public_key::<Blake256>());
+        assert_eq!(
+            public.commitment::<Blake256>(),
+            key().public_key::<Blake256>().commitment::<Blake256>()
+        );
+        let other = WotsSecretKey::from_seeds::<Blake256>(params, vec![3u8; 32], vec![2u8; 32]).unwrap();
+        assert_ne!(
+            public.commitment::<Blake256>(),
+            other.public_key::<Blake256>().commitment::<Blake256>()
+        );
+        assert_eq!(
+            WotsSecretKey::from_seeds::<Blake256>(params, vec![1u8; 31], vec![2u8; 32]).err(),
+            Some(HashSigError::InvalidSeed)
+        );
+        assert_eq!(
+            WotsSecretKey::from_seeds::<Sha512>(params, vec![1u8; 32], vec![2u8; 32]).err(),
+            Some(HashSigError::IncompatibleHashFunction)
+        );
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Deterministic key derivation from a master key.
+//!
+//! A [KeyManager] holds a master secret key and a branch label, and derives child keys by index:
+//!
+//!   k_i = H(label || len(branch) || branch || master_key || i)
+//!
+//! Every key can be recovered from the master key, branch and index alone, so backing up the (serializable) manager
+//! state backs up every key it will ever produce. Different branches give unrelated keys from the same master key,
+//! e.g. one branch for spending keys and another for script keys.
+//!
+//! ## Labelled keys
+//! [KeyManager::derive_labeled_key] derives a [LabeledKey] for one [KeyUsage], from a sub-branch named after the
+//! usage. The usage is part of the key's type, so a key derived for Diffie-Hellman can't be passed where a signing
+//! key is expected:
+//!
+//! ```compile_fail
+//! # use tari_crypto::common::Blake256;
+//! # use tari_crypto::key_manager::{Dh, KeyManager};
+//! # use tari_crypto::ristretto::{RistrettoPublicKey, RistrettoSecretKey};
+//! let manager = KeyManager::<RistrettoSecretKey, Blake256>::new(&mut rand::thread_rng(), "wallet");
+//! let dh_key = manager.derive_labeled_key::<Dh>(0).unwrap();
+//! let sig = dh_key.sign::<RistrettoPublicKey, Blake256>(b"not allowed");
+//! ```
+
+use crate::{
+    keys::{DiffieHellmanSharedSecret, PublicKey, SecretKey},
+    signatures::{SchnorrSignature, SchnorrSignatureError},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    marker::PhantomData,
+    ops::{Add, Mul},
+};
+use thiserror::Error;
+
+const KEY_MANAGER_LABEL: &[u8] = b"com.tari.key_manager.derive";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum KeyManagerError {
+    #[error("The digest output could not be converted into a secret key: {0}")]
+    InvalidDigest(String),
+    #[error("The key index has reached its maximum value")]
+    KeyIndexOverflow,
+}
+
+/// A key derived by a [KeyManager], along with the index it was derived at
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedKey<K> {
+    pub key: K,
+    pub key_index: u64,
+}
+
+/// Derives secret keys from a master key and branch label. The digest, `D`, must produce output that `K` accepts in
+/// `from_bytes`; for `RistrettoSecretKey` that means a 32-byte digest, such as `Blake256`.
+///
+/// ```edition2018
+/// # use tari_crypto::common::Blake256;
+/// # use tari_crypto::key_manager::KeyManager;
+/// # use tari_crypto::ristretto::RistrettoSecretKey;
+/// let mut manager = KeyManager::<RistrettoSecretKey, Blake256>::new(&mut rand::thread_rng(), "spend");
+/// let first = manager.next_key().unwrap();
+/// let second = manager.next_key().unwrap();
+/// assert_eq!((first.key_index, second.key_index), (1, 2));
+/// // Any key can be recovered from its index
+/// assert_eq!(manager.derive_key(1).unwrap(), first);
+/// ```
+#[derive(Serialize, Deserialize)]
+pub struct KeyManager<K, D> {
+    master_key: K,
+    branch_seed: String,
+    primary_key_index: u64,
+    #[serde(skip)]
+    digest_type: PhantomData<D>,
+}
+
+impl<K, D> KeyManager<K, D>
+where
+    K: SecretKey,
+    D: Digest,
+{
+    /// Create a key manager with a new random master key
+    pub fn new<R: Rng + CryptoRng>(rng: &mut R, branch_seed: &str) -> Self {
+        KeyManager::from(K::random(rng), branch_seed.to_string(), 0)
+    }
+
+    /// Restore a key manager from its parts. `primary_key_index` is the index of the last key handed out by
+    /// [next_key](#method.next_key).
+    pub fn from(master_key: K, branch_seed: String, primary_key_index: u64) -> Self {
+        KeyManager {
+            master_key,
+            branch_seed,
+            primary_key_index,
+            digest_type: PhantomData,
+        }
+    }
+
+    /// Derive the key at `key_index`. This doesn't change the manager's state.
+    pub fn derive_key(&self, key_index: u64) -> Result<DerivedKey<K>, KeyManagerError> {
+        let seed = self.branch_seed.as_bytes();
+        let hash = D::new()
+            .chain(KEY_MANAGER_LABEL)
+            .chain((seed.len() as u64).to_le_bytes())
+            .chain(seed)
+            .chain(self.master_key.as_bytes())
+            .chain(key_index.to_le_bytes())
+            .finalize();
+        let key = K::from_bytes(&hash).map_err(|e| KeyManagerError::InvalidDigest(e.to_string()))?;
+        Ok(DerivedKey { key, key_index })
+    }
+
+    /// Derive the key at `key_index` for `U`'s usage. The key comes from the sub-branch `<branch>/<U::LABEL>`, so keys
+    /// for different usages are unrelated, and none of them match the plain [derive_key](#method.derive_key) output.
+    pub fn derive_labeled_key<U: KeyUsage>(&self, key_index: u64) -> Result<LabeledKey<K, U>, KeyManagerError> {
+        let branch = format!("{}/{}", self.branch_seed, U::LABEL);
+        let sub_branch = KeyManager::<K, D>::from(self.master_key.clone(), branch, 0);
+        sub_branch.derive_key(key_index).map(|k| LabeledKey::new(k.key))
+    }
+
+    /// Advance the key index and derive the key at the new index
+    pub fn next_key(&mut self) -> Result<DerivedKey<K>, KeyManagerError> {
+        let index = self
+            .primary_key_index
+            .checked_add(1)
+            .ok_or(KeyManagerError::KeyIndexOverflow)?;
+        let key = self.derive_key(index)?;
+        self.primary_key_index = index;
+        Ok(key)
+    }
+
+    pub fn master_key(&self) -> &K {
+        &self.master_key
+    }
+
+    pub fn branch_seed(&self) -> &str {
+        &self.branch_seed
+    }
+
+    /// The index of the last key handed out by [next_key](#method.next_key)
+    pub fn key_index(&self) -> u64 {
+        self.primary_key_index
+    }
+
+    /// Set the key index, e.g. after scanning for keys in use during recovery
+    pub fn update_key_index(&mut self, index: u64) {
+        self.primary_key_index = index;
+    }
+}
+
+// Implemented by hand so that the digest type needn't be `Clone` or `PartialEq` itself
+impl<K: Clone, D> Clone for KeyManager<K, D> {
+    fn clone(&self) -> Self {
+        KeyManager {
+            master_key: self.master_key.clone(),
+            branch_seed: self.branch_seed.clone(),
+            primary_key_index: self.primary_key_index,
+            digest_type: PhantomData,
+        }
+    }
+}
+
+impl<K: PartialEq, D> PartialEq for KeyManager<K, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.master_key == other.master_key &&
+            self.branch_seed == other.branch_seed &&
+            self.primary_key_index == other.primary_key_index
+    }
+}
+
+impl<K: Eq, D> Eq for KeyManager<K, D> {}
+
+/// What a [LabeledKey] may be used for. The label names the key manager sub-branch that keys for this usage come from.
+pub trait KeyUsage {
+    const LABEL: &'static str;
+}
+
+/// Keys for Schnorr signatures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signing;
+
+/// Keys for Diffie-Hellman key exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dh;
+
+/// Blinding factors for commitments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment;
+
+impl KeyUsage for Signing {
+    const LABEL: &'static str = "signing";
+}
+
+impl KeyUsage for Dh {
+    const LABEL: &'static str = "dh";
+}
+
+impl KeyUsage for Commitment {
+    const LABEL: &'static str = "commitment";
+}
+
+/// A secret key that may only be used for `U`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabeledKey<K, U> {
+    key: K,
+    usage: PhantomData<U>,
+}
+
+impl<K: SecretKey, U: KeyUsage> LabeledKey<K, U> {
+    /// Label an existing key. Prefer [KeyManager::derive_labeled_key], which guarantees that keys for different
+    /// usages are unrelated.
+    pub fn new(key: K) -> Self {
+        LabeledKey {
+            key,
+            usage: PhantomData,
+        }
+    }
+
+    pub fn usage(&self) -> &'static str {
+        U::LABEL
+    }
+
+    pub fn public_key<P: PublicKey<K = K>>(&self) -> P {
+        P::from_secret_key(&self.key)
+    }
+
+    /// The raw secret key, for APIs that don't take labelled keys. This bypasses the usage check.
+    pub fn secret_key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: SecretKey> LabeledKey<K, Signing> {
+    /// Sign `message` with a deterministic nonce, as `SchnorrSignature::sign_deterministic` does
+    pub fn sign<P, D>(&self, message: &[u8]) -> Result<SchnorrSignature<P, K>, SchnorrSignatureError>
+    where
+        P: PublicKey<K = K>,
+        K: Add<Output = K> + Mul<P, Output = P> + Mul<Output = K>,
+        D: Digest,
+    {
+        SchnorrSignature::sign_deterministic::<D>(self.key.clone(), message)
+    }
+}
+
+impl<K: SecretKey> LabeledKey<K, Dh> {
+    /// The Diffie-Hellman shared secret with `public_key`
+    pub fn shared_secret<P>(&self, public_key: &P) -> P
+    where P: DiffieHellmanSharedSecret<PK = P> + PublicKey<K = K> {
+        P::shared_secret(&self.key, public_key)
+    }
+}
+
+impl<K: SecretKey> LabeledKey<K, Commitment> {
+    /// The key, for use as a commitment's blinding factor
+    pub fn blinding_factor(&self) -> &K {
+        &self.key
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        common::Blake256,
+        ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+    };
+
+    type RistrettoKeyManager = KeyManager<RistrettoSecretKey, Blake256>;
+
+    #[test]
+    fn the_colour_of_magic() {
+        let mut rng = rand::thread_rng();
+        let mut manager = RistrettoKeyManager::new(&mut rng, "Rincewind");
+        assert_eq!(manager.key_index(), 0);
+        let keys = (0..5).map(|_| manager.next_key().unwrap()).collect::<Vec<_>>();
+        assert_eq!(manager.key_index(), 5);
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(k.key_index, i as u64 + 1);
+            assert_eq!(&manager.derive_key(k.key_index).unwrap(), k);
+        }
+        assert_ne!(keys[0].key, keys[1].key);
+        // A manager restored from the same parts derives the same keys
+        let restored = RistrettoKeyManager::from(manager.master_key().clone(), "Rincewind".to_string(), 0);
+        assert_eq!(restored.derive_key(3).unwrap(), keys[2]);
+        // Other branches and master keys give different keys
+        let other_branch = RistrettoKeyManager::from(manager.master_key().clone(), "Twoflower".to_string(), 0);
+        assert_ne!(other_branch.derive_key(3).unwrap().key, keys[2].key);
+        let other_master = RistrettoKeyManager::new(&mut rng, "Rincewind");
+        assert_ne!(other_master.derive_key(3).unwrap().key, keys[2].key);
+        manager.update_key_index(10);
+        assert_eq!(manager.next_key().unwrap().key_index, 11);
+        // The index can't wrap back around to keys that were already handed out
+        manager.update_key_index(u64::MAX);
+        assert_eq!(manager.next_key(), Err(KeyManagerError::KeyIndexOverflow));
+        assert_eq!(manager.key_index(), u64::MAX);
+    }
+
+    #[test]
+    fn serialization() {
+        let mut manager = RistrettoKeyManager::new(&mut rand::thread_rng(), "The Luggage");
+        manager.next_key().unwrap();
+        let json = serde_json::to_string(&manager).unwrap();
+        let restored: RistrettoKeyManager = serde_json::from_str(&json).unwrap();
+        assert!(restored == manager);
+        assert!(restored.clone() == restored);
+        assert_eq!(restored.derive_key(1).unwrap(), manager.derive_key(1).unwrap());
+        assert_eq!(restored.branch_seed(), "The Luggage");
+    }
+
+    #[test]
+    fn incompatible_digest() {
+        let manager = KeyManager::<RistrettoSecretKey, sha2::Sha512>::new(&mut rand::thread_rng(), "Hex");
+        assert!(matches!(manager.derive_key(0), Err(KeyManagerError::InvalidDigest(_))));
+    }
+
+    #[test]
+    fn labeled_keys() {
+        let manager = RistrettoKeyManager::new(&mut rand::thread_rng(), "Sourcery");
+        let signing = manager.derive_labeled_key::<Signing>(1).unwrap();
+        let dh = manager.derive_labeled_key::<Dh>(1).unwrap();
+        let blinding = manager.derive_labeled_key::<Commitment>(1).unwrap();
+        assert_eq!(signing.usage(), "signing");
+        // Each usage gets its own keys, unrelated to the plain branch
+        assert_ne!(signing.secret_key(), dh.secret_key());
+        assert_ne!(dh.secret_key(), blinding.blinding_factor());
+        assert_ne!(signing.secret_key(), &manager.derive_key(1).unwrap().key);
+        assert_eq!(manager.derive_labeled_key::<Signing>(1).unwrap(), signing);
+        // The sub-branch is an ordinary key manager branch
+        let sub_branch = RistrettoKeyManager::from(manager.master_key().clone(), "Sourcery/dh".to_string(), 0);
+        assert_eq!(&sub_branch.derive_key(1).unwrap().key, dh.secret_key());
+
+        let sig = signing.sign::<RistrettoPublicKey, Blake256>(b"Coin").unwrap();
+        assert!(sig.verify_message::<Blake256>(&signing.public_key(), b"Coin"));
+        let other = LabeledKey::<RistrettoSecretKey, Dh>::new(RistrettoSecretKey::from(42));
+        let shared = dh.shared_secret(&other.public_key::<RistrettoPublicKey>());
+        assert_eq!(shared, other.shared_secret(&dh.public_key::<RistrettoPublicKey>()));
+    }
+}
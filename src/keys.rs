@@ -28,7 +28,18 @@
 use rand::{CryptoRng, Rng};
 use serde::{de::DeserializeOwned, ser::Serialize};
 use std::ops::Add;
-use tari_utilities::ByteArray;
+use subtle::{Choice, ConstantTimeEq};
+use tari_utilities::{ByteArray, ByteArrayError};
+use thiserror::Error;
+
+/// The reasons [PublicKey::validate] can reject a key
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PublicKeyValidationError {
+    #[error("The point is not on the curve")]
+    NotOnCurve,
+    #[error("The point has a small-order component")]
+    SmallOrder,
+}
 
 /// A trait specifying common behaviour for representing `SecretKey`s. Specific elliptic curve
 /// implementations need to implement this trait for them to be used in Tari.
@@ -47,6 +58,18 @@ use tari_utilities::ByteArray;
 pub trait SecretKey: ByteArray + Clone + PartialEq + Eq + Add<Output = Self> + Default {
     fn key_length() -> usize;
     fn random<R: Rng + CryptoRng>(rng: &mut R) -> Self;
+
+    /// The number of uniformly random bytes that [from_uniform_bytes](#method.from_uniform_bytes) takes
+    fn uniform_length() -> usize {
+        Self::key_length()
+    }
+
+    /// Derive a key from [uniform_length](#method.uniform_length) uniformly random bytes, e.g. hash or KDF output. The
+    /// default uses `from_bytes`. Implementations whose `from_bytes` reduces its input modulo the group order, which
+    /// biases the result, should override both methods to reduce a wider input instead.
+    fn from_uniform_bytes(bytes: &[u8]) -> Result<Self, ByteArrayError> {
+        Self::from_bytes(bytes)
+    }
 }
 
 //----------------------------------------   Public Keys  ----------------------------------------//
@@ -65,8 +88,24 @@ pub trait PublicKey:
 
     fn key_length() -> usize;
 
+    /// Calculate _\sum k_i.P_i_ in constant time
     fn batch_mul(scalars: &[Self::K], points: &[Self]) -> Self;
 
+    /// Calculate _\sum k_i.P_i_ in variable time, which leaks the scalars through timing. Only use this when the
+    /// scalars are public, e.g. when verifying signatures or proofs. The default falls back to the constant-time
+    /// [batch_mul](#tymethod.batch_mul).
+    fn batch_mul_vartime(scalars: &[Self::K], points: &[Self]) -> Self {
+        Self::batch_mul(scalars, points)
+    }
+
+    /// Check that the key is a point on the curve in the prime-order subgroup. Protocols written against this trait
+    /// should validate keys received from other parties, since for curves with a cofactor a key with a small-order
+    /// component can leak bits of secret keys it is combined with. Implementations that can only represent valid
+    /// keys can keep the default, which accepts every key.
+    fn validate(&self) -> Result<(), PublicKeyValidationError> {
+        Ok(())
+    }
+
     fn random_keypair<R: Rng + CryptoRng>(rng: &mut R) -> (Self::K, Self) {
         let k = Self::K::random(rng);
         let pk = Self::from_secret_key(&k);
@@ -79,4 +118,11 @@ pub trait DiffieHellmanSharedSecret: ByteArray + Clone + PartialEq + Eq + Add<Ou
     type PK: PublicKey;
     /// Generate a shared secret from one party's private key and another party's public key
     fn shared_secret(k: &<Self::PK as PublicKey>::K, pk: &Self::PK) -> Self::PK;
+
+    /// Compare two shared secrets in constant time. Use this rather than `==` when one side was received from a peer,
+    /// e.g. when checking a key confirmation value. The default compares the byte representations, which is correct
+    /// for any implementation with a canonical encoding.
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.as_bytes().ct_eq(other.as_bytes())
+    }
 }
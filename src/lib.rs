@@ -8,7 +8,10 @@ extern crate lazy_static;
 pub mod macros;
 pub mod commitment;
 pub mod hash;
+pub mod hash_sig;
+pub mod key_manager;
 pub mod keys;
+pub mod merkle;
 pub mod musig;
 pub mod range_proof;
 pub mod signatures;
@@ -0,0 +1,85 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use digest::Digest;
+use std::marker::PhantomData;
+
+/// A Merkle node or leaf hash. Its length is the output size of the digest that produced it.
+pub type Hash = Vec<u8>;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Computes leaf and node hashes for a given application domain. Every hash commits to the length-prefixed domain,
+/// followed by a one-byte tag distinguishing leaves from internal nodes.
+#[derive(Clone, Debug)]
+pub struct MerkleHasher<D> {
+    domain: Vec<u8>,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest> MerkleHasher<D> {
+    pub fn new(domain: &[u8]) -> Self {
+        MerkleHasher {
+            domain: domain.to_vec(),
+            _digest: PhantomData,
+        }
+    }
+
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    /// A hasher that has absorbed the domain and `tag`, for building hashes of other node types
+    pub fn tagged(&self, tag: u8) -> D {
+        D::new()
+            .chain((self.domain.len() as u64).to_le_bytes())
+            .chain(&self.domain)
+            .chain([tag])
+    }
+
+    pub fn hash_leaf(&self, data: &[u8]) -> Hash {
+        self.tagged(LEAF_PREFIX).chain(data).finalize().to_vec()
+    }
+
+    pub fn hash_node(&self, left: &[u8], right: &[u8]) -> Hash {
+        self.tagged(NODE_PREFIX).chain(left).chain(right).finalize().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::blake2::Blake256;
+
+    #[test]
+    fn leaves_and_nodes_differ() {
+        let hasher = MerkleHasher::<Blake256>::new(b"test");
+        let a = hasher.hash_leaf(b"a");
+        let b = hasher.hash_leaf(b"b");
+        assert_eq!(a.len(), 32);
+        let concatenated = [a.clone(), b.clone()].concat();
+        assert_ne!(hasher.hash_node(&a, &b), hasher.hash_leaf(&concatenated));
+        assert_ne!(hasher.hash_node(&a, &b), hasher.hash_node(&b, &a));
+        assert_ne!(a, MerkleHasher::<Blake256>::new(b"other").hash_leaf(b"a"));
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::merkle::{Hash, MerkleError, MerkleHasher};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+
+/// Tag for the final hash over the bagged peaks. It must differ from the leaf and node tags in [MerkleHasher].
+const ROOT_PREFIX: u8 = 0x02;
+
+/// An append-only Merkle Mountain Range.
+///
+/// Nodes are stored by position in post-order, so a range of `n` leaves occupies `2n - popcount(n)` positions. The
+/// root commits to the size of the MMR and to its peaks, bagged from right to left.
+///
+/// Leaves can be pruned once they are spent or otherwise no longer need to be proven. Once both children of a node
+/// are pruned their hashes are discarded, since only the parent is needed to prove other leaves or to extend the
+/// range. Pruning never changes the root.
+///
+/// ```edition2018
+/// # use tari_crypto::merkle::MerkleMountainRange;
+/// # use tari_crypto::hash::blake2::Blake256;
+/// let mut mmr = MerkleMountainRange::<Blake256>::new(b"com.example.mmr");
+/// for leaf in &["Vimes", "Carrot", "Angua", "Nobby", "Colon"] {
+///     mmr.push(leaf.as_bytes());
+/// }
+/// let root = mmr.root();
+/// let proof = mmr.prove(2).unwrap();
+/// assert!(proof.verify::<Blake256>(b"com.example.mmr", &root, b"Angua"));
+/// mmr.prune(0).unwrap();
+/// assert_eq!(mmr.root(), root);
+/// assert!(mmr.prove(0).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MerkleMountainRange<D> {
+    hasher: MerkleHasher<D>,
+    /// Node hashes by position, or `None` where they have been pruned away
+    nodes: Vec<Option<Hash>>,
+    /// Whether each node's entire subtree has been pruned
+    pruned: Vec<bool>,
+    leaf_count: usize,
+}
+
+impl<D: Digest> MerkleMountainRange<D> {
+    pub fn new(domain: &[u8]) -> Self {
+        MerkleMountainRange {
+            hasher: MerkleHasher::new(domain),
+            nodes: Vec::new(),
+            pruned: Vec::new(),
+            leaf_count: 0,
+        }
+    }
+
+    pub fn hasher(&self) -> &MerkleHasher<D> {
+        &self.hasher
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count == 0
+    }
+
+    /// The number of nodes, including internal ones
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Append a leaf, returning its index
+    pub fn push(&mut self, leaf: &[u8]) -> usize {
+        let leaf_hash = self.hasher.hash_leaf(leaf);
+        self.push_leaf_hash(leaf_hash)
+    }
+
+    /// Append a leaf that has already been hashed with [MerkleHasher::hash_leaf], returning its index
+    pub fn push_leaf_hash(&mut self, leaf_hash: Hash) -> usize {
+        let mut hash = leaf_hash;
+        let mut height = 0;
+        loop {
+            let pos = self.nodes.len();
+            self.nodes.push(Some(hash));
+            self.pruned.push(false);
+            if node_height(pos + 1) <= height {
+                break;
+            }
+            // The next position is the parent of the node we just added
+            let left = sibling_of(pos, height);
+            hash = self.hasher.hash_node(
+                self.nodes[left].as_ref().expect("peaks are never pruned"),
+                self.nodes[pos].as_ref().expect("peaks are never pruned"),
+            );
+            height += 1;
+        }
+        self.leaf_count += 1;
+        self.leaf_count - 1
+    }
+
+    /// The hashes of the peaks, from left to right
+    pub fn peaks(&self) -> Vec<Hash> {
+        peak_positions(self.size())
+            .into_iter()
+            .map(|pos| self.nodes[pos].clone().expect("peaks are never pruned"))
+            .collect()
+    }
+
+    pub fn root(&self) -> Hash {
+        bag_peaks(&self.hasher, self.size(), &self.peaks())
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Option<&Hash> {
+        if index >= self.leaf_count {
+            return None;
+        }
+        self.nodes[leaf_position(index)].as_ref()
+    }
+
+    pub fn is_pruned(&self, index: usize) -> bool {
+        index < self.leaf_count && self.pruned[leaf_position(index)]
+    }
+
+    /// Mark a leaf as pruned and discard every hash that is no longer needed. Pruned leaves can't be proven.
+    pub fn prune(&mut self, index: usize) -> Result<(), MerkleError> {
+        if index >= self.leaf_count {
+            return Err(MerkleError::IndexOutOfBounds(index));
+        }
+        let peaks = peak_positions(self.size());
+        let mut pos = leaf_position(index);
+        let mut height = 0;
+        self.pruned[pos] = true;
+        while !peaks.contains(&pos) {
+            let (sibling, parent) = family(pos, height);
+            if !self.pruned[sibling] {
+                break;
+            }
+            self.pruned[parent] = true;
+            self.nodes[pos] = None;
+            self.nodes[sibling] = None;
+            pos = parent;
+            height += 1;
+        }
+        Ok(())
+    }
+
+    /// A membership proof for the leaf at `index` against the current root
+    pub fn prove(&self, index: usize) -> Result<MmrProof, MerkleError> {
+        if index >= self.leaf_count {
+            return Err(MerkleError::IndexOutOfBounds(index));
+        }
+        if self.is_pruned(index) {
+            return Err(MerkleError::LeafPruned(index));
+        }
+        let peaks = peak_positions(self.size());
+        let mut pos = leaf_position(index);
+        let mut height = 0;
+        let mut path = Vec::new();
+        while !peaks.contains(&pos) {
+            let (sibling, parent) = family(pos, height);
+            // A sibling of an unpruned node is never discarded, since their parent can't be fully pruned
+            path.push(self.nodes[sibling].clone().ok_or(MerkleError::LeafPruned(index))?);
+            pos = parent;
+            height += 1;
+        }
+        Ok(MmrProof {
+            mmr_size: self.size(),
+            leaf_index: index,
+            path,
+            peaks: self.peaks(),
+        })
+    }
+}
+
+/// Proof that a leaf is at `leaf_index` in an MMR with `mmr_size` nodes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MmrProof {
+    mmr_size: usize,
+    leaf_index: usize,
+    /// Sibling hashes from the leaf up to its peak
+    path: Vec<Hash>,
+    peaks: Vec<Hash>,
+}
+
+impl MmrProof {
+    pub fn mmr_size(&self) -> usize {
+        self.mmr_size
+    }
+
+    pub fn leaf_index(&self) -> usize {
+        self.leaf_index
+    }
+
+    pub fn verify<D: Digest>(&self, domain: &[u8], root: &[u8], leaf: &[u8]) -> bool {
+        let hasher = MerkleHasher::<D>::new(domain);
+        self.verify_leaf_hash(&hasher, root, &hasher.hash_leaf(leaf))
+    }
+
+    /// As [verify](MmrProof::verify), for a leaf that has already been hashed
+    pub fn verify_leaf_hash<D: Digest>(&self, hasher: &MerkleHasher<D>, root: &[u8], leaf_hash: &[u8]) -> bool {
+        // Check the index against the leaf count before doing any position arithmetic with it
+        match leaf_count(self.mmr_size) {
+            Some(n) if self.leaf_index < n => {},
+            _ => return false,
+        }
+        let peaks = peak_positions(self.mmr_size);
+        if peaks.len() != self.peaks.len() {
+            return false;
+        }
+        let mut pos = leaf_position(self.leaf_index);
+        let mut hash = leaf_hash.to_vec();
+        let mut height = 0;
+        let mut path = self.path.iter();
+        while !peaks.contains(&pos) {
+            let sibling_hash = match path.next() {
+                Some(h) => h,
+                None => return false,
+            };
+            let (sibling, parent) = family(pos, height);
+            hash = if sibling < pos {
+                hasher.hash_node(sibling_hash, &hash)
+            } else {
+                hasher.hash_node(&hash, sibling_hash)
+            };
+            pos = parent;
+            height += 1;
+        }
+        if path.next().is_some() {
+            return false;
+        }
+        let peak_index = peaks.iter().position(|&p| p == pos).expect("loop ends on a peak");
+        self.peaks[peak_index] == hash && bag_peaks(hasher, self.mmr_size, &self.peaks) == root
+    }
+}
+
+/// Fold the peaks from the right, then bind the result to the MMR size
+fn bag_peaks<D: Digest>(hasher: &MerkleHasher<D>, size: usize, peaks: &[Hash]) -> Hash {
+    let bagged = peaks
+        .iter()
+        .rev()
+        .fold(None, |acc: Option<Hash>, peak| match acc {
+            None => Some(peak.clone()),
+            Some(right) => Some(hasher.hash_node(peak, &right)),
+        })
+        .unwrap_or_default();
+    hasher
+        .tagged(ROOT_PREFIX)
+        .chain((size as u64).to_le_bytes())
+        .chain(bagged)
+        .finalize()
+        .to_vec()
+}
+
+fn bit_length(n: usize) -> u32 {
+    (std::mem::size_of::<usize>() * 8) as u32 - n.leading_zeros()
+}
+
+/// The height of the node at `pos`, where leaves have height zero
+fn node_height(pos: usize) -> u32 {
+    // In 1-based positions, the leftmost node at each height is all ones; jump left until we reach one
+    let mut p = pos + 1;
+    while p & (p + 1) != 0 {
+        p -= (1 << (bit_length(p) - 1)) - 1;
+    }
+    bit_length(p) - 1
+}
+
+/// The position of the left sibling of the right child at `pos`
+fn sibling_of(pos: usize, height: u32) -> usize {
+    pos + 1 - (2 << height)
+}
+
+/// The sibling and parent positions of the node at `pos`
+fn family(pos: usize, height: u32) -> (usize, usize) {
+    if node_height(pos + 1) > height {
+        (sibling_of(pos, height), pos + 1)
+    } else {
+        let sibling = pos + (2 << height) - 1;
+        (sibling, sibling + 1)
+    }
+}
+
+fn leaf_position(index: usize) -> usize {
+    2 * index - index.count_ones() as usize
+}
+
+/// The number of leaves in an MMR with `size` nodes, or `None` if no MMR has that many nodes
+fn leaf_count(size: usize) -> Option<usize> {
+    // n leaves give 2n - popcount(n) nodes. That is strictly increasing in n, and popcount(n) is at most the bit
+    // length, so the only candidates are a few values just above size / 2.
+    (size / 2..=size / 2 + bit_length(size) as usize).find(|&n| {
+        n.checked_mul(2)
+            .map_or(false, |double| double - n.count_ones() as usize == size)
+    })
+}
+
+/// Positions of the peaks of an MMR with `size` nodes, from left to right
+fn peak_positions(size: usize) -> Vec<usize> {
+    let mut peaks = Vec::new();
+    if size == 0 {
+        return peaks;
+    }
+    let mut tree_size = usize::MAX >> size.leading_zeros();
+    let mut offset = 0;
+    let mut remaining = size;
+    while tree_size > 0 {
+        if remaining >= tree_size {
+            peaks.push(offset + tree_size - 1);
+            offset += tree_size;
+            remaining -= tree_size;
+        }
+        tree_size >>= 1;
+    }
+    peaks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::blake2::Blake256;
+
+    const DOMAIN: &[u8] = b"com.tari.test.mmr";
+
+    fn mmr(n: usize) -> MerkleMountainRange<Blake256> {
+        let mut mmr = MerkleMountainRange::new(DOMAIN);
+        for i in 0..n {
+            assert_eq!(mmr.push(format!("leaf {}", i).as_bytes()), i);
+        }
+        mmr
+    }
+
+    #[test]
+    fn positions() {
+        let heights: Vec<u32> = (0..11).map(node_height).collect();
+        assert_eq!(heights, vec![0, 0, 1, 0, 0, 1, 2, 0, 0, 1, 0]);
+        let leaves: Vec<usize> = (0..6).map(leaf_position).collect();
+        assert_eq!(leaves, vec![0, 1, 3, 4, 7, 8]);
+        assert_eq!(peak_positions(0), Vec::<usize>::new());
+        assert_eq!(peak_positions(1), vec![0]);
+        assert_eq!(peak_positions(7), vec![6]);
+        assert_eq!(peak_positions(11), vec![6, 9, 10]);
+        let sizes: Vec<usize> = (0..12).filter(|&s| leaf_count(s).is_some()).collect();
+        assert_eq!(sizes, vec![0, 1, 3, 4, 7, 8, 10, 11]);
+        assert_eq!(leaf_count(11), Some(7));
+        assert_eq!(leaf_count(usize::MAX), None);
+        assert_eq!(family(3, 0), (4, 5));
+        assert_eq!(family(5, 1), (2, 6));
+    }
+
+    #[test]
+    fn structure() {
+        let mmr = mmr(3);
+        let h = mmr.hasher();
+        let l: Vec<Hash> = (0..3).map(|i| h.hash_leaf(format!("leaf {}", i).as_bytes())).collect();
+        assert_eq!(mmr.size(), 4);
+        assert_eq!(mmr.peaks(), vec![h.hash_node(&l[0], &l[1]), l[2].clone()]);
+        assert_eq!(mmr.root(), bag_peaks(h, 4, &mmr.peaks()));
+        assert_ne!(MerkleMountainRange::<Blake256>::new(DOMAIN).root(), mmr.root());
+    }
+
+    #[test]
+    fn proofs() {
+        for n in 1..=20 {
+            let mmr = mmr(n);
+            let root = mmr.root();
+            for i in 0..n {
+                let leaf = format!("leaf {}", i);
+                let proof = mmr.prove(i).unwrap();
+                assert!(proof.verify::<Blake256>(DOMAIN, &root, leaf.as_bytes()));
+                assert!(!proof.verify::<Blake256>(DOMAIN, &root, b"not a leaf"));
+                assert!(!proof.verify::<Blake256>(b"wrong domain", &root, leaf.as_bytes()));
+            }
+            assert_eq!(mmr.prove(n), Err(MerkleError::IndexOutOfBounds(n)));
+        }
+    }
+
+    #[test]
+    fn old_proofs_fail_after_append() {
+        let mut mmr = mmr(5);
+        let proof = mmr.prove(1).unwrap();
+        mmr.push(b"new leaf");
+        assert!(!proof.verify::<Blake256>(DOMAIN, &mmr.root(), b"leaf 1"));
+        assert!(mmr.prove(1).unwrap().verify::<Blake256>(DOMAIN, &mmr.root(), b"leaf 1"));
+    }
+
+    #[test]
+    fn tampered_proofs() {
+        let mmr = mmr(11);
+        let root = mmr.root();
+        let proof = mmr.prove(4).unwrap();
+        let mut moved = proof.clone();
+        moved.leaf_index = 5;
+        assert!(!moved.verify::<Blake256>(DOMAIN, &root, b"leaf 4"));
+        let mut resized = proof.clone();
+        resized.mmr_size = 12;
+        assert!(!resized.verify::<Blake256>(DOMAIN, &root, b"leaf 4"));
+        // Sizes that no MMR has, and indices past the last leaf, are rejected without overflowing
+        let mut invalid = mmr(1).prove(0).unwrap();
+        invalid.mmr_size = 2;
+        assert!(!invalid.verify::<Blake256>(DOMAIN, &mmr(1).root(), b"leaf 0"));
+        let mut far = proof.clone();
+        far.leaf_index = usize::MAX;
+        assert!(!far.verify::<Blake256>(DOMAIN, &root, b"leaf 4"));
+        far.leaf_index = 7;
+        assert!(!far.verify::<Blake256>(DOMAIN, &root, b"leaf 4"));
+        let mut short = proof;
+        short.path.pop();
+        assert!(!short.verify::<Blake256>(DOMAIN, &root, b"leaf 4"));
+    }
+
+    #[test]
+    fn pruning() {
+        let mut mmr = mmr(8);
+        let root = mmr.root();
+        mmr.prune(0).unwrap();
+        assert!(mmr.is_pruned(0));
+        // Leaf 1 still needs leaf 0's hash for its proof
+        assert!(mmr.leaf_hash(0).is_some());
+        assert!(mmr.prove(1).unwrap().verify::<Blake256>(DOMAIN, &root, b"leaf 1"));
+        assert_eq!(mmr.prove(0), Err(MerkleError::LeafPruned(0)));
+        mmr.prune(1).unwrap();
+        assert!(mmr.leaf_hash(0).is_none());
+        assert!(mmr.leaf_hash(1).is_none());
+        assert!(mmr.nodes[2].is_some());
+        for i in 2..8 {
+            mmr.prune(i).unwrap();
+        }
+        // Only the single peak should remain
+        assert_eq!(mmr.nodes.iter().filter(|n| n.is_some()).count(), 1);
+        assert_eq!(mmr.root(), root);
+        // The range can still be extended and proven
+        mmr.push(b"leaf 8");
+        assert!(mmr.prove(8).unwrap().verify::<Blake256>(DOMAIN, &mmr.root(), b"leaf 8"));
+        assert_eq!(mmr.prune(9), Err(MerkleError::IndexOutOfBounds(9)));
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Merkle trees and related accumulators with domain-separated hashing.
+//!
+//! Leaves and internal nodes are hashed with distinct prefixes, so a leaf can never be passed off as an internal node
+//! (or vice versa) to forge a proof. Every structure also takes an application domain, which is absorbed into every
+//! hash; trees built for different purposes over the same data have unrelated roots. All structures are generic over
+//! any [Digest](digest::Digest), such as [Blake256](crate::hash::blake2::Blake256) or SHA-256.
+
+mod error;
+mod hasher;
+mod mmr;
+mod sparse;
+mod tree;
+
+pub use error::MerkleError;
+pub use hasher::{Hash, MerkleHasher};
+pub use mmr::{MerkleMountainRange, MmrProof};
+pub use sparse::{SparseMerkleProof, SparseMerkleTree, SMT_KEY_LENGTH};
+pub use tree::{MerkleProof, MerkleTree};
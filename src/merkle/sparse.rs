@@ -0,0 +1,346 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::merkle::{Hash, MerkleHasher};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const SMT_KEY_LENGTH: usize = 32;
+const KEY_BITS: usize = SMT_KEY_LENGTH * 8;
+
+/// Tags for the empty subtree placeholder and for value hashes. They must differ from the leaf and node tags in
+/// [MerkleHasher] and from the tree and MMR root tags.
+const EMPTY_PREFIX: u8 = 0x03;
+const VALUE_PREFIX: u8 = 0x04;
+
+/// A sparse Merkle tree mapping 256-bit keys to values, supporting proofs of both inclusion and non-inclusion.
+///
+/// Conceptually every one of the 2^256 keys has a slot at depth 256. To keep hashing proportional to the number of
+/// entries, any subtree holding a single entry is represented by that entry's leaf hash, and empty subtrees by a
+/// fixed placeholder, so an entry sits only as deep as needed to separate it from its neighbours. The tree commits
+/// to hashes of the values; callers keep the values themselves.
+///
+/// ```edition2018
+/// # use tari_crypto::merkle::SparseMerkleTree;
+/// # use tari_crypto::hash::blake2::Blake256;
+/// let mut tree = SparseMerkleTree::<Blake256>::new(b"com.example.state");
+/// tree.insert([1u8; 32], b"Granny Weatherwax");
+/// tree.insert([2u8; 32], b"Nanny Ogg");
+/// let root = tree.root();
+/// let proof = tree.prove(&[1u8; 32]);
+/// assert!(proof.verify_inclusion::<Blake256>(b"com.example.state", &root, &[1u8; 32], b"Granny Weatherwax"));
+/// let proof = tree.prove(&[3u8; 32]);
+/// assert!(proof.verify_exclusion::<Blake256>(b"com.example.state", &root, &[3u8; 32]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<D> {
+    hasher: MerkleHasher<D>,
+    /// Value hashes by key
+    entries: BTreeMap<[u8; SMT_KEY_LENGTH], Hash>,
+}
+
+impl<D: Digest> SparseMerkleTree<D> {
+    pub fn new(domain: &[u8]) -> Self {
+        SparseMerkleTree {
+            hasher: MerkleHasher::new(domain),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn hasher(&self) -> &MerkleHasher<D> {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Set the value for `key`, returning true if this replaced an existing value
+    pub fn insert(&mut self, key: [u8; SMT_KEY_LENGTH], value: &[u8]) -> bool {
+        let value_hash = value_hash(&self.hasher, value);
+        self.entries.insert(key, value_hash).is_some()
+    }
+
+    /// Remove `key`, returning true if it was present
+    pub fn remove(&mut self, key: &[u8; SMT_KEY_LENGTH]) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    pub fn contains(&self, key: &[u8; SMT_KEY_LENGTH]) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn root(&self) -> Hash {
+        let leaves = self.leaves();
+        subtree_hash(&self.hasher, &leaves, 0)
+    }
+
+    /// A proof of the value at `key` if there is one, or of its absence otherwise
+    pub fn prove(&self, key: &[u8; SMT_KEY_LENGTH]) -> SparseMerkleProof {
+        let leaves = self.leaves();
+        let mut subtree = &leaves[..];
+        let mut siblings = Vec::new();
+        let mut depth = 0;
+        while subtree.len() > 1 {
+            let split = subtree.partition_point(|(k, _)| bit(k, depth) == 0);
+            let (left, right) = subtree.split_at(split);
+            let (ours, theirs) = if bit(key, depth) == 0 {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            siblings.push(subtree_hash(&self.hasher, theirs, depth + 1));
+            subtree = ours;
+            depth += 1;
+        }
+        SparseMerkleProof {
+            siblings,
+            leaf: subtree.first().map(|(k, _)| (*k, self.entries[k].clone())),
+        }
+    }
+
+    /// Entries in key order, with their leaf hashes
+    fn leaves(&self) -> Vec<([u8; SMT_KEY_LENGTH], Hash)> {
+        self.entries
+            .iter()
+            .map(|(k, v)| (*k, leaf_hash(&self.hasher, k, v)))
+            .collect()
+    }
+}
+
+/// A path from the root towards a key's slot. `siblings` run from the root downwards, and `leaf` is the key and
+/// value hash of the single entry where the path ends, or `None` if it ends in an empty subtree.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseMerkleProof {
+    siblings: Vec<Hash>,
+    leaf: Option<([u8; SMT_KEY_LENGTH], Hash)>,
+}
+
+impl SparseMerkleProof {
+    pub fn siblings(&self) -> &[Hash] {
+        &self.siblings
+    }
+
+    /// Check that `root` maps `key` to `value`
+    pub fn verify_inclusion<D: Digest>(
+        &self,
+        domain: &[u8],
+        root: &[u8],
+        key: &[u8; SMT_KEY_LENGTH],
+        value: &[u8],
+    ) -> bool {
+        let hasher = MerkleHasher::<D>::new(domain);
+        match &self.leaf {
+            Some((k, v)) if k == key && *v == value_hash(&hasher, value) => {
+                self.compute_root(&hasher, key, leaf_hash(&hasher, k, v)) == Some(root.to_vec())
+            },
+            _ => false,
+        }
+    }
+
+    /// Check that `root` has no value for `key`
+    pub fn verify_exclusion<D: Digest>(&self, domain: &[u8], root: &[u8], key: &[u8; SMT_KEY_LENGTH]) -> bool {
+        if self.siblings.len() > KEY_BITS {
+            return false;
+        }
+        let hasher = MerkleHasher::<D>::new(domain);
+        let end = match &self.leaf {
+            None => empty_hash(&hasher),
+            // Another entry can only occupy this position if it shares the path so far
+            Some((k, v)) if k != key && (0..self.siblings.len()).all(|d| bit(k, d) == bit(key, d)) => {
+                leaf_hash(&hasher, k, v)
+            },
+            Some(_) => return false,
+        };
+        self.compute_root(&hasher, key, end) == Some(root.to_vec())
+    }
+
+    fn compute_root<D: Digest>(&self, hasher: &MerkleHasher<D>, key: &[u8; SMT_KEY_LENGTH], end: Hash) -> Option<Hash> {
+        if self.siblings.len() > KEY_BITS {
+            return None;
+        }
+        let root = self
+            .siblings
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(end, |hash, (depth, sibling)| {
+                if bit(key, depth) == 0 {
+                    hasher.hash_node(&hash, sibling)
+                } else {
+                    hasher.hash_node(sibling, &hash)
+                }
+            });
+        Some(root)
+    }
+}
+
+/// The hash of the subtree at `depth` holding `leaves`, which all share their first `depth` bits
+fn subtree_hash<D: Digest>(hasher: &MerkleHasher<D>, leaves: &[([u8; SMT_KEY_LENGTH], Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => empty_hash(hasher),
+        [(_, leaf)] => leaf.clone(),
+        _ => {
+            let split = leaves.partition_point(|(k, _)| bit(k, depth) == 0);
+            let (left, right) = leaves.split_at(split);
+            hasher.hash_node(
+                &subtree_hash(hasher, left, depth + 1),
+                &subtree_hash(hasher, right, depth + 1),
+            )
+        },
+    }
+}
+
+fn empty_hash<D: Digest>(hasher: &MerkleHasher<D>) -> Hash {
+    hasher.tagged(EMPTY_PREFIX).finalize().to_vec()
+}
+
+fn value_hash<D: Digest>(hasher: &MerkleHasher<D>, value: &[u8]) -> Hash {
+    hasher.tagged(VALUE_PREFIX).chain(value).finalize().to_vec()
+}
+
+fn leaf_hash<D: Digest>(hasher: &MerkleHasher<D>, key: &[u8; SMT_KEY_LENGTH], value_hash: &[u8]) -> Hash {
+    hasher.hash_leaf(&[&key[..], value_hash].concat())
+}
+
+/// Bit `depth` of `key`, counting from the most significant bit of the first byte
+fn bit(key: &[u8; SMT_KEY_LENGTH], depth: usize) -> u8 {
+    (key[depth / 8] >> (7 - depth % 8)) & 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::blake2::Blake256;
+    use rand::{Rng, RngCore};
+
+    const DOMAIN: &[u8] = b"com.tari.test.smt";
+
+    fn key(n: u8) -> [u8; SMT_KEY_LENGTH] {
+        let mut k = [0u8; SMT_KEY_LENGTH];
+        k[0] = n;
+        k
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        let root = tree.root();
+        assert_eq!(root, empty_hash(tree.hasher()));
+        let proof = tree.prove(&key(1));
+        assert!(proof.siblings().is_empty());
+        assert!(proof.verify_exclusion::<Blake256>(DOMAIN, &root, &key(1)));
+        assert!(!proof.verify_inclusion::<Blake256>(DOMAIN, &root, &key(1), b""));
+    }
+
+    #[test]
+    fn structure() {
+        // 0x00.. and 0x40.. first differ at bit 1, so they sit at depth 2 under an empty right subtree
+        let mut tree = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        tree.insert(key(0x00), b"a");
+        tree.insert(key(0x40), b"b");
+        let h = tree.hasher();
+        let a = leaf_hash(h, &key(0x00), &value_hash(h, b"a"));
+        let b = leaf_hash(h, &key(0x40), &value_hash(h, b"b"));
+        assert_eq!(tree.root(), h.hash_node(&h.hash_node(&a, &b), &empty_hash(h)));
+        // Insertion order doesn't matter
+        let mut other = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        other.insert(key(0x40), b"b");
+        other.insert(key(0x00), b"a");
+        assert_eq!(tree.root(), other.root());
+    }
+
+    #[test]
+    fn proofs() {
+        let mut rng = rand::thread_rng();
+        let mut tree = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        let mut keys = Vec::new();
+        for i in 0..50u32 {
+            let mut k = [0u8; SMT_KEY_LENGTH];
+            rng.fill_bytes(&mut k);
+            // Force some long shared prefixes
+            if i % 5 == 0 {
+                k[..30].copy_from_slice(&[7u8; 30]);
+            }
+            assert!(!tree.insert(k, &i.to_le_bytes()));
+            keys.push(k);
+        }
+        let root = tree.root();
+        for (i, k) in keys.iter().enumerate() {
+            let value = (i as u32).to_le_bytes();
+            let proof = tree.prove(k);
+            assert!(proof.verify_inclusion::<Blake256>(DOMAIN, &root, k, &value));
+            assert!(!proof.verify_inclusion::<Blake256>(DOMAIN, &root, k, b"wrong"));
+            assert!(!proof.verify_inclusion::<Blake256>(b"wrong domain", &root, k, &value));
+            assert!(!proof.verify_exclusion::<Blake256>(DOMAIN, &root, k));
+        }
+        for _ in 0..50 {
+            let mut k = [0u8; SMT_KEY_LENGTH];
+            rng.fill_bytes(&mut k);
+            if rng.gen() {
+                k[..30].copy_from_slice(&[7u8; 30]);
+            }
+            if tree.contains(&k) {
+                continue;
+            }
+            let proof = tree.prove(&k);
+            assert!(proof.verify_exclusion::<Blake256>(DOMAIN, &root, &k));
+            assert!(!proof.verify_inclusion::<Blake256>(DOMAIN, &root, &k, b""));
+        }
+    }
+
+    #[test]
+    fn updates_and_removal() {
+        let mut tree = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        tree.insert(key(1), b"Esk");
+        let root_1 = tree.root();
+        tree.insert(key(2), b"Simon");
+        let root_2 = tree.root();
+        assert!(tree.insert(key(2), b"Simon the wizard"));
+        assert_ne!(tree.root(), root_2);
+        assert!(tree.remove(&key(2)));
+        assert!(!tree.remove(&key(2)));
+        assert_eq!(tree.root(), root_1);
+        // Old proofs don't verify against the new state
+        let proof = tree.prove(&key(1));
+        assert!(!proof.verify_inclusion::<Blake256>(DOMAIN, &root_2, &key(1), b"Esk"));
+        assert!(proof.verify_inclusion::<Blake256>(DOMAIN, &root_1, &key(1), b"Esk"));
+    }
+
+    #[test]
+    fn forged_exclusion() {
+        let mut tree = SparseMerkleTree::<Blake256>::new(DOMAIN);
+        tree.insert(key(0x00), b"a");
+        tree.insert(key(0x80), b"b");
+        let root = tree.root();
+        // The proof for 0x80 ends at its own leaf, so it can't show 0x80 is absent
+        let proof = tree.prove(&key(0x80));
+        assert!(!proof.verify_exclusion::<Blake256>(DOMAIN, &root, &key(0x80)));
+        // Nor can it be replayed for an unrelated key whose path differs
+        assert!(!proof.verify_exclusion::<Blake256>(DOMAIN, &root, &key(0x01)));
+    }
+}
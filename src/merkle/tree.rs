@@ -0,0 +1,294 @@
+// Copyright 2020 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::merkle::{Hash, MerkleError, MerkleHasher};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+
+/// Tag for the final hash binding the leaf count to the top node. It must differ from the leaf and node tags in
+/// [MerkleHasher] and from the root tags of the other structures in this module.
+const ROOT_PREFIX: u8 = 0x05;
+
+/// A binary Merkle tree over a fixed list of leaves.
+///
+/// When a level has an odd number of nodes, the last node is promoted to the next level unchanged rather than being
+/// paired with a copy of itself. Duplicating it would let two different leaf lists share a root.
+///
+/// The root commits to the number of leaves as well as to the top node. Without it, a proof could be relabelled
+/// with a smaller leaf count and a different index (e.g. the last leaf of `[a, b, c]` passed off as the second leaf
+/// of a two-leaf tree) and still verify, so the index of a verified proof is only meaningful because of this.
+///
+/// ```edition2018
+/// # use tari_crypto::merkle::MerkleTree;
+/// # use tari_crypto::hash::blake2::Blake256;
+/// let leaves = vec![b"Rincewind".to_vec(), b"Twoflower".to_vec(), b"The Luggage".to_vec()];
+/// let tree = MerkleTree::<Blake256>::new(b"com.example.merkle", &leaves).unwrap();
+/// let proof = tree.prove(1).unwrap();
+/// assert!(proof.verify::<Blake256>(b"com.example.merkle", tree.root(), b"Twoflower"));
+/// assert!(!proof.verify::<Blake256>(b"com.example.merkle", tree.root(), b"Rincewind"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MerkleTree<D> {
+    hasher: MerkleHasher<D>,
+    /// `levels[0]` holds the leaf hashes and the last level holds only the top node
+    levels: Vec<Vec<Hash>>,
+    root: Hash,
+}
+
+impl<D: Digest> MerkleTree<D> {
+    pub fn new<T: AsRef<[u8]>>(domain: &[u8], leaves: &[T]) -> Result<Self, MerkleError> {
+        let hasher = MerkleHasher::new(domain);
+        let leaf_hashes = leaves.iter().map(|leaf| hasher.hash_leaf(leaf.as_ref())).collect();
+        Self::from_leaf_hashes(hasher, leaf_hashes)
+    }
+
+    /// Build a tree from leaves that have already been hashed with [MerkleHasher::hash_leaf]
+    pub fn from_leaf_hashes(hasher: MerkleHasher<D>, leaf_hashes: Vec<Hash>) -> Result<Self, MerkleError> {
+        if leaf_hashes.is_empty() {
+            return Err(MerkleError::EmptyTree);
+        }
+        let mut levels = vec![leaf_hashes];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hasher.hash_node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        let root = bind_root(&hasher, levels[0].len(), &levels[levels.len() - 1][0]);
+        Ok(MerkleTree { hasher, levels, root })
+    }
+
+    pub fn root(&self) -> &Hash {
+        &self.root
+    }
+
+    /// The number of leaves
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Always false, since empty trees can't be constructed
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn hasher(&self) -> &MerkleHasher<D> {
+        &self.hasher
+    }
+
+    /// An inclusion proof for the leaf at `index`
+    pub fn prove(&self, index: usize) -> Result<MerkleProof, MerkleError> {
+        if index >= self.len() {
+            return Err(MerkleError::IndexOutOfBounds(index));
+        }
+        let mut siblings = Vec::with_capacity(self.levels.len() - 1);
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(sibling.clone());
+            }
+            i /= 2;
+        }
+        Ok(MerkleProof {
+            index,
+            leaf_count: self.len(),
+            siblings,
+        })
+    }
+}
+
+/// Proof that a leaf sits at `index` in a tree of `leaf_count` leaves. Levels where the leaf's ancestor was promoted
+/// without a sibling contribute nothing to `siblings`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    index: usize,
+    leaf_count: usize,
+    siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn siblings(&self) -> &[Hash] {
+        &self.siblings
+    }
+
+    /// Check that `leaf` is committed to by `root` at this proof's index
+    pub fn verify<D: Digest>(&self, domain: &[u8], root: &[u8], leaf: &[u8]) -> bool {
+        let hasher = MerkleHasher::<D>::new(domain);
+        self.verify_leaf_hash(&hasher, root, &hasher.hash_leaf(leaf))
+    }
+
+    /// As [verify](MerkleProof::verify), for a leaf that has already been hashed
+    pub fn verify_leaf_hash<D: Digest>(&self, hasher: &MerkleHasher<D>, root: &[u8], leaf_hash: &[u8]) -> bool {
+        match self.compute_root(hasher, leaf_hash) {
+            Some(computed) => computed == root,
+            None => false,
+        }
+    }
+
+    /// The root implied by this proof for the given leaf hash, or `None` if the proof is malformed
+    fn compute_root<D: Digest>(&self, hasher: &MerkleHasher<D>, leaf_hash: &[u8]) -> Option<Hash> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = leaf_hash.to_vec();
+        let mut i = self.index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            if (i ^ 1) < width {
+                let sibling = siblings.next()?;
+                hash = if i % 2 == 0 {
+                    hasher.hash_node(&hash, sibling)
+                } else {
+                    hasher.hash_node(sibling, &hash)
+                };
+            }
+            i /= 2;
+            width = (width + 1) / 2;
+        }
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(bind_root(hasher, self.leaf_count, &hash)),
+        }
+    }
+}
+
+fn bind_root<D: Digest>(hasher: &MerkleHasher<D>, leaf_count: usize, top: &[u8]) -> Hash {
+    hasher
+        .tagged(ROOT_PREFIX)
+        .chain((leaf_count as u64).to_le_bytes())
+        .chain(top)
+        .finalize()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::blake2::Blake256;
+    use sha2::Sha256;
+
+    const DOMAIN: &[u8] = b"com.tari.test.merkle";
+
+    fn leaves(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("leaf {}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn single_leaf() {
+        let tree = MerkleTree::<Blake256>::new(DOMAIN, &leaves(1)).unwrap();
+        let h = tree.hasher();
+        assert_eq!(tree.root(), &bind_root(h, 1, &h.hash_leaf(b"leaf 0")));
+        let proof = tree.prove(0).unwrap();
+        assert!(proof.siblings().is_empty());
+        assert!(proof.verify::<Blake256>(DOMAIN, tree.root(), b"leaf 0"));
+    }
+
+    #[test]
+    fn three_leaves() {
+        let tree = MerkleTree::<Sha256>::new(DOMAIN, &leaves(3)).unwrap();
+        let h = tree.hasher();
+        let expected = h.hash_node(
+            &h.hash_node(&h.hash_leaf(b"leaf 0"), &h.hash_leaf(b"leaf 1")),
+            &h.hash_leaf(b"leaf 2"),
+        );
+        assert_eq!(tree.root(), &bind_root(h, 3, &expected));
+    }
+
+    #[test]
+    fn proofs() {
+        for n in 1..=17 {
+            let data = leaves(n);
+            let tree = MerkleTree::<Blake256>::new(DOMAIN, &data).unwrap();
+            for (i, leaf) in data.iter().enumerate() {
+                let proof = tree.prove(i).unwrap();
+                assert!(proof.verify::<Blake256>(DOMAIN, tree.root(), leaf));
+                assert!(!proof.verify::<Blake256>(DOMAIN, tree.root(), b"not a leaf"));
+                assert!(!proof.verify::<Blake256>(b"wrong domain", tree.root(), leaf));
+                assert!(!proof.verify::<Sha256>(DOMAIN, tree.root(), leaf));
+            }
+            assert_eq!(tree.prove(n), Err(MerkleError::IndexOutOfBounds(n)));
+        }
+    }
+
+    #[test]
+    fn tampered_proofs() {
+        let data = leaves(6);
+        let tree = MerkleTree::<Blake256>::new(DOMAIN, &data).unwrap();
+        let proof = tree.prove(2).unwrap();
+        // Claiming a different position fails
+        let mut moved = proof.clone();
+        moved.index = 3;
+        assert!(!moved.verify::<Blake256>(DOMAIN, tree.root(), &data[2]));
+        // So does an extra or missing sibling
+        let mut extended = proof.clone();
+        extended.siblings.push(tree.root().clone());
+        assert!(!extended.verify::<Blake256>(DOMAIN, tree.root(), &data[2]));
+        let mut truncated = proof;
+        truncated.siblings.pop();
+        assert!(!truncated.verify::<Blake256>(DOMAIN, tree.root(), &data[2]));
+    }
+
+    #[test]
+    fn relabelled_proofs() {
+        // In [a, b, c], c is promoted and its only sibling is node(a, b). Claiming it is leaf 1 of a two-leaf tree
+        // gives the same top node, but not the same root.
+        let data = leaves(3);
+        let tree = MerkleTree::<Blake256>::new(DOMAIN, &data).unwrap();
+        let mut relabelled = tree.prove(2).unwrap();
+        relabelled.index = 1;
+        relabelled.leaf_count = 2;
+        assert!(!relabelled.verify::<Blake256>(DOMAIN, tree.root(), &data[2]));
+        let h = tree.hasher();
+        let two_leaf_top = h.hash_node(&relabelled.siblings[0], &h.hash_leaf(&data[2]));
+        assert_eq!(bind_root(h, 3, &two_leaf_top), *tree.root());
+    }
+
+    #[test]
+    fn no_duplicate_roots() {
+        // With last-node duplication, [a, b, c] and [a, b, c, c] would share a root
+        let mut data = leaves(3);
+        let tree = MerkleTree::<Blake256>::new(DOMAIN, &data).unwrap();
+        data.push(data[2].clone());
+        let padded = MerkleTree::<Blake256>::new(DOMAIN, &data).unwrap();
+        assert_ne!(tree.root(), padded.root());
+        let empty: Vec<Vec<u8>> = Vec::new();
+        assert_eq!(
+            MerkleTree::<Blake256>::new(DOMAIN, &empty).unwrap_err(),
+            MerkleError::EmptyTree
+        );
+    }
+}
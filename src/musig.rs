@@ -61,6 +61,12 @@ pub enum MuSigError {
     InvalidMessage,
     #[error("MuSig requires a hash function with a 32 byte digest")]
     IncompatibleHashFunction,
+    #[error("A public nonce has already been provided for this participant")]
+    DuplicateNonce,
+    #[error("A partial signature has already been provided for this participant")]
+    DuplicatePartialSignature,
+    #[error("The secret nonce does not match the public nonce provided for this participant")]
+    InvalidSecretNonce,
 }
 
 //----------------------------------------------     Joint Key     ------------------------------------------------//
@@ -43,6 +43,10 @@ pub enum RangeProofError {
     InvalidRangeProof,
     #[error("Invalid range proof rewind, the rewind keys provided must be invalid")]
     InvalidRewind,
+    #[error("Aggregated range proofs must cover a power-of-two number of commitments, within the service's limit")]
+    InvalidAggregationSize,
+    #[error("The value is smaller than the promised minimum")]
+    ValueBelowMinimum,
 }
 
 pub trait RangeProofService {
@@ -0,0 +1,246 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A builder for summing Pedersen commitments, as done when checking that a transaction balances.
+//!
+//! [CommitmentSum] adds up commitments and, where they are known, the values and blinding factors behind them. The
+//! value column is checked for `u64` overflow as it goes, since a sum that silently wraps is a balance that doesn't
+//! add up. The aggregate value and blinding factor are only reported if every commitment in the sum was supplied with
+//! them.
+
+use crate::{
+    commitment::HomomorphicCommitmentFactory,
+    ristretto::{
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        RistrettoSecretKey,
+    },
+};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum CommitmentSumError {
+    #[error("The sum of the committed values overflows a u64")]
+    ValueOverflow,
+}
+
+/// A running sum of Pedersen commitments, with optional value and blinding factor columns.
+///
+/// ```edition2018
+/// # use tari_crypto::commitment::HomomorphicCommitmentFactory;
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::ristretto::{commitment_sum::CommitmentSum, pedersen::PedersenCommitmentFactory};
+/// # use tari_crypto::ristretto::RistrettoSecretKey;
+/// let factory = PedersenCommitmentFactory::default();
+/// let mut rng = rand::thread_rng();
+/// let (k1, k2) = (RistrettoSecretKey::random(&mut rng), RistrettoSecretKey::random(&mut rng));
+/// let mut sum = CommitmentSum::new();
+/// sum.add_with_opening(&factory.commit_value(&k1, 10), &k1, 10)?
+///     .add_with_opening(&factory.commit_value(&k2, 32), &k2, 32)?;
+/// assert_eq!(sum.value(), Some(42));
+/// assert!(sum.verify_opening(&factory));
+/// # Ok::<(), tari_crypto::ristretto::commitment_sum::CommitmentSumError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct CommitmentSum {
+    commitment: PedersenCommitment,
+    count: usize,
+    value: u64,
+    values_complete: bool,
+    blinding: RistrettoSecretKey,
+    blindings_complete: bool,
+}
+
+impl CommitmentSum {
+    /// An empty sum, i.e. the identity, with a known value and blinding factor of zero
+    pub fn new() -> Self {
+        CommitmentSum {
+            commitment: PedersenCommitment::default(),
+            count: 0,
+            value: 0,
+            values_complete: true,
+            blinding: RistrettoSecretKey::default(),
+            blindings_complete: true,
+        }
+    }
+
+    /// Add a commitment whose opening is unknown. The sum will no longer report a value or blinding factor.
+    pub fn add(&mut self, commitment: &PedersenCommitment) -> &mut Self {
+        self.add_commitment(commitment);
+        self.values_complete = false;
+        self.blindings_complete = false;
+        self
+    }
+
+    /// Add a commitment along with the value it commits to. The blinding factor column becomes unknown. Fails,
+    /// leaving the sum untouched, if the value total would overflow.
+    pub fn add_with_value(
+        &mut self,
+        commitment: &PedersenCommitment,
+        value: u64,
+    ) -> Result<&mut Self, CommitmentSumError> {
+        self.add_value(value)?;
+        self.add_commitment(commitment);
+        self.blindings_complete = false;
+        Ok(self)
+    }
+
+    /// Add a commitment along with its full opening. Fails, leaving the sum untouched, if the value total would
+    /// overflow. The opening is not checked against the commitment here; use
+    /// [verify_opening](#method.verify_opening) on the final sum.
+    pub fn add_with_opening(
+        &mut self,
+        commitment: &PedersenCommitment,
+        blinding: &RistrettoSecretKey,
+        value: u64,
+    ) -> Result<&mut Self, CommitmentSumError> {
+        self.add_value(value)?;
+        self.add_commitment(commitment);
+        self.blinding = &self.blinding + blinding;
+        Ok(self)
+    }
+
+    /// The sum of the commitments added so far
+    pub fn commitment(&self) -> &PedersenCommitment {
+        &self.commitment
+    }
+
+    /// The total value, if every commitment was added with its value
+    pub fn value(&self) -> Option<u64> {
+        if self.values_complete {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+
+    /// The total blinding factor, if every commitment was added with its full opening
+    pub fn blinding_factor(&self) -> Option<&RistrettoSecretKey> {
+        if self.blindings_complete && self.values_complete {
+            Some(&self.blinding)
+        } else {
+            None
+        }
+    }
+
+    /// The number of commitments in the sum
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Check that the aggregate value and blinding factor open the aggregate commitment. Returns false if either
+    /// is unknown.
+    pub fn verify_opening(&self, factory: &PedersenCommitmentFactory) -> bool {
+        match (self.blinding_factor(), self.value()) {
+            (Some(k), Some(v)) => factory
+                .open_ct(k, &RistrettoSecretKey::from(v), &self.commitment)
+                .into(),
+            _ => false,
+        }
+    }
+
+    fn add_value(&mut self, value: u64) -> Result<(), CommitmentSumError> {
+        self.value = self.value.checked_add(value).ok_or(CommitmentSumError::ValueOverflow)?;
+        Ok(())
+    }
+
+    fn add_commitment(&mut self, commitment: &PedersenCommitment) {
+        self.commitment = &self.commitment + commitment;
+        self.count += 1;
+    }
+}
+
+impl Default for CommitmentSum {
+    fn default() -> Self {
+        CommitmentSum::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::SecretKey;
+
+    #[test]
+    fn the_last_continent() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let keys = (0..3).map(|_| RistrettoSecretKey::random(&mut rng)).collect::<Vec<_>>();
+        let values = [5u64, 15, 80];
+        let commitments = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(k, v)| factory.commit_value(k, *v))
+            .collect::<Vec<_>>();
+        let mut sum = CommitmentSum::new();
+        assert!(sum.is_empty());
+        assert!(sum.verify_opening(&factory));
+        for ((c, k), v) in commitments.iter().zip(keys.iter()).zip(values.iter()) {
+            sum.add_with_opening(c, k, *v).unwrap();
+        }
+        assert_eq!(sum.len(), 3);
+        assert_eq!(sum.value(), Some(100));
+        let k_total = keys.iter().sum::<RistrettoSecretKey>();
+        assert_eq!(sum.blinding_factor(), Some(&k_total));
+        assert_eq!(sum.commitment(), &commitments.iter().sum::<PedersenCommitment>());
+        assert!(sum.verify_opening(&factory));
+
+        // Values only: the blinding factor is no longer known
+        let mut sum = CommitmentSum::new();
+        sum.add_with_value(&commitments[0], 5)
+            .unwrap()
+            .add_with_value(&commitments[1], 15)
+            .unwrap();
+        assert_eq!(sum.value(), Some(20));
+        assert_eq!(sum.blinding_factor(), None);
+        assert!(!sum.verify_opening(&factory));
+
+        // Commitments only
+        let mut sum = CommitmentSum::new();
+        sum.add_with_opening(&commitments[0], &keys[0], 5).unwrap();
+        sum.add(&commitments[1]);
+        assert_eq!(sum.value(), None);
+        assert_eq!(sum.blinding_factor(), None);
+        assert_eq!(sum.commitment(), &(&commitments[0] + &commitments[1]));
+    }
+
+    #[test]
+    fn overflow_is_detected() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let big = factory.commit_value(&k, u64::MAX);
+        let mut sum = CommitmentSum::new();
+        sum.add_with_opening(&big, &k, u64::MAX).unwrap();
+        assert_eq!(
+            sum.add_with_value(&factory.commit_value(&k, 1), 1).err(),
+            Some(CommitmentSumError::ValueOverflow)
+        );
+        // The failed addition left the sum as it was
+        assert_eq!(sum.len(), 1);
+        assert_eq!(sum.value(), Some(u64::MAX));
+        assert!(sum.verify_opening(&factory));
+    }
+}
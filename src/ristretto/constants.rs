@@ -21,6 +21,9 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use sha2::{Digest, Sha512};
+
+const DERIVE_GENERATORS_LABEL: &[u8] = b"com.tari.ristretto.derive_generators";
 
 /// These points on the Ristretto curve have been created by sequentially hashing the Generator point with SHA512 and
 /// using the byte string representation of the hash as input into the `from_uniform_bytes` constructor in
@@ -80,9 +83,30 @@ lazy_static! {
 
 pub const RISTRETTO_PEDERSEN_H: CompressedRistretto = RISTRETTO_NUMS_POINTS_COMPRESSED[0];
 
+/// Derive `n` independent NUMS points for the given domain `label`.
+///
+/// Point _i_ is the hash to curve of _H(prefix || len(label) || label || i)_ using SHA-512 and
+/// `RistrettoPoint::from_hash`, so nobody knows the discrete log of any point with respect to any other, or to the
+/// generator. Different labels give unrelated sets of points, and a set for a given label can be extended without
+/// changing the points already derived. Use this when [RISTRETTO_NUMS_POINTS] is too small, or when a protocol needs
+/// generators of its own.
+pub fn derive_generators(label: &[u8], n: usize) -> Vec<RistrettoPoint> {
+    (0..n as u64).map(|i| derive_generator(label, i)).collect()
+}
+
+/// Point _i_ of [derive_generators] for `label`, without deriving the points before it
+pub(crate) fn derive_generator(label: &[u8], i: u64) -> RistrettoPoint {
+    let hasher = Sha512::new()
+        .chain(DERIVE_GENERATORS_LABEL)
+        .chain((label.len() as u64).to_le_bytes())
+        .chain(label)
+        .chain(i.to_le_bytes());
+    RistrettoPoint::from_hash(hasher)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::ristretto::constants::{RISTRETTO_NUMS_POINTS, RISTRETTO_NUMS_POINTS_COMPRESSED};
+    use crate::ristretto::constants::{derive_generators, RISTRETTO_NUMS_POINTS, RISTRETTO_NUMS_POINTS_COMPRESSED};
     use curve25519_dalek::{
         constants::RISTRETTO_BASEPOINT_POINT,
         ristretto::{CompressedRistretto, RistrettoPoint},
@@ -119,4 +143,19 @@ mod test {
             assert_eq!(v_arr.1[i], RISTRETTO_NUMS_POINTS_COMPRESSED[i]);
         }
     }
+
+    #[test]
+    fn derived_generators() {
+        let points = derive_generators(b"Interesting Times", 20);
+        assert_eq!(points.len(), 20);
+        for (i, p) in points.iter().enumerate() {
+            assert_ne!(*p, RISTRETTO_BASEPOINT_POINT);
+            assert!(!RISTRETTO_NUMS_POINTS.contains(p));
+            assert!(points[i + 1..].iter().all(|q| q != p));
+        }
+        // Derivation is deterministic, extensible and domain-separated
+        assert_eq!(derive_generators(b"Interesting Times", 5), points[..5].to_vec());
+        assert!(derive_generators(b"Eric", 5).iter().all(|p| !points.contains(p)));
+        assert!(derive_generators(b"", 0).is_empty());
+    }
 }
@@ -43,35 +43,183 @@ use bulletproofs::{
     PedersenGens,
     RangeProof as DalekProof,
 };
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 
 /// A wrapper around the Dalek library implementation of Bulletproof range proofs.
 pub struct DalekRangeProofService {
     range: usize,
+    max_aggregation: usize,
     pc_gens: PedersenGens,
     bp_gens: BulletproofGens,
 }
 
 const MASK: usize = 0b111_1000; // Mask for 8,16,32,64; the valid ranges on the Dalek library
+/// The number of bytes used to carry the promised minimum value at the front of a minimum value proof
+const MINIMUM_VALUE_LENGTH: usize = 8;
 
 impl DalekRangeProofService {
     /// Create a new RangeProofService. The Dalek library can only generate proofs for ranges between [0; 2^range),
     /// where valid range values are 8, 16, 32 and 64.
     pub fn new(range: usize, base: &PedersenCommitmentFactory) -> Result<DalekRangeProofService, RangeProofError> {
-        if range == 0 || (range | MASK != MASK) {
+        DalekRangeProofService::new_with_aggregation(range, base, 1)
+    }
+
+    /// Create a new RangeProofService that can also create and verify aggregated proofs for up to `max_aggregation`
+    /// commitments at once. `max_aggregation` must be a power of two. The generators grow linearly with it, so don't
+    /// make it larger than you need.
+    pub fn new_with_aggregation(
+        range: usize,
+        base: &PedersenCommitmentFactory,
+        max_aggregation: usize,
+    ) -> Result<DalekRangeProofService, RangeProofError> {
+        if range == 0 || (range | MASK != MASK) || !max_aggregation.is_power_of_two() {
             return Err(RangeProofError::InitializationError);
         }
         let pc_gens = PedersenGens {
             B_blinding: base.G,
             B: base.H,
         };
-        let bp_gens = BulletproofGens::new(64, 1);
+        let bp_gens = BulletproofGens::new(64, max_aggregation);
         Ok(DalekRangeProofService {
             range,
+            max_aggregation,
             pc_gens,
             bp_gens,
         })
     }
+
+    /// The largest number of commitments that a single aggregated proof can cover
+    pub fn max_aggregation(&self) -> usize {
+        self.max_aggregation
+    }
+
+    /// Construct a single proof that every `(key, value)` opening lies in range. Aggregated proofs are only
+    /// logarithmically larger than a single proof. The number of openings must be a power of two, and at most
+    /// [max_aggregation](#method.max_aggregation).
+    pub fn construct_aggregated_proof(
+        &self,
+        openings: &[(RistrettoSecretKey, u64)],
+    ) -> Result<Vec<u8>, RangeProofError> {
+        self.check_aggregation_size(openings.len())?;
+        let values = openings.iter().map(|(_, v)| *v).collect::<Vec<_>>();
+        let blindings = openings.iter().map(|(k, _)| k.0).collect::<Vec<_>>();
+        let mut pt = Transcript::new(b"tari");
+        let (proof, _) =
+            DalekProof::prove_multiple(&self.bp_gens, &self.pc_gens, &mut pt, &values, &blindings, self.range)
+                .map_err(|_| RangeProofError::ProofConstructionError)?;
+        Ok(proof.to_bytes())
+    }
+
+    /// Verify an aggregated proof against the commitments it was made for, in the same order
+    pub fn verify_aggregated(&self, proof: &[u8], commitments: &[PedersenCommitment]) -> bool {
+        let statement = match RangeProofStatement::new(commitments) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+        self.verify_statement(proof, &statement)
+    }
+
+    /// Verify a batch of (aggregated or single) proofs, each against its own statement. Returns true only if every
+    /// proof is valid. The Bulletproofs library has no multi-proof verification equation, so this is no faster than
+    /// verifying the proofs one by one, but it stops at the first failure.
+    pub fn verify_batch(&self, proofs: &[(&[u8], &RangeProofStatement)]) -> bool {
+        proofs
+            .iter()
+            .all(|(proof, statement)| self.verify_statement(proof, statement))
+    }
+
+    fn verify_statement(&self, proof: &[u8], statement: &RangeProofStatement) -> bool {
+        if statement.commitments.len() > self.max_aggregation {
+            return false;
+        }
+        let rp = match DalekProof::from_bytes(proof) {
+            Ok(rp) => rp,
+            Err(_) => return false,
+        };
+        let mut pt = Transcript::new(b"tari");
+        rp.verify_multiple(
+            &self.bp_gens,
+            &self.pc_gens,
+            &mut pt,
+            &statement.commitments,
+            self.range,
+        )
+        .is_ok()
+    }
+
+    /// Construct a proof that the committed `value` is at least `minimum`. The proof covers `value - minimum` in the
+    /// usual range, so it attests to `minimum <= value < minimum + 2^range`. The promised minimum travels with the
+    /// proof and is bound into the transcript, so a verifier doesn't need to know it in advance.
+    pub fn construct_proof_with_minimum(
+        &self,
+        key: &RistrettoSecretKey,
+        value: u64,
+        minimum: u64,
+    ) -> Result<Vec<u8>, RangeProofError> {
+        let shifted = value.checked_sub(minimum).ok_or(RangeProofError::ValueBelowMinimum)?;
+        let mut pt = Transcript::new(b"tari");
+        pt.append_u64(b"minimum_value", minimum);
+        let (proof, _) = DalekProof::prove_single(&self.bp_gens, &self.pc_gens, &mut pt, shifted, &key.0, self.range)
+            .map_err(|_| RangeProofError::ProofConstructionError)?;
+        let mut result = minimum.to_le_bytes().to_vec();
+        result.extend_from_slice(&proof.to_bytes());
+        Ok(result)
+    }
+
+    /// Verify a proof made with [construct_proof_with_minimum](#method.construct_proof_with_minimum) against
+    /// `commitment`. On success the promised minimum is returned; callers must still check that it satisfies their
+    /// own rules.
+    pub fn verify_with_minimum(&self, proof: &[u8], commitment: &PedersenCommitment) -> Result<u64, RangeProofError> {
+        if proof.len() < MINIMUM_VALUE_LENGTH {
+            return Err(RangeProofError::InvalidProof);
+        }
+        let (minimum, proof) = proof.split_at(MINIMUM_VALUE_LENGTH);
+        let mut buf = [0u8; MINIMUM_VALUE_LENGTH];
+        buf.copy_from_slice(minimum);
+        let minimum = u64::from_le_bytes(buf);
+        let rp = DalekProof::from_bytes(proof).map_err(|_| RangeProofError::InvalidProof)?;
+        let shifted = commitment.as_public_key().point - Scalar::from(minimum) * self.pc_gens.B;
+        let mut pt = Transcript::new(b"tari");
+        pt.append_u64(b"minimum_value", minimum);
+        rp.verify_single(&self.bp_gens, &self.pc_gens, &mut pt, &shifted.compress(), self.range)
+            .map_err(|_| RangeProofError::InvalidRangeProof)?;
+        Ok(minimum)
+    }
+
+    fn check_aggregation_size(&self, n: usize) -> Result<(), RangeProofError> {
+        if n.is_power_of_two() && n <= self.max_aggregation {
+            Ok(())
+        } else {
+            Err(RangeProofError::InvalidAggregationSize)
+        }
+    }
+}
+
+/// The commitments covered by a (possibly aggregated) range proof, in the order they were proven
+#[derive(Clone, Debug)]
+pub struct RangeProofStatement {
+    commitments: Vec<CompressedRistretto>,
+}
+
+impl RangeProofStatement {
+    /// Build a statement from commitments. There must be a power-of-two number of them.
+    pub fn new(commitments: &[PedersenCommitment]) -> Result<Self, RangeProofError> {
+        if !commitments.len().is_power_of_two() {
+            return Err(RangeProofError::InvalidAggregationSize);
+        }
+        let commitments = commitments.iter().map(|c| c.as_public_key().compressed).collect();
+        Ok(RangeProofStatement { commitments })
+    }
+
+    pub fn len(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// Always false, since a statement covers at least one commitment
+    pub fn is_empty(&self) -> bool {
+        self.commitments.is_empty()
+    }
 }
 
 impl RangeProofService for DalekRangeProofService {
@@ -220,7 +368,7 @@ mod test {
         keys::{PublicKey, SecretKey},
         range_proof::{RangeProofError, RangeProofService},
         ristretto::{
-            dalek_range_proof::DalekRangeProofService,
+            dalek_range_proof::{DalekRangeProofService, RangeProofStatement},
             pedersen::PedersenCommitmentFactory,
             RistrettoPublicKey,
             RistrettoSecretKey,
@@ -348,4 +496,96 @@ mod test {
             assert_eq!(prover.verify(&proof, &c), false);
         }
     }
+
+    #[test]
+    fn aggregated_proofs() {
+        let mut rng = thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let prover = DalekRangeProofService::new_with_aggregation(32, &factory, 4).unwrap();
+        assert_eq!(prover.max_aggregation(), 4);
+        let openings = (0..4u64)
+            .map(|i| (RistrettoSecretKey::random(&mut rng), 1_000 * i))
+            .collect::<Vec<_>>();
+        let commitments = openings
+            .iter()
+            .map(|(k, v)| factory.commit_value(k, *v))
+            .collect::<Vec<_>>();
+        let proof = prover.construct_aggregated_proof(&openings).unwrap();
+        assert!(prover.verify_aggregated(&proof, &commitments));
+        // The order of commitments matters
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(!prover.verify_aggregated(&proof, &swapped));
+        assert!(!prover.verify_aggregated(&proof, &commitments[..2]));
+        // Sizes must be powers of two within the aggregation limit
+        assert_eq!(
+            prover.construct_aggregated_proof(&openings[..3]),
+            Err(RangeProofError::InvalidAggregationSize)
+        );
+        let five = vec![(RistrettoSecretKey::random(&mut rng), 1); 5];
+        assert_eq!(
+            prover.construct_aggregated_proof(&five),
+            Err(RangeProofError::InvalidAggregationSize)
+        );
+        assert!(DalekRangeProofService::new_with_aggregation(32, &factory, 3).is_err());
+    }
+
+    #[test]
+    fn batch_verification() {
+        let mut rng = thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let prover = DalekRangeProofService::new_with_aggregation(16, &factory, 2).unwrap();
+        let k1 = RistrettoSecretKey::random(&mut rng);
+        let k2 = RistrettoSecretKey::random(&mut rng);
+        let single = prover.construct_proof(&k1, 7).unwrap();
+        let single_statement = RangeProofStatement::new(&[factory.commit_value(&k1, 7)]).unwrap();
+        let pair = prover
+            .construct_aggregated_proof(&[(k1.clone(), 8), (k2.clone(), 9)])
+            .unwrap();
+        let pair_statement =
+            RangeProofStatement::new(&[factory.commit_value(&k1, 8), factory.commit_value(&k2, 9)]).unwrap();
+        assert_eq!(pair_statement.len(), 2);
+        assert!(prover.verify_batch(&[
+            (single.as_slice(), &single_statement),
+            (pair.as_slice(), &pair_statement)
+        ]));
+        assert!(prover.verify_batch(&[]));
+        assert!(!prover.verify_batch(&[(single.as_slice(), &pair_statement), (pair.as_slice(), &pair_statement)]));
+        assert_eq!(
+            RangeProofStatement::new(&[]).err(),
+            Some(RangeProofError::InvalidAggregationSize)
+        );
+    }
+
+    #[test]
+    fn minimum_value_promise() {
+        let mut rng = thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let prover = DalekRangeProofService::new(32, &factory).unwrap();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let c = factory.commit_value(&k, 1_500);
+        let proof = prover.construct_proof_with_minimum(&k, 1_500, 1_000).unwrap();
+        assert_eq!(prover.verify_with_minimum(&proof, &c), Ok(1_000));
+        // Exactly the minimum is fine
+        let proof = prover.construct_proof_with_minimum(&k, 1_500, 1_500).unwrap();
+        assert_eq!(prover.verify_with_minimum(&proof, &c), Ok(1_500));
+        // Raising the promised minimum on an honest proof breaks it
+        let mut tampered = proof.clone();
+        tampered[..8].copy_from_slice(&1_600u64.to_le_bytes());
+        assert_eq!(
+            prover.verify_with_minimum(&tampered, &c),
+            Err(RangeProofError::InvalidRangeProof)
+        );
+        // A different commitment doesn't verify
+        let other = factory.commit_value(&k, 1_499);
+        assert!(prover.verify_with_minimum(&proof, &other).is_err());
+        assert_eq!(
+            prover.construct_proof_with_minimum(&k, 999, 1_000),
+            Err(RangeProofError::ValueBelowMinimum)
+        );
+        assert_eq!(
+            prover.verify_with_minimum(&proof[..4], &c),
+            Err(RangeProofError::InvalidProof)
+        );
+    }
 }
@@ -0,0 +1,214 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Designated-verifier Schnorr signatures.
+//!
+//! A designated-verifier signature convinces exactly one party, the verifier holding the secret key _v_ of
+//! _V = v.G_, that the signer produced it. It is an OR-proof (Cramer, Damgård and Schoenmakers) of knowledge of
+//! _either_ the signer's secret key _or_ the verifier's. The verifier knows that they didn't make the proof
+//! themselves, so they are convinced; but since they _could_ have made it (see
+//! [DesignatedVerifierSignature::simulate]), the signature means nothing to anyone else and can't be used to prove to
+//! a third party that the signer signed. This makes it suitable for non-transferable receipts.
+//!
+//! The signature is a pair of Schnorr signatures, one for each key, with challenges _c_P_ and _c_V_ that must sum to
+//! the challenge _c_ drawn from a [Transcript] over _P, V, R_P, R_V_ and _m_. Exactly one of the pair is a genuine
+//! signature; the other is simulated by choosing its challenge first.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{transcript::Transcript, RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+
+const CHALLENGE_LABEL: &[u8] = b"com.tari.designated_verifier.challenge";
+
+/// A signature on a message by signer _P_ that only the designated verifier _V_ can be convinced by.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{designated_verifier::DesignatedVerifierSignature, RistrettoPublicKey};
+/// # use tari_crypto::keys::PublicKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let (v, verifier) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let sig = DesignatedVerifierSignature::sign::<Blake256, _>(&k, &verifier, b"Jingo", &mut rng);
+/// assert!(sig.verify::<Blake256>(&p, &verifier, b"Jingo"));
+/// // The verifier can produce an equally valid signature without the signer, so can't convince anyone else
+/// let fake = DesignatedVerifierSignature::simulate::<Blake256, _>(&v, &p, b"Jingo", &mut rng);
+/// assert!(fake.verify::<Blake256>(&p, &verifier, b"Jingo"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DesignatedVerifierSignature {
+    signer_signature: RistrettoSchnorr,
+    verifier_signature: RistrettoSchnorr,
+    verifier_challenge: RistrettoSecretKey,
+}
+
+impl DesignatedVerifierSignature {
+    /// Sign `message` for the designated `verifier`
+    pub fn sign<D: Digest + Clone, R: Rng + CryptoRng>(
+        secret: &RistrettoSecretKey,
+        verifier: &RistrettoPublicKey,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let signer = RistrettoPublicKey::from_secret_key(secret);
+        let (verifier_signature, verifier_challenge) = simulate_signature(verifier, rng);
+        let r = RistrettoSecretKey::random(rng);
+        let signer_nonce = RistrettoPublicKey::from_secret_key(&r);
+        let c = challenge::<D>(
+            &signer,
+            verifier,
+            &signer_nonce,
+            verifier_signature.get_public_nonce(),
+            message,
+        );
+        let signer_challenge = &c - &verifier_challenge;
+        let signer_signature = RistrettoSchnorr::new(signer_nonce, &r + &(&signer_challenge * secret));
+        DesignatedVerifierSignature {
+            signer_signature,
+            verifier_signature,
+            verifier_challenge,
+        }
+    }
+
+    /// Produce a signature on `message`, apparently from `signer`, using the _verifier's_ secret key. It is
+    /// indistinguishable from one made with [sign](#method.sign), which is what makes these signatures
+    /// non-transferable.
+    pub fn simulate<D: Digest + Clone, R: Rng + CryptoRng>(
+        verifier_secret: &RistrettoSecretKey,
+        signer: &RistrettoPublicKey,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let verifier = RistrettoPublicKey::from_secret_key(verifier_secret);
+        let (signer_signature, signer_challenge) = simulate_signature(signer, rng);
+        let r = RistrettoSecretKey::random(rng);
+        let verifier_nonce = RistrettoPublicKey::from_secret_key(&r);
+        let c = challenge::<D>(
+            signer,
+            &verifier,
+            signer_signature.get_public_nonce(),
+            &verifier_nonce,
+            message,
+        );
+        let verifier_challenge = &c - &signer_challenge;
+        let verifier_signature = RistrettoSchnorr::new(verifier_nonce, &r + &(&verifier_challenge * verifier_secret));
+        DesignatedVerifierSignature {
+            signer_signature,
+            verifier_signature,
+            verifier_challenge,
+        }
+    }
+
+    /// Check the signature on `message` from `signer` to `verifier`. A valid result only convinces the holder of the
+    /// verifier's secret key, and only if they know they didn't [simulate](#method.simulate) it.
+    pub fn verify<D: Digest + Clone>(
+        &self,
+        signer: &RistrettoPublicKey,
+        verifier: &RistrettoPublicKey,
+        message: &[u8],
+    ) -> bool {
+        let c = challenge::<D>(
+            signer,
+            verifier,
+            self.signer_signature.get_public_nonce(),
+            self.verifier_signature.get_public_nonce(),
+            message,
+        );
+        let signer_challenge = &c - &self.verifier_challenge;
+        self.signer_signature.verify(signer, &signer_challenge) &&
+            self.verifier_signature.verify(verifier, &self.verifier_challenge)
+    }
+}
+
+/// A Schnorr signature for `public_key` on a challenge chosen in advance, _R = s.G - c.P_
+fn simulate_signature<R: Rng + CryptoRng>(
+    public_key: &RistrettoPublicKey,
+    rng: &mut R,
+) -> (RistrettoSchnorr, RistrettoSecretKey) {
+    let c = RistrettoSecretKey::random(rng);
+    let s = RistrettoSecretKey::random(rng);
+    let nonce = RistrettoPublicKey::from_secret_key(&s) - &c * public_key;
+    (RistrettoSchnorr::new(nonce, s), c)
+}
+
+fn challenge<D: Digest + Clone>(
+    signer: &RistrettoPublicKey,
+    verifier: &RistrettoPublicKey,
+    signer_nonce: &RistrettoPublicKey,
+    verifier_nonce: &RistrettoPublicKey,
+    message: &[u8],
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_point(b"P", signer);
+    transcript.append_point(b"V", verifier);
+    transcript.append_point(b"R_P", signer_nonce);
+    transcript.append_point(b"R_V", verifier_nonce);
+    transcript.append_message(b"message", message);
+    transcript.challenge_scalar(b"c")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+
+    #[test]
+    fn sign_and_verify() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (_, verifier) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (_, other) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = DesignatedVerifierSignature::sign::<Blake256, _>(&k, &verifier, b"Thud!", &mut rng);
+        assert!(sig.verify::<Blake256>(&p, &verifier, b"Thud!"));
+        assert!(!sig.verify::<Blake256>(&p, &verifier, b"Snuff"));
+        assert!(!sig.verify::<Blake256>(&p, &other, b"Thud!"));
+        assert!(!sig.verify::<Blake256>(&other, &verifier, b"Thud!"));
+        assert!(!sig.verify::<Blake256>(&verifier, &p, b"Thud!"));
+    }
+
+    #[test]
+    fn verifier_can_simulate() {
+        let mut rng = rand::thread_rng();
+        let (_, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (v, verifier) = RistrettoPublicKey::random_keypair(&mut rng);
+        let fake = DesignatedVerifierSignature::simulate::<Blake256, _>(&v, &p, b"Thud!", &mut rng);
+        assert!(fake.verify::<Blake256>(&p, &verifier, b"Thud!"));
+        // A third party can't simulate without either secret key
+        let (x, _) = RistrettoPublicKey::random_keypair(&mut rng);
+        let forged = DesignatedVerifierSignature::simulate::<Blake256, _>(&x, &p, b"Thud!", &mut rng);
+        assert!(!forged.verify::<Blake256>(&p, &verifier, b"Thud!"));
+    }
+
+    #[test]
+    fn wide_hash_function() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (_, verifier) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = DesignatedVerifierSignature::sign::<sha2::Sha512, _>(&k, &verifier, b"Thud!", &mut rng);
+        assert!(sig.verify::<sha2::Sha512>(&p, &verifier, b"Thud!"));
+        assert!(!sig.verify::<Blake256>(&p, &verifier, b"Thud!"));
+    }
+}
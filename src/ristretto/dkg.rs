@@ -0,0 +1,449 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A Pedersen-style distributed key generation protocol, built on [Feldman VSS](crate::ristretto::shamir).
+//!
+//! Each of the _n_ participants acts as a dealer. It shares a random secret \\( f_i(0) \\) with a polynomial of degree
+//! \\( t - 1 \\), broadcasts the [DealerMessage] holding the Feldman commitment to that polynomial, and privately
+//! sends each participant _j_ a [ShareMessage] holding \\( f_i(j) \\).
+//!
+//! A participant who receives a share that doesn't match its dealer's commitment broadcasts a [Complaint]. The
+//! accused dealer must answer by publishing the disputed share with [DkgParticipant::answer_complaint]. Every
+//! participant then calls [DkgParticipant::resolve_complaint]: a dealer whose published share verifies is cleared,
+//! and one who publishes a bad share, or nothing at all, is disqualified.
+//!
+//! Once all messages and complaints have been processed, [DkgParticipant::finalize] sums the shares from the qualified
+//! dealers into the participant's secret share, \\( s_j = \sum_i f_i(j) \\), and the dealers' commitments into the
+//! group's commitment, whose constant term is the shared public key. Participants only agree on the qualified set
+//! (and so on the key) if they process the same broadcasts and complaint resolutions, so these must go over a reliable
+//! broadcast channel.
+//!
+//! All the messages are serializable.
+//!
+//! ## The group key is not uniformly distributed
+//! This is plain Joint-Feldman with a complaint round. Dealers don't prove knowledge of their constant terms, and
+//! the commitments aren't committed to before they are revealed. A malicious dealer can therefore wait until the
+//! complaints are in and then decide whether to answer them, and so whether it is qualified. That choice lets it bias
+//! the group public key, as shown by Gennaro, Jarecki, Krawczyk and Rabin in _Secure Distributed Key Generation for
+//! Discrete-Log Based Cryptosystems_. The attacker still can't learn the secret key, but the key must not be used
+//! where it has to be uniformly random, e.g. as a source of shared randomness, or in a protocol whose security proof
+//! assumes a uniform key.
+
+use crate::{
+    keys::SecretKey,
+    ristretto::{
+        shamir::{split_verifiable, FeldmanCommitment, ShamirError, Share},
+        threshold_com_sig::ShareIndex,
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+    },
+};
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum DkgError {
+    #[error("Secret sharing failed: {0}")]
+    Sharing(ShamirError),
+    #[error("Participant {0} is not part of this key generation")]
+    UnknownParticipant(ShareIndex),
+    #[error("A message from participant {0} has already been received")]
+    DuplicateMessage(ShareIndex),
+    #[error("The commitment from participant {0} has the wrong threshold")]
+    MismatchedThreshold(ShareIndex),
+    #[error("No commitment has been received from participant {0}")]
+    MissingCommitment(ShareIndex),
+    #[error("No valid share has been received from participant {0}")]
+    MissingShare(ShareIndex),
+    #[error("The message is addressed to participant {0}")]
+    WrongRecipient(ShareIndex),
+    #[error("Participant {0} has been disqualified")]
+    Disqualified(ShareIndex),
+    #[error("Fewer than the threshold of dealers are qualified")]
+    NotEnoughDealers,
+}
+
+impl From<ShamirError> for DkgError {
+    fn from(e: ShamirError) -> Self {
+        DkgError::Sharing(e)
+    }
+}
+
+/// The broadcast part of a dealer's contribution: the commitment to its sharing polynomial
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DealerMessage {
+    pub sender: ShareIndex,
+    pub commitment: FeldmanCommitment,
+}
+
+/// The private part of a dealer's contribution: the share it dealt to `recipient`. It must be sent over an encrypted
+/// channel, unless it is being published in answer to a [Complaint].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareMessage {
+    pub sender: ShareIndex,
+    pub recipient: ShareIndex,
+    pub share: Share,
+}
+
+/// A public accusation that `accused` dealt `accuser` a share that doesn't match its commitment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Complaint {
+    pub accuser: ShareIndex,
+    pub accused: ShareIndex,
+}
+
+/// One participant's view of a key generation run
+pub struct DkgParticipant {
+    index: ShareIndex,
+    threshold: usize,
+    n: usize,
+    dealt: Vec<Share>,
+    commitments: BTreeMap<ShareIndex, FeldmanCommitment>,
+    shares: BTreeMap<ShareIndex, RistrettoSecretKey>,
+    disqualified: BTreeSet<ShareIndex>,
+}
+
+impl DkgParticipant {
+    /// Start a key generation run among participants `1..=n` as participant `index`, sampling a fresh secret to deal
+    pub fn new<R: Rng + CryptoRng>(
+        index: ShareIndex,
+        threshold: usize,
+        n: usize,
+        rng: &mut R,
+    ) -> Result<Self, DkgError> {
+        if index == 0 || index > n as u64 {
+            return Err(DkgError::UnknownParticipant(index));
+        }
+        let (dealt, commitment) = split_verifiable(&RistrettoSecretKey::random(rng), threshold, n, rng)?;
+        let mut commitments = BTreeMap::new();
+        commitments.insert(index, commitment);
+        let mut shares = BTreeMap::new();
+        shares.insert(index, dealt[index as usize - 1].value().clone());
+        Ok(DkgParticipant {
+            index,
+            threshold,
+            n,
+            dealt,
+            commitments,
+            shares,
+            disqualified: BTreeSet::new(),
+        })
+    }
+
+    pub fn index(&self) -> ShareIndex {
+        self.index
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The message to broadcast to every other participant
+    pub fn dealer_message(&self) -> DealerMessage {
+        DealerMessage {
+            sender: self.index,
+            commitment: self.commitments[&self.index].clone(),
+        }
+    }
+
+    /// The share to send privately to `recipient`
+    pub fn share_for(&self, recipient: ShareIndex) -> Result<ShareMessage, DkgError> {
+        self.check_participant(recipient)?;
+        Ok(ShareMessage {
+            sender: self.index,
+            recipient,
+            share: self.dealt[recipient as usize - 1].clone(),
+        })
+    }
+
+    /// Record another dealer's broadcast commitment
+    pub fn receive_dealer_message(&mut self, message: &DealerMessage) -> Result<(), DkgError> {
+        self.check_participant(message.sender)?;
+        if self.commitments.contains_key(&message.sender) {
+            return Err(DkgError::DuplicateMessage(message.sender));
+        }
+        if message.commitment.threshold() != self.threshold {
+            return Err(DkgError::MismatchedThreshold(message.sender));
+        }
+        self.commitments.insert(message.sender, message.commitment.clone());
+        Ok(())
+    }
+
+    /// Check and record a share dealt to this participant. The dealer's commitment must already have been received.
+    /// If the share doesn't match it, the returned [Complaint] should be broadcast.
+    pub fn receive_share(&mut self, message: &ShareMessage) -> Result<Option<Complaint>, DkgError> {
+        if message.recipient != self.index {
+            return Err(DkgError::WrongRecipient(message.recipient));
+        }
+        let commitment = self.dealer_commitment(message.sender)?;
+        if self.shares.contains_key(&message.sender) {
+            return Err(DkgError::DuplicateMessage(message.sender));
+        }
+        if message.share.index() != self.index || !commitment.verify_share(&message.share) {
+            return Ok(Some(Complaint {
+                accuser: self.index,
+                accused: message.sender,
+            }));
+        }
+        self.shares.insert(message.sender, message.share.value().clone());
+        Ok(None)
+    }
+
+    /// The share to publish in answer to a complaint against this participant
+    pub fn answer_complaint(&self, complaint: &Complaint) -> Result<ShareMessage, DkgError> {
+        if complaint.accused != self.index {
+            return Err(DkgError::WrongRecipient(complaint.accused));
+        }
+        self.share_for(complaint.accuser)
+    }
+
+    /// Settle a complaint, given the accused dealer's published answer, if it gave one. Returns `true` if the dealer
+    /// is cleared, in which case an accuser adopts the published share, and `false` if it is disqualified.
+    ///
+    /// The accused dealer sees every complaint before it chooses to answer, which is what lets a malicious dealer bias
+    /// the group key; see the [module docs](self).
+    pub fn resolve_complaint(
+        &mut self,
+        complaint: &Complaint,
+        answer: Option<&ShareMessage>,
+    ) -> Result<bool, DkgError> {
+        self.check_participant(complaint.accuser)?;
+        let commitment = self.dealer_commitment(complaint.accused)?;
+        let cleared = answer.map_or(false, |a| {
+            a.sender == complaint.accused &&
+                a.recipient == complaint.accuser &&
+                a.share.index() == complaint.accuser &&
+                commitment.verify_share(&a.share)
+        });
+        if !cleared {
+            self.disqualified.insert(complaint.accused);
+            self.shares.remove(&complaint.accused);
+        } else if let (Some(a), true) = (answer, complaint.accuser == self.index) {
+            self.shares.insert(complaint.accused, a.share.value().clone());
+        }
+        Ok(cleared)
+    }
+
+    /// The dealers that have published a commitment and haven't been disqualified
+    pub fn qualified_dealers(&self) -> Vec<ShareIndex> {
+        self.commitments
+            .keys()
+            .filter(|i| !self.disqualified.contains(i))
+            .copied()
+            .collect()
+    }
+
+    /// Combine the contributions of the qualified dealers into this participant's share of the group key. Every
+    /// qualified dealer must have dealt this participant a valid share.
+    ///
+    /// The resulting group key is not uniformly distributed if a dealer is malicious; see the [module docs](self).
+    pub fn finalize(&self) -> Result<DkgOutput, DkgError> {
+        let qualified = self.qualified_dealers();
+        if qualified.len() < self.threshold {
+            return Err(DkgError::NotEnoughDealers);
+        }
+        let mut secret = RistrettoSecretKey::default();
+        let mut coefficients = vec![RistrettoPublicKey::default(); self.threshold];
+        for dealer in &qualified {
+            let share = self.shares.get(dealer).ok_or(DkgError::MissingShare(*dealer))?;
+            secret = &secret + share;
+            for (sum, c) in coefficients.iter_mut().zip(self.commitments[dealer].commitments()) {
+                *sum = &*sum + c;
+            }
+        }
+        Ok(DkgOutput {
+            share: Share::new(self.index, secret),
+            group_commitment: FeldmanCommitment::try_from(coefficients)?,
+            qualified,
+        })
+    }
+
+    fn check_participant(&self, index: ShareIndex) -> Result<(), DkgError> {
+        if index == 0 || index > self.n as u64 {
+            return Err(DkgError::UnknownParticipant(index));
+        }
+        Ok(())
+    }
+
+    fn dealer_commitment(&self, dealer: ShareIndex) -> Result<&FeldmanCommitment, DkgError> {
+        self.check_participant(dealer)?;
+        if self.disqualified.contains(&dealer) {
+            return Err(DkgError::Disqualified(dealer));
+        }
+        self.commitments.get(&dealer).ok_or(DkgError::MissingCommitment(dealer))
+    }
+}
+
+/// The result of a successful key generation run for one participant
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DkgOutput {
+    share: Share,
+    group_commitment: FeldmanCommitment,
+    qualified: Vec<ShareIndex>,
+}
+
+impl DkgOutput {
+    /// The shared public key, whose secret key is never held by anyone
+    pub fn public_key(&self) -> &RistrettoPublicKey {
+        self.group_commitment.public_key()
+    }
+
+    /// This participant's share of the group secret key
+    pub fn secret_share(&self) -> &Share {
+        &self.share
+    }
+
+    /// The commitment that every participant's share can be checked against
+    pub fn group_commitment(&self) -> &FeldmanCommitment {
+        &self.group_commitment
+    }
+
+    /// The public key of participant `index`'s secret share
+    pub fn verification_key(&self, index: ShareIndex) -> RistrettoPublicKey {
+        self.group_commitment.verification_key(index)
+    }
+
+    pub fn qualified_dealers(&self) -> &[ShareIndex] {
+        &self.qualified
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{keys::PublicKey, ristretto::shamir::combine};
+
+    fn setup(threshold: usize, n: usize) -> Vec<DkgParticipant> {
+        let mut rng = rand::thread_rng();
+        (1..=n as u64)
+            .map(|i| DkgParticipant::new(i, threshold, n, &mut rng).unwrap())
+            .collect()
+    }
+
+    fn broadcast_commitments(participants: &mut [DkgParticipant]) {
+        let messages: Vec<DealerMessage> = participants.iter().map(|p| p.dealer_message()).collect();
+        for p in participants.iter_mut() {
+            for m in messages.iter().filter(|m| m.sender != p.index()) {
+                p.receive_dealer_message(m).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn the_truth() {
+        let mut participants = setup(3, 5);
+        broadcast_commitments(&mut participants);
+        for i in 0..5 {
+            for j in 0..5 {
+                if i == j {
+                    continue;
+                }
+                let message = participants[i].share_for(j as u64 + 1).unwrap();
+                // Round messages survive serialization
+                let message: ShareMessage = serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap();
+                assert_eq!(participants[j].receive_share(&message).unwrap(), None);
+            }
+        }
+        let outputs: Vec<DkgOutput> = participants.iter().map(|p| p.finalize().unwrap()).collect();
+        let public_key = outputs[0].public_key();
+        assert!(outputs.iter().all(|o| o.public_key() == public_key));
+        for o in &outputs {
+            assert_eq!(o.qualified_dealers(), &[1, 2, 3, 4, 5]);
+            let share = o.secret_share();
+            assert_eq!(
+                outputs[0].verification_key(share.index()),
+                RistrettoPublicKey::from_secret_key(share.value())
+            );
+        }
+        let shares: Vec<Share> = outputs.iter().map(|o| o.secret_share().clone()).collect();
+        let secret = combine(&shares[2..5]).unwrap();
+        assert_eq!(&RistrettoPublicKey::from_secret_key(&secret), public_key);
+    }
+
+    #[test]
+    fn complaints() {
+        let mut participants = setup(2, 4);
+        broadcast_commitments(&mut participants);
+        for i in 0..4 {
+            for j in 0..4 {
+                if i == j {
+                    continue;
+                }
+                let mut message = participants[i].share_for(j as u64 + 1).unwrap();
+                // Dealer 1 cheats participant 2, but then answers the complaint honestly. Dealer 4 cheats
+                // participant 3 and never answers
+                let cheating = (i == 0 && j == 1) || (i == 3 && j == 2);
+                if cheating {
+                    message.share = Share::new(j as u64 + 1, RistrettoSecretKey::from(42));
+                }
+                let complaint = participants[j].receive_share(&message).unwrap();
+                assert_eq!(complaint.is_some(), cheating);
+            }
+        }
+        let honest = Complaint { accuser: 2, accused: 1 };
+        let answer = participants[0].answer_complaint(&honest).unwrap();
+        let silent = Complaint { accuser: 3, accused: 4 };
+        for p in participants.iter_mut() {
+            assert!(p.resolve_complaint(&honest, Some(&answer)).unwrap());
+            assert!(!p.resolve_complaint(&silent, None).unwrap());
+        }
+        let late = participants[3].share_for(1).unwrap();
+        assert_eq!(participants[0].receive_share(&late), Err(DkgError::Disqualified(4)));
+        let outputs: Vec<DkgOutput> = participants.iter().map(|p| p.finalize().unwrap()).collect();
+        for o in &outputs {
+            assert_eq!(o.qualified_dealers(), &[1, 2, 3]);
+            assert_eq!(o.public_key(), outputs[0].public_key());
+            assert!(outputs[0].group_commitment().verify_share(o.secret_share()));
+        }
+    }
+
+    #[test]
+    fn invalid_messages() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(
+            DkgParticipant::new(0, 2, 3, &mut rng).err(),
+            Some(DkgError::UnknownParticipant(0))
+        );
+        assert_eq!(
+            DkgParticipant::new(1, 4, 3, &mut rng).err(),
+            Some(DkgError::Sharing(ShamirError::InvalidThreshold(3, 4)))
+        );
+        let mut participants = setup(2, 3);
+        let other = DkgParticipant::new(2, 3, 3, &mut rng).unwrap();
+        assert_eq!(
+            participants[0].receive_dealer_message(&other.dealer_message()),
+            Err(DkgError::MismatchedThreshold(2))
+        );
+        let share = participants[1].share_for(1).unwrap();
+        assert_eq!(
+            participants[0].receive_share(&share),
+            Err(DkgError::MissingCommitment(2))
+        );
+        assert_eq!(participants[2].receive_share(&share), Err(DkgError::WrongRecipient(1)));
+        assert_eq!(participants[0].finalize().err(), Some(DkgError::NotEnoughDealers));
+    }
+}
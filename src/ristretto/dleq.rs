@@ -0,0 +1,169 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Discrete log equality (DLEQ) proofs.
+//!
+//! A [DleqProof] is a Chaum-Pedersen proof that two points, _P_1 = k.G_1_ and _P_2 = k.G_2_, share the same discrete
+//! log, _k_, with respect to two different bases, without revealing _k_. For example, with the Pedersen generators
+//! it shows that a public key _k.G_ and the blinding term _k.H_ of a commitment use the same secret.
+//!
+//! The prover picks a nonce, _r_, and calculates _A_1 = r.G_1_, _A_2 = r.G_2_, draws the challenge _e_ from a
+//! [Transcript] over _G_1, G_2, P_1, P_2, A_1, A_2_ and calculates the response _s = r + e.k_. The proof is _(e, s)_.
+
+use crate::{
+    keys::SecretKey,
+    ristretto::{transcript::Transcript, RistrettoPublicKey, RistrettoSecretKey},
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, traits::VartimeMultiscalarMul};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+
+const CHALLENGE_LABEL: &[u8] = b"com.tari.dleq.challenge";
+
+/// A proof that _P_1 = k.G_1_ and _P_2 = k.G_2_ for the same secret, _k_.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{dleq::DleqProof, RistrettoSecretKey};
+/// # use tari_crypto::ristretto::pedersen::{RISTRETTO_PEDERSEN_G, RISTRETTO_PEDERSEN_H};
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let k = RistrettoSecretKey::random(&mut rng);
+/// let (G, H) = (RISTRETTO_PEDERSEN_G, *RISTRETTO_PEDERSEN_H);
+/// let (proof, p1, p2) = DleqProof::prove::<Blake256, _>(&k, &G, &H, &mut rng);
+/// assert!(proof.verify::<Blake256>(&G, &H, &p1, &p2));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DleqProof {
+    challenge: RistrettoSecretKey,
+    response: RistrettoSecretKey,
+}
+
+impl DleqProof {
+    /// Prove that _k.G_1_ and _k.G_2_ share the discrete log `secret`. Returns the proof along with the two points.
+    #[allow(non_snake_case)]
+    pub fn prove<D: Digest + Clone, R: Rng + CryptoRng>(
+        secret: &RistrettoSecretKey,
+        base1: &RistrettoPoint,
+        base2: &RistrettoPoint,
+        rng: &mut R,
+    ) -> (Self, RistrettoPublicKey, RistrettoPublicKey) {
+        let P1 = RistrettoPublicKey::new_from_pk(secret.0 * base1);
+        let P2 = RistrettoPublicKey::new_from_pk(secret.0 * base2);
+        let r = RistrettoSecretKey::random(rng);
+        let A1 = r.0 * base1;
+        let A2 = r.0 * base2;
+        let challenge = challenge::<D>(base1, base2, &P1, &P2, &A1, &A2);
+        let response = &r + &(&challenge * secret);
+        (DleqProof { challenge, response }, P1, P2)
+    }
+
+    /// Check that `point1` and `point2` share the same discrete log with respect to `base1` and `base2` respectively
+    #[allow(non_snake_case)]
+    pub fn verify<D: Digest + Clone>(
+        &self,
+        base1: &RistrettoPoint,
+        base2: &RistrettoPoint,
+        point1: &RistrettoPublicKey,
+        point2: &RistrettoPublicKey,
+    ) -> bool {
+        let s = self.response.0;
+        let neg_e = -self.challenge.0;
+        // A_i = s.G_i - e.P_i
+        let A1 = RistrettoPoint::vartime_multiscalar_mul(&[s, neg_e], &[*base1, point1.point]);
+        let A2 = RistrettoPoint::vartime_multiscalar_mul(&[s, neg_e], &[*base2, point2.point]);
+        challenge::<D>(base1, base2, point1, point2, &A1, &A2) == self.challenge
+    }
+
+    pub fn challenge(&self) -> &RistrettoSecretKey {
+        &self.challenge
+    }
+
+    pub fn response(&self) -> &RistrettoSecretKey {
+        &self.response
+    }
+}
+
+#[allow(non_snake_case)]
+fn challenge<D: Digest + Clone>(
+    base1: &RistrettoPoint,
+    base2: &RistrettoPoint,
+    P1: &RistrettoPublicKey,
+    P2: &RistrettoPublicKey,
+    A1: &RistrettoPoint,
+    A2: &RistrettoPoint,
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_message(b"G1", base1.compress().as_bytes());
+    transcript.append_message(b"G2", base2.compress().as_bytes());
+    transcript.append_point(b"P1", P1);
+    transcript.append_point(b"P2", P2);
+    transcript.append_message(b"A1", A1.compress().as_bytes());
+    transcript.append_message(b"A2", A2.compress().as_bytes());
+    transcript.challenge_scalar(b"e")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        common::Blake256,
+        keys::PublicKey,
+        ristretto::pedersen::{RISTRETTO_PEDERSEN_G, RISTRETTO_PEDERSEN_H},
+    };
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn prove_and_verify() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let (G, H) = (RISTRETTO_PEDERSEN_G, *RISTRETTO_PEDERSEN_H);
+        let (proof, P1, P2) = DleqProof::prove::<Blake256, _>(&k, &G, &H, &mut rng);
+        assert_eq!(P1, RistrettoPublicKey::from_secret_key(&k));
+        assert!(proof.verify::<Blake256>(&G, &H, &P1, &P2));
+        // The points must be in the right order and for the right bases
+        assert!(!proof.verify::<Blake256>(&G, &H, &P2, &P1));
+        assert!(!proof.verify::<Blake256>(&H, &G, &P1, &P2));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn different_discrete_logs() {
+        let mut rng = rand::thread_rng();
+        let (G, H) = (RISTRETTO_PEDERSEN_G, *RISTRETTO_PEDERSEN_H);
+        let (proof, P1, _) = DleqProof::prove::<Blake256, _>(&RistrettoSecretKey::from(5), &G, &H, &mut rng);
+        let (_, _, Q2) = DleqProof::prove::<Blake256, _>(&RistrettoSecretKey::from(6), &G, &H, &mut rng);
+        assert!(!proof.verify::<Blake256>(&G, &H, &P1, &Q2));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn wide_hash_function() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let (G, H) = (RISTRETTO_PEDERSEN_G, *RISTRETTO_PEDERSEN_H);
+        let (proof, P1, P2) = DleqProof::prove::<sha2::Sha512, _>(&k, &G, &H, &mut rng);
+        assert!(proof.verify::<sha2::Sha512>(&G, &H, &P1, &P2));
+        assert!(!proof.verify::<Blake256>(&G, &H, &P1, &P2));
+    }
+}
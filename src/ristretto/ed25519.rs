@@ -0,0 +1,257 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Ed25519 ([RFC 8032](https://www.rfc-editor.org/rfc/rfc8032)) signature verification, available with the `ed25519`
+//! feature.
+//!
+//! Ristretto and Ed25519 share the same curve and scalar field, but they are different groups: a Ristretto element
+//! is an equivalence class of Edwards points, and curve25519-dalek doesn't expose a mapping between the two encodings.
+//! The mapping that _is_ valid goes through secret keys. A [RistrettoSecretKey], _k_, has the Ed25519 public key
+//! _k.B_, and can sign Ed25519 messages with [Ed25519Signature::sign]. Note that such a key is a raw scalar rather than
+//! an RFC 8032 seed, so signatures made this way verify anywhere, but the key can't be loaded into wallets that expect
+//! a seed.
+//!
+//! Verification follows RFC 8032 (and `ed25519-dalek`'s `verify`): _s.B = R + H(R || A || M).A_, checked
+//! without the cofactor, with _s_ required to be canonical.
+
+use crate::{keys::PublicKeyValidationError, ristretto::RistrettoSecretKey};
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_TABLE,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use sha2::Sha512;
+use std::convert::TryInto;
+use thiserror::Error;
+
+/// The length of an encoded Ed25519 public key
+pub const ED25519_PUBLIC_KEY_LENGTH: usize = 32;
+/// The length of an encoded Ed25519 signature, _R || s_
+pub const ED25519_SIGNATURE_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum Ed25519Error {
+    #[error("The bytes are not a valid Ed25519 public key")]
+    InvalidPublicKey,
+    #[error("The bytes are not a valid Ed25519 signature")]
+    InvalidSignature,
+}
+
+/// An Ed25519 public key, _A_
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ed25519PublicKey {
+    compressed: CompressedEdwardsY,
+    pub(crate) point: EdwardsPoint,
+}
+
+impl Ed25519PublicKey {
+    /// Decode a compressed Edwards point
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Ed25519Error> {
+        let bytes: [u8; ED25519_PUBLIC_KEY_LENGTH] = bytes.try_into().map_err(|_| Ed25519Error::InvalidPublicKey)?;
+        let compressed = CompressedEdwardsY(bytes);
+        let point = compressed.decompress().ok_or(Ed25519Error::InvalidPublicKey)?;
+        Ok(Ed25519PublicKey { compressed, point })
+    }
+
+    /// The Ed25519 public key, _k.B_, for a Ristretto secret key
+    pub fn from_secret_key(secret: &RistrettoSecretKey) -> Self {
+        let point = &secret.0 * &ED25519_BASEPOINT_TABLE;
+        Ed25519PublicKey {
+            compressed: point.compress(),
+            point,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; ED25519_PUBLIC_KEY_LENGTH] {
+        self.compressed.as_bytes()
+    }
+
+    /// Keys of small order (including the identity) are accepted by RFC 8032 verification, but any signature will
+    /// verify under them for some messages. Callers that accept keys from untrusted sources should reject these.
+    pub fn is_weak(&self) -> bool {
+        self.point.is_small_order()
+    }
+
+    /// Check that the key is in the prime-order subgroup, as `PublicKey::validate` does for the crate's key types.
+    /// This is stricter than [is_weak](#method.is_weak), which only rejects keys of small order: it also rejects any
+    /// key with a small-order component. Keys made with [from_secret_key](#method.from_secret_key) always pass.
+    pub fn validate(&self) -> Result<(), PublicKeyValidationError> {
+        if self.point.is_torsion_free() {
+            Ok(())
+        } else {
+            Err(PublicKeyValidationError::SmallOrder)
+        }
+    }
+}
+
+/// An Ed25519 signature, _(R, s)_
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ed25519Signature {
+    r: CompressedEdwardsY,
+    s: Scalar,
+}
+
+impl Ed25519Signature {
+    /// Decode a signature from _R || s_. The scalar _s_ must be canonical (less than the group order), which prevents
+    /// signature malleability.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Ed25519Error> {
+        if bytes.len() != ED25519_SIGNATURE_LENGTH {
+            return Err(Ed25519Error::InvalidSignature);
+        }
+        let mut r = [0u8; 32];
+        r.copy_from_slice(&bytes[..32]);
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&bytes[32..]);
+        let s = Scalar::from_canonical_bytes(s).ok_or(Ed25519Error::InvalidSignature)?;
+        Ok(Ed25519Signature {
+            r: CompressedEdwardsY(r),
+            s,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; ED25519_SIGNATURE_LENGTH] {
+        let mut bytes = [0u8; ED25519_SIGNATURE_LENGTH];
+        bytes[..32].copy_from_slice(self.r.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    /// Sign `message` with a Ristretto secret key, using the given secret nonce. As with
+    /// [RistrettoSchnorr::sign](crate::ristretto::RistrettoSchnorr), the nonce must be uniformly random and never
+    /// reused.
+    pub fn sign(secret: &RistrettoSecretKey, nonce: &RistrettoSecretKey, message: &[u8]) -> Self {
+        let public_key = Ed25519PublicKey::from_secret_key(secret);
+        let r = (&nonce.0 * &ED25519_BASEPOINT_TABLE).compress();
+        let k = challenge(&r, &public_key, message);
+        Ed25519Signature {
+            r,
+            s: nonce.0 + k * secret.0,
+        }
+    }
+
+    /// Verify the signature on `message` under `public_key`, as specified in RFC 8032
+    pub fn verify(&self, public_key: &Ed25519PublicKey, message: &[u8]) -> bool {
+        let k = challenge(&self.r, public_key, message);
+        // R' = s.B - k.A
+        let r = EdwardsPoint::vartime_double_scalar_mul_basepoint(&(-k), &public_key.point, &self.s);
+        r.compress() == self.r
+    }
+}
+
+/// k = H(R || A || M), reduced mod the group order
+fn challenge(r: &CompressedEdwardsY, public_key: &Ed25519PublicKey, message: &[u8]) -> Scalar {
+    let hasher = Sha512::new()
+        .chain(r.as_bytes())
+        .chain(public_key.as_bytes())
+        .chain(message);
+    Scalar::from_hash(hasher)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::SecretKey;
+    use tari_utilities::hex::from_hex;
+
+    /// Test vectors 1 and 2 from RFC 8032, section 7.1
+    #[test]
+    fn rfc8032_vectors() {
+        let vectors: [(&str, &[u8], &str, &str); 2] = [
+            (
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                b"",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+                "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                &[0x72],
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+                "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+        ];
+        for (pk, msg, r, s) in vectors.iter() {
+            let pk = Ed25519PublicKey::from_bytes(&from_hex(pk).unwrap()).unwrap();
+            let sig = from_hex(&format!("{}{}", r, s)).unwrap();
+            let sig = Ed25519Signature::from_bytes(&sig).unwrap();
+            assert!(sig.verify(&pk, msg));
+            assert!(!sig.verify(&pk, b"Equal Rites"));
+            assert!(!pk.is_weak());
+            assert_eq!(pk.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn sign_with_ristretto_key() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let r = RistrettoSecretKey::random(&mut rng);
+        let pk = Ed25519PublicKey::from_secret_key(&k);
+        let sig = Ed25519Signature::sign(&k, &r, b"The Colour of Magic");
+        assert!(sig.verify(&pk, b"The Colour of Magic"));
+        let decoded = Ed25519Signature::from_bytes(&sig.to_bytes()).unwrap();
+        let decoded_pk = Ed25519PublicKey::from_bytes(pk.as_bytes()).unwrap();
+        assert!(decoded.verify(&decoded_pk, b"The Colour of Magic"));
+        let other = Ed25519PublicKey::from_secret_key(&RistrettoSecretKey::random(&mut rng));
+        assert!(!sig.verify(&other, b"The Colour of Magic"));
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        assert_eq!(
+            Ed25519PublicKey::from_bytes(&[0u8; 31]),
+            Err(Ed25519Error::InvalidPublicKey)
+        );
+        assert_eq!(
+            Ed25519Signature::from_bytes(&[0u8; 63]),
+            Err(Ed25519Error::InvalidSignature)
+        );
+        // A non-canonical s is rejected
+        let mut bytes = [0u8; 64];
+        bytes[32..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            Ed25519Signature::from_bytes(&bytes),
+            Err(Ed25519Error::InvalidSignature)
+        );
+        // The identity is a valid point, but weak
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        assert!(Ed25519PublicKey::from_bytes(&identity).unwrap().is_weak());
+    }
+
+    #[test]
+    fn torsion_is_rejected() {
+        let k = RistrettoSecretKey::random(&mut rand::thread_rng());
+        let pk = Ed25519PublicKey::from_secret_key(&k);
+        assert_eq!(pk.validate(), Ok(()));
+        // A point of order 8
+        let torsion = from_hex("c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a").unwrap();
+        let torsion = Ed25519PublicKey::from_bytes(&torsion).unwrap();
+        assert!(torsion.is_weak());
+        assert_eq!(torsion.validate(), Err(PublicKeyValidationError::SmallOrder));
+        // Adding it to a good key isn't caught by is_weak, but is by validate
+        let mixed = Ed25519PublicKey::from_bytes((pk.point + torsion.point).compress().as_bytes()).unwrap();
+        assert!(!mixed.is_weak());
+        assert_eq!(mixed.validate(), Err(PublicKeyValidationError::SmallOrder));
+    }
+}
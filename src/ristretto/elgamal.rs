@@ -0,0 +1,215 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Exponent ElGamal encryption of small values under a [RistrettoPublicKey].
+//!
+//! A value _v_ is encrypted to the public key _P = x.G_ as _(R, V) = (r.G, v.H + r.P)_, where _H_ is the Pedersen
+//! value generator, [RISTRETTO_PEDERSEN_H]. Ciphertexts are additively homomorphic: adding two ciphertexts gives an
+//! encryption of the sum of the values.
+//!
+//! Decryption recovers _v.H = V - x.R_, and then _v_ by solving the discrete log with a baby-step giant-step
+//! [DecryptionTable]. That is only feasible for small values, so the table is built for values of at most `bits` bits.
+//!
+//! ```edition2018
+//! # use tari_crypto::ristretto::{elgamal::{DecryptionTable, ElGamalCiphertext}, RistrettoPublicKey};
+//! # use tari_crypto::keys::PublicKey;
+//! let mut rng = rand::thread_rng();
+//! let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+//! let a = ElGamalCiphertext::encrypt(1_500, &p, &mut rng);
+//! let b = ElGamalCiphertext::encrypt(2_000, &p, &mut rng);
+//! let table = DecryptionTable::new(16).unwrap();
+//! assert_eq!((&a + &b).decrypt(&k, &table), Ok(3_500));
+//! ```
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{pedersen::RISTRETTO_PEDERSEN_H, RistrettoPublicKey, RistrettoSecretKey},
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ops::Add};
+use thiserror::Error;
+
+/// The largest value size that a [DecryptionTable] can be built for
+pub const MAX_DECRYPTION_BITS: u32 = 48;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ElGamalError {
+    #[error("Decryption tables can be built for at most {0} bit values")]
+    InvalidTableSize(u32),
+    #[error("The decrypted value is out of range of the decryption table")]
+    ValueOutOfRange,
+}
+
+/// An exponent ElGamal ciphertext, _(R, V) = (r.G, v.H + r.P)_
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElGamalCiphertext {
+    nonce: RistrettoPublicKey,
+    value: RistrettoPublicKey,
+}
+
+impl ElGamalCiphertext {
+    /// Encrypt `value` to `public_key` using a random nonce
+    pub fn encrypt<R: Rng + CryptoRng>(value: u64, public_key: &RistrettoPublicKey, rng: &mut R) -> Self {
+        Self::encrypt_with_nonce(value, public_key, &RistrettoSecretKey::random(rng))
+    }
+
+    /// Encrypt `value` to `public_key` using the secret nonce `r`, which must be random and never reused. Knowing the
+    /// nonce also allows the ciphertext to be opened, so it can be used to prove what was encrypted.
+    pub fn encrypt_with_nonce(value: u64, public_key: &RistrettoPublicKey, r: &RistrettoSecretKey) -> Self {
+        let v = Scalar::from(value);
+        ElGamalCiphertext {
+            nonce: RistrettoPublicKey::from_secret_key(r),
+            value: RistrettoPublicKey::new_from_pk(v * *RISTRETTO_PEDERSEN_H + r.0 * public_key.point),
+        }
+    }
+
+    /// The nonce component of the ciphertext, _R = r.G_
+    pub fn nonce(&self) -> &RistrettoPublicKey {
+        &self.nonce
+    }
+
+    /// The masked value component of the ciphertext, _V = v.H + r.P_
+    pub fn masked_value(&self) -> &RistrettoPublicKey {
+        &self.value
+    }
+
+    /// Decrypt the ciphertext with the secret key, `k`, using `table` to recover the value
+    pub fn decrypt(&self, k: &RistrettoSecretKey, table: &DecryptionTable) -> Result<u64, ElGamalError> {
+        let v_h = self.value.point - k.0 * self.nonce.point;
+        table.solve(v_h)
+    }
+}
+
+impl<'a> Add for &'a ElGamalCiphertext {
+    type Output = ElGamalCiphertext;
+
+    /// Add two ciphertexts encrypted to the same key; the result encrypts the sum of the values
+    fn add(self, rhs: &'a ElGamalCiphertext) -> ElGamalCiphertext {
+        ElGamalCiphertext {
+            nonce: &self.nonce + &rhs.nonce,
+            value: &self.value + &rhs.value,
+        }
+    }
+}
+
+define_add_variants!(
+    LHS = ElGamalCiphertext,
+    RHS = ElGamalCiphertext,
+    Output = ElGamalCiphertext
+);
+
+/// A baby-step giant-step table for recovering _v_ from _v.H_, for values of up to `bits` bits.
+///
+/// The table holds _2^{bits/2}_ baby steps, and decryption takes up to as many giant steps, so a 32-bit table holds
+/// 65,536 entries.
+pub struct DecryptionTable {
+    baby_steps: HashMap<[u8; 32], u64>,
+    step_size: u64,
+    giant_steps: u64,
+}
+
+impl DecryptionTable {
+    pub fn new(bits: u32) -> Result<Self, ElGamalError> {
+        if bits == 0 || bits > MAX_DECRYPTION_BITS {
+            return Err(ElGamalError::InvalidTableSize(MAX_DECRYPTION_BITS));
+        }
+        let step_size = 1u64 << ((bits + 1) / 2);
+        let giant_steps = 1u64 << (bits / 2);
+        let mut baby_steps = HashMap::with_capacity(step_size as usize);
+        let mut p = RistrettoPoint::default();
+        for j in 0..step_size {
+            baby_steps.insert(p.compress().to_bytes(), j);
+            p += *RISTRETTO_PEDERSEN_H;
+        }
+        Ok(DecryptionTable {
+            baby_steps,
+            step_size,
+            giant_steps,
+        })
+    }
+
+    /// The largest value that can be recovered with this table
+    pub fn max_value(&self) -> u64 {
+        self.step_size * self.giant_steps - 1
+    }
+
+    fn solve(&self, v_h: RistrettoPoint) -> Result<u64, ElGamalError> {
+        let giant_step = Scalar::from(self.step_size) * *RISTRETTO_PEDERSEN_H;
+        let mut p = v_h;
+        for i in 0..self.giant_steps {
+            if let Some(j) = self.baby_steps.get(&p.compress().to_bytes()) {
+                return Ok(i * self.step_size + j);
+            }
+            p -= giant_step;
+        }
+        Err(ElGamalError::ValueOutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let table = DecryptionTable::new(16).unwrap();
+        assert_eq!(table.max_value(), 65_535);
+        for &v in &[0, 1, 255, 256, 12_345, 65_535] {
+            let c = ElGamalCiphertext::encrypt(v, &p, &mut rng);
+            assert_eq!(c.decrypt(&k, &table), Ok(v));
+        }
+        let c = ElGamalCiphertext::encrypt(65_536, &p, &mut rng);
+        assert_eq!(c.decrypt(&k, &table), Err(ElGamalError::ValueOutOfRange));
+        // Odd-sized tables cover their full range too
+        let table = DecryptionTable::new(9).unwrap();
+        assert_eq!(table.max_value(), 511);
+        let c = ElGamalCiphertext::encrypt(511, &p, &mut rng);
+        assert_eq!(c.decrypt(&k, &table), Ok(511));
+    }
+
+    #[test]
+    fn homomorphic_addition() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let table = DecryptionTable::new(20).unwrap();
+        let a = ElGamalCiphertext::encrypt(100_000, &p, &mut rng);
+        let b = ElGamalCiphertext::encrypt(23_456, &p, &mut rng);
+        assert_eq!((&a + &b).decrypt(&k, &table), Ok(123_456));
+        assert_eq!((a.clone() + b).decrypt(&k, &table), Ok(123_456));
+        // The wrong key gives garbage (which is almost certainly out of range)
+        let (other, _) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert!(a.decrypt(&other, &table).is_err());
+    }
+
+    #[test]
+    fn invalid_tables() {
+        assert!(DecryptionTable::new(0).is_err());
+        assert_eq!(
+            DecryptionTable::new(MAX_DECRYPTION_BITS + 1).err(),
+            Some(ElGamalError::InvalidTableSize(MAX_DECRYPTION_BITS))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Proofs that two commitments, made with different generators, commit to the same value.
+//!
+//! Given _C_1 = k_1.G_1 + v.H_1_ and _C_2 = k_2.G_2 + v.H_2_, a [CommitmentEqualityProof] shows that the prover
+//! knows _k_1_, _k_2_ and a single _v_ that open both, without revealing any of them. This is what is needed to move an
+//! output from one commitment scheme to another. The prover picks nonces _(a_1, a_2, b)_ and publishes
+//! _T_1 = a_1.G_1 + b.H_1_ and _T_2 = a_2.G_2 + b.H_2_. With the challenge _e_ drawn from a [Transcript] over
+//! _G_1, H_1, G_2, H_2, C_1, C_2, T_1, T_2_ and the context, the responses are _z_i = a_i + e.k_i_ and
+//! _z_v = b + e.v_. Sharing _z_v_ between both verification equations is what ties the two values together.
+
+use crate::{
+    commitment::HomomorphicCommitmentFactory,
+    keys::SecretKey,
+    ristretto::{
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        transcript::Transcript,
+        RistrettoSecretKey,
+    },
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+
+const CHALLENGE_LABEL: &[u8] = b"com.tari.equality_proof.challenge";
+
+/// A proof that two commitments under different factories commit to the same value.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{equality_proof::CommitmentEqualityProof, pedersen::PedersenCommitmentFactory};
+/// # use tari_crypto::ristretto::{constants::RISTRETTO_NUMS_POINTS, RistrettoSecretKey};
+/// # use tari_crypto::commitment::HomomorphicCommitmentFactory;
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let old = PedersenCommitmentFactory::default();
+/// let new = PedersenCommitmentFactory::new(RISTRETTO_NUMS_POINTS[1], RISTRETTO_NUMS_POINTS[2]);
+/// let (k1, k2) = (RistrettoSecretKey::random(&mut rng), RistrettoSecretKey::random(&mut rng));
+/// let v = RistrettoSecretKey::from(42);
+/// let proof = CommitmentEqualityProof::prove::<Blake256, _>(&v, &k1, &old, &k2, &new, b"migrate", &mut rng);
+/// let (c1, c2) = (old.commit(&k1, &v), new.commit(&k2, &v));
+/// assert!(proof.verify::<Blake256>(&c1, &old, &c2, &new, b"migrate"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentEqualityProof {
+    nonce1: PedersenCommitment,
+    nonce2: PedersenCommitment,
+    response1: RistrettoSecretKey,
+    response2: RistrettoSecretKey,
+    value_response: RistrettoSecretKey,
+}
+
+impl CommitmentEqualityProof {
+    /// Prove that `factory1.commit(blinding1, value)` and `factory2.commit(blinding2, value)` commit to the same value
+    pub fn prove<D: Digest + Clone, R: Rng + CryptoRng>(
+        value: &RistrettoSecretKey,
+        blinding1: &RistrettoSecretKey,
+        factory1: &PedersenCommitmentFactory,
+        blinding2: &RistrettoSecretKey,
+        factory2: &PedersenCommitmentFactory,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let commitment1 = factory1.commit(blinding1, value);
+        let commitment2 = factory2.commit(blinding2, value);
+        let a1 = RistrettoSecretKey::random(rng);
+        let a2 = RistrettoSecretKey::random(rng);
+        let b = RistrettoSecretKey::random(rng);
+        let nonce1 = factory1.commit(&a1, &b);
+        let nonce2 = factory2.commit(&a2, &b);
+        let e = challenge::<D>(
+            (factory1, &commitment1, &nonce1),
+            (factory2, &commitment2, &nonce2),
+            context,
+        );
+        CommitmentEqualityProof {
+            nonce1,
+            nonce2,
+            response1: &a1 + &(&e * blinding1),
+            response2: &a2 + &(&e * blinding2),
+            value_response: &b + &(&e * value),
+        }
+    }
+
+    /// Check that `commitment1` (under `factory1`) and `commitment2` (under `factory2`) commit to the same value
+    pub fn verify<D: Digest + Clone>(
+        &self,
+        commitment1: &PedersenCommitment,
+        factory1: &PedersenCommitmentFactory,
+        commitment2: &PedersenCommitment,
+        factory2: &PedersenCommitmentFactory,
+        context: &[u8],
+    ) -> bool {
+        let e = challenge::<D>(
+            (factory1, commitment1, &self.nonce1),
+            (factory2, commitment2, &self.nonce2),
+            context,
+        );
+        let lhs1 = factory1.commit(&self.response1, &self.value_response);
+        let lhs2 = factory2.commit(&self.response2, &self.value_response);
+        lhs1 == &self.nonce1 + &(commitment1 * &e) && lhs2 == &self.nonce2 + &(commitment2 * &e)
+    }
+}
+
+fn challenge<D: Digest + Clone>(
+    first: (&PedersenCommitmentFactory, &PedersenCommitment, &PedersenCommitment),
+    second: (&PedersenCommitmentFactory, &PedersenCommitment, &PedersenCommitment),
+    context: &[u8],
+) -> RistrettoSecretKey {
+    let (factory1, commitment1, nonce1) = first;
+    let (factory2, commitment2, nonce2) = second;
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_message(b"G1", factory1.G.compress().as_bytes());
+    transcript.append_message(b"H1", factory1.H.compress().as_bytes());
+    transcript.append_message(b"G2", factory2.G.compress().as_bytes());
+    transcript.append_message(b"H2", factory2.H.compress().as_bytes());
+    transcript.append_message(b"C1", commitment1.as_bytes());
+    transcript.append_message(b"C2", commitment2.as_bytes());
+    transcript.append_message(b"T1", nonce1.as_bytes());
+    transcript.append_message(b"T2", nonce2.as_bytes());
+    transcript.append_message(b"context", context);
+    transcript.challenge_scalar(b"e")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::Blake256, ristretto::constants::RISTRETTO_NUMS_POINTS};
+    use blake2::Blake2b;
+
+    fn factories() -> (PedersenCommitmentFactory, PedersenCommitmentFactory) {
+        let custom = PedersenCommitmentFactory::new(RISTRETTO_NUMS_POINTS[3], RISTRETTO_NUMS_POINTS[4]);
+        (PedersenCommitmentFactory::default(), custom)
+    }
+
+    #[test]
+    fn equal_values() {
+        let mut rng = rand::thread_rng();
+        let (f1, f2) = factories();
+        let k1 = RistrettoSecretKey::random(&mut rng);
+        let k2 = RistrettoSecretKey::random(&mut rng);
+        let v = RistrettoSecretKey::from(1_000);
+        let c1 = f1.commit(&k1, &v);
+        let c2 = f2.commit(&k2, &v);
+        let proof = CommitmentEqualityProof::prove::<Blake256, _>(&v, &k1, &f1, &k2, &f2, b"Moving Pictures", &mut rng);
+        assert!(proof.verify::<Blake256>(&c1, &f1, &c2, &f2, b"Moving Pictures"));
+        // Wrong context, swapped factories or a different commitment all fail
+        assert!(!proof.verify::<Blake256>(&c1, &f1, &c2, &f2, b"Soul Music"));
+        assert!(!proof.verify::<Blake256>(&c2, &f2, &c1, &f1, b"Moving Pictures"));
+        let c3 = f2.commit(&k2, &RistrettoSecretKey::from(1_001));
+        assert!(!proof.verify::<Blake256>(&c1, &f1, &c3, &f2, b"Moving Pictures"));
+    }
+
+    #[test]
+    fn unequal_values() {
+        let mut rng = rand::thread_rng();
+        let (f1, f2) = factories();
+        let k1 = RistrettoSecretKey::random(&mut rng);
+        let k2 = RistrettoSecretKey::random(&mut rng);
+        let v1 = RistrettoSecretKey::from(1_000);
+        let v2 = RistrettoSecretKey::from(2_000);
+        // An honest proof for v1 doesn't verify against a commitment to v2
+        let proof = CommitmentEqualityProof::prove::<Blake256, _>(&v1, &k1, &f1, &k2, &f2, b"", &mut rng);
+        assert!(!proof.verify::<Blake256>(&f1.commit(&k1, &v1), &f1, &f2.commit(&k2, &v2), &f2, b""));
+    }
+
+    #[test]
+    fn wide_digest() {
+        let mut rng = rand::thread_rng();
+        let (f1, f2) = factories();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let (c1, c2) = (f1.commit(&k, &k), f2.commit(&k, &k));
+        let proof = CommitmentEqualityProof::prove::<Blake2b, _>(&k, &k, &f1, &k, &f2, b"", &mut rng);
+        assert!(proof.verify::<Blake2b>(&c1, &f1, &c2, &f2, b""));
+        assert!(!proof.verify::<Blake256>(&c1, &f1, &c2, &f2, b""));
+    }
+}
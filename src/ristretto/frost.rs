@@ -0,0 +1,696 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! FROST: Flexible Round-Optimised Schnorr Threshold signatures. See
+//! [the FROST paper](https://eprint.iacr.org/2020/852) for details.
+//!
+//! Any _t_ of the _n_ holders of a [KeyShare] can jointly produce an ordinary `RistrettoSchnorr` signature that
+//! verifies against the group public key.
+//!
+//! ## Key generation
+//! Each participant creates a [DkgParticipant], which samples a secret polynomial \\( f_i \\) of degree \\( t - 1 \\),
+//! and broadcasts the [DkgRound1Package] containing the Feldman commitments to its coefficients and a proof of
+//! knowledge of \\( f_i(0) \\). Each participant then privately sends \\( f_i(j) \\) to participant _j_. Once all
+//! packages and shares are received, [KeyShare::from_dkg] verifies them and produces the participant's long-lived
+//! share, \\( s_j = \sum_i f_i(j) \\).
+//!
+//! ## Signing
+//! 1. Each signer generates a fresh pair of [SigningNonces] and publishes the corresponding [SigningCommitment].
+//! 2. The commitments of the signing set and the message are bundled into a [SigningPackage], from which every
+//!    signer derives the binding factors \\( \rho_i \\), the group nonce \\( R = \sum (D_i + \rho_i E_i) \\) and the
+//!    challenge \\( e = H(R || Y || m) \\). Each signer returns
+//!    \\( z_i = d_i + \rho_i e_i + \lambda_i s_i e \\), where \\( \lambda_i \\) is its Lagrange coefficient.
+//! 3. The partial signatures are summed into the final signature, \\( (R, \sum z_i) \\).
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+    signatures::SchnorrSignature,
+};
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+/// Participants are identified by a non-zero integer, which is also the point at which their share is evaluated
+pub type ParticipantId = u64;
+
+const DKG_PROOF_LABEL: &[u8] = b"com.tari.frost.dkg_proof";
+const BINDING_FACTOR_LABEL: &[u8] = b"com.tari.frost.binding_factor";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum FrostError {
+    #[error("The threshold must be at least one")]
+    InvalidThreshold,
+    #[error("Participant identifiers must be non-zero")]
+    InvalidParticipantId,
+    #[error("Participant {0} appears more than once")]
+    DuplicateParticipant(ParticipantId),
+    #[error("Participant {0} is not part of the signing set")]
+    ParticipantNotFound(ParticipantId),
+    #[error("The number of shares does not match the number of key generation packages")]
+    MismatchedShares,
+    #[error("Participant {0} used a different threshold")]
+    MismatchedThreshold(ParticipantId),
+    #[error("The secret share from participant {0} does not match their commitments")]
+    InvalidShare(ParticipantId),
+    #[error("The proof of knowledge from participant {0} is invalid")]
+    InvalidProofOfKnowledge(ParticipantId),
+    #[error("At least {0} signers are required")]
+    NotEnoughSigners(usize),
+    #[error("The signing nonces do not match the published commitment")]
+    InvalidSigningNonces,
+    #[error("The partial signature from participant {0} is invalid")]
+    InvalidPartialSignature(ParticipantId),
+    #[error("The aggregated signature is invalid")]
+    InvalidAggregateSignature,
+    #[error("FROST requires a hash function with a 32 byte digest")]
+    IncompatibleHashFunction,
+}
+
+//-----------------------------------------   Key generation   -------------------------------------------------------//
+
+/// The secret state of a participant during distributed key generation. The polynomial coefficients are cleared when
+/// this is dropped.
+pub struct DkgParticipant {
+    id: ParticipantId,
+    coefficients: Vec<RistrettoSecretKey>,
+}
+
+impl DkgParticipant {
+    /// Sample a random polynomial of degree `threshold - 1` for participant `id`
+    pub fn new<R: Rng + CryptoRng>(id: ParticipantId, threshold: usize, rng: &mut R) -> Result<Self, FrostError> {
+        if id == 0 {
+            return Err(FrostError::InvalidParticipantId);
+        }
+        if threshold == 0 {
+            return Err(FrostError::InvalidThreshold);
+        }
+        let coefficients = (0..threshold).map(|_| RistrettoSecretKey::random(rng)).collect();
+        Ok(DkgParticipant { id, coefficients })
+    }
+
+    pub fn id(&self) -> ParticipantId {
+        self.id
+    }
+
+    /// The package to broadcast to every other participant: the commitments to the polynomial coefficients and a
+    /// Schnorr proof of knowledge of the constant term.
+    pub fn round1_package<D: Digest, R: Rng + CryptoRng>(&self, rng: &mut R) -> Result<DkgRound1Package, FrostError> {
+        let commitments: Vec<RistrettoPublicKey> = self
+            .coefficients
+            .iter()
+            .map(RistrettoPublicKey::from_secret_key)
+            .collect();
+        let (k, r) = RistrettoPublicKey::random_keypair(rng);
+        let c = dkg_proof_challenge::<D>(self.id, &commitments[0], &r)?;
+        let s = &k + &c * &self.coefficients[0];
+        Ok(DkgRound1Package {
+            id: self.id,
+            commitments,
+            proof: SchnorrSignature::new(r, s),
+        })
+    }
+
+    /// The secret share, \\( f_i(j) \\), to send privately to participant `recipient`
+    pub fn share_for(&self, recipient: ParticipantId) -> Result<RistrettoSecretKey, FrostError> {
+        if recipient == 0 {
+            return Err(FrostError::InvalidParticipantId);
+        }
+        let x = RistrettoSecretKey::from(recipient);
+        let share = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(RistrettoSecretKey::default(), |acc, a| &acc * &x + a);
+        Ok(share)
+    }
+}
+
+/// The public output of the first round of key generation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DkgRound1Package {
+    id: ParticipantId,
+    commitments: Vec<RistrettoPublicKey>,
+    proof: RistrettoSchnorr,
+}
+
+impl DkgRound1Package {
+    pub fn id(&self) -> ParticipantId {
+        self.id
+    }
+
+    pub fn commitments(&self) -> &[RistrettoPublicKey] {
+        &self.commitments
+    }
+
+    /// The threshold implied by the number of commitments
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// Check the proof of knowledge of the constant term. This prevents rogue-key attacks on the group key.
+    pub fn verify_proof<D: Digest>(&self) -> Result<(), FrostError> {
+        let a_0 = self.commitments.first().ok_or(FrostError::InvalidThreshold)?;
+        let c = dkg_proof_challenge::<D>(self.id, a_0, self.proof.get_public_nonce())?;
+        if self.proof.verify(a_0, &c) {
+            Ok(())
+        } else {
+            Err(FrostError::InvalidProofOfKnowledge(self.id))
+        }
+    }
+
+    /// Check that `share` is the evaluation of this participant's committed polynomial at `recipient`
+    pub fn verify_share(&self, recipient: ParticipantId, share: &RistrettoSecretKey) -> bool {
+        RistrettoPublicKey::from_secret_key(share) == evaluate_commitments(&self.commitments, recipient)
+    }
+}
+
+/// The public commitments to the group's shared polynomial. This is enough to derive the group public key and every
+/// participant's verification share, \\( Y_i = s_i G \\).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupKey {
+    commitments: Vec<RistrettoPublicKey>,
+}
+
+impl GroupKey {
+    /// The group public key, \\( Y \\), that threshold signatures verify against
+    pub fn public_key(&self) -> &RistrettoPublicKey {
+        &self.commitments[0]
+    }
+
+    /// The minimum number of signers required to produce a signature
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// The public key corresponding to participant `id`'s secret share
+    pub fn verification_share(&self, id: ParticipantId) -> RistrettoPublicKey {
+        evaluate_commitments(&self.commitments, id)
+    }
+}
+
+/// A participant's long-lived share of the group secret key
+pub struct KeyShare {
+    id: ParticipantId,
+    secret_share: RistrettoSecretKey,
+    group_key: GroupKey,
+}
+
+impl KeyShare {
+    /// Complete key generation for participant `id`. `shares[i]` must be the secret share received from the sender of
+    /// `packages[i]`, and the participant's own package and share must be included.
+    pub fn from_dkg<D: Digest>(
+        id: ParticipantId,
+        packages: &[DkgRound1Package],
+        shares: &[RistrettoSecretKey],
+    ) -> Result<KeyShare, FrostError> {
+        if id == 0 {
+            return Err(FrostError::InvalidParticipantId);
+        }
+        if packages.len() != shares.len() {
+            return Err(FrostError::MismatchedShares);
+        }
+        let threshold = packages.first().ok_or(FrostError::MismatchedShares)?.threshold();
+        if threshold == 0 {
+            return Err(FrostError::InvalidThreshold);
+        }
+        let mut commitments = vec![RistrettoPublicKey::default(); threshold];
+        let mut secret_share = RistrettoSecretKey::default();
+        for (i, (package, share)) in packages.iter().zip(shares.iter()).enumerate() {
+            if packages[..i].iter().any(|p| p.id == package.id) {
+                return Err(FrostError::DuplicateParticipant(package.id));
+            }
+            if package.threshold() != threshold {
+                return Err(FrostError::MismatchedThreshold(package.id));
+            }
+            package.verify_proof::<D>()?;
+            if !package.verify_share(id, share) {
+                return Err(FrostError::InvalidShare(package.id));
+            }
+            for (c, a) in commitments.iter_mut().zip(package.commitments.iter()) {
+                *c = &*c + a;
+            }
+            secret_share = secret_share + share;
+        }
+        Ok(KeyShare {
+            id,
+            secret_share,
+            group_key: GroupKey { commitments },
+        })
+    }
+
+    pub fn id(&self) -> ParticipantId {
+        self.id
+    }
+
+    pub fn group_key(&self) -> &GroupKey {
+        &self.group_key
+    }
+
+    /// This participant's verification share, \\( Y_i = s_i G \\)
+    pub fn public_share(&self) -> RistrettoPublicKey {
+        RistrettoPublicKey::from_secret_key(&self.secret_share)
+    }
+}
+
+//-----------------------------------------------   Signing   --------------------------------------------------------//
+
+/// A signer's single-use hiding and binding nonces, \\( (d_i, e_i) \\).
+///
+/// This type is deliberately not `Clone`, and is consumed when signing. Reusing nonces WILL leak your key share.
+pub struct SigningNonces {
+    id: ParticipantId,
+    hiding: RistrettoSecretKey,
+    binding: RistrettoSecretKey,
+}
+
+impl SigningNonces {
+    pub fn new<R: Rng + CryptoRng>(id: ParticipantId, rng: &mut R) -> Self {
+        SigningNonces {
+            id,
+            hiding: RistrettoSecretKey::random(rng),
+            binding: RistrettoSecretKey::random(rng),
+        }
+    }
+
+    /// The public commitment, \\( (D_i, E_i) \\), to publish to the other signers
+    pub fn commitment(&self) -> SigningCommitment {
+        SigningCommitment {
+            id: self.id,
+            hiding: RistrettoPublicKey::from_secret_key(&self.hiding),
+            binding: RistrettoPublicKey::from_secret_key(&self.binding),
+        }
+    }
+}
+
+/// A signer's published nonce commitment, \\( (D_i, E_i) \\)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningCommitment {
+    id: ParticipantId,
+    hiding: RistrettoPublicKey,
+    binding: RistrettoPublicKey,
+}
+
+impl SigningCommitment {
+    pub fn new(id: ParticipantId, hiding: RistrettoPublicKey, binding: RistrettoPublicKey) -> Self {
+        SigningCommitment { id, hiding, binding }
+    }
+
+    pub fn id(&self) -> ParticipantId {
+        self.id
+    }
+
+    pub fn hiding(&self) -> &RistrettoPublicKey {
+        &self.hiding
+    }
+
+    pub fn binding(&self) -> &RistrettoPublicKey {
+        &self.binding
+    }
+}
+
+/// The message and nonce commitments of the signers taking part in one signing operation, along with the values
+/// derived from them.
+pub struct SigningPackage<D: Digest> {
+    group_key: GroupKey,
+    commitments: Vec<SigningCommitment>,
+    binding_factors: Vec<RistrettoSecretKey>,
+    group_nonce: RistrettoPublicKey,
+    challenge: RistrettoSecretKey,
+    digest_type: PhantomData<D>,
+}
+
+impl<D: Digest> SigningPackage<D> {
+    /// Bundle the signing set's commitments. At least `threshold` signers are required, and each may only appear once.
+    pub fn new(
+        group_key: &GroupKey,
+        message: &[u8],
+        mut commitments: Vec<SigningCommitment>,
+    ) -> Result<Self, FrostError> {
+        // Ristretto requires a 256 bit hash
+        if D::output_size() != 32 {
+            return Err(FrostError::IncompatibleHashFunction);
+        }
+        commitments.sort_by_key(|c| c.id);
+        if commitments.iter().any(|c| c.id == 0) {
+            return Err(FrostError::InvalidParticipantId);
+        }
+        if let Some(w) = commitments.windows(2).find(|w| w[0].id == w[1].id) {
+            return Err(FrostError::DuplicateParticipant(w[0].id));
+        }
+        if commitments.len() < group_key.threshold() {
+            return Err(FrostError::NotEnoughSigners(group_key.threshold()));
+        }
+        let message = D::digest(message).to_vec();
+        let mut encoded = Vec::with_capacity(commitments.len() * 72);
+        for c in commitments.iter() {
+            encoded.extend_from_slice(&c.id.to_le_bytes());
+            encoded.extend_from_slice(c.hiding.as_bytes());
+            encoded.extend_from_slice(c.binding.as_bytes());
+        }
+        let y = group_key.public_key();
+        let binding_factors = commitments
+            .iter()
+            .map(|c| {
+                hash_to_scalar(
+                    D::new()
+                        .chain(BINDING_FACTOR_LABEL)
+                        .chain(y.as_bytes())
+                        .chain(&c.id.to_le_bytes())
+                        .chain(&message)
+                        .chain(&encoded),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let group_nonce = commitments
+            .iter()
+            .zip(binding_factors.iter())
+            .fold(RistrettoPublicKey::default(), |acc, (c, rho)| {
+                acc + &c.hiding + rho * &c.binding
+            });
+        let challenge = hash_to_scalar(
+            D::new()
+                .chain(group_nonce.as_bytes())
+                .chain(y.as_bytes())
+                .chain(&message),
+        )?;
+        Ok(SigningPackage {
+            group_key: group_key.clone(),
+            commitments,
+            binding_factors,
+            group_nonce,
+            challenge,
+            digest_type: PhantomData,
+        })
+    }
+
+    /// The identifiers of the signing set, in ascending order
+    pub fn signer_ids(&self) -> Vec<ParticipantId> {
+        self.commitments.iter().map(|c| c.id).collect()
+    }
+
+    /// The group nonce, \\( R = \sum (D_i + \rho_i E_i) \\)
+    pub fn group_nonce(&self) -> &RistrettoPublicKey {
+        &self.group_nonce
+    }
+
+    /// The challenge, \\( e = H(R || Y || m) \\), that the final signature verifies with
+    pub fn challenge(&self) -> &RistrettoSecretKey {
+        &self.challenge
+    }
+
+    /// The Lagrange coefficient at zero for `id` over the signing set
+    pub fn lagrange_coefficient(&self, id: ParticipantId) -> Result<RistrettoSecretKey, FrostError> {
+        self.index_of(id)?;
+        let x_i = Scalar::from(id);
+        let mut num = Scalar::one();
+        let mut den = Scalar::one();
+        for c in self.commitments.iter().filter(|c| c.id != id) {
+            let x_j = Scalar::from(c.id);
+            num *= x_j;
+            den *= x_j - x_i;
+        }
+        Ok(RistrettoSecretKey(num * den.invert()))
+    }
+
+    /// Calculate this signer's partial signature, \\( z_i = d_i + \rho_i e_i + \lambda_i s_i e \\). The nonces are
+    /// consumed and must match the commitment included in this package.
+    pub fn sign(&self, key_share: &KeyShare, nonces: SigningNonces) -> Result<RistrettoSchnorr, FrostError> {
+        if key_share.id != nonces.id {
+            return Err(FrostError::InvalidSigningNonces);
+        }
+        let index = self.index_of(key_share.id)?;
+        if self.commitments[index] != nonces.commitment() {
+            return Err(FrostError::InvalidSigningNonces);
+        }
+        let rho = &self.binding_factors[index];
+        let lambda = self.lagrange_coefficient(key_share.id)?;
+        let z = &nonces.hiding + rho * &nonces.binding + lambda * &key_share.secret_share * &self.challenge;
+        Ok(SchnorrSignature::new(self.signer_nonce(index), z))
+    }
+
+    /// Check a partial signature against the signer's verification share:
+    /// \\( z_i G = D_i + \rho_i E_i + \lambda_i e Y_i \\)
+    pub fn verify_partial_signature(&self, id: ParticipantId, signature: &RistrettoSchnorr) -> bool {
+        let index = match self.index_of(id) {
+            Ok(i) => i,
+            Err(_) => return false,
+        };
+        if signature.get_public_nonce() != &self.signer_nonce(index) {
+            return false;
+        }
+        let lambda = match self.lagrange_coefficient(id) {
+            Ok(l) => l,
+            Err(_) => return false,
+        };
+        let p = lambda * self.group_key.verification_share(id);
+        signature.verify(&p, &self.challenge)
+    }
+
+    /// Validate and sum the partial signatures of the whole signing set into the final signature, \\( (R, \sum z_i) \\)
+    pub fn aggregate(&self, partials: &[(ParticipantId, RistrettoSchnorr)]) -> Result<RistrettoSchnorr, FrostError> {
+        let mut z = RistrettoSecretKey::default();
+        for c in self.commitments.iter() {
+            let sig = match partials.iter().find(|(id, _)| *id == c.id) {
+                Some((_, sig)) => sig,
+                None => return Err(FrostError::NotEnoughSigners(self.commitments.len())),
+            };
+            if !self.verify_partial_signature(c.id, sig) {
+                return Err(FrostError::InvalidPartialSignature(c.id));
+            }
+            z = z + sig.get_signature();
+        }
+        if let Some((id, _)) = partials.iter().find(|(id, _)| self.index_of(*id).is_err()) {
+            return Err(FrostError::ParticipantNotFound(*id));
+        }
+        let signature = SchnorrSignature::new(self.group_nonce.clone(), z);
+        if !signature.verify(self.group_key.public_key(), &self.challenge) {
+            return Err(FrostError::InvalidAggregateSignature);
+        }
+        Ok(signature)
+    }
+
+    fn index_of(&self, id: ParticipantId) -> Result<usize, FrostError> {
+        self.commitments
+            .binary_search_by_key(&id, |c| c.id)
+            .map_err(|_| FrostError::ParticipantNotFound(id))
+    }
+
+    fn signer_nonce(&self, index: usize) -> RistrettoPublicKey {
+        let c = &self.commitments[index];
+        &c.hiding + &self.binding_factors[index] * &c.binding
+    }
+}
+
+//-----------------------------------------------   Helpers   --------------------------------------------------------//
+
+/// Evaluate the committed polynomial at `x`, i.e. \\( \sum x^k A_k \\)
+fn evaluate_commitments(commitments: &[RistrettoPublicKey], x: ParticipantId) -> RistrettoPublicKey {
+    let x = RistrettoSecretKey::from(x);
+    commitments
+        .iter()
+        .rev()
+        .fold(RistrettoPublicKey::default(), |acc, a| &acc * &x + a)
+}
+
+fn dkg_proof_challenge<D: Digest>(
+    id: ParticipantId,
+    a_0: &RistrettoPublicKey,
+    r: &RistrettoPublicKey,
+) -> Result<RistrettoSecretKey, FrostError> {
+    hash_to_scalar(
+        D::new()
+            .chain(DKG_PROOF_LABEL)
+            .chain(&id.to_le_bytes())
+            .chain(a_0.as_bytes())
+            .chain(r.as_bytes()),
+    )
+}
+
+fn hash_to_scalar<D: Digest>(hasher: D) -> Result<RistrettoSecretKey, FrostError> {
+    RistrettoSecretKey::from_bytes(&hasher.finalize()).map_err(|_| FrostError::IncompatibleHashFunction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    /// Run a complete key generation ceremony for participants 1..=n
+    fn run_dkg(t: usize, n: u64) -> Vec<KeyShare> {
+        let mut rng = rand::thread_rng();
+        let participants: Vec<DkgParticipant> = (1..=n).map(|i| DkgParticipant::new(i, t, &mut rng).unwrap()).collect();
+        let packages: Vec<DkgRound1Package> = participants
+            .iter()
+            .map(|p| p.round1_package::<Sha256, _>(&mut rng).unwrap())
+            .collect();
+        (1..=n)
+            .map(|j| {
+                let shares: Vec<RistrettoSecretKey> = participants.iter().map(|p| p.share_for(j).unwrap()).collect();
+                KeyShare::from_dkg::<Sha256>(j, &packages, &shares).unwrap()
+            })
+            .collect()
+    }
+
+    fn threshold_sign(
+        shares: &[&KeyShare],
+        msg: &[u8],
+    ) -> Result<(RistrettoSchnorr, SigningPackage<Sha256>), FrostError> {
+        let mut rng = rand::thread_rng();
+        let nonces: Vec<SigningNonces> = shares.iter().map(|s| SigningNonces::new(s.id(), &mut rng)).collect();
+        let commitments = nonces.iter().map(SigningNonces::commitment).collect();
+        let package = SigningPackage::<Sha256>::new(shares[0].group_key(), msg, commitments)?;
+        let partials = shares
+            .iter()
+            .zip(nonces.into_iter())
+            .map(|(s, n)| package.sign(s, n).map(|sig| (s.id(), sig)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sig = package.aggregate(&partials)?;
+        Ok((sig, package))
+    }
+
+    #[test]
+    fn dkg_agrees_on_group_key() {
+        let shares = run_dkg(3, 5);
+        let group_key = shares[0].group_key();
+        for s in shares.iter() {
+            assert_eq!(s.group_key(), group_key);
+            assert_eq!(s.public_share(), group_key.verification_share(s.id()));
+        }
+    }
+
+    #[test]
+    fn three_of_five() {
+        let shares = run_dkg(3, 5);
+        let group_key = shares[0].group_key().public_key().clone();
+        for signers in [[0usize, 2, 4], [1, 2, 3], [4, 0, 1]].iter() {
+            let signing_set: Vec<&KeyShare> = signers.iter().map(|&i| &shares[i]).collect();
+            let (sig, package) = threshold_sign(&signing_set, b"Hello FROST").unwrap();
+            assert!(sig.verify(&group_key, package.challenge()));
+        }
+        // More than the threshold works too
+        let all: Vec<&KeyShare> = shares.iter().collect();
+        let (sig, package) = threshold_sign(&all, b"Hello FROST").unwrap();
+        assert!(sig.verify(&group_key, package.challenge()));
+    }
+
+    #[test]
+    fn not_enough_signers() {
+        let shares = run_dkg(3, 5);
+        let signing_set = vec![&shares[0], &shares[1]];
+        assert_eq!(
+            threshold_sign(&signing_set, b"msg").err().unwrap(),
+            FrostError::NotEnoughSigners(3)
+        );
+    }
+
+    #[test]
+    fn invalid_dkg_share_is_rejected() {
+        let mut rng = rand::thread_rng();
+        let participants: Vec<DkgParticipant> = (1..=3).map(|i| DkgParticipant::new(i, 2, &mut rng).unwrap()).collect();
+        let packages: Vec<DkgRound1Package> = participants
+            .iter()
+            .map(|p| p.round1_package::<Sha256, _>(&mut rng).unwrap())
+            .collect();
+        // Participant 2 sends participant 1 the share meant for participant 3
+        let shares = vec![
+            participants[0].share_for(1).unwrap(),
+            participants[1].share_for(3).unwrap(),
+            participants[2].share_for(1).unwrap(),
+        ];
+        assert_eq!(
+            KeyShare::from_dkg::<Sha256>(1, &packages, &shares).err().unwrap(),
+            FrostError::InvalidShare(2)
+        );
+    }
+
+    #[test]
+    fn invalid_proof_of_knowledge() {
+        let mut rng = rand::thread_rng();
+        let p = DkgParticipant::new(1, 2, &mut rng).unwrap();
+        let mut package = p.round1_package::<Sha256, _>(&mut rng).unwrap();
+        assert!(package.verify_proof::<Sha256>().is_ok());
+        package.commitments[0] = RistrettoPublicKey::random_keypair(&mut rng).1;
+        assert_eq!(
+            package.verify_proof::<Sha256>().err().unwrap(),
+            FrostError::InvalidProofOfKnowledge(1)
+        );
+    }
+
+    #[test]
+    fn invalid_partial_signature() {
+        let shares = run_dkg(2, 3);
+        let mut rng = rand::thread_rng();
+        let n1 = SigningNonces::new(1, &mut rng);
+        let n2 = SigningNonces::new(2, &mut rng);
+        let package =
+            SigningPackage::<Sha256>::new(shares[0].group_key(), b"msg", vec![n1.commitment(), n2.commitment()])
+                .unwrap();
+        let s1 = package.sign(&shares[0], n1).unwrap();
+        let s2 = package.sign(&shares[1], n2).unwrap();
+        assert!(package.verify_partial_signature(1, &s1));
+        assert!(!package.verify_partial_signature(2, &s1));
+        assert!(!package.verify_partial_signature(3, &s2));
+        let forged = SchnorrSignature::new(s2.get_public_nonce().clone(), s1.get_signature().clone());
+        assert_eq!(
+            package.aggregate(&[(1, s1), (2, forged)]).err().unwrap(),
+            FrostError::InvalidPartialSignature(2)
+        );
+    }
+
+    #[test]
+    fn mismatched_nonces() {
+        let shares = run_dkg(2, 3);
+        let mut rng = rand::thread_rng();
+        let n1 = SigningNonces::new(1, &mut rng);
+        let n2 = SigningNonces::new(2, &mut rng);
+        let package =
+            SigningPackage::<Sha256>::new(shares[0].group_key(), b"msg", vec![n1.commitment(), n2.commitment()])
+                .unwrap();
+        let other = SigningNonces::new(1, &mut rng);
+        assert_eq!(
+            package.sign(&shares[0], other).err().unwrap(),
+            FrostError::InvalidSigningNonces
+        );
+        assert_eq!(
+            package.sign(&shares[0], n2).err().unwrap(),
+            FrostError::InvalidSigningNonces
+        );
+        assert_eq!(
+            package.sign(&shares[2], SigningNonces::new(3, &mut rng)).err().unwrap(),
+            FrostError::ParticipantNotFound(3)
+        );
+    }
+
+    #[test]
+    fn duplicate_signer() {
+        let shares = run_dkg(2, 3);
+        let n1 = SigningNonces::new(1, &mut rand::thread_rng());
+        let result =
+            SigningPackage::<Sha256>::new(shares[0].group_key(), b"msg", vec![n1.commitment(), n1.commitment()]);
+        assert_eq!(result.err().unwrap(), FrostError::DuplicateParticipant(1));
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Hierarchical deterministic keys for Ristretto, in the style of SLIP-10.
+//!
+//! An extended key is a key together with a 32-byte chain code. Child _i_ of an extended secret key _(k, c)_ is
+//! _(k + t_i, c_i)_, where the tweak _t_i_ and the child chain code _c_i_ are hashes of _c_, _i_ and either the parent
+//! secret key (for hardened children, _i >= 2^31_) or the parent public key (for normal children). Because the tweak
+//! of a normal child only depends on public data, anyone holding an [ExtendedPublicKey] can derive the public keys
+//! _K + t_i.G_ of its normal children, which is what watch-only wallets need. Hardened children can only be derived
+//! from the secret key.
+//!
+//! **Caution:** as with BIP32, revealing the secret key of a normal child along with the parent's extended public key
+//! reveals the parent secret key. Use hardened derivation for anything above the level that is shared.
+//!
+//! The tweaks are reduced from 64-byte SHA-512 digests, so they are uniform. Paths are written as in BIP32, e.g.
+//! `m/44'/0'/0/1`, where `'` (or `h`) marks a hardened index.
+
+use crate::{
+    keys::PublicKey,
+    ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+};
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_TABLE, scalar::Scalar};
+use sha2::{Digest, Sha512};
+use std::{convert::TryFrom, fmt, str::FromStr};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+/// Indices at and above this are hardened
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+const MASTER_KEY_LABEL: &[u8] = b"com.tari.hd_key.master.key";
+const MASTER_CHAIN_CODE_LABEL: &[u8] = b"com.tari.hd_key.master.chain_code";
+const CHILD_TWEAK_LABEL: &[u8] = b"com.tari.hd_key.child.tweak";
+const CHILD_CHAIN_CODE_LABEL: &[u8] = b"com.tari.hd_key.child.chain_code";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum HdKeyError {
+    #[error("Hardened children cannot be derived from a public key")]
+    HardenedFromPublic,
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+    #[error("The maximum derivation depth has been reached")]
+    MaximumDepth,
+}
+
+/// A sequence of child indices, e.g. `m/44'/0'/0/1`
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(indices: Vec<u32>) -> Self {
+        DerivationPath(indices)
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// True if any index on the path is hardened, in which case only a secret key can follow it
+    pub fn has_hardened(&self) -> bool {
+        self.0.iter().any(|i| is_hardened(*i))
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = HdKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(HdKeyError::InvalidPath("a path must start with 'm'".to_string()));
+        }
+        parts
+            .map(|part| {
+                let (digits, hardened) = match part.strip_suffix('\'').or_else(|| part.strip_suffix('h')) {
+                    Some(digits) => (digits, true),
+                    None => (part, false),
+                };
+                let index = digits
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|i| *i < HARDENED_OFFSET)
+                    .ok_or_else(|| HdKeyError::InvalidPath(format!("'{}' is not a valid index", part)))?;
+                Ok(if hardened { index + HARDENED_OFFSET } else { index })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for i in &self.0 {
+            if is_hardened(*i) {
+                write!(f, "/{}'", i - HARDENED_OFFSET)?;
+            } else {
+                write!(f, "/{}", i)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A secret key with a chain code, from which child keys can be derived
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::hd_key::{DerivationPath, ExtendedSecretKey};
+/// let master = ExtendedSecretKey::from_seed(b"an example seed, use at least 32 random bytes");
+/// let account = master.derive_path(&"m/44'/0'".parse().unwrap()).unwrap();
+/// // A watch-only wallet can derive the public keys of normal children
+/// let watch_only = account.public_key();
+/// let path: DerivationPath = "m/0/7".parse().unwrap();
+/// assert_eq!(
+///     watch_only.derive_path(&path).unwrap().key(),
+///     account.derive_path(&path).unwrap().public_key().key()
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedSecretKey {
+    key: RistrettoSecretKey,
+    chain_code: [u8; 32],
+    depth: u8,
+    child_number: u32,
+}
+
+impl ExtendedSecretKey {
+    /// Derive the master key from a seed. The seed should hold at least 32 bytes of entropy.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let key = RistrettoSecretKey(Scalar::from_hash(Sha512::new().chain(MASTER_KEY_LABEL).chain(seed)));
+        let chain_code = chain_code(Sha512::new().chain(MASTER_CHAIN_CODE_LABEL).chain(seed));
+        ExtendedSecretKey {
+            key,
+            chain_code,
+            depth: 0,
+            child_number: 0,
+        }
+    }
+
+    /// Derive child `index`. Indices at or above [HARDENED_OFFSET] give hardened children.
+    pub fn derive_child(&self, index: u32) -> Result<Self, HdKeyError> {
+        let depth = self.depth.checked_add(1).ok_or(HdKeyError::MaximumDepth)?;
+        let (tweak, chain_code) = if is_hardened(index) {
+            child_tweak(&self.chain_code, 0, self.key.as_bytes(), index)
+        } else {
+            let public_key = RistrettoPublicKey::from_secret_key(&self.key);
+            child_tweak(&self.chain_code, 1, public_key.as_bytes(), index)
+        };
+        Ok(ExtendedSecretKey {
+            key: RistrettoSecretKey(self.key.0 + tweak),
+            chain_code,
+            depth,
+            child_number: index,
+        })
+    }
+
+    /// Derive the key at `path`, relative to this one
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, HdKeyError> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    /// The extended public key with the same chain code, for deriving normal children without the secret key
+    pub fn public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            key: RistrettoPublicKey::from_secret_key(&self.key),
+            chain_code: self.chain_code,
+            depth: self.depth,
+            child_number: self.child_number,
+        }
+    }
+
+    pub fn key(&self) -> &RistrettoSecretKey {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// The number of derivations between the master key and this one
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The index this key was derived with, or zero for the master key
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+}
+
+/// A public key with a chain code, from which the public keys of normal (non-hardened) children can be derived
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    key: RistrettoPublicKey,
+    chain_code: [u8; 32],
+    depth: u8,
+    child_number: u32,
+}
+
+impl ExtendedPublicKey {
+    /// Derive normal child `index`. Hardened indices fail with [HdKeyError::HardenedFromPublic].
+    pub fn derive_child(&self, index: u32) -> Result<Self, HdKeyError> {
+        if is_hardened(index) {
+            return Err(HdKeyError::HardenedFromPublic);
+        }
+        let depth = self.depth.checked_add(1).ok_or(HdKeyError::MaximumDepth)?;
+        let (tweak, chain_code) = child_tweak(&self.chain_code, 1, self.key.as_bytes(), index);
+        Ok(ExtendedPublicKey {
+            key: RistrettoPublicKey::new_from_pk(self.key.point + &tweak * &RISTRETTO_BASEPOINT_TABLE),
+            chain_code,
+            depth,
+            child_number: index,
+        })
+    }
+
+    /// Derive the public key at `path`, relative to this one. The path may not contain hardened indices.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, HdKeyError> {
+        path.indices()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    pub fn key(&self) -> &RistrettoPublicKey {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+}
+
+fn is_hardened(index: u32) -> bool {
+    index >= HARDENED_OFFSET
+}
+
+/// Returns the tweak and chain code for a child. `key_type` separates secret (hardened) and public (normal) inputs.
+fn child_tweak(parent_chain_code: &[u8; 32], key_type: u8, key: &[u8], index: u32) -> (Scalar, [u8; 32]) {
+    let hash_input = |label: &[u8]| {
+        Sha512::new()
+            .chain(label)
+            .chain(parent_chain_code)
+            .chain([key_type])
+            .chain(key)
+            .chain(index.to_be_bytes())
+    };
+    let tweak = Scalar::from_hash(hash_input(CHILD_TWEAK_LABEL));
+    (tweak, chain_code(hash_input(CHILD_CHAIN_CODE_LABEL)))
+}
+
+fn chain_code(hasher: Sha512) -> [u8; 32] {
+    let hash = hasher.finalize();
+    <[u8; 32]>::try_from(&hash[..32]).expect("a SHA-512 digest has more than 32 bytes")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SEED: &[u8] = b"Lords and Ladies, the Queen of the Elves, and the Long Man";
+
+    #[test]
+    fn path_parsing() {
+        let path: DerivationPath = "m/44'/0h/0/1".parse().unwrap();
+        assert_eq!(path.indices(), &[44 + HARDENED_OFFSET, HARDENED_OFFSET, 0, 1]);
+        assert!(path.has_hardened());
+        assert_eq!(path.to_string(), "m/44'/0'/0/1");
+        assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::default());
+        assert!(!"m/0/1".parse::<DerivationPath>().unwrap().has_hardened());
+        for bad in &["", "44'/0", "m/", "m/x", "m/2147483648", "m/1''", "k/1"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn secret_derivation() {
+        let master = ExtendedSecretKey::from_seed(SEED);
+        assert_eq!(master, ExtendedSecretKey::from_seed(SEED));
+        assert_ne!(master.key(), ExtendedSecretKey::from_seed(b"Witches Abroad").key());
+        let path = "m/44'/0'/3".parse().unwrap();
+        let child = master.derive_path(&path).unwrap();
+        assert_eq!(child.depth(), 3);
+        assert_eq!(child.child_number(), 3);
+        let step_by_step = master
+            .derive_child(44 + HARDENED_OFFSET)
+            .and_then(|k| k.derive_child(HARDENED_OFFSET))
+            .and_then(|k| k.derive_child(3))
+            .unwrap();
+        assert_eq!(child, step_by_step);
+        // Hardened and normal children with the same number are different keys
+        assert_ne!(
+            master.derive_child(1).unwrap().key(),
+            master.derive_child(1 + HARDENED_OFFSET).unwrap().key()
+        );
+        assert_ne!(master.derive_child(1).unwrap().chain_code(), master.chain_code());
+    }
+
+    #[test]
+    fn public_derivation() {
+        let account = ExtendedSecretKey::from_seed(SEED)
+            .derive_path(&"m/44'/1'".parse().unwrap())
+            .unwrap();
+        let watch_only = account.public_key();
+        for i in 0..5 {
+            let path = DerivationPath::new(vec![0, i]);
+            assert_eq!(
+                watch_only.derive_path(&path).unwrap(),
+                account.derive_path(&path).unwrap().public_key()
+            );
+        }
+        assert_eq!(
+            watch_only.derive_child(HARDENED_OFFSET),
+            Err(HdKeyError::HardenedFromPublic)
+        );
+        assert_eq!(
+            watch_only.derive_path(&"m/0/5'".parse().unwrap()),
+            Err(HdKeyError::HardenedFromPublic)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A Ristretto secret key bundled with its public key.
+
+use crate::{
+    keys::{DiffieHellmanSharedSecret, PublicKey, SecretKey},
+    ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+    signatures::{SchnorrSignatureError, Signer},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use std::fmt;
+
+/// A secret key and its public key, which can't get out of step with one another.
+///
+/// The secret key is cleared from memory when the keypair is dropped (as every [RistrettoSecretKey] is), and `Debug`
+/// only shows the public key.
+///
+/// ```edition2018
+/// # use tari_crypto::common::Blake256;
+/// # use tari_crypto::ristretto::RistrettoKeypair;
+/// let keypair = RistrettoKeypair::generate(&mut rand::thread_rng());
+/// let sig = keypair.sign::<Blake256>(b"Snuff").unwrap();
+/// assert!(sig.verify_message::<Blake256>(keypair.public(), b"Snuff"));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct RistrettoKeypair {
+    secret: RistrettoSecretKey,
+    public: RistrettoPublicKey,
+}
+
+impl RistrettoKeypair {
+    /// Generate a new random keypair
+    pub fn generate<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        RistrettoKeypair::from_secret_key(RistrettoSecretKey::random(rng))
+    }
+
+    /// Build the keypair for `secret`, calculating its public key
+    pub fn from_secret_key(secret: RistrettoSecretKey) -> Self {
+        let public = RistrettoPublicKey::from_secret_key(&secret);
+        RistrettoKeypair { secret, public }
+    }
+
+    pub fn public(&self) -> &RistrettoPublicKey {
+        &self.public
+    }
+
+    pub fn secret(&self) -> &RistrettoSecretKey {
+        &self.secret
+    }
+
+    /// Sign `message` with a deterministic nonce. See [RistrettoSchnorr::sign_deterministic]; the signature is checked
+    /// with `verify_message`.
+    pub fn sign<D: Digest>(&self, message: &[u8]) -> Result<RistrettoSchnorr, SchnorrSignatureError> {
+        RistrettoSchnorr::sign_deterministic::<D>(self.secret.clone(), message)
+    }
+}
+
+impl fmt::Debug for RistrettoKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RistrettoKeypair")
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+impl From<RistrettoSecretKey> for RistrettoKeypair {
+    fn from(secret: RistrettoSecretKey) -> Self {
+        RistrettoKeypair::from_secret_key(secret)
+    }
+}
+
+/// An in-memory [Signer], for code written against the trait that should also work with software keys
+impl Signer for RistrettoSecretKey {
+    type Error = SchnorrSignatureError;
+    type PK = RistrettoPublicKey;
+
+    fn public_key(&self) -> Result<RistrettoPublicKey, SchnorrSignatureError> {
+        Ok(RistrettoPublicKey::from_secret_key(self))
+    }
+
+    fn sign_with<R: Rng + CryptoRng>(
+        &self,
+        challenge: &dyn Fn(&RistrettoPublicKey, &RistrettoPublicKey) -> Vec<u8>,
+        rng: &mut R,
+    ) -> Result<RistrettoSchnorr, SchnorrSignatureError> {
+        let nonce = RistrettoSecretKey::random(rng);
+        let e = challenge(&RistrettoPublicKey::from_secret_key(&nonce), &self.public_key()?);
+        RistrettoSchnorr::sign_raw(self.clone(), nonce, &e)
+    }
+
+    fn shared_secret(&self, public_key: &RistrettoPublicKey) -> Result<RistrettoPublicKey, SchnorrSignatureError> {
+        Ok(RistrettoPublicKey::shared_secret(self, public_key))
+    }
+}
+
+impl Signer for RistrettoKeypair {
+    type Error = SchnorrSignatureError;
+    type PK = RistrettoPublicKey;
+
+    fn public_key(&self) -> Result<RistrettoPublicKey, SchnorrSignatureError> {
+        Ok(self.public.clone())
+    }
+
+    fn sign_with<R: Rng + CryptoRng>(
+        &self,
+        challenge: &dyn Fn(&RistrettoPublicKey, &RistrettoPublicKey) -> Vec<u8>,
+        rng: &mut R,
+    ) -> Result<RistrettoSchnorr, SchnorrSignatureError> {
+        self.secret.sign_with(challenge, rng)
+    }
+
+    fn shared_secret(&self, public_key: &RistrettoPublicKey) -> Result<RistrettoPublicKey, SchnorrSignatureError> {
+        self.secret.shared_secret(public_key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+    use tari_utilities::hex::Hex;
+
+    #[test]
+    fn raising_steam() {
+        let mut rng = rand::thread_rng();
+        let keypair = RistrettoKeypair::generate(&mut rng);
+        assert_eq!(keypair.public(), &RistrettoPublicKey::from_secret_key(keypair.secret()));
+        assert_eq!(RistrettoKeypair::from(keypair.secret().clone()), keypair);
+        let sig = keypair.sign::<Blake256>(b"Iron Girder").unwrap();
+        assert!(sig.verify_message::<Blake256>(keypair.public(), b"Iron Girder"));
+        let other = RistrettoKeypair::generate(&mut rng);
+        assert!(!sig.verify_message::<Blake256>(other.public(), b"Iron Girder"));
+        // The secret never shows up in debug output
+        let debug = format!("{:?}", keypair);
+        assert!(!debug.contains(&keypair.secret().to_hex()));
+    }
+
+    #[test]
+    fn signers() {
+        let mut rng = rand::thread_rng();
+        let keypair = RistrettoKeypair::generate(&mut rng);
+        assert_eq!(&Signer::public_key(&keypair).unwrap(), keypair.public());
+        let sig = RistrettoSchnorr::sign_message_with_signer::<Blake256, _, _>(&keypair, b"Moist", &mut rng).unwrap();
+        assert!(sig.verify_message::<Blake256>(keypair.public(), b"Moist"));
+        let sig = RistrettoSchnorr::sign_with_domain_with_signer::<Blake256, _, _>(
+            keypair.secret(),
+            b"post-office",
+            b"von Lipwig",
+            &mut rng,
+        )
+        .unwrap();
+        assert!(sig.verify_with_domain::<Blake256>(keypair.public(), b"post-office", b"von Lipwig"));
+        assert!(!sig.verify_with_domain::<Blake256>(keypair.public(), b"bank", b"von Lipwig"));
+
+        let other = RistrettoKeypair::generate(&mut rng);
+        assert_eq!(
+            Signer::shared_secret(&keypair, other.public()).unwrap(),
+            Signer::shared_secret(other.secret(), keypair.public()).unwrap()
+        );
+    }
+}
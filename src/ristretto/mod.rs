@@ -20,19 +20,49 @@
 // WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+pub mod commitment_sum;
 pub mod constants;
 pub mod dalek_range_proof;
+pub mod designated_verifier;
+pub mod dkg;
+pub mod dleq;
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
+pub mod elgamal;
+pub mod equality_proof;
+pub mod frost;
+pub mod hd_key;
+pub mod keypair;
 pub mod musig;
+pub mod musig2;
+pub mod nonce_commitment;
+pub mod opening_proof;
 pub mod pedersen;
+pub mod pedersen_hash;
+pub mod poly_commit;
+pub mod precomputed_key;
+pub mod ring_signature;
 pub mod ristretto_com_sig;
 pub mod ristretto_keys;
 pub mod ristretto_sig;
 pub mod script_commitment;
 pub mod serialize;
+pub mod shamir;
+pub mod signing_session;
+pub mod stealth;
+pub mod switch_commitment;
+pub mod threshold_com_sig;
+pub mod transcript;
 pub mod utils;
+pub mod verifiable_encryption;
+pub mod vrf;
+#[cfg(feature = "x25519")]
+pub mod x25519;
+pub mod zero_value_proof;
 
 // Re-export
 pub use self::{
+    keypair::RistrettoKeypair,
     ristretto_com_sig::RistrettoComSig,
     ristretto_keys::{RistrettoPublicKey, RistrettoSecretKey},
     ristretto_sig::RistrettoSchnorr,
@@ -0,0 +1,476 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! MuSig2 is a two-round multi-signature scheme that produces an ordinary Schnorr signature for the MuSig aggregate
+//! public key. See [the MuSig2 paper](https://eprint.iacr.org/2020/1261) for details.
+//!
+//! Key aggregation is identical to MuSig, so the [JointKey](../../musig/struct.JointKey.html) is reused here. What
+//! changes is the nonce exchange: each signer publishes _two_ public nonces, \\( R_{i,1} \\) and \\( R_{i,2} \\),
+//! which may be shared before the message is known. Once all nonces are collected every participant calculates
+//!
+//! $$
+//!   R_1 = \sum R_{i,1}, \quad R_2 = \sum R_{i,2}, \quad R = R_1 + b R_2
+//! $$
+//!
+//! where the nonce coefficient \( b \) is drawn from a [Transcript] over \( X, R_1, R_2 \) and \( m \), and
+//! the standard challenge \\( e = H(R || X || m) \\). Signer _i_ produces the partial signature
+//! \\( s_i = r_{i,1} + b r_{i,2} + e a_i k_i \\), and the final signature is \\( (R, \sum s_i) \\), which verifies
+//! against the joint public key \\( X \\) like any other `RistrettoSchnorr` signature.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    musig::{JointKey, JointKeyBuilder, MuSigError},
+    ristretto::{transcript::Transcript, RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+    signatures::SchnorrSignature,
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use tari_utilities::ByteArray;
+
+//-----------------------------------------  Constants and aliases    ------------------------------------------------//
+
+type JKBuilder = JointKeyBuilder<RistrettoPublicKey, RistrettoSecretKey>;
+type JointPubKey = JointKey<RistrettoPublicKey, RistrettoSecretKey>;
+type MessageHash = Vec<u8>;
+
+/// Domain label for the nonce coefficient hash, keeping it distinct from the signature challenge
+const NONCE_COEFFICIENT_LABEL: &[u8] = b"com.tari.musig2.nonce_coefficient";
+
+//-----------------------------------------------   Nonces   ---------------------------------------------------------//
+
+/// A signer's pair of secret nonces, \\( (r_{i,1}, r_{i,2}) \\).
+///
+/// This type is deliberately not `Clone`, and is consumed when calculating a partial signature. Using the same
+/// secret nonce for two different signing sessions WILL leak your secret key.
+pub struct MuSig2SecretNonce {
+    r1: RistrettoSecretKey,
+    r2: RistrettoSecretKey,
+}
+
+impl MuSig2SecretNonce {
+    /// Generate a fresh pair of secret nonces
+    pub fn random<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        MuSig2SecretNonce {
+            r1: RistrettoSecretKey::random(rng),
+            r2: RistrettoSecretKey::random(rng),
+        }
+    }
+
+    /// Return the public nonce pair that should be shared with the other participants
+    pub fn public_nonce(&self) -> MuSig2PublicNonce {
+        MuSig2PublicNonce {
+            r1: RistrettoPublicKey::from_secret_key(&self.r1),
+            r2: RistrettoPublicKey::from_secret_key(&self.r2),
+        }
+    }
+}
+
+/// A signer's pair of public nonces, \\( (R_{i,1}, R_{i,2}) \\), as shared during the nonce exchange
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MuSig2PublicNonce {
+    r1: RistrettoPublicKey,
+    r2: RistrettoPublicKey,
+}
+
+impl MuSig2PublicNonce {
+    pub fn new(r1: RistrettoPublicKey, r2: RistrettoPublicKey) -> Self {
+        MuSig2PublicNonce { r1, r2 }
+    }
+
+    pub fn r1(&self) -> &RistrettoPublicKey {
+        &self.r1
+    }
+
+    pub fn r2(&self) -> &RistrettoPublicKey {
+        &self.r2
+    }
+}
+
+//-----------------------------------------------   Session   --------------------------------------------------------//
+
+/// The values every participant derives once all public nonces have been collected
+struct SigningContext {
+    nonce_coefficient: RistrettoSecretKey,
+    aggregate_nonce: RistrettoPublicKey,
+    challenge: RistrettoSecretKey,
+}
+
+/// A MuSig2 signing session for a fixed set of participants and a fixed message.
+///
+/// Alice and Bob construct a 2-of-2 aggregated signature:
+///
+/// ```edition2018
+///       # use tari_crypto::ristretto::{ musig2::*, ristretto_keys::* };
+///       # use tari_crypto::keys::PublicKey;
+///       # use sha2::Sha256;
+///       let mut rng = rand::thread_rng();
+///       let (k_a, p_a) = RistrettoPublicKey::random_keypair(&mut rng);
+///       let (k_b, p_b) = RistrettoPublicKey::random_keypair(&mut rng);
+///       // Each party sets up a session with every participant's public key and the message
+///       let mut alice = RistrettoMuSig2::<Sha256>::new(vec![p_a.clone(), p_b.clone()], b"Discworld").unwrap();
+///       let mut bob = RistrettoMuSig2::<Sha256>::new(vec![p_b.clone(), p_a.clone()], b"Discworld").unwrap();
+///       // Round 1 - exchange public nonces
+///       let n_a = MuSig2SecretNonce::random(&mut rng);
+///       let n_b = MuSig2SecretNonce::random(&mut rng);
+///       for session in [&mut alice, &mut bob].iter_mut() {
+///           session.add_public_nonce(&p_a, n_a.public_nonce()).unwrap();
+///           session.add_public_nonce(&p_b, n_b.public_nonce()).unwrap();
+///       }
+///       // Round 2 - exchange partial signatures
+///       let s_a = alice.calculate_partial_signature(&p_a, &k_a, n_a).unwrap();
+///       let s_b = bob.calculate_partial_signature(&p_b, &k_b, n_b).unwrap();
+///       alice.add_partial_signature(&p_a, s_a.clone(), true).unwrap();
+///       alice.add_partial_signature(&p_b, s_b.clone(), true).unwrap();
+///       bob.add_partial_signature(&p_b, s_b, true).unwrap();
+///       bob.add_partial_signature(&p_a, s_a, true).unwrap();
+///       let sig = alice.aggregate_signature().unwrap();
+///       assert_eq!(sig, bob.aggregate_signature().unwrap());
+///       // The result is an ordinary Schnorr signature on the joint key
+///       let e = alice.get_challenge().unwrap();
+///       assert!(sig.verify(alice.get_aggregated_public_key(), &e));
+/// ```
+pub struct RistrettoMuSig2<D: Digest> {
+    joint_key: JointPubKey,
+    message: MessageHash,
+    public_nonces: Vec<Option<MuSig2PublicNonce>>,
+    partial_signatures: Vec<Option<RistrettoSchnorr>>,
+    digest_type: PhantomData<D>,
+}
+
+impl<D: Digest + Clone> RistrettoMuSig2<D> {
+    /// Create a new session for the given participants and message. The public keys may be supplied in any order;
+    /// they are sorted when the joint key is built.
+    pub fn new(pub_keys: Vec<RistrettoPublicKey>, message: &[u8]) -> Result<Self, MuSigError> {
+        // The joint key and the standard Schnorr challenge need a 256 bit hash
+        if D::output_size() != 32 {
+            return Err(MuSigError::IncompatibleHashFunction);
+        }
+        let mut builder = JKBuilder::new(pub_keys.len())?;
+        builder.add_keys(pub_keys)?;
+        let joint_key = builder.build::<D>()?;
+        let n = joint_key.size();
+        Ok(RistrettoMuSig2 {
+            joint_key,
+            message: D::digest(message).to_vec(),
+            public_nonces: vec![None; n],
+            partial_signatures: vec![None; n],
+            digest_type: PhantomData,
+        })
+    }
+
+    /// Return the index of the public key in the sorted participant list, or `ParticipantNotFound`
+    pub fn index_of(&self, pub_key: &RistrettoPublicKey) -> Result<usize, MuSigError> {
+        self.joint_key.index_of(pub_key)
+    }
+
+    /// The joint MuSig public key that the final signature verifies against
+    pub fn get_aggregated_public_key(&self) -> &RistrettoPublicKey {
+        self.joint_key.get_joint_pubkey()
+    }
+
+    /// Record the public nonce pair of the given participant. Each participant may only supply one nonce pair.
+    pub fn add_public_nonce(
+        &mut self,
+        pub_key: &RistrettoPublicKey,
+        nonce: MuSig2PublicNonce,
+    ) -> Result<(), MuSigError> {
+        let index = self.index_of(pub_key)?;
+        if self.public_nonces[index].is_some() {
+            return Err(MuSigError::DuplicateNonce);
+        }
+        self.public_nonces[index] = Some(nonce);
+        Ok(())
+    }
+
+    /// Returns true once every participant's public nonce has been collected
+    pub fn has_all_nonces(&self) -> bool {
+        self.public_nonces.iter().all(Option::is_some)
+    }
+
+    /// The aggregate nonce, \\( R = R_1 + b R_2 \\), or `MismatchedNonces` if nonces are still being collected
+    pub fn get_aggregated_nonce(&self) -> Result<RistrettoPublicKey, MuSigError> {
+        Ok(self.signing_context()?.aggregate_nonce)
+    }
+
+    /// The standard challenge \\( e = H(R || X || m) \\), or `MismatchedNonces` if nonces are still being collected
+    pub fn get_challenge(&self) -> Result<RistrettoSecretKey, MuSigError> {
+        Ok(self.signing_context()?.challenge)
+    }
+
+    /// Calculate my partial signature, \\( s_i = r_{i,1} + b r_{i,2} + e a_i k_i \\). The secret nonce is consumed,
+    /// and must correspond to the public nonce previously registered for `pub_key`.
+    pub fn calculate_partial_signature(
+        &self,
+        pub_key: &RistrettoPublicKey,
+        secret: &RistrettoSecretKey,
+        nonce: MuSig2SecretNonce,
+    ) -> Result<RistrettoSchnorr, MuSigError> {
+        let index = self.index_of(pub_key)?;
+        let ctx = self.signing_context()?;
+        let public_nonce = self.public_nonces[index].as_ref().ok_or(MuSigError::MismatchedNonces)?;
+        if &nonce.public_nonce() != public_nonce {
+            return Err(MuSigError::InvalidSecretNonce);
+        }
+        let b = &ctx.nonce_coefficient;
+        let a_i = self.joint_key.get_musig_scalar(index);
+        let s = &nonce.r1 + b * &nonce.r2 + a_i * &ctx.challenge * secret;
+        let r = &public_nonce.r1 + b * &public_nonce.r2;
+        Ok(SchnorrSignature::new(r, s))
+    }
+
+    /// Add a participant's partial signature to the session, optionally validating it first.
+    pub fn add_partial_signature(
+        &mut self,
+        pub_key: &RistrettoPublicKey,
+        signature: RistrettoSchnorr,
+        should_validate: bool,
+    ) -> Result<(), MuSigError> {
+        let index = self.index_of(pub_key)?;
+        if self.partial_signatures[index].is_some() {
+            return Err(MuSigError::DuplicatePartialSignature);
+        }
+        if should_validate && !self.validate_partial_signature(index, &signature)? {
+            return Err(MuSigError::InvalidPartialSignature(index));
+        }
+        self.partial_signatures[index] = Some(signature);
+        Ok(())
+    }
+
+    /// Sum the partial signatures into the final signature, \\( (R, \sum s_i) \\). Returns `MismatchedSignatures` if
+    /// partial signatures are still outstanding, or `InvalidAggregateSignature` if the result does not verify.
+    pub fn aggregate_signature(&self) -> Result<RistrettoSchnorr, MuSigError> {
+        let ctx = self.signing_context()?;
+        let mut s = RistrettoSecretKey::default();
+        for sig in self.partial_signatures.iter() {
+            let sig = sig.as_ref().ok_or(MuSigError::MismatchedSignatures)?;
+            s = s + sig.get_signature();
+        }
+        let signature = SchnorrSignature::new(ctx.aggregate_nonce, s);
+        if !signature.verify(self.get_aggregated_public_key(), &ctx.challenge) {
+            return Err(MuSigError::InvalidAggregateSignature);
+        }
+        Ok(signature)
+    }
+
+    fn validate_partial_signature(&self, index: usize, signature: &RistrettoSchnorr) -> Result<bool, MuSigError> {
+        // s_i = r_{i,1} + b.r_{i,2} + e.a_i.k_i, so
+        // s_i.G = R_{i,1} + b.R_{i,2} + e.a_i.P_i
+        let ctx = self.signing_context()?;
+        let public_nonce = self.public_nonces[index].as_ref().ok_or(MuSigError::MismatchedNonces)?;
+        let r = &public_nonce.r1 + &ctx.nonce_coefficient * &public_nonce.r2;
+        if signature.get_public_nonce() != &r {
+            return Ok(false);
+        }
+        let p = self.joint_key.get_musig_scalar(index) * self.joint_key.get_pub_keys(index);
+        Ok(signature.verify(&p, &ctx.challenge))
+    }
+
+    fn signing_context(&self) -> Result<SigningContext, MuSigError> {
+        let mut r1 = RistrettoPublicKey::default();
+        let mut r2 = RistrettoPublicKey::default();
+        for nonce in self.public_nonces.iter() {
+            let nonce = nonce.as_ref().ok_or(MuSigError::MismatchedNonces)?;
+            r1 = r1 + &nonce.r1;
+            r2 = r2 + &nonce.r2;
+        }
+        let joint_key = self.get_aggregated_public_key();
+        let mut transcript = Transcript::<D>::new(NONCE_COEFFICIENT_LABEL);
+        transcript.append_point(b"X", joint_key);
+        transcript.append_point(b"R1", &r1);
+        transcript.append_point(b"R2", &r2);
+        transcript.append_message(b"message", &self.message);
+        let nonce_coefficient = transcript.challenge_scalar(b"b");
+        let aggregate_nonce = r1 + &nonce_coefficient * r2;
+        let e = D::new()
+            .chain(aggregate_nonce.as_bytes())
+            .chain(joint_key.as_bytes())
+            .chain(&self.message)
+            .finalize();
+        let challenge =
+            RistrettoSecretKey::from_bytes(&e).expect("Found a u256 that does not map to a valid Ristretto scalar");
+        Ok(SigningContext {
+            nonce_coefficient,
+            aggregate_nonce,
+            challenge,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    struct Signer {
+        secret: RistrettoSecretKey,
+        pub_key: RistrettoPublicKey,
+        nonce: MuSig2SecretNonce,
+    }
+
+    fn create_session(n: usize, msg: &[u8]) -> (RistrettoMuSig2<Sha256>, Vec<Signer>) {
+        let mut rng = rand::thread_rng();
+        let signers: Vec<Signer> = (0..n)
+            .map(|_| {
+                let (secret, pub_key) = RistrettoPublicKey::random_keypair(&mut rng);
+                let nonce = MuSig2SecretNonce::random(&mut rng);
+                Signer { secret, pub_key, nonce }
+            })
+            .collect();
+        let pub_keys = signers.iter().map(|s| s.pub_key.clone()).collect();
+        let mut session = RistrettoMuSig2::<Sha256>::new(pub_keys, msg).unwrap();
+        for s in signers.iter() {
+            session.add_public_nonce(&s.pub_key, s.nonce.public_nonce()).unwrap();
+        }
+        (session, signers)
+    }
+
+    #[test]
+    fn three_of_three() {
+        let (mut session, signers) = create_session(3, b"Three rings for the Elven-kings");
+        assert!(session.has_all_nonces());
+        let partials: Vec<(RistrettoPublicKey, RistrettoSchnorr)> = signers
+            .into_iter()
+            .map(|s| {
+                let sig = session
+                    .calculate_partial_signature(&s.pub_key, &s.secret, s.nonce)
+                    .unwrap();
+                (s.pub_key, sig)
+            })
+            .collect();
+        for (p, sig) in partials {
+            session.add_partial_signature(&p, sig, true).unwrap();
+        }
+        let sig = session.aggregate_signature().unwrap();
+        let e = session.get_challenge().unwrap();
+        assert_eq!(sig.get_public_nonce(), &session.get_aggregated_nonce().unwrap());
+        assert!(sig.verify(session.get_aggregated_public_key(), &e));
+    }
+
+    #[test]
+    fn single_signer() {
+        let (mut session, mut signers) = create_session(1, b"Solo");
+        let s = signers.remove(0);
+        let sig = session
+            .calculate_partial_signature(&s.pub_key, &s.secret, s.nonce)
+            .unwrap();
+        session.add_partial_signature(&s.pub_key, sig, true).unwrap();
+        assert!(session.aggregate_signature().is_ok());
+    }
+
+    #[test]
+    fn incomplete_nonces() {
+        let mut rng = rand::thread_rng();
+        let (k_a, p_a) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (_, p_b) = RistrettoPublicKey::random_keypair(&mut rng);
+        let n_a = MuSig2SecretNonce::random(&mut rng);
+        let mut session = RistrettoMuSig2::<Sha256>::new(vec![p_a.clone(), p_b], b"msg").unwrap();
+        session.add_public_nonce(&p_a, n_a.public_nonce()).unwrap();
+        assert!(!session.has_all_nonces());
+        assert_eq!(session.get_challenge().unwrap_err(), MuSigError::MismatchedNonces);
+        assert_eq!(
+            session.calculate_partial_signature(&p_a, &k_a, n_a).unwrap_err(),
+            MuSigError::MismatchedNonces
+        );
+    }
+
+    #[test]
+    fn duplicate_nonce() {
+        let (mut session, signers) = create_session(2, b"msg");
+        let nonce = signers[0].nonce.public_nonce();
+        assert_eq!(
+            session.add_public_nonce(&signers[0].pub_key, nonce).unwrap_err(),
+            MuSigError::DuplicateNonce
+        );
+    }
+
+    #[test]
+    fn mismatched_secret_nonce() {
+        let (session, mut signers) = create_session(2, b"msg");
+        let s = signers.remove(0);
+        let other_nonce = MuSig2SecretNonce::random(&mut rand::thread_rng());
+        assert_eq!(
+            session
+                .calculate_partial_signature(&s.pub_key, &s.secret, other_nonce)
+                .unwrap_err(),
+            MuSigError::InvalidSecretNonce
+        );
+    }
+
+    #[test]
+    fn invalid_partial_signature() {
+        let (mut session, mut signers) = create_session(2, b"msg");
+        let s0 = signers.remove(0);
+        let s1 = signers.remove(0);
+        let index = session.index_of(&s1.pub_key).unwrap();
+        // Signer 1 signs with the wrong secret key
+        let bad = session
+            .calculate_partial_signature(&s1.pub_key, &s0.secret, s1.nonce)
+            .unwrap();
+        assert_eq!(
+            session
+                .add_partial_signature(&s1.pub_key, bad.clone(), true)
+                .unwrap_err(),
+            MuSigError::InvalidPartialSignature(index)
+        );
+        // Without validation, the bad partial is only caught at aggregation
+        session.add_partial_signature(&s1.pub_key, bad, false).unwrap();
+        let good = session
+            .calculate_partial_signature(&s0.pub_key, &s0.secret, s0.nonce)
+            .unwrap();
+        session.add_partial_signature(&s0.pub_key, good, true).unwrap();
+        assert_eq!(
+            session.aggregate_signature().unwrap_err(),
+            MuSigError::InvalidAggregateSignature
+        );
+    }
+
+    #[test]
+    fn missing_partial_signature() {
+        let (mut session, mut signers) = create_session(2, b"msg");
+        let s = signers.remove(0);
+        let sig = session
+            .calculate_partial_signature(&s.pub_key, &s.secret, s.nonce)
+            .unwrap();
+        session.add_partial_signature(&s.pub_key, sig.clone(), true).unwrap();
+        assert_eq!(
+            session.add_partial_signature(&s.pub_key, sig, true).unwrap_err(),
+            MuSigError::DuplicatePartialSignature
+        );
+        assert_eq!(
+            session.aggregate_signature().unwrap_err(),
+            MuSigError::MismatchedSignatures
+        );
+    }
+
+    #[test]
+    fn unknown_participant() {
+        let (mut session, _) = create_session(2, b"msg");
+        let (_, p) = RistrettoPublicKey::random_keypair(&mut rand::thread_rng());
+        let nonce = MuSig2SecretNonce::random(&mut rand::thread_rng()).public_nonce();
+        assert_eq!(
+            session.add_public_nonce(&p, nonce).unwrap_err(),
+            MuSigError::ParticipantNotFound
+        );
+    }
+}
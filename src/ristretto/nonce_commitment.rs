@@ -0,0 +1,161 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Hash commitments to public nonces, for commit-reveal nonce exchange.
+//!
+//! Multi-party signing protocols that sum public nonces (such as MuSig) must have every party commit to its nonce
+//! before any nonce is revealed; otherwise the last party to reveal can choose its nonce as a function of the others.
+//! A [NonceCommitment] is the binding hash, _H(label || R)_, that is shared in the commit round, and
+//! [NonceCommitment::verify] checks a revealed nonce against it. [CommittedNonce] keeps a secret nonce together with
+//! its public nonce and commitment, and wipes the secret from memory when dropped.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+};
+use clear_on_drop::clear::Clear;
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+
+const NONCE_COMMITMENT_LABEL: &[u8] = b"com.tari.nonce_commitment";
+
+/// A binding hash commitment, _H(label || R)_, to a public nonce
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NonceCommitment(Vec<u8>);
+
+impl NonceCommitment {
+    /// Commit to `public_nonce`
+    pub fn new<D: Digest>(public_nonce: &RistrettoPublicKey) -> Self {
+        let hash = D::new()
+            .chain(NONCE_COMMITMENT_LABEL)
+            .chain(public_nonce.as_bytes())
+            .finalize();
+        NonceCommitment(hash.to_vec())
+    }
+
+    /// Check that a revealed `public_nonce` is the one that was committed to
+    pub fn verify<D: Digest>(&self, public_nonce: &RistrettoPublicKey) -> bool {
+        *self == NonceCommitment::new::<D>(public_nonce)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A secret nonce with its public nonce and commitment.
+///
+/// This type is deliberately not `Clone`. The secret nonce is only released by consuming the value with
+/// [into_secret](#method.into_secret), and is cleared from memory when the `CommittedNonce` is dropped.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::nonce_commitment::CommittedNonce;
+/// # use sha2::Sha256;
+/// let mut rng = rand::thread_rng();
+/// let nonce = CommittedNonce::random::<Sha256, _>(&mut rng);
+/// // Share the commitment first, and only reveal the public nonce once everyone has committed
+/// let commitment = nonce.commitment().clone();
+/// assert!(commitment.verify::<Sha256>(nonce.public_nonce()));
+/// ```
+pub struct CommittedNonce {
+    secret: RistrettoSecretKey,
+    public_nonce: RistrettoPublicKey,
+    commitment: NonceCommitment,
+}
+
+impl CommittedNonce {
+    /// Generate a fresh secret nonce and commit to its public nonce
+    pub fn random<D: Digest, R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        let secret = RistrettoSecretKey::random(rng);
+        let public_nonce = RistrettoPublicKey::from_secret_key(&secret);
+        let commitment = NonceCommitment::new::<D>(&public_nonce);
+        CommittedNonce {
+            secret,
+            public_nonce,
+            commitment,
+        }
+    }
+
+    /// The commitment, to share in the commit round
+    pub fn commitment(&self) -> &NonceCommitment {
+        &self.commitment
+    }
+
+    /// The public nonce, to reveal once every party has committed
+    pub fn public_nonce(&self) -> &RistrettoPublicKey {
+        &self.public_nonce
+    }
+
+    /// Consume the committed nonce and release the secret nonce, e.g. to calculate a partial signature
+    pub fn into_secret(mut self) -> RistrettoSecretKey {
+        let secret = RistrettoSecretKey(self.secret.0);
+        self.secret.0.clear();
+        secret
+    }
+}
+
+impl Drop for CommittedNonce {
+    fn drop(&mut self) {
+        self.secret.0.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    #[test]
+    fn commit_and_verify() {
+        let mut rng = rand::thread_rng();
+        let nonce = CommittedNonce::random::<Sha256, _>(&mut rng);
+        let other = CommittedNonce::random::<Sha256, _>(&mut rng);
+        assert!(nonce.commitment().verify::<Sha256>(nonce.public_nonce()));
+        assert!(!nonce.commitment().verify::<Sha256>(other.public_nonce()));
+        assert!(!nonce.commitment().verify::<sha2::Sha512>(nonce.public_nonce()));
+        // The commitment is not a bare hash of the nonce
+        assert_ne!(
+            nonce.commitment().as_bytes(),
+            Sha256::digest(nonce.public_nonce().as_bytes()).as_slice()
+        );
+    }
+
+    #[test]
+    fn into_secret() {
+        let mut rng = rand::thread_rng();
+        let nonce = CommittedNonce::random::<Sha256, _>(&mut rng);
+        let public_nonce = nonce.public_nonce().clone();
+        let secret = nonce.into_secret();
+        assert_eq!(RistrettoPublicKey::from_secret_key(&secret), public_nonce);
+    }
+
+    #[test]
+    fn serialize_commitment() {
+        let mut rng = rand::thread_rng();
+        let nonce = CommittedNonce::random::<Sha256, _>(&mut rng);
+        let json = serde_json::to_string(nonce.commitment()).unwrap();
+        let commitment: NonceCommitment = serde_json::from_str(&json).unwrap();
+        assert_eq!(&commitment, nonce.commitment());
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Zero-knowledge proofs of knowledge of a commitment opening.
+//!
+//! A [CommitmentOpeningProof] shows that the prover knows the value, _v_, and blinding factor, _k_, of a Pedersen
+//! commitment _C = k.G + v.H_ without revealing either. It is a [RistrettoComSig] made non-interactive with a
+//! Fiat-Shamir challenge drawn from a [Transcript] over everything the verifier relies on: _G, H, C_, the public
+//! nonce _R_ and the context.
+//!
+//! Binding the generators and an application-supplied `context` stops a proof made for one factory or protocol from
+//! being replayed in another. Use a distinct context (e.g. a protocol name and session id) for each use.
+//!
+//! A [MultiOpeningProof] proves knowledge of the openings of many commitments at once, in the same space as a single
+//! proof. The commitments are folded into one with weights, _w_i_, drawn from a transcript over _G, H, n_, every
+//! commitment _C_1, ..., C_n_ and the context, and a single [CommitmentOpeningProof] is made for _\sum w_i.C_i_. Since
+//! the weights can't be predicted before the commitments are fixed, a prover that doesn't know every opening can't
+//! produce an opening of the sum.
+
+use crate::{
+    commitment::HomomorphicCommitmentFactory,
+    keys::SecretKey,
+    ristretto::{
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        transcript::Transcript,
+        RistrettoComSig,
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+    },
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar, traits::VartimeMultiscalarMul};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+const CHALLENGE_LABEL: &[u8] = b"com.tari.opening_proof.challenge";
+const MULTI_OPENING_LABEL: &[u8] = b"com.tari.opening_proof.multi_opening";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum OpeningProofError {
+    #[error("A multi-opening proof needs at least one commitment")]
+    EmptyBatch,
+}
+
+/// A proof of knowledge of the opening, _(k, v)_, of a Pedersen commitment.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{opening_proof::CommitmentOpeningProof, pedersen::PedersenCommitmentFactory};
+/// # use tari_crypto::ristretto::RistrettoSecretKey;
+/// # use tari_crypto::commitment::HomomorphicCommitmentFactory;
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let factory = PedersenCommitmentFactory::default();
+/// let k = RistrettoSecretKey::random(&mut rng);
+/// let v = RistrettoSecretKey::from(42);
+/// let commitment = factory.commit(&k, &v);
+/// let proof = CommitmentOpeningProof::prove::<Blake256, _>(&k, &v, &factory, b"example", &mut rng);
+/// assert!(proof.verify::<Blake256>(&commitment, &factory, b"example"));
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitmentOpeningProof(RistrettoComSig);
+
+impl CommitmentOpeningProof {
+    /// Prove knowledge of the blinding factor and value of `factory.commit(blinding_factor, value)`
+    pub fn prove<D: Digest + Clone, R: Rng + CryptoRng>(
+        blinding_factor: &RistrettoSecretKey,
+        value: &RistrettoSecretKey,
+        factory: &PedersenCommitmentFactory,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Self {
+        let commitment = factory.commit(blinding_factor, value);
+        let nonce_k = RistrettoSecretKey::random(rng);
+        let nonce_v = RistrettoSecretKey::random(rng);
+        let public_nonce = factory.commit(&nonce_k, &nonce_v);
+        let challenge = challenge::<D>(factory, &commitment, &public_nonce, context);
+        let signature = RistrettoComSig::sign_raw(
+            value.clone(),
+            blinding_factor.clone(),
+            nonce_v,
+            nonce_k,
+            challenge.as_bytes(),
+            factory,
+        )
+        .expect("A transcript challenge is a canonical scalar");
+        CommitmentOpeningProof(signature)
+    }
+
+    /// Check the proof against `commitment`, for the same factory and context it was made with
+    pub fn verify<D: Digest + Clone>(
+        &self,
+        commitment: &PedersenCommitment,
+        factory: &PedersenCommitmentFactory,
+        context: &[u8],
+    ) -> bool {
+        let challenge = challenge::<D>(factory, commitment, self.0.public_nonce(), context);
+        self.0.verify_challenge(commitment, challenge.as_bytes(), factory)
+    }
+
+    /// The underlying commitment signature
+    pub fn as_commitment_signature(&self) -> &RistrettoComSig {
+        &self.0
+    }
+}
+
+/// A single proof of knowledge of the openings of a batch of Pedersen commitments.
+///
+/// The proof is the size of one [CommitmentOpeningProof], however many commitments it covers.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{opening_proof::MultiOpeningProof, pedersen::PedersenCommitmentFactory};
+/// # use tari_crypto::ristretto::RistrettoSecretKey;
+/// # use tari_crypto::commitment::HomomorphicCommitmentFactory;
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let factory = PedersenCommitmentFactory::default();
+/// let openings = (1..=5u64)
+///     .map(|v| (RistrettoSecretKey::random(&mut rng), RistrettoSecretKey::from(v)))
+///     .collect::<Vec<_>>();
+/// let commitments = openings.iter().map(|(k, v)| factory.commit(k, v)).collect::<Vec<_>>();
+/// let proof = MultiOpeningProof::prove::<Blake256, _>(&openings, &factory, b"audit", &mut rng).unwrap();
+/// assert!(proof.verify::<Blake256>(&commitments, &factory, b"audit"));
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiOpeningProof(CommitmentOpeningProof);
+
+impl MultiOpeningProof {
+    /// Prove knowledge of every `(blinding_factor, value)` opening in `openings`
+    pub fn prove<D: Digest + Clone, R: Rng + CryptoRng>(
+        openings: &[(RistrettoSecretKey, RistrettoSecretKey)],
+        factory: &PedersenCommitmentFactory,
+        context: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, OpeningProofError> {
+        if openings.is_empty() {
+            return Err(OpeningProofError::EmptyBatch);
+        }
+        let commitments = openings.iter().map(|(k, v)| factory.commit(k, v)).collect::<Vec<_>>();
+        let (seed, weights) = multi_opening_weights::<D>(factory, &commitments, context);
+        let mut k_sum = Scalar::zero();
+        let mut v_sum = Scalar::zero();
+        for ((k, v), w) in openings.iter().zip(weights.iter()) {
+            k_sum += w * k.0;
+            v_sum += w * v.0;
+        }
+        let k_sum = RistrettoSecretKey(k_sum);
+        let v_sum = RistrettoSecretKey(v_sum);
+        Ok(MultiOpeningProof(CommitmentOpeningProof::prove::<D, R>(
+            &k_sum, &v_sum, factory, &seed, rng,
+        )))
+    }
+
+    /// Check the proof against `commitments`, in the order the openings were given to the prover
+    pub fn verify<D: Digest + Clone>(
+        &self,
+        commitments: &[PedersenCommitment],
+        factory: &PedersenCommitmentFactory,
+        context: &[u8],
+    ) -> bool {
+        if commitments.is_empty() {
+            return false;
+        }
+        let (seed, weights) = multi_opening_weights::<D>(factory, commitments, context);
+        let folded = RistrettoPoint::vartime_multiscalar_mul(&weights, commitments.iter().map(|c| c.0.point));
+        let folded = PedersenCommitment::from_public_key(&RistrettoPublicKey::new_from_pk(folded));
+        self.0.verify::<D>(&folded, factory, &seed)
+    }
+
+    /// The opening proof for the weighted sum of the commitments
+    pub fn as_opening_proof(&self) -> &CommitmentOpeningProof {
+        &self.0
+    }
+}
+
+/// Returns the seed that binds the batch, and the weight for each commitment
+fn multi_opening_weights<D: Digest + Clone>(
+    factory: &PedersenCommitmentFactory,
+    commitments: &[PedersenCommitment],
+    context: &[u8],
+) -> (Vec<u8>, Vec<Scalar>) {
+    let mut transcript = Transcript::<D>::new(MULTI_OPENING_LABEL);
+    transcript.append_message(b"G", factory.G.compress().as_bytes());
+    transcript.append_message(b"H", factory.H.compress().as_bytes());
+    transcript.append_u64(b"n", commitments.len() as u64);
+    for c in commitments {
+        transcript.append_message(b"C", c.as_bytes());
+    }
+    transcript.append_message(b"context", context);
+    let mut seed = vec![0u8; 32];
+    transcript.challenge_bytes(b"seed", &mut seed);
+    let weights = commitments
+        .iter()
+        .map(|_| transcript.challenge_scalar(b"w").0)
+        .collect();
+    (seed, weights)
+}
+
+fn challenge<D: Digest + Clone>(
+    factory: &PedersenCommitmentFactory,
+    commitment: &PedersenCommitment,
+    public_nonce: &PedersenCommitment,
+    context: &[u8],
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_message(b"G", factory.G.compress().as_bytes());
+    transcript.append_message(b"H", factory.H.compress().as_bytes());
+    transcript.append_message(b"C", commitment.as_bytes());
+    transcript.append_message(b"R", public_nonce.as_bytes());
+    transcript.append_message(b"context", context);
+    transcript.challenge_scalar(b"e")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::Blake256, ristretto::pedersen::RISTRETTO_PEDERSEN_G};
+
+    #[test]
+    fn prove_and_verify() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let v = RistrettoSecretKey::from(100);
+        let commitment = factory.commit(&k, &v);
+        let proof = CommitmentOpeningProof::prove::<Blake256, _>(&k, &v, &factory, b"Hogfather", &mut rng);
+        assert!(proof.verify::<Blake256>(&commitment, &factory, b"Hogfather"));
+        // The proof is bound to the context, commitment and generators
+        assert!(!proof.verify::<Blake256>(&commitment, &factory, b"Feet of Clay"));
+        assert!(!proof.verify::<Blake256>(&factory.commit_value(&k, 101), &factory, b"Hogfather"));
+        let other_factory = PedersenCommitmentFactory::new(RISTRETTO_PEDERSEN_G, factory.G + factory.H);
+        assert!(!proof.verify::<Blake256>(&commitment, &other_factory, b"Hogfather"));
+    }
+
+    #[test]
+    fn wrong_opening() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let commitment = factory.commit_value(&k, 100);
+        let proof = CommitmentOpeningProof::prove::<Blake256, _>(
+            &k,
+            &RistrettoSecretKey::from(99),
+            &factory,
+            b"Hogfather",
+            &mut rng,
+        );
+        assert!(!proof.verify::<Blake256>(&commitment, &factory, b"Hogfather"));
+    }
+
+    #[test]
+    fn wide_hash_function() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let v = RistrettoSecretKey::from(100);
+        let commitment = factory.commit(&k, &v);
+        let proof = CommitmentOpeningProof::prove::<sha2::Sha512, _>(&k, &v, &factory, b"Hogfather", &mut rng);
+        assert!(proof.verify::<sha2::Sha512>(&commitment, &factory, b"Hogfather"));
+        assert!(!proof.verify::<Blake256>(&commitment, &factory, b"Hogfather"));
+    }
+
+    #[test]
+    fn multi_opening() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let mut openings = (0..8u64)
+            .map(|v| (RistrettoSecretKey::random(&mut rng), RistrettoSecretKey::from(v * 10)))
+            .collect::<Vec<_>>();
+        let commitments = openings.iter().map(|(k, v)| factory.commit(k, v)).collect::<Vec<_>>();
+        let proof = MultiOpeningProof::prove::<Blake256, _>(&openings, &factory, b"Thief of Time", &mut rng).unwrap();
+        assert!(proof.verify::<Blake256>(&commitments, &factory, b"Thief of Time"));
+        assert!(!proof.verify::<Blake256>(&commitments, &factory, b"Night Watch"));
+        // Order, membership and every opening matter
+        let mut swapped = commitments.clone();
+        swapped.swap(2, 5);
+        assert!(!proof.verify::<Blake256>(&swapped, &factory, b"Thief of Time"));
+        assert!(!proof.verify::<Blake256>(&commitments[..7], &factory, b"Thief of Time"));
+        assert!(!proof.verify::<Blake256>(&[], &factory, b"Thief of Time"));
+        openings[3].1 = RistrettoSecretKey::from(31);
+        let bad = MultiOpeningProof::prove::<Blake256, _>(&openings, &factory, b"Thief of Time", &mut rng).unwrap();
+        assert!(!bad.verify::<Blake256>(&commitments, &factory, b"Thief of Time"));
+        assert_eq!(
+            MultiOpeningProof::prove::<Blake256, _>(&[], &factory, b"Thief of Time", &mut rng).unwrap_err(),
+            OpeningProofError::EmptyBatch
+        );
+        // The proof survives serialization
+        let bytes = bincode::serialize(&proof).unwrap();
+        let restored: MultiOpeningProof = bincode::deserialize(&bytes).unwrap();
+        assert!(restored.verify::<Blake256>(&commitments, &factory, b"Thief of Time"));
+    }
+}
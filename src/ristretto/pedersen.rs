@@ -22,13 +22,28 @@
 
 use crate::{
     commitment::HomomorphicCommitment,
-    ristretto::{constants::RISTRETTO_NUMS_POINTS, RistrettoPublicKey},
+    ristretto::{
+        constants::{derive_generators, RISTRETTO_NUMS_POINTS},
+        RistrettoPublicKey,
+    },
+};
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoBasepointTable, RistrettoPoint},
+    traits::MultiscalarMul,
 };
-use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, traits::MultiscalarMul};
 
 use crate::{commitment::HomomorphicCommitmentFactory, ristretto::RistrettoSecretKey};
 use curve25519_dalek::scalar::Scalar;
-use std::{borrow::Borrow, iter::Sum};
+use rand::{CryptoRng, Rng};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::{
+    borrow::Borrow,
+    iter::Sum,
+    ops::{Add, Mul, Sub},
+};
+use subtle::{Choice, ConstantTimeEq};
 
 pub const RISTRETTO_PEDERSEN_G: RistrettoPoint = RISTRETTO_BASEPOINT_POINT;
 lazy_static! {
@@ -37,6 +52,17 @@ lazy_static! {
 
 pub type PedersenCommitment = HomomorphicCommitment<RistrettoPublicKey>;
 
+impl PedersenCommitment {
+    /// The compressed (canonical 32-byte) form of the commitment. Ristretto encodings are unique, so two commitments
+    /// are equal exactly when their compressed forms are.
+    pub fn as_compressed(&self) -> &CompressedRistretto {
+        &self.0.compressed
+    }
+}
+
+/// Batches at least this large are committed to with precomputed tables
+const BATCH_PRECOMPUTE_THRESHOLD: usize = 64;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[allow(non_snake_case)]
 pub struct PedersenCommitmentFactory {
@@ -51,6 +77,110 @@ impl PedersenCommitmentFactory {
     pub fn new(G: RistrettoPoint, H: RistrettoPoint) -> PedersenCommitmentFactory {
         PedersenCommitmentFactory { G, H }
     }
+
+    /// Create a factory that uses the default _G_ (so that blinding factors remain compatible with keys), and a value
+    /// generator, _H_, derived from `label` with [derive_generators]. Commitments made with different labels cannot be
+    /// confused with one another, or with those of the default factory.
+    pub fn new_with_label(label: &[u8]) -> PedersenCommitmentFactory {
+        PedersenCommitmentFactory::new(RISTRETTO_PEDERSEN_G, derive_generators(label, 1)[0])
+    }
+
+    /// Create a factory with the default bases and precomputed tables for both of them. See
+    /// [PrecomputedPedersenCommitmentFactory].
+    pub fn new_precomputed() -> PrecomputedPedersenCommitmentFactory {
+        PedersenCommitmentFactory::default().precompute()
+    }
+
+    /// Build precomputed tables for this factory's bases
+    pub fn precompute(&self) -> PrecomputedPedersenCommitmentFactory {
+        PrecomputedPedersenCommitmentFactory {
+            G_table: Box::new(RistrettoBasepointTable::create(&self.G)),
+            H_table: Box::new(RistrettoBasepointTable::create(&self.H)),
+            factory: self.clone(),
+        }
+    }
+
+    /// Commit to each `(k, v)` pair in `openings`, returning the commitments in the same order. Large batches build
+    /// precomputed tables once and share them across the batch; with the `rayon` feature they are also calculated in
+    /// parallel.
+    pub fn commit_batch(&self, openings: &[(RistrettoSecretKey, RistrettoSecretKey)]) -> Vec<PedersenCommitment> {
+        if openings.len() < BATCH_PRECOMPUTE_THRESHOLD {
+            openings.iter().map(|(k, v)| self.commit(k, v)).collect()
+        } else {
+            self.precompute().commit_batch(openings)
+        }
+    }
+
+    /// Check that each `(k, v)` pair in `openings` opens the commitment at the same position in `commitments`.
+    ///
+    /// Rather than recalculating every commitment, the openings are checked with a single random linear combination,
+    /// _\sum w_i.C_i = (\sum w_i.k_i).G + (\sum w_i.v_i).H_, where the weights _w_i_ are drawn from `rng`. A batch
+    /// with an invalid opening passes with negligible probability. Returns false if the slices have different lengths.
+    pub fn open_batch<R: Rng + CryptoRng>(
+        &self,
+        openings: &[(RistrettoSecretKey, RistrettoSecretKey)],
+        commitments: &[PedersenCommitment],
+        rng: &mut R,
+    ) -> bool {
+        if openings.len() != commitments.len() {
+            return false;
+        }
+        let mut k_sum = Scalar::zero();
+        let mut v_sum = Scalar::zero();
+        let mut weights = Vec::with_capacity(commitments.len() + 2);
+        for (k, v) in openings {
+            let w = Scalar::random(rng);
+            k_sum += w * k.0;
+            v_sum += w * v.0;
+            weights.push(-w);
+        }
+        weights.push(k_sum);
+        weights.push(v_sum);
+        let points = commitments
+            .iter()
+            .map(|c| c.0.point)
+            .chain(vec![self.G, self.H])
+            .collect::<Vec<_>>();
+        // The weighted sum of the commitments should cancel out exactly
+        RistrettoPoint::multiscalar_mul(&weights, &points) == RistrettoPoint::default()
+    }
+
+    /// Test, in constant time, whether the blinding factor `k` and value `v` open `commitment`. Use this rather than
+    /// comparing commitments yourself when the opening is secret, e.g. in wallet code. The `Choice` can be combined
+    /// with other constant-time checks before it is converted to a `bool`.
+    ///
+    /// The comparison is made on compressed points, which `commitment` already carries, so it is never decompressed
+    /// or re-encoded.
+    pub fn open_ct(&self, k: &RistrettoSecretKey, v: &RistrettoSecretKey, commitment: &PedersenCommitment) -> Choice {
+        self.commit(k, v).as_compressed().ct_eq(commitment.as_compressed())
+    }
+
+    /// Commit to a signed value. A negative value _-v_ is encoded as the field negation of _v_, so the commitment is
+    /// _k.G - v.H_, and the usual homomorphism carries over to signed arithmetic: the sum of commitments to _a_ and _b_
+    /// is a commitment to _a + b_, and `C(a) - C(b)` is a commitment to _a - b_, whatever their signs (provided the
+    /// true sum fits in an `i64`).
+    ///
+    /// Non-negative values give exactly the same commitment as [commit_value](#method.commit_value). Note that a
+    /// commitment to a negative value is a commitment to a huge field element, and so will never pass a range proof.
+    pub fn commit_signed_value(&self, k: &RistrettoSecretKey, value: i64) -> PedersenCommitment {
+        self.commit(k, &signed_value(value))
+    }
+
+    /// Test whether the given private key and signed value open the given commitment. See
+    /// [commit_signed_value](#method.commit_signed_value).
+    pub fn open_signed_value(&self, k: &RistrettoSecretKey, value: i64, commitment: &PedersenCommitment) -> bool {
+        self.open(k, &signed_value(value), commitment)
+    }
+}
+
+/// Map a signed value to a scalar, with negative values becoming the field negation of their magnitude
+fn signed_value(value: i64) -> RistrettoSecretKey {
+    let magnitude = Scalar::from(value.unsigned_abs());
+    if value < 0 {
+        RistrettoSecretKey(-magnitude)
+    } else {
+        RistrettoSecretKey(magnitude)
+    }
 }
 
 /// The default Ristretto Commitment factory uses the Base point for x25519 and its first Blake256 hash.
@@ -75,8 +205,71 @@ impl HomomorphicCommitmentFactory for PedersenCommitmentFactory {
     }
 
     fn open(&self, k: &RistrettoSecretKey, v: &RistrettoSecretKey, commitment: &PedersenCommitment) -> bool {
-        let c_test = self.commit(k, v);
-        commitment.0 == c_test.0
+        self.open_ct(k, v, commitment).into()
+    }
+
+    fn commit_value(&self, k: &RistrettoSecretKey, value: u64) -> PedersenCommitment {
+        let v = RistrettoSecretKey::from(value);
+        self.commit(k, &v)
+    }
+
+    fn open_value(&self, k: &RistrettoSecretKey, v: u64, commitment: &HomomorphicCommitment<Self::P>) -> bool {
+        let kv = RistrettoSecretKey::from(v);
+        self.open(k, &kv, commitment)
+    }
+}
+
+/// A Pedersen commitment factory that uses fixed-base precomputation for _G_ and _H_.
+///
+/// Building the tables takes a little time and about 60kB of memory, but each commitment is then considerably faster
+/// to calculate than with [PedersenCommitmentFactory]. It's worth it when creating or opening many commitments with
+/// the same factory, e.g. when rescanning a wallet. The commitments are identical to those of the factory the
+/// tables were built from.
+#[derive(Clone)]
+#[allow(non_snake_case)]
+pub struct PrecomputedPedersenCommitmentFactory {
+    G_table: Box<RistrettoBasepointTable>,
+    H_table: Box<RistrettoBasepointTable>,
+    factory: PedersenCommitmentFactory,
+}
+
+impl PrecomputedPedersenCommitmentFactory {
+    /// The factory (and bases) that the tables were built from
+    pub fn factory(&self) -> &PedersenCommitmentFactory {
+        &self.factory
+    }
+
+    /// Commit to each `(k, v)` pair in `openings`, returning the commitments in the same order. With the `rayon`
+    /// feature, the commitments are calculated in parallel.
+    pub fn commit_batch(&self, openings: &[(RistrettoSecretKey, RistrettoSecretKey)]) -> Vec<PedersenCommitment> {
+        #[cfg(feature = "rayon")]
+        let iter = openings.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let iter = openings.iter();
+        iter.map(|(k, v)| self.commit(k, v)).collect()
+    }
+
+    /// Test, in constant time, whether the blinding factor `k` and value `v` open `commitment`. See
+    /// [PedersenCommitmentFactory::open_ct].
+    pub fn open_ct(&self, k: &RistrettoSecretKey, v: &RistrettoSecretKey, commitment: &PedersenCommitment) -> Choice {
+        self.commit(k, v).as_compressed().ct_eq(commitment.as_compressed())
+    }
+}
+
+impl HomomorphicCommitmentFactory for PrecomputedPedersenCommitmentFactory {
+    type P = RistrettoPublicKey;
+
+    fn commit(&self, k: &RistrettoSecretKey, v: &RistrettoSecretKey) -> PedersenCommitment {
+        let c = &k.0 * &*self.G_table + &v.0 * &*self.H_table;
+        HomomorphicCommitment(RistrettoPublicKey::new_from_pk(c))
+    }
+
+    fn zero(&self) -> PedersenCommitment {
+        self.factory.zero()
+    }
+
+    fn open(&self, k: &RistrettoSecretKey, v: &RistrettoSecretKey, commitment: &PedersenCommitment) -> bool {
+        self.open_ct(k, v, commitment).into()
     }
 
     fn commit_value(&self, k: &RistrettoSecretKey, value: u64) -> PedersenCommitment {
@@ -105,6 +298,32 @@ where T: Borrow<PedersenCommitment>
     }
 }
 
+define_add_variants!(
+    LHS = PedersenCommitment,
+    RHS = PedersenCommitment,
+    Output = PedersenCommitment
+);
+define_sub_variants!(
+    LHS = PedersenCommitment,
+    RHS = PedersenCommitment,
+    Output = PedersenCommitment
+);
+define_mul_variants!(
+    LHS = PedersenCommitment,
+    RHS = RistrettoSecretKey,
+    Output = PedersenCommitment
+);
+define_add_variants!(
+    LHS = PedersenCommitment,
+    RHS = RistrettoPublicKey,
+    Output = PedersenCommitment
+);
+define_sub_variants!(
+    LHS = PedersenCommitment,
+    RHS = RistrettoPublicKey,
+    Output = PedersenCommitment
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -145,6 +364,204 @@ mod test {
         assert_eq!(b, &c - &a);
     }
 
+    #[test]
+    fn commitment_operators() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k1 = RistrettoSecretKey::random(&mut rng);
+        let k2 = RistrettoSecretKey::random(&mut rng);
+        let c1 = factory.commit_value(&k1, 100);
+        let c2 = factory.commit_value(&k2, 40);
+        // Owned and borrowed operands all agree
+        let diff = factory.commit(&(&k1 - &k2), &RistrettoSecretKey::from(60));
+        assert_eq!(c1.clone() - c2.clone(), diff);
+        assert_eq!(c1.clone() - &c2, diff);
+        assert_eq!(&c1 - c2.clone(), diff);
+        assert_eq!(c1.clone() + c2.clone(), &c1 + &c2);
+        let three = RistrettoSecretKey::from(3);
+        let tripled = factory.commit(&(&k1 * &three), &RistrettoSecretKey::from(300));
+        assert_eq!(c1.clone() * three.clone(), tripled);
+        assert_eq!(&c1 * three.clone(), tripled);
+        assert_eq!(c1.clone() * &three, tripled);
+        // Negation
+        assert_eq!(&c1 + &(-&c1), factory.zero());
+        assert_eq!(-(c1.clone() - &c2), &c2 - &c1);
+        // Checked variants reject identity results
+        assert_eq!(c1.checked_sub(&c2), Some(diff));
+        assert_eq!(c1.checked_sub(&c1), None);
+        assert_eq!(c1.checked_add(&-&c1), None);
+        assert_eq!(c1.checked_mul(&three), Some(tripled));
+        assert_eq!(c1.checked_mul(&RistrettoSecretKey::default()), None);
+    }
+
+    #[test]
+    fn commitment_and_key_operators() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let x = RistrettoSecretKey::random(&mut rng);
+        let p = RistrettoPublicKey::from_secret_key(&x);
+        let c = factory.commit_value(&k, 25);
+        let sum = factory.commit_value(&(&k + &x), 25);
+        assert_eq!(&c + &p, sum);
+        assert_eq!(c.clone() + &p, sum);
+        assert_eq!(&c + p.clone(), sum);
+        assert_eq!(c.clone() + p.clone(), sum);
+        let diff = factory.commit_value(&(&k - &x), 25);
+        assert_eq!(&c - &p, diff);
+        assert_eq!(c.clone() - &p, diff);
+        assert_eq!(&c - p.clone(), diff);
+        assert_eq!(c.clone() - p.clone(), diff);
+        // Sums over references and owned values agree
+        let keys = vec![k.clone(), x.clone()];
+        let commitments = vec![c.clone(), factory.commit_value(&x, 5)];
+        assert_eq!(
+            commitments.iter().sum::<PedersenCommitment>(),
+            factory.commit_value(&(&k + &x), 30)
+        );
+        assert_eq!(keys.iter().sum::<RistrettoSecretKey>(), &k + &x);
+        assert_eq!(keys.into_iter().sum::<RistrettoSecretKey>(), &k + &x);
+        let points = vec![p.clone(), RistrettoPublicKey::from_secret_key(&k)];
+        assert_eq!(points.iter().sum::<RistrettoPublicKey>(), &p + &points[1]);
+        assert_eq!(
+            Vec::<RistrettoPublicKey>::new().into_iter().sum::<RistrettoPublicKey>(),
+            RistrettoPublicKey::default()
+        );
+    }
+
+    #[test]
+    fn precomputed_factory() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let precomputed = PedersenCommitmentFactory::new_precomputed();
+        assert_eq!(precomputed.factory(), &factory);
+        for _ in 0..10 {
+            let k = RistrettoSecretKey::random(&mut rng);
+            let v = RistrettoSecretKey::random(&mut rng);
+            let c = factory.commit(&k, &v);
+            assert_eq!(precomputed.commit(&k, &v), c);
+            assert!(precomputed.open(&k, &v, &c));
+            assert!(!precomputed.open(&v, &k, &c));
+        }
+        let k = RistrettoSecretKey::random(&mut rng);
+        assert!(precomputed.open_value(&k, 42, &factory.commit_value(&k, 42)));
+        assert_eq!(precomputed.zero(), factory.zero());
+        // Tables can be built for any bases
+        let custom = PedersenCommitmentFactory::new(RISTRETTO_NUMS_POINTS[1], RISTRETTO_NUMS_POINTS[2]);
+        assert_eq!(custom.precompute().commit(&k, &k), custom.commit(&k, &k));
+    }
+
+    #[test]
+    fn commit_batch() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        for &n in &[0, 1, 10, BATCH_PRECOMPUTE_THRESHOLD + 10] {
+            let openings: Vec<_> = (0..n)
+                .map(|_| {
+                    (
+                        RistrettoSecretKey::random(&mut rng),
+                        RistrettoSecretKey::random(&mut rng),
+                    )
+                })
+                .collect();
+            let expected: Vec<_> = openings.iter().map(|(k, v)| factory.commit(k, v)).collect();
+            assert_eq!(factory.commit_batch(&openings), expected);
+            assert_eq!(factory.precompute().commit_batch(&openings), expected);
+        }
+    }
+
+    #[test]
+    fn open_batch() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let mut openings: Vec<_> = (0..20)
+            .map(|_| {
+                (
+                    RistrettoSecretKey::random(&mut rng),
+                    RistrettoSecretKey::random(&mut rng),
+                )
+            })
+            .collect();
+        let commitments = factory.commit_batch(&openings);
+        assert!(factory.open_batch(&openings, &commitments, &mut rng));
+        assert!(factory.open_batch(&[], &[], &mut rng));
+        assert!(!factory.open_batch(&openings[1..], &commitments, &mut rng));
+        openings[7].1 = &openings[7].1 + &RistrettoSecretKey::from(1);
+        assert!(!factory.open_batch(&openings, &commitments, &mut rng));
+        assert!(factory.open_batch(&openings[..7], &commitments[..7], &mut rng));
+    }
+
+    #[test]
+    fn constant_time_open() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let precomputed = factory.precompute();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let v = RistrettoSecretKey::from(1_234);
+        let c = factory.commit(&k, &v);
+        assert!(bool::from(factory.open_ct(&k, &v, &c)));
+        assert!(bool::from(precomputed.open_ct(&k, &v, &c)));
+        assert!(!bool::from(factory.open_ct(&v, &k, &c)));
+        assert!(!bool::from(precomputed.open_ct(&k, &k, &c)));
+        let both = factory.open_ct(&k, &v, &c) & factory.open_ct(&k, &k, &c);
+        assert!(!bool::from(both));
+    }
+
+    #[test]
+    fn compressed_commitments() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let c = factory.commit_value(&k, 99);
+        assert_eq!(c.as_compressed().as_bytes(), c.as_bytes());
+        assert_eq!(c.as_compressed().decompress(), Some(c.as_public_key().point));
+        let c2 = PedersenCommitment::from_bytes(c.as_bytes()).unwrap();
+        assert_eq!(c2.as_compressed(), c.as_compressed());
+        assert!(factory.open_value(&k, 99, &c2));
+        assert!(!factory.open_value(&k, 98, &c2));
+    }
+
+    #[test]
+    fn signed_values() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k1 = RistrettoSecretKey::random(&mut rng);
+        let k2 = RistrettoSecretKey::random(&mut rng);
+        assert_eq!(factory.commit_signed_value(&k1, 42), factory.commit_value(&k1, 42));
+        let debit = factory.commit_signed_value(&k1, -500);
+        assert!(factory.open_signed_value(&k1, -500, &debit));
+        assert!(!factory.open_signed_value(&k1, 500, &debit));
+        assert!(!factory.open_value(&k1, 500, &debit));
+        let credit = factory.commit_signed_value(&k2, 300);
+        let k_sum = &k1 + &k2;
+        assert!(factory.open_signed_value(&k_sum, -200, &(&debit + &credit)));
+        let k_diff = &k2 - &k1;
+        assert!(factory.open_signed_value(&k_diff, 800, &(&credit - &debit)));
+        // A value and its negation cancel out
+        let c = &factory.commit_signed_value(&k1, 77) + &factory.commit_signed_value(&k2, -77);
+        assert!(factory.open_value(&k_sum, 0, &c));
+        let c = factory.commit_signed_value(&k1, i64::MIN);
+        assert!(factory.open_signed_value(&k1, i64::MIN, &c));
+        assert!(!factory.open_signed_value(&k1, i64::MAX, &c));
+    }
+
+    #[test]
+    fn labelled_factory() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::new_with_label(b"Unseen Academicals");
+        assert_eq!(factory.G, RISTRETTO_PEDERSEN_G);
+        assert_ne!(factory.H, *RISTRETTO_PEDERSEN_H);
+        assert_eq!(
+            factory,
+            PedersenCommitmentFactory::new_with_label(b"Unseen Academicals")
+        );
+        assert_ne!(factory, PedersenCommitmentFactory::new_with_label(b"Making Money"));
+        let k = RistrettoSecretKey::random(&mut rng);
+        let c = factory.commit_value(&k, 10);
+        assert!(factory.open_value(&k, 10, &c));
+        assert!(!PedersenCommitmentFactory::default().open_value(&k, 10, &c));
+    }
+
     #[test]
     fn check_g_ne_h() {
         assert_ne!(RISTRETTO_PEDERSEN_G, *RISTRETTO_PEDERSEN_H);
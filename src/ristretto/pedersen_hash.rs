@@ -0,0 +1,181 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A Pedersen hash function over Ristretto.
+//!
+//! The message is split into 4-bit windows, taken low nibble first, and the windows are grouped into segments of
+//! [SEGMENT_WINDOWS]. Segment _s_, with windows _m_0, m_1, ..._, maps to the point _(\sum (m_j + 1).17^j).G_s_, and the
+//! hash is the sum of the segment points. The generators _G_s_ come from [derive_generators] with their own label, so
+//! nobody knows a discrete log relation between them, or with the commitment generators.
+//!
+//! Each window contributes a digit between 1 and 16 in base 17, and a segment's scalar stays below the group order, so
+//! distinct messages (including those that differ only by trailing zeros) give distinct scalar vectors. A collision
+//! would therefore reveal a discrete log relation between the generators. Being a sum of scalar multiples, the hash
+//! is algebraic, which is what makes it useful inside commitments and accumulators. Note that it is _not_ a random
+//! oracle: it is collision resistant, but its output is structured. The empty message hashes to the identity.
+//!
+//! The value is the same as a windowed-lookup Pedersen hash whose table for window _j_ of segment _s_ holds
+//! _d.17^j.G_s_ for the digits _d_ in 1..=16. Rather than keep those tables (976 points per segment, for an
+//! unbounded number of segments), the hasher folds a segment's digits into its scalar as they arrive and does one
+//! scalar multiplication when the segment closes. Precomputed tables could be added later without changing any
+//! output.
+//!
+//! [derive_generators]: crate::ristretto::constants::derive_generators
+
+use crate::ristretto::constants::derive_generator;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+
+const PEDERSEN_HASH_LABEL: &[u8] = b"com.tari.ristretto.pedersen_hash";
+/// The number of 4-bit windows in each segment. It is the largest number for which _17^n_ is less than the group
+/// order.
+pub const SEGMENT_WINDOWS: usize = 61;
+const WINDOW_BASE: u64 = 17;
+
+/// Hash `bytes` to a point with the Pedersen hash. This is shorthand for [PedersenHasher].
+pub fn pedersen_hash(bytes: &[u8]) -> RistrettoPoint {
+    PedersenHasher::new().chain(bytes).finalize()
+}
+
+/// An incremental Pedersen hasher, with an interface like that of [Digest](digest::Digest). The result depends only
+/// on the concatenation of the data passed to it, not on how it was split up.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::pedersen_hash::{pedersen_hash, PedersenHasher};
+/// let mut hasher = PedersenHasher::new();
+/// hasher.update(b"Good ");
+/// hasher.update(b"Omens");
+/// assert_eq!(hasher.finalize(), pedersen_hash(b"Good Omens"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PedersenHasher {
+    total: RistrettoPoint,
+    segment: u64,
+    segment_scalar: Scalar,
+    power: Scalar,
+    windows: usize,
+}
+
+impl PedersenHasher {
+    pub fn new() -> Self {
+        PedersenHasher {
+            total: RistrettoPoint::default(),
+            segment: 0,
+            segment_scalar: Scalar::zero(),
+            power: Scalar::one(),
+            windows: 0,
+        }
+    }
+
+    /// Feed `data` into the hash
+    pub fn update<B: AsRef<[u8]>>(&mut self, data: B) {
+        for byte in data.as_ref() {
+            self.absorb_window(byte & 0x0f);
+            self.absorb_window(byte >> 4);
+        }
+    }
+
+    /// Feed `data` into the hash, in a chained manner
+    pub fn chain<B: AsRef<[u8]>>(mut self, data: B) -> Self {
+        self.update(data);
+        self
+    }
+
+    /// Return the hash of all the data fed in so far
+    pub fn finalize(mut self) -> RistrettoPoint {
+        self.close_segment();
+        self.total
+    }
+
+    fn absorb_window(&mut self, window: u8) {
+        self.segment_scalar += self.power * Scalar::from(u64::from(window) + 1);
+        self.power *= Scalar::from(WINDOW_BASE);
+        self.windows += 1;
+        if self.windows == SEGMENT_WINDOWS {
+            self.close_segment();
+        }
+    }
+
+    fn close_segment(&mut self) {
+        if self.windows == 0 {
+            return;
+        }
+        self.total += self.segment_scalar * derive_generator(PEDERSEN_HASH_LABEL, self.segment);
+        self.segment += 1;
+        self.segment_scalar = Scalar::zero();
+        self.power = Scalar::one();
+        self.windows = 0;
+    }
+}
+
+impl Default for PedersenHasher {
+    fn default() -> Self {
+        PedersenHasher::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ristretto::constants::derive_generators;
+
+    #[test]
+    fn known_structure() {
+        let g = derive_generators(PEDERSEN_HASH_LABEL, 2);
+        // 0x21 has windows 1, 2, so maps to (2 + 3 * 17).G_0
+        assert_eq!(pedersen_hash(&[0x21]), Scalar::from(53u64) * g[0]);
+        assert_eq!(pedersen_hash(&[]), RistrettoPoint::default());
+        // 31 bytes is 62 windows, so the last one lands in a second segment
+        let message = [0u8; 31];
+        let mut first = Scalar::zero();
+        let mut power = Scalar::one();
+        for _ in 0..SEGMENT_WINDOWS {
+            first += power;
+            power *= Scalar::from(WINDOW_BASE);
+        }
+        assert_eq!(pedersen_hash(&message), first * g[0] + g[1]);
+    }
+
+    #[test]
+    fn incremental_hashing() {
+        let message = (0..200u8).collect::<Vec<_>>();
+        let expected = pedersen_hash(&message);
+        for split in &[0, 1, 30, 31, 61, 199, 200] {
+            let (a, b) = message.split_at(*split);
+            assert_eq!(PedersenHasher::new().chain(a).chain(b).finalize(), expected);
+        }
+        let mut hasher = PedersenHasher::default();
+        for byte in &message {
+            hasher.update(&[*byte]);
+        }
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    #[test]
+    fn distinct_messages() {
+        let messages: [&[u8]; 6] = [b"", b"\x00", b"\x00\x00", b"\x01", b"\x10", b"Small Gods"];
+        for (i, a) in messages.iter().enumerate() {
+            for b in &messages[i + 1..] {
+                assert_ne!(pedersen_hash(a), pedersen_hash(b));
+            }
+        }
+    }
+}
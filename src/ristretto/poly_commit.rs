@@ -0,0 +1,315 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Pedersen commitments to polynomials, with evaluation proofs.
+//!
+//! A polynomial _f(x) = a_0 + a_1.x + ... + a_d.x^d_ is committed to coefficient by coefficient, with the
+//! coefficients of a random blinding polynomial _r(x)_ as blinding factors: _C_j = r_j.G + a_j.H_. The commitment is
+//! perfectly hiding, so it reveals nothing about _f_ (in particular, nothing about the secret _f(0)_).
+//!
+//! Because the commitments are homomorphic, anyone can compute a commitment to the evaluation of _f_ at any point _z_:
+//!
+//!   \\( \sum_j z^j C_j = r(z).G + f(z).H \\)
+//!
+//! The evaluation proof for _z_ is just the pair _(f(z), r(z))_, which must open this commitment. This is the
+//! building block for Pedersen verifiable secret sharing: share _i_ is the evaluation at _z = i_, sent privately to
+//! party _i_, who checks it against the public [PolynomialCommitment].
+
+use crate::{
+    commitment::{HomomorphicCommitment, HomomorphicCommitmentFactory},
+    keys::{PublicKey, SecretKey},
+    ristretto::{
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+    },
+};
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PolyCommitError {
+    #[error("A polynomial must have at least one coefficient")]
+    EmptyPolynomial,
+    #[error("The polynomial and its blinding polynomial must have the same degree")]
+    DegreeMismatch,
+    #[error("A polynomial commitment needs at least one coefficient commitment")]
+    EmptyCommitment,
+}
+
+/// A polynomial over the Ristretto scalar field. The coefficients are secret, and are cleared from memory when the
+/// polynomial is dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<RistrettoSecretKey>,
+}
+
+impl Polynomial {
+    /// Create a polynomial from its coefficients, lowest degree first
+    pub fn new(coefficients: Vec<RistrettoSecretKey>) -> Result<Self, PolyCommitError> {
+        if coefficients.is_empty() {
+            return Err(PolyCommitError::EmptyPolynomial);
+        }
+        Ok(Polynomial { coefficients })
+    }
+
+    /// A random polynomial of the given degree
+    pub fn random<R: Rng + CryptoRng>(degree: usize, rng: &mut R) -> Self {
+        let coefficients = (0..=degree).map(|_| RistrettoSecretKey::random(rng)).collect();
+        Polynomial { coefficients }
+    }
+
+    /// A random polynomial of the given degree with constant term `secret`, i.e. _f(0) = secret_
+    pub fn random_with_secret<R: Rng + CryptoRng>(secret: &RistrettoSecretKey, degree: usize, rng: &mut R) -> Self {
+        let mut poly = Polynomial::random(degree, rng);
+        poly.coefficients[0] = secret.clone();
+        poly
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// The coefficients, lowest degree first
+    pub fn coefficients(&self) -> &[RistrettoSecretKey] {
+        &self.coefficients
+    }
+
+    /// Evaluate the polynomial at `x`, using Horner's rule
+    pub fn evaluate(&self, x: &RistrettoSecretKey) -> RistrettoSecretKey {
+        let mut result = RistrettoSecretKey::default();
+        for a in self.coefficients.iter().rev() {
+            result = &(&result * x) + a;
+        }
+        result
+    }
+}
+
+/// A public commitment to a polynomial: one Pedersen commitment per coefficient
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<PedersenCommitment>", into = "Vec<PedersenCommitment>")]
+pub struct PolynomialCommitment {
+    commitments: Vec<PedersenCommitment>,
+}
+
+impl PolynomialCommitment {
+    /// Commit to `poly`, using the coefficients of `blinding` as blinding factors
+    pub fn commit(
+        poly: &Polynomial,
+        blinding: &Polynomial,
+        factory: &PedersenCommitmentFactory,
+    ) -> Result<Self, PolyCommitError> {
+        if poly.degree() != blinding.degree() {
+            return Err(PolyCommitError::DegreeMismatch);
+        }
+        let commitments = poly
+            .coefficients
+            .iter()
+            .zip(&blinding.coefficients)
+            .map(|(a, r)| factory.commit(r, a))
+            .collect();
+        Ok(PolynomialCommitment { commitments })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.commitments.len() - 1
+    }
+
+    /// The coefficient commitments, lowest degree first
+    pub fn commitments(&self) -> &[PedersenCommitment] {
+        &self.commitments
+    }
+
+    /// Calculate the commitment to the evaluation at `x`, \\( \sum_j x^j C_j = r(x).G + f(x).H \\)
+    pub fn evaluate(&self, x: &RistrettoSecretKey) -> PedersenCommitment {
+        let mut powers = Vec::with_capacity(self.commitments.len());
+        let mut power = RistrettoSecretKey::from(1);
+        for _ in 0..self.commitments.len() {
+            let next = &power * x;
+            powers.push(power);
+            power = next;
+        }
+        let points = self
+            .commitments
+            .iter()
+            .map(|c| c.as_public_key().clone())
+            .collect::<Vec<_>>();
+        HomomorphicCommitment::from_public_key(&RistrettoPublicKey::batch_mul_vartime(&powers, &points))
+    }
+
+    /// Check that `evaluation` is the correct evaluation of the committed polynomial at its point
+    pub fn verify_evaluation(&self, evaluation: &PolynomialEvaluation, factory: &PedersenCommitmentFactory) -> bool {
+        factory.open(
+            &evaluation.blinding,
+            &evaluation.value,
+            &self.evaluate(&evaluation.point),
+        )
+    }
+}
+
+impl TryFrom<Vec<PedersenCommitment>> for PolynomialCommitment {
+    type Error = PolyCommitError;
+
+    fn try_from(commitments: Vec<PedersenCommitment>) -> Result<Self, Self::Error> {
+        if commitments.is_empty() {
+            return Err(PolyCommitError::EmptyCommitment);
+        }
+        Ok(PolynomialCommitment { commitments })
+    }
+}
+
+impl From<PolynomialCommitment> for Vec<PedersenCommitment> {
+    fn from(commitment: PolynomialCommitment) -> Self {
+        commitment.commitments
+    }
+}
+
+/// The evaluation of a committed polynomial at a point, with the evaluation of the blinding polynomial that proves
+/// it: _(z, f(z), r(z))_
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolynomialEvaluation {
+    point: RistrettoSecretKey,
+    value: RistrettoSecretKey,
+    blinding: RistrettoSecretKey,
+}
+
+impl PolynomialEvaluation {
+    pub fn point(&self) -> &RistrettoSecretKey {
+        &self.point
+    }
+
+    /// The value of the polynomial at the point, _f(z)_
+    pub fn value(&self) -> &RistrettoSecretKey {
+        &self.value
+    }
+
+    /// The value of the blinding polynomial at the point, _r(z)_
+    pub fn blinding(&self) -> &RistrettoSecretKey {
+        &self.blinding
+    }
+}
+
+/// A polynomial together with the blinding polynomial used to commit to it; this is the prover's side
+pub struct CommittedPolynomial {
+    poly: Polynomial,
+    blinding: Polynomial,
+}
+
+impl CommittedPolynomial {
+    /// Commit to `poly` with a fresh random blinding polynomial
+    pub fn commit<R: Rng + CryptoRng>(
+        poly: Polynomial,
+        factory: &PedersenCommitmentFactory,
+        rng: &mut R,
+    ) -> (Self, PolynomialCommitment) {
+        let blinding = Polynomial::random(poly.degree(), rng);
+        let commitment =
+            PolynomialCommitment::commit(&poly, &blinding, factory).expect("Degrees match by construction");
+        (CommittedPolynomial { poly, blinding }, commitment)
+    }
+
+    pub fn polynomial(&self) -> &Polynomial {
+        &self.poly
+    }
+
+    /// Open the committed polynomial at `x`
+    pub fn evaluate(&self, x: &RistrettoSecretKey) -> PolynomialEvaluation {
+        PolynomialEvaluation {
+            point: x.clone(),
+            value: self.poly.evaluate(x),
+            blinding: self.blinding.evaluate(x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_polynomial() {
+        // f(x) = 3 + 2x + x^2
+        let poly = Polynomial::new(vec![3u64.into(), 2u64.into(), 1u64.into()]).unwrap();
+        assert_eq!(poly.degree(), 2);
+        assert_eq!(
+            poly.evaluate(&RistrettoSecretKey::default()),
+            RistrettoSecretKey::from(3)
+        );
+        assert_eq!(
+            poly.evaluate(&RistrettoSecretKey::from(5)),
+            RistrettoSecretKey::from(38)
+        );
+        assert_eq!(Polynomial::new(vec![]), Err(PolyCommitError::EmptyPolynomial));
+    }
+
+    #[test]
+    fn evaluation_proofs() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let poly = Polynomial::random_with_secret(&secret, 3, &mut rng);
+        let (committed, commitment) = CommittedPolynomial::commit(poly, &factory, &mut rng);
+        assert_eq!(commitment.degree(), 3);
+        for i in 1..=5u64 {
+            let share = committed.evaluate(&RistrettoSecretKey::from(i));
+            assert!(commitment.verify_evaluation(&share, &factory));
+        }
+        assert_eq!(committed.evaluate(&RistrettoSecretKey::default()).value(), &secret);
+        // A tampered share fails
+        let mut share = committed.evaluate(&RistrettoSecretKey::from(1));
+        share.value = &share.value + &RistrettoSecretKey::from(1);
+        assert!(!commitment.verify_evaluation(&share, &factory));
+        // So does a share for a different point
+        let mut share = committed.evaluate(&RistrettoSecretKey::from(1));
+        share.point = RistrettoSecretKey::from(2);
+        assert!(!commitment.verify_evaluation(&share, &factory));
+    }
+
+    #[test]
+    fn degree_mismatch() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let poly = Polynomial::random(2, &mut rng);
+        let blinding = Polynomial::random(3, &mut rng);
+        assert_eq!(
+            PolynomialCommitment::commit(&poly, &blinding, &factory),
+            Err(PolyCommitError::DegreeMismatch)
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let (_, commitment) = CommittedPolynomial::commit(Polynomial::random(2, &mut rng), &factory, &mut rng);
+        let json = serde_json::to_string(&commitment).unwrap();
+        assert_eq!(serde_json::from_str::<PolynomialCommitment>(&json).unwrap(), commitment);
+        // An empty commitment would have no degree, and would accept any evaluation of zero
+        assert!(serde_json::from_str::<PolynomialCommitment>("[]").is_err());
+        assert_eq!(
+            PolynomialCommitment::try_from(Vec::new()),
+            Err(PolyCommitError::EmptyCommitment)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A public key with a precomputed multiplication table, for keys that are used over and over again.
+//!
+//! Building the table costs about as much as a few dozen scalar multiplications, and after that each multiplication
+//! by the key is several times faster. It pays off for a service that verifies many signatures from a small, fixed
+//! set of signers, or does Diffie-Hellman with the same peer repeatedly.
+
+use crate::ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey};
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_TABLE, ristretto::RistrettoBasepointTable};
+use digest::Digest;
+use std::fmt;
+use tari_utilities::ByteArray;
+
+/// A [RistrettoPublicKey] together with a table of its multiples. The table takes roughly 30kB.
+#[derive(Clone)]
+pub struct PrecomputedPublicKey {
+    key: RistrettoPublicKey,
+    table: Box<RistrettoBasepointTable>,
+}
+
+impl PrecomputedPublicKey {
+    pub fn new(key: RistrettoPublicKey) -> Self {
+        let table = Box::new(RistrettoBasepointTable::create(&key.point));
+        PrecomputedPublicKey { key, table }
+    }
+
+    pub fn public_key(&self) -> &RistrettoPublicKey {
+        &self.key
+    }
+
+    /// Calculate _k.P_ using the table
+    pub fn scalar_mul(&self, k: &RistrettoSecretKey) -> RistrettoPublicKey {
+        RistrettoPublicKey::new_from_pk(&k.0 * &*self.table)
+    }
+
+    /// The Diffie-Hellman shared secret between this key and the secret key `k`. This gives the same result as
+    /// `RistrettoPublicKey::shared_secret`.
+    pub fn shared_secret(&self, k: &RistrettoSecretKey) -> RistrettoPublicKey {
+        self.scalar_mul(k)
+    }
+
+    /// Check a Schnorr signature by this key against the challenge, _e_: _s.G = R + e.P_
+    pub fn verify(&self, signature: &RistrettoSchnorr, challenge: &RistrettoSecretKey) -> bool {
+        let lhs = &signature.get_signature().0 * &RISTRETTO_BASEPOINT_TABLE;
+        let rhs = signature.get_public_nonce().point + &challenge.0 * &*self.table;
+        lhs == rhs
+    }
+
+    /// As [verify](#method.verify), with the challenge given as bytes. Returns false if the bytes are not a valid
+    /// secret key.
+    pub fn verify_challenge(&self, signature: &RistrettoSchnorr, challenge: &[u8]) -> bool {
+        match RistrettoSecretKey::from_bytes(challenge) {
+            Ok(e) => self.verify(signature, &e),
+            Err(_) => false,
+        }
+    }
+
+    /// Check a signature on `message` made with `sign_deterministic`, as `verify_message` does
+    pub fn verify_message<D: Digest>(&self, signature: &RistrettoSchnorr, message: &[u8]) -> bool {
+        let challenge = RistrettoSchnorr::message_challenge::<D>(signature.get_public_nonce(), &self.key, message);
+        self.verify_challenge(signature, &challenge)
+    }
+}
+
+impl From<RistrettoPublicKey> for PrecomputedPublicKey {
+    fn from(key: RistrettoPublicKey) -> Self {
+        PrecomputedPublicKey::new(key)
+    }
+}
+
+impl fmt::Debug for PrecomputedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PrecomputedPublicKey").field(&self.key).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        common::Blake256,
+        keys::{DiffieHellmanSharedSecret, PublicKey, SecretKey},
+    };
+
+    #[test]
+    fn the_colour_of_magic() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let precomputed = PrecomputedPublicKey::from(p.clone());
+        assert_eq!(precomputed.public_key(), &p);
+        let x = RistrettoSecretKey::random(&mut rng);
+        assert_eq!(precomputed.scalar_mul(&x), &x * &p);
+        assert_eq!(precomputed.shared_secret(&x), RistrettoPublicKey::shared_secret(&x, &p));
+
+        let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, b"Rincewind").unwrap();
+        assert!(sig.verify_message::<Blake256>(&p, b"Rincewind"));
+        assert!(precomputed.verify_message::<Blake256>(&sig, b"Rincewind"));
+        assert!(!precomputed.verify_message::<Blake256>(&sig, b"Twoflower"));
+        let other = PrecomputedPublicKey::new(RistrettoPublicKey::random_keypair(&mut rng).1);
+        assert!(!other.verify_message::<Blake256>(&sig, b"Rincewind"));
+        assert!(!precomputed.verify_challenge(&sig, &[0u8; 31]));
+    }
+}
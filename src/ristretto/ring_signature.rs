@@ -0,0 +1,298 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Linkable spontaneous anonymous group (LSAG) signatures, in the "back's LSAG" form described in
+//! [Zero to Monero](https://web.getmonero.org/library/Zero-to-Monero-2-0-0.pdf), section 3.4.
+//!
+//! A ring signature proves that the signer holds the secret key of _one_ of the public keys in the ring, without
+//! revealing which. Every signature also carries the signer's [KeyImage], \\( I = k H_p(P) \\), which is the same
+//! for every signature made with the same key. Two signatures with the same key image were made by the same signer,
+//! so the images can be used to detect double-spends without de-anonymising anyone.
+//!
+//! This module implements LSAG only. CLSAG (section 3.6 of the same reference) pays off when each ring member has an
+//! auxiliary key as well, such as a commitment to zero: it folds both keys into a single response per member, where
+//! LSAG would need a second signature. Rings here are plain [RistrettoPublicKey]s with no auxiliary keys, and with
+//! one key per member CLSAG's aggregation does nothing, so it collapses to the LSAG below. Non-linkable SAG
+//! signatures aren't offered separately either; they are this scheme without the key image.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+const HASH_TO_POINT_LABEL: &[u8] = b"com.tari.ring_signature.hash_to_point";
+const CHALLENGE_LABEL: &[u8] = b"com.tari.ring_signature.challenge";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum RingSignatureError {
+    #[error("The ring must contain at least one public key")]
+    EmptyRing,
+    #[error("The signer's public key is not in the ring")]
+    SignerNotInRing,
+    #[error("Ring signatures require a hash function with a 32 byte digest")]
+    IncompatibleHashFunction,
+}
+
+/// The linking tag of a ring signature, \\( I = k H_p(P) \\). It is unique to the signer's key pair and reveals
+/// nothing about which ring member produced the signature.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KeyImage(RistrettoPublicKey);
+
+impl KeyImage {
+    /// Calculate the key image for the given secret key
+    pub fn new(secret: &RistrettoSecretKey) -> Self {
+        let p = RistrettoPublicKey::from_secret_key(secret);
+        KeyImage(secret * hash_to_point(&p))
+    }
+
+    pub fn as_public_key(&self) -> &RistrettoPublicKey {
+        &self.0
+    }
+}
+
+/// A linkable ring signature, \\( (c_0, r_0, \ldots, r_{n-1}, I) \\).
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{ring_signature::RistrettoRingSignature, RistrettoPublicKey};
+/// # use tari_crypto::keys::PublicKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let mut ring: Vec<RistrettoPublicKey> = (0..4).map(|_| RistrettoPublicKey::random_keypair(&mut rng).1).collect();
+/// ring.insert(2, p);
+/// let sig = RistrettoRingSignature::sign::<Blake256, _>(&k, &ring, b"Going Postal", &mut rng).unwrap();
+/// assert!(sig.verify::<Blake256>(&ring, b"Going Postal"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RistrettoRingSignature {
+    challenge: RistrettoSecretKey,
+    responses: Vec<RistrettoSecretKey>,
+    key_image: KeyImage,
+}
+
+impl RistrettoRingSignature {
+    /// Sign `message` on behalf of `ring`. The public key corresponding to `secret` must be a member of the ring.
+    pub fn sign<D: Digest, R: Rng + CryptoRng>(
+        secret: &RistrettoSecretKey,
+        ring: &[RistrettoPublicKey],
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, RingSignatureError> {
+        if D::output_size() != 32 {
+            return Err(RingSignatureError::IncompatibleHashFunction);
+        }
+        if ring.is_empty() {
+            return Err(RingSignatureError::EmptyRing);
+        }
+        let pub_key = RistrettoPublicKey::from_secret_key(secret);
+        let pi = ring
+            .iter()
+            .position(|p| p == &pub_key)
+            .ok_or(RingSignatureError::SignerNotInRing)?;
+        let n = ring.len();
+        let key_image = KeyImage::new(secret);
+        let prefix = challenge_prefix::<D>(ring, &key_image, message);
+
+        let mut challenges = vec![RistrettoSecretKey::default(); n];
+        let mut responses = vec![RistrettoSecretKey::default(); n];
+        // c_{pi+1} = H(prefix || a.G || a.H_p(P_pi))
+        let alpha = RistrettoSecretKey::random(rng);
+        let l = RistrettoPublicKey::from_secret_key(&alpha);
+        let r = &alpha * hash_to_point(&pub_key);
+        challenges[(pi + 1) % n] = ring_challenge::<D>(&prefix, &l, &r);
+        // Fill in the rest of the ring with random responses
+        let mut i = (pi + 1) % n;
+        while i != pi {
+            responses[i] = RistrettoSecretKey::random(rng);
+            let (l, r) = ring_step(&ring[i], &key_image, &challenges[i], &responses[i]);
+            challenges[(i + 1) % n] = ring_challenge::<D>(&prefix, &l, &r);
+            i = (i + 1) % n;
+        }
+        // Close the ring: r_pi = a - c_pi.k
+        responses[pi] = alpha - &challenges[pi] * secret;
+
+        Ok(RistrettoRingSignature {
+            challenge: challenges[0].clone(),
+            responses,
+            key_image,
+        })
+    }
+
+    /// Verify the signature for `message` against `ring`. The ring must contain the same keys, in the same order, as
+    /// the one used to sign.
+    pub fn verify<D: Digest>(&self, ring: &[RistrettoPublicKey], message: &[u8]) -> bool {
+        if D::output_size() != 32 || ring.is_empty() || ring.len() != self.responses.len() {
+            return false;
+        }
+        if self.key_image.0 == RistrettoPublicKey::default() {
+            return false;
+        }
+        let prefix = challenge_prefix::<D>(ring, &self.key_image, message);
+        let c = ring
+            .iter()
+            .zip(self.responses.iter())
+            .fold(self.challenge.clone(), |c, (p, r)| {
+                let (l, r) = ring_step(p, &self.key_image, &c, r);
+                ring_challenge::<D>(&prefix, &l, &r)
+            });
+        // Implementors should make this a constant time comparison
+        c == self.challenge
+    }
+
+    pub fn key_image(&self) -> &KeyImage {
+        &self.key_image
+    }
+
+    /// Returns true if both signatures were produced with the same secret key
+    pub fn is_linked(&self, other: &RistrettoRingSignature) -> bool {
+        self.key_image == other.key_image
+    }
+
+    /// The number of members in the ring that this signature was made for
+    pub fn ring_size(&self) -> usize {
+        self.responses.len()
+    }
+}
+
+/// Hash a public key to a point whose discrete log with respect to the generator is unknown
+fn hash_to_point(p: &RistrettoPublicKey) -> RistrettoPublicKey {
+    let hasher = Sha512::new().chain(HASH_TO_POINT_LABEL).chain(p.as_bytes());
+    RistrettoPublicKey::new_from_pk(RistrettoPoint::from_hash(hasher))
+}
+
+/// Calculate \\( L_i = r_i G + c_i P_i \\) and \\( R_i = r_i H_p(P_i) + c_i I \\)
+fn ring_step(
+    p: &RistrettoPublicKey,
+    key_image: &KeyImage,
+    c: &RistrettoSecretKey,
+    r: &RistrettoSecretKey,
+) -> (RistrettoPublicKey, RistrettoPublicKey) {
+    let l = RistrettoPublicKey::from_secret_key(r) + c * p;
+    let r = r * hash_to_point(p) + c * &key_image.0;
+    (l, r)
+}
+
+/// Bind every challenge to the ring, the key image and the message
+fn challenge_prefix<D: Digest>(ring: &[RistrettoPublicKey], key_image: &KeyImage, message: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new().chain(CHALLENGE_LABEL);
+    for p in ring {
+        hasher = hasher.chain(p.as_bytes());
+    }
+    hasher.chain(key_image.0.as_bytes()).chain(message).finalize().to_vec()
+}
+
+fn ring_challenge<D: Digest>(prefix: &[u8], l: &RistrettoPublicKey, r: &RistrettoPublicKey) -> RistrettoSecretKey {
+    let e = D::new()
+        .chain(prefix)
+        .chain(l.as_bytes())
+        .chain(r.as_bytes())
+        .finalize();
+    RistrettoSecretKey::from_bytes(&e).expect("Found a u256 that does not map to a valid Ristretto scalar")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+
+    fn random_ring(n: usize) -> (Vec<RistrettoSecretKey>, Vec<RistrettoPublicKey>) {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| RistrettoPublicKey::random_keypair(&mut rng)).unzip()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut rng = rand::thread_rng();
+        for n in 1..6 {
+            let (secrets, ring) = random_ring(n);
+            for k in secrets.iter() {
+                let sig = RistrettoRingSignature::sign::<Blake256, _>(k, &ring, b"Night Watch", &mut rng).unwrap();
+                assert_eq!(sig.ring_size(), n);
+                assert_eq!(sig.key_image(), &KeyImage::new(k));
+                assert!(sig.verify::<Blake256>(&ring, b"Night Watch"));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_signatures() {
+        let mut rng = rand::thread_rng();
+        let (secrets, ring) = random_ring(4);
+        let sig = RistrettoRingSignature::sign::<Blake256, _>(&secrets[1], &ring, b"Thud!", &mut rng).unwrap();
+        // Wrong message
+        assert!(!sig.verify::<Blake256>(&ring, b"Snuff"));
+        // Re-ordered ring
+        let mut shuffled = ring.clone();
+        shuffled.swap(0, 1);
+        assert!(!sig.verify::<Blake256>(&shuffled, b"Thud!"));
+        // Truncated ring
+        assert!(!sig.verify::<Blake256>(&ring[..3], b"Thud!"));
+        // Tampered response
+        let mut bad = sig.clone();
+        bad.responses[2] = RistrettoSecretKey::random(&mut rng);
+        assert!(!bad.verify::<Blake256>(&ring, b"Thud!"));
+        // Key image from a different key
+        let mut bad = sig;
+        bad.key_image = KeyImage::new(&secrets[0]);
+        assert!(!bad.verify::<Blake256>(&ring, b"Thud!"));
+    }
+
+    #[test]
+    fn linkability() {
+        let mut rng = rand::thread_rng();
+        let (secrets, ring) = random_ring(3);
+        let (_, other_ring) = random_ring(3);
+        let mut other_ring = other_ring;
+        other_ring[0] = ring[0].clone();
+        let s1 = RistrettoRingSignature::sign::<Blake256, _>(&secrets[0], &ring, b"msg 1", &mut rng).unwrap();
+        let s2 = RistrettoRingSignature::sign::<Blake256, _>(&secrets[0], &other_ring, b"msg 2", &mut rng).unwrap();
+        let s3 = RistrettoRingSignature::sign::<Blake256, _>(&secrets[1], &ring, b"msg 1", &mut rng).unwrap();
+        assert!(s1.is_linked(&s2));
+        assert!(!s1.is_linked(&s3));
+    }
+
+    #[test]
+    fn errors() {
+        let mut rng = rand::thread_rng();
+        let (_, ring) = random_ring(3);
+        let k = RistrettoSecretKey::random(&mut rng);
+        assert_eq!(
+            RistrettoRingSignature::sign::<Blake256, _>(&k, &ring, b"msg", &mut rng).unwrap_err(),
+            RingSignatureError::SignerNotInRing
+        );
+        assert_eq!(
+            RistrettoRingSignature::sign::<Blake256, _>(&k, &[], b"msg", &mut rng).unwrap_err(),
+            RingSignatureError::EmptyRing
+        );
+        assert_eq!(
+            RistrettoRingSignature::sign::<Sha512, _>(&k, &ring, b"msg", &mut rng).unwrap_err(),
+            RingSignatureError::IncompatibleHashFunction
+        );
+    }
+}
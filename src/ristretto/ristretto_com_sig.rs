@@ -105,12 +105,15 @@ mod test {
         keys::{PublicKey, SecretKey},
         ristretto::{
             pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+            test_common::signature_batch,
             RistrettoComSig,
             RistrettoPublicKey,
             RistrettoSecretKey,
         },
+        signatures::CommitmentSignatureError,
     };
     use digest::Digest;
+    use rand::rngs::ThreadRng;
     use tari_utilities::{hex::from_hex, ByteArray};
 
     #[test]
@@ -227,4 +230,95 @@ mod test {
         assert_eq!(bytes.capacity(), bytes.len());
         assert!(bytes.iter().all(|b| *b == 0x00));
     }
+
+    fn create_signature_batch(n: usize) -> (Vec<RistrettoComSig>, Vec<PedersenCommitment>, Vec<RistrettoSecretKey>) {
+        let factory = PedersenCommitmentFactory::default();
+        let keygen = |rng: &mut ThreadRng| {
+            let a = RistrettoSecretKey::random(rng);
+            let x = RistrettoSecretKey::random(rng);
+            let commitment = factory.commit(&x, &a);
+            ((a, x), commitment)
+        };
+        signature_batch(n, keygen, |(a, x), e, rng| {
+            let (nonce_a, nonce_x) = (RistrettoSecretKey::random(rng), RistrettoSecretKey::random(rng));
+            RistrettoComSig::sign(a, x, nonce_a, nonce_x, e.as_bytes(), &factory).unwrap()
+        })
+    }
+
+    #[test]
+    fn batch_verify() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let (sigs, commitments, mut challenges) = create_signature_batch(10);
+        assert!(RistrettoComSig::batch_verify(
+            &sigs,
+            &commitments,
+            &challenges,
+            &factory,
+            &mut rng
+        ));
+        let challenge_bytes: Vec<&[u8]> = challenges.iter().map(|e| e.as_bytes()).collect();
+        assert!(RistrettoComSig::batch_verify_challenges(
+            &sigs,
+            &commitments,
+            &challenge_bytes,
+            &factory,
+            &mut rng
+        ));
+        // An empty batch is valid
+        assert!(RistrettoComSig::batch_verify(&[], &[], &[], &factory, &mut rng));
+        // Mismatched lengths fail
+        assert!(!RistrettoComSig::batch_verify(
+            &sigs,
+            &commitments[..9],
+            &challenges,
+            &factory,
+            &mut rng
+        ));
+        // A single bad signature fails the whole batch
+        challenges.swap(3, 4);
+        assert!(!RistrettoComSig::batch_verify(
+            &sigs,
+            &commitments,
+            &challenges,
+            &factory,
+            &mut rng
+        ));
+    }
+
+    #[test]
+    fn batch_verify_find_invalid() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let (sigs, commitments, mut challenges) = create_signature_batch(10);
+        let invalid = RistrettoComSig::batch_verify_find_invalid(&sigs, &commitments, &challenges, &factory, &mut rng);
+        assert_eq!(invalid, Ok(vec![]));
+        challenges[0] = RistrettoSecretKey::from(0);
+        challenges[7] = RistrettoSecretKey::from(7);
+        let invalid = RistrettoComSig::batch_verify_find_invalid(&sigs, &commitments, &challenges, &factory, &mut rng);
+        assert_eq!(invalid, Ok(vec![0, 7]));
+        assert_eq!(
+            RistrettoComSig::batch_verify_find_invalid(&sigs, &commitments[..9], &challenges, &factory, &mut rng),
+            Err(CommitmentSignatureError::BatchLengthMismatch)
+        );
+    }
+
+    #[test]
+    fn deterministic_signature() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let a = RistrettoSecretKey::random(&mut rng);
+        let x = RistrettoSecretKey::random(&mut rng);
+        let commitment = factory.commit(&x, &a);
+        let sig1 =
+            RistrettoComSig::sign_deterministic::<Blake256, _>(a.clone(), x.clone(), b"Jingo", &factory).unwrap();
+        let sig2 =
+            RistrettoComSig::sign_deterministic::<Blake256, _>(a.clone(), x.clone(), b"Jingo", &factory).unwrap();
+        assert_eq!(sig1, sig2);
+        assert!(sig1.verify_message::<Blake256, _>(&commitment, b"Jingo", &factory));
+        assert!(!sig1.verify_message::<Blake256, _>(&commitment, b"Hogfather", &factory));
+        let sig3 = RistrettoComSig::sign_deterministic::<Blake256, _>(a, x, b"Hogfather", &factory).unwrap();
+        assert_ne!(sig1.public_nonce(), sig3.public_nonce());
+        assert!(sig3.verify_message::<Blake256, _>(&commitment, b"Hogfather", &factory));
+    }
 }
@@ -21,26 +21,43 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! The Tari-compatible implementation of Ristretto based on the curve25519-dalek implementation
-use crate::keys::{DiffieHellmanSharedSecret, PublicKey, SecretKey};
+use crate::{
+    common::Blake256,
+    keys::{DiffieHellmanSharedSecret, PublicKey, PublicKeyValidationError, SecretKey},
+    ristretto::RistrettoSchnorr,
+};
 use blake2::Blake2b;
-use clear_on_drop::clear::Clear;
+use clear_on_drop::{clear::Clear, ClearOnDrop};
 use curve25519_dalek::{
     constants::RISTRETTO_BASEPOINT_TABLE,
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
-    traits::MultiscalarMul,
+    traits::{MultiscalarMul, VartimeMultiscalarMul},
+};
+use digest::{
+    consts::U64,
+    generic_array::typenum::{IsGreaterOrEqual, B1},
+    Digest,
 };
-use digest::Digest;
 use rand::{CryptoRng, Rng};
+use sha2::Sha512;
 use std::{
+    borrow::Borrow,
     cmp::Ordering,
     fmt,
     fmt::Debug,
     hash::{Hash, Hasher},
-    ops::{Add, Mul, Sub},
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
+use subtle::{Choice, ConstantTimeEq};
 use tari_utilities::{hex::Hex, ByteArray, ByteArrayError, ExtendBytes, Hashable};
 
+const POP_DOMAIN: &[u8] = b"com.tari.ristretto.pop";
+const POP_NONCE_LABEL: &[u8] = b"com.tari.ristretto.pop.nonce";
+const TWEAK_DOMAIN: &[u8] = b"com.tari.ristretto.tweak";
+const SEEDED_KEYPAIR_DOMAIN: &[u8] = b"com.tari.ristretto.seeded_keypair";
+
 /// The [SecretKey](trait.SecretKey.html) implementation for [Ristretto](https://ristretto.group) is a thin wrapper
 /// around the Dalek [Scalar](struct.Scalar.html) type, representing a 256-bit integer (mod the group order).
 ///
@@ -76,6 +93,16 @@ impl SecretKey for RistrettoSecretKey {
     fn random<R: Rng + CryptoRng>(rng: &mut R) -> Self {
         RistrettoSecretKey(Scalar::random(rng))
     }
+
+    fn uniform_length() -> usize {
+        2 * SCALAR_LENGTH
+    }
+
+    /// Reduce 64 uniformly random bytes mod _l_, as
+    /// [from_uniform_bytes](struct.RistrettoSecretKey.html#method.from_uniform_bytes) does
+    fn from_uniform_bytes(bytes: &[u8]) -> Result<Self, ByteArrayError> {
+        RistrettoSecretKey::from_uniform_bytes(bytes)
+    }
 }
 
 //----------------------------------    Ristretto Secret Key Default   -----------------------------------------------//
@@ -100,16 +127,11 @@ impl Drop for RistrettoSecretKey {
 impl ByteArray for RistrettoSecretKey {
     /// Create a secret key on the Ristretto255 curve using the given little-endian byte array. If the byte array is
     /// not exactly 32 bytes long, `from_bytes` returns an error. This function is guaranteed to return a valid key
-    /// in the group since it performs a mod _l_ on the input.
+    /// in the group since it performs a mod _l_ on the input; it is the same as
+    /// [from_bytes_mod_order](struct.RistrettoSecretKey.html#method.from_bytes_mod_order).
     fn from_bytes(bytes: &[u8]) -> Result<RistrettoSecretKey, ByteArrayError>
     where Self: Sized {
-        if bytes.len() != 32 {
-            return Err(ByteArrayError::IncorrectLength);
-        }
-        let mut a = [0u8; 32];
-        a.copy_from_slice(bytes);
-        let k = Scalar::from_bytes_mod_order(a);
-        Ok(RistrettoSecretKey(k))
+        RistrettoSecretKey::from_bytes_mod_order(bytes)
     }
 
     /// Return the byte array for the secret key in little-endian order
@@ -118,6 +140,121 @@ impl ByteArray for RistrettoSecretKey {
     }
 }
 
+impl RistrettoSecretKey {
+    /// Read a secret key from its 32-byte little-endian encoding, rejecting values that are not fully reduced mod _l_.
+    /// Every key has exactly one canonical encoding, so use this when the bytes come from somewhere else and two
+    /// encodings of the same key must not both be accepted. Deserialization uses this constructor.
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<RistrettoSecretKey, ByteArrayError> {
+        let a = scalar_bytes(bytes)?;
+        Scalar::from_canonical_bytes(a)
+            .map(RistrettoSecretKey)
+            .ok_or_else(|| ByteArrayError::ConversionError("Non-canonical scalar encoding".to_string()))
+    }
+
+    /// Read a secret key from 32 little-endian bytes, reducing the value mod _l_. Any 32 bytes give a valid key, but
+    /// the result is slightly biased if the bytes are random; use [from_uniform_bytes](#method.from_uniform_bytes) to
+    /// derive keys from random data.
+    pub fn from_bytes_mod_order(bytes: &[u8]) -> Result<RistrettoSecretKey, ByteArrayError> {
+        let a = scalar_bytes(bytes)?;
+        Ok(RistrettoSecretKey(Scalar::from_bytes_mod_order(a)))
+    }
+
+    /// Create a secret key from 64 uniformly random bytes, e.g. the output of a 512-bit hash. The bytes are reduced mod
+    /// _l_ as a 512-bit integer, so the key is uniformly distributed. Prefer this to [from_bytes](#method.from_bytes)
+    /// when deriving keys from hashes, since reducing 32 bytes mod _l_ is noticeably biased.
+    pub fn from_uniform_bytes(bytes: &[u8]) -> Result<RistrettoSecretKey, ByteArrayError> {
+        if bytes.len() != 64 {
+            return Err(ByteArrayError::IncorrectLength);
+        }
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(bytes);
+        let k = Scalar::from_bytes_mod_order_wide(&wide);
+        wide.clear();
+        Ok(RistrettoSecretKey(k))
+    }
+
+    /// Copy the secret key's little-endian bytes into a buffer that is cleared when it is dropped. Use this rather
+    /// than `to_vec` (or copying `as_bytes` into an array) when the bytes must leave the key, e.g. to be written to
+    /// a hardware device, so that no uncleared copy is left behind.
+    pub fn reveal(&self) -> ClearOnDrop<Box<[u8; SCALAR_LENGTH]>> {
+        let mut bytes = ClearOnDrop::new(Box::new([0u8; SCALAR_LENGTH]));
+        bytes.copy_from_slice(self.0.as_bytes());
+        bytes
+    }
+
+    /// Create a secret key from the output of a hasher with a digest of at least 64 bytes, such as `Sha512` or
+    /// `Blake2b`, by reducing its first 64 bytes. Shorter digests would give a biased key, so they are rejected at
+    /// compile time. Any hasher that has absorbed its input will do, e.g. one from
+    /// [SignatureChallengeBuilder::into_hasher](crate::signatures::SignatureChallengeBuilder::into_hasher).
+    ///
+    /// ```compile_fail
+    /// # use tari_crypto::ristretto::RistrettoSecretKey;
+    /// # use digest::Digest;
+    /// let k = RistrettoSecretKey::from_hasher(sha2::Sha256::new().chain(b"too short"));
+    /// ```
+    pub fn from_hasher<D>(hasher: D) -> RistrettoSecretKey
+    where
+        D: Digest,
+        D::OutputSize: IsGreaterOrEqual<U64, Output = B1>,
+    {
+        let mut wide = wide_digest(hasher);
+        let k = RistrettoSecretKey(Scalar::from_bytes_mod_order_wide(&wide));
+        wide.clear();
+        k
+    }
+
+    /// Hash `data` to a secret key under `domain`, using SHA-512 with a length-prefixed domain. Different domains give
+    /// unrelated keys for the same data.
+    pub fn from_domain_hash(domain: &[u8], data: &[u8]) -> RistrettoSecretKey {
+        RistrettoSecretKey::from_hasher(
+            Sha512::new()
+                .chain((domain.len() as u64).to_le_bytes())
+                .chain(domain)
+                .chain(data),
+        )
+    }
+
+    /// The multiplicative inverse, _1/k_ mod _l_, or `None` if the key is zero
+    pub fn invert(&self) -> Option<RistrettoSecretKey> {
+        if self.0 == Scalar::zero() {
+            return None;
+        }
+        Some(RistrettoSecretKey(self.0.invert()))
+    }
+
+    /// Invert every key in place with Montgomery's trick, which needs a single field inversion plus three
+    /// multiplications per key. If any key is zero, nothing is changed and `false` is returned.
+    pub fn batch_invert(keys: &mut [RistrettoSecretKey]) -> bool {
+        if keys.iter().any(|k| k.0 == Scalar::zero()) {
+            return false;
+        }
+        // prefix[i] is the product of the keys before i
+        let mut prefix = Vec::with_capacity(keys.len());
+        let mut acc = Scalar::one();
+        for k in keys.iter() {
+            prefix.push(acc);
+            acc *= k.0;
+        }
+        // Walking backwards, acc is the inverse of the product of keys[..=i]
+        acc = acc.invert();
+        for (k, p) in keys.iter_mut().zip(prefix.iter()).rev() {
+            let inverse = acc * p;
+            acc *= k.0;
+            k.0 = inverse;
+        }
+        for p in prefix.iter_mut() {
+            p.clear();
+        }
+        acc.clear();
+        true
+    }
+
+    /// The secret key matching [RistrettoPublicKey::tweak](struct.RistrettoPublicKey.html#method.tweak): _k + t_
+    pub fn tweak(&self, tweak: &RistrettoSecretKey) -> RistrettoSecretKey {
+        self + tweak
+    }
+}
+
 impl Hash for RistrettoSecretKey {
     /// Require the implementation of the Hash trait for Hashmaps
     fn hash<H: Hasher>(&self, state: &mut H) {
@@ -125,12 +262,41 @@ impl Hash for RistrettoSecretKey {
     }
 }
 
+impl ConstantTimeEq for RistrettoSecretKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+/// Secret keys are always compared in constant time
 impl PartialEq for RistrettoSecretKey {
     fn eq(&self, other: &Self) -> bool {
-        self.0.eq(&other.0)
+        self.ct_eq(other).into()
     }
 }
 
+fn scalar_bytes(bytes: &[u8]) -> Result<[u8; SCALAR_LENGTH], ByteArrayError> {
+    if bytes.len() != SCALAR_LENGTH {
+        return Err(ByteArrayError::IncorrectLength);
+    }
+    let mut a = [0u8; SCALAR_LENGTH];
+    a.copy_from_slice(bytes);
+    Ok(a)
+}
+
+/// The first 64 bytes of a digest that is at least that long
+fn wide_digest<D>(hasher: D) -> [u8; 64]
+where
+    D: Digest,
+    D::OutputSize: IsGreaterOrEqual<U64, Output = B1>,
+{
+    let mut digest = hasher.finalize();
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&digest[..64]);
+    digest.as_mut_slice().clear();
+    wide
+}
+
 //----------------------------------   RistrettoSecretKey Mul / Add / Sub --------------------------------------------//
 
 impl<'a, 'b> Mul<&'b RistrettoPublicKey> for &'a RistrettoSecretKey {
@@ -175,6 +341,53 @@ define_mul_variants!(
     Output = RistrettoPublicKey
 );
 
+// The in-place operators update the scalar directly, so accumulating a sum doesn't create a new key per step
+
+impl<'b> AddAssign<&'b RistrettoSecretKey> for RistrettoSecretKey {
+    fn add_assign(&mut self, rhs: &'b RistrettoSecretKey) {
+        self.0 += &rhs.0;
+    }
+}
+
+impl AddAssign<RistrettoSecretKey> for RistrettoSecretKey {
+    fn add_assign(&mut self, rhs: RistrettoSecretKey) {
+        *self += &rhs;
+    }
+}
+
+impl<'b> SubAssign<&'b RistrettoSecretKey> for RistrettoSecretKey {
+    fn sub_assign(&mut self, rhs: &'b RistrettoSecretKey) {
+        self.0 -= &rhs.0;
+    }
+}
+
+impl SubAssign<RistrettoSecretKey> for RistrettoSecretKey {
+    fn sub_assign(&mut self, rhs: RistrettoSecretKey) {
+        *self -= &rhs;
+    }
+}
+
+impl<'b> MulAssign<&'b RistrettoSecretKey> for RistrettoSecretKey {
+    fn mul_assign(&mut self, rhs: &'b RistrettoSecretKey) {
+        self.0 *= &rhs.0;
+    }
+}
+
+impl MulAssign<RistrettoSecretKey> for RistrettoSecretKey {
+    fn mul_assign(&mut self, rhs: RistrettoSecretKey) {
+        *self *= &rhs;
+    }
+}
+
+impl<T> Sum<T> for RistrettoSecretKey
+where T: Borrow<RistrettoSecretKey>
+{
+    fn sum<I>(iter: I) -> Self
+    where I: Iterator<Item = T> {
+        RistrettoSecretKey(iter.map(|k| k.borrow().0).sum())
+    }
+}
+
 //---------------------------------------------      Conversions     -------------------------------------------------//
 
 impl From<u64> for RistrettoSecretKey {
@@ -219,6 +432,132 @@ impl RistrettoPublicKey {
             compressed: pk.compress(),
         }
     }
+
+    /// Decompress many public keys at once. The result has one entry per input, so a failure can be traced back to
+    /// its index. The keys keep the bytes they were decoded from as their compressed form, so unlike calling
+    /// [from_bytes](#method.from_bytes) in a loop, no point is compressed again.
+    pub fn batch_from_bytes(bytes: &[[u8; PUBLIC_KEY_LENGTH]]) -> Vec<Result<RistrettoPublicKey, ByteArrayError>> {
+        bytes
+            .iter()
+            .map(|b| {
+                let compressed = CompressedRistretto(*b);
+                compressed
+                    .decompress()
+                    .map(|point| RistrettoPublicKey { point, compressed })
+                    .ok_or_else(|| ByteArrayError::ConversionError("Invalid compressed Ristretto point".to_string()))
+            })
+            .collect()
+    }
+
+    /// Map 64 uniformly random bytes to a point with the Elligator-based `RistrettoPoint::from_uniform_bytes`. If the
+    /// bytes are uniform (e.g. a hash output), nobody knows the discrete log of the result, so it can be used as a
+    /// NUMS generator.
+    pub fn from_uniform_bytes(bytes: &[u8]) -> Result<RistrettoPublicKey, ByteArrayError> {
+        if bytes.len() != 64 {
+            return Err(ByteArrayError::IncorrectLength);
+        }
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(bytes);
+        Ok(RistrettoPublicKey::new_from_pk(RistrettoPoint::from_uniform_bytes(
+            &wide,
+        )))
+    }
+
+    /// Hash to a point using the first 64 bytes of the output of a hasher. As with
+    /// [RistrettoSecretKey::from_hasher], digests shorter than 64 bytes are rejected at compile time.
+    pub fn from_hasher<D>(hasher: D) -> RistrettoPublicKey
+    where
+        D: Digest,
+        D::OutputSize: IsGreaterOrEqual<U64, Output = B1>,
+    {
+        RistrettoPublicKey::new_from_pk(RistrettoPoint::from_uniform_bytes(&wide_digest(hasher)))
+    }
+
+    /// Hash `data` to a point under `domain`, using SHA-512 with a length-prefixed domain. This gives, e.g., a
+    /// per-user generator from an identity; different domains give unrelated points for the same data.
+    pub fn from_domain_hash(domain: &[u8], data: &[u8]) -> RistrettoPublicKey {
+        RistrettoPublicKey::from_hasher(
+            Sha512::new()
+                .chain((domain.len() as u64).to_le_bytes())
+                .chain(domain)
+                .chain(data),
+        )
+    }
+
+    /// Derive a keypair deterministically from `seed`, under an application `label`. The same seed and label always
+    /// give the same keypair, and different labels give unrelated ones, which makes this suitable for test vectors
+    /// and for service identities that must survive a restart. The seed must be secret and high-entropy: anyone who
+    /// knows it can recreate the secret key.
+    ///
+    /// ```edition2018
+    /// # use tari_crypto::ristretto::RistrettoPublicKey;
+    /// let seed = [7u8; 32];
+    /// let (_k, p) = RistrettoPublicKey::keypair_from_seed(&seed, b"my-service");
+    /// assert_eq!(RistrettoPublicKey::keypair_from_seed(&seed, b"my-service").1, p);
+    /// ```
+    pub fn keypair_from_seed(seed: &[u8], label: &[u8]) -> (RistrettoSecretKey, RistrettoPublicKey) {
+        // Equivalent to `from_domain_hash` over `len(label) || label || seed`, without copying the seed into a buffer
+        let k = RistrettoSecretKey::from_hasher(
+            Sha512::new()
+                .chain((SEEDED_KEYPAIR_DOMAIN.len() as u64).to_le_bytes())
+                .chain(SEEDED_KEYPAIR_DOMAIN)
+                .chain((label.len() as u64).to_le_bytes())
+                .chain(label)
+                .chain(seed),
+        );
+        let p = RistrettoPublicKey::from_secret_key(&k);
+        (k, p)
+    }
+
+    /// Offset the key by a multiple of the generator, _P + t.G_. The holder of the secret key for _P_ finds the
+    /// secret key of the result with [RistrettoSecretKey::tweak], so a child key can be derived from the parent public
+    /// key alone and spent by the parent's owner.
+    pub fn tweak(&self, tweak: &RistrettoSecretKey) -> RistrettoPublicKey {
+        self + &RistrettoPublicKey::from_secret_key(tweak)
+    }
+
+    /// Derive the tweak for the child of this key labelled `label`, e.g. an address index: _t = H(P || label)_.
+    /// Binding the parent key into the hash means the same label gives unrelated tweaks for different parents.
+    /// Anyone who knows the parent public key can calculate the tweak, so child keys are linkable by them.
+    pub fn derive_tweak(&self, label: &[u8]) -> RistrettoSecretKey {
+        let mut data = Vec::with_capacity(PUBLIC_KEY_LENGTH + label.len());
+        data.extend_from_slice(self.as_bytes());
+        data.extend_from_slice(label);
+        RistrettoSecretKey::from_domain_hash(TWEAK_DOMAIN, &data)
+    }
+
+    /// Prove possession of the secret key for `secret`'s public key. The proof is a Schnorr signature over `context`
+    /// whose challenge is domain-separated from ordinary signatures, so that it cannot be replayed as one (or vice
+    /// versa). The nonce is derived deterministically from the secret key and context.
+    ///
+    /// ```edition2018
+    /// # use tari_crypto::ristretto::{RistrettoPublicKey, RistrettoSecretKey};
+    /// # use tari_crypto::keys::PublicKey;
+    /// let mut rng = rand::thread_rng();
+    /// let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+    /// let pop = RistrettoPublicKey::create_pop(&k, b"Unseen University registry");
+    /// assert!(p.verify_pop(&pop, b"Unseen University registry"));
+    /// assert!(!p.verify_pop(&pop, b"Thieves' Guild registry"));
+    /// ```
+    pub fn create_pop(secret: &RistrettoSecretKey, context: &[u8]) -> RistrettoSchnorr {
+        let mut hash = [0u8; 64];
+        hash.copy_from_slice(
+            &Blake2b::new()
+                .chain(POP_NONCE_LABEL)
+                .chain(secret.as_bytes())
+                .chain(context)
+                .finalize(),
+        );
+        let nonce = RistrettoSecretKey(Scalar::from_bytes_mod_order_wide(&hash));
+        hash.clear();
+        RistrettoSchnorr::sign::<Blake256>(secret.clone(), nonce, POP_DOMAIN, context)
+            .expect("Blake256 produces 32 byte challenges")
+    }
+
+    /// Verify a proof of possession created by [create_pop](#method.create_pop) for this key and `context`
+    pub fn verify_pop(&self, pop: &RistrettoSchnorr, context: &[u8]) -> bool {
+        pop.verify_with_domain::<Blake256>(self, POP_DOMAIN, context)
+    }
 }
 
 impl PublicKey for RistrettoPublicKey {
@@ -240,6 +579,17 @@ impl PublicKey for RistrettoPublicKey {
         let p = RistrettoPoint::multiscalar_mul(s, p);
         RistrettoPublicKey::new_from_pk(p)
     }
+
+    /// Decoding a Ristretto point already guarantees that it is a valid element of the prime-order group, so there is
+    /// nothing left to check
+    fn validate(&self) -> Result<(), PublicKeyValidationError> {
+        Ok(())
+    }
+
+    fn batch_mul_vartime(scalars: &[Self::K], points: &[Self]) -> Self {
+        let p = RistrettoPoint::vartime_multiscalar_mul(scalars.iter().map(|k| &k.0), points.iter().map(|p| &p.point));
+        RistrettoPublicKey::new_from_pk(p)
+    }
 }
 
 impl DiffieHellmanSharedSecret for RistrettoPublicKey {
@@ -385,6 +735,22 @@ impl<'a, 'b> Mul<&'b RistrettoSecretKey> for &'a RistrettoSecretKey {
     }
 }
 
+impl<'a> Neg for &'a RistrettoPublicKey {
+    type Output = RistrettoPublicKey;
+
+    fn neg(self) -> RistrettoPublicKey {
+        RistrettoPublicKey::new_from_pk(-self.point)
+    }
+}
+
+impl Neg for RistrettoPublicKey {
+    type Output = RistrettoPublicKey;
+
+    fn neg(self) -> RistrettoPublicKey {
+        -&self
+    }
+}
+
 define_add_variants!(
     LHS = RistrettoPublicKey,
     RHS = RistrettoPublicKey,
@@ -406,6 +772,15 @@ define_mul_variants!(
     Output = RistrettoSecretKey
 );
 
+impl<T> Sum<T> for RistrettoPublicKey
+where T: Borrow<RistrettoPublicKey>
+{
+    fn sum<I>(iter: I) -> Self
+    where I: Iterator<Item = T> {
+        RistrettoPublicKey::new_from_pk(iter.map(|p| p.borrow().point).sum())
+    }
+}
+
 //----------------------------------         PublicKey From implementations      -------------------------------------//
 
 impl From<RistrettoSecretKey> for Scalar {
@@ -440,7 +815,7 @@ impl From<RistrettoPublicKey> for CompressedRistretto {
 mod test {
     use super::*;
     use crate::{keys::PublicKey, ristretto::test_common::get_keypair};
-    use tari_utilities::{message_format::MessageFormat, ByteArray};
+    use tari_utilities::{hex::from_hex, message_format::MessageFormat, ByteArray};
 
     fn assert_completely_equal(k1: &RistrettoPublicKey, k2: &RistrettoPublicKey) {
         assert_eq!(k1, k2);
@@ -613,8 +988,14 @@ mod test {
         let (k1, p1) = get_keypair();
         let (k2, p2) = get_keypair();
         let p_slow = &(&k1 * &p1) + &(&k2 * &p2);
-        let b_batch = RistrettoPublicKey::batch_mul(&[k1, k2], &[p1, p2]);
+        let b_batch = RistrettoPublicKey::batch_mul(&[k1.clone(), k2.clone()], &[p1.clone(), p2.clone()]);
         assert_completely_equal(&p_slow, &b_batch);
+        let b_vartime = RistrettoPublicKey::batch_mul_vartime(&[k1, k2], &[p1, p2]);
+        assert_completely_equal(&p_slow, &b_vartime);
+        assert_eq!(
+            RistrettoPublicKey::batch_mul_vartime(&[], &[]),
+            RistrettoPublicKey::default()
+        );
     }
 
     #[test]
@@ -699,6 +1080,69 @@ mod test {
         assert_completely_equal(&pk, &pk2);
     }
 
+    #[test]
+    fn uniform_secret_keys() {
+        let bytes = [0xffu8; 64];
+        let k = RistrettoSecretKey::from_uniform_bytes(&bytes).unwrap();
+        assert_eq!(k.0, Scalar::from_bytes_mod_order_wide(&bytes));
+        assert!(matches!(
+            RistrettoSecretKey::from_uniform_bytes(&bytes[..32]),
+            Err(ByteArrayError::IncorrectLength)
+        ));
+        let hashed = RistrettoSecretKey::from_hasher(Sha512::new().chain(b"Maskerade"));
+        assert_eq!(
+            hashed,
+            RistrettoSecretKey::from_uniform_bytes(&Sha512::digest(b"Maskerade")).unwrap()
+        );
+        let (_, nonce) = RistrettoPublicKey::random_keypair(&mut rand::thread_rng());
+        let builder = || {
+            crate::signatures::SignatureChallengeBuilder::<Sha512>::new(b"opera", &nonce, &nonce)
+                .chain_message(b"Maskerade")
+        };
+        assert_eq!(
+            RistrettoSecretKey::from_hasher(builder().into_hasher()),
+            RistrettoSecretKey::from_uniform_bytes(&builder().finalize()).unwrap()
+        );
+        let k1 = RistrettoSecretKey::from_domain_hash(b"opera", b"Maskerade");
+        assert_eq!(k1, RistrettoSecretKey::from_domain_hash(b"opera", b"Maskerade"));
+        assert_ne!(k1, RistrettoSecretKey::from_domain_hash(b"ghost", b"Maskerade"));
+        // The domain is length-prefixed, so moving bytes between domain and data changes the key
+        assert_ne!(
+            RistrettoSecretKey::from_domain_hash(b"ab", b"c"),
+            RistrettoSecretKey::from_domain_hash(b"a", b"bc")
+        );
+    }
+
+    #[test]
+    fn hash_to_point() {
+        let bytes = Sha512::digest(b"Jingo");
+        let p = RistrettoPublicKey::from_uniform_bytes(&bytes).unwrap();
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&bytes);
+        assert_eq!(p.point, RistrettoPoint::from_uniform_bytes(&wide));
+        assert!(RistrettoPublicKey::from_uniform_bytes(&bytes[..63]).is_err());
+        assert_eq!(RistrettoPublicKey::from_hasher(Sha512::new().chain(b"Jingo")), p);
+        let vimes = RistrettoPublicKey::from_domain_hash(b"watch", b"Vimes");
+        assert_eq!(vimes, RistrettoPublicKey::from_domain_hash(b"watch", b"Vimes"));
+        assert_ne!(vimes, RistrettoPublicKey::from_domain_hash(b"watch", b"Carrot"));
+        assert_ne!(vimes, RistrettoPublicKey::from_domain_hash(b"guild", b"Vimes"));
+        assert_ne!(vimes, RistrettoPublicKey::default());
+    }
+
+    #[test]
+    fn proof_of_possession() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let pop = RistrettoPublicKey::create_pop(&k, b"Mort");
+        assert!(p.verify_pop(&pop, b"Mort"));
+        assert!(!p.verify_pop(&pop, b"Reaper Man"));
+        let (_, other) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert!(!other.verify_pop(&pop, b"Mort"));
+        // A proof of possession is not an ordinary signature on the context
+        let e = RistrettoSchnorr::message_challenge::<Blake256>(pop.get_public_nonce(), &p, b"Mort");
+        assert!(!pop.verify_challenge(&p, &e));
+    }
+
     #[test]
     fn display_and_debug() {
         let hex = "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76";
@@ -706,4 +1150,159 @@ mod test {
         assert_eq!(format!("{}", pk), hex);
         assert_eq!(format!("{:?}", pk), hex);
     }
+
+    #[test]
+    fn constant_time_equality() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        assert!(bool::from(ConstantTimeEq::ct_eq(&k, &k.clone())));
+        assert!(!bool::from(ConstantTimeEq::ct_eq(
+            &k,
+            &RistrettoSecretKey::random(&mut rng)
+        )));
+        // l + 1 reduces to the same key as 1
+        let one =
+            RistrettoSecretKey::from_hex("eed3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010").unwrap();
+        assert_eq!(one, RistrettoSecretKey::from(1));
+
+        let (a, a_pub) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (b, b_pub) = RistrettoPublicKey::random_keypair(&mut rng);
+        let ab = RistrettoPublicKey::shared_secret(&a, &b_pub);
+        let ba = RistrettoPublicKey::shared_secret(&b, &a_pub);
+        assert!(bool::from(DiffieHellmanSharedSecret::ct_eq(&ab, &ba)));
+        assert!(!bool::from(DiffieHellmanSharedSecret::ct_eq(&ab, &a_pub)));
+    }
+
+    #[test]
+    fn in_place_arithmetic() {
+        let mut rng = rand::thread_rng();
+        let a = RistrettoSecretKey::random(&mut rng);
+        let b = RistrettoSecretKey::random(&mut rng);
+        let mut k = a.clone();
+        k += &b;
+        assert_eq!(k, &a + &b);
+        k -= &a;
+        assert_eq!(k, b);
+        k *= &a;
+        assert_eq!(k, &b * &a);
+        k += b.clone();
+        k -= a.clone();
+        k *= RistrettoSecretKey::from(2);
+        assert_eq!(k, &(&(&(&b * &a) + &b) - &a) * &RistrettoSecretKey::from(2));
+        let mut total = RistrettoSecretKey::default();
+        for i in 1..=10u64 {
+            total += RistrettoSecretKey::from(i);
+        }
+        assert_eq!(total, RistrettoSecretKey::from(55));
+    }
+
+    #[test]
+    fn batch_decompression() {
+        let mut rng = rand::thread_rng();
+        let keys: Vec<RistrettoPublicKey> = (0..5).map(|_| RistrettoPublicKey::random_keypair(&mut rng).1).collect();
+        let mut encoded: Vec<[u8; 32]> = keys.iter().map(|k| k.compressed.to_bytes()).collect();
+        // Not a valid Ristretto encoding
+        encoded[2] = [0xff; 32];
+        let decoded = RistrettoPublicKey::batch_from_bytes(&encoded);
+        assert_eq!(decoded.len(), 5);
+        for (i, result) in decoded.iter().enumerate() {
+            match result {
+                Ok(k) if i != 2 => assert_completely_equal(k, &keys[i]),
+                Err(ByteArrayError::ConversionError(_)) if i == 2 => {},
+                _ => panic!("Unexpected result at index {}", i),
+            }
+        }
+        assert!(RistrettoPublicKey::batch_from_bytes(&[]).is_empty());
+    }
+
+    #[test]
+    fn canonical_secret_keys() {
+        let k = RistrettoSecretKey::random(&mut rand::thread_rng());
+        assert_eq!(RistrettoSecretKey::from_canonical_bytes(k.as_bytes()).unwrap(), k);
+        assert_eq!(RistrettoSecretKey::from_bytes_mod_order(k.as_bytes()).unwrap(), k);
+        // l + 1 is only accepted when reducing
+        let l_plus_one = from_hex("eed3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010").unwrap();
+        assert_eq!(
+            RistrettoSecretKey::from_bytes_mod_order(&l_plus_one).unwrap(),
+            RistrettoSecretKey::from(1)
+        );
+        assert_eq!(
+            RistrettoSecretKey::from_bytes(&l_plus_one).unwrap(),
+            RistrettoSecretKey::from(1)
+        );
+        assert!(matches!(
+            RistrettoSecretKey::from_canonical_bytes(&l_plus_one),
+            Err(ByteArrayError::ConversionError(_))
+        ));
+        assert!(matches!(
+            RistrettoSecretKey::from_canonical_bytes(&[1u8; 31]),
+            Err(ByteArrayError::IncorrectLength)
+        ));
+    }
+
+    #[test]
+    fn tweaked_keys() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        // A watch-only party derives child public keys; the owner derives the matching secrets
+        for i in 0..3u64 {
+            let t = p.derive_tweak(&i.to_le_bytes());
+            let child = p.tweak(&t);
+            assert_eq!(RistrettoPublicKey::from_secret_key(&k.tweak(&t)), child);
+            assert_ne!(child, p);
+        }
+        assert_ne!(p.derive_tweak(b"0"), p.derive_tweak(b"1"));
+        let (_, q) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert_ne!(p.derive_tweak(b"0"), q.derive_tweak(b"0"));
+        assert_eq!(p.tweak(&RistrettoSecretKey::default()), p);
+    }
+
+    #[test]
+    fn seeded_keypairs() {
+        let (k, p) = RistrettoPublicKey::keypair_from_seed(b"Hogswatch", b"Death");
+        assert_eq!(RistrettoPublicKey::from_secret_key(&k), p);
+        assert_eq!(
+            RistrettoPublicKey::keypair_from_seed(b"Hogswatch", b"Death"),
+            (k, p.clone())
+        );
+        assert_ne!(RistrettoPublicKey::keypair_from_seed(b"Hogswatch", b"Albert").1, p);
+        assert_ne!(RistrettoPublicKey::keypair_from_seed(b"Hogfather", b"Death").1, p);
+        // The label and seed can't be traded for one another
+        assert_ne!(RistrettoPublicKey::keypair_from_seed(b"Death", b"Hogswatch").1, p);
+        assert_ne!(RistrettoPublicKey::keypair_from_seed(b"thHogswatch", b"Dea").1, p);
+    }
+
+    #[test]
+    fn revealed_bytes() {
+        let k = RistrettoSecretKey::random(&mut rand::thread_rng());
+        let bytes = k.reveal();
+        assert_eq!(&bytes[..], k.as_bytes());
+        assert_eq!(RistrettoSecretKey::from_canonical_bytes(&bytes[..]).unwrap(), k);
+    }
+
+    #[test]
+    fn every_ristretto_key_is_valid() {
+        let (_, p) = RistrettoPublicKey::random_keypair(&mut rand::thread_rng());
+        assert_eq!(p.validate(), Ok(()));
+        assert_eq!(RistrettoPublicKey::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn inversion() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        assert_eq!(&k * &k.invert().unwrap(), RistrettoSecretKey::from(1));
+        assert_eq!(RistrettoSecretKey::default().invert(), None);
+
+        let keys: Vec<RistrettoSecretKey> = (0..5).map(|_| RistrettoSecretKey::random(&mut rng)).collect();
+        let mut inverses = keys.clone();
+        assert!(RistrettoSecretKey::batch_invert(&mut inverses));
+        for (k, inv) in keys.iter().zip(&inverses) {
+            assert_eq!(&k.invert().unwrap(), inv);
+        }
+        let mut with_zero = vec![k.clone(), RistrettoSecretKey::default()];
+        assert!(!RistrettoSecretKey::batch_invert(&mut with_zero));
+        assert_eq!(with_zero, vec![k, RistrettoSecretKey::default()]);
+        assert!(RistrettoSecretKey::batch_invert(&mut []));
+    }
 }
@@ -21,9 +21,20 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use crate::{
+    keys::PublicKey,
     ristretto::{RistrettoPublicKey, RistrettoSecretKey},
-    signatures::SchnorrSignature,
+    script::ScriptContext,
+    signatures::{
+        Bip340Challenge,
+        HalfAggregateSignature,
+        SchnorrSignature,
+        SchnorrSignatureError,
+        SignatureChallengeBuilder,
+        SignedMessage,
+    },
 };
+use digest::Digest;
+use tari_utilities::ByteArray;
 
 /// # A Schnorr signature implementation on Ristretto
 ///
@@ -70,8 +81,24 @@ use crate::{
 /// #[allow(non_snake_case)]
 /// let (k, P) = get_keypair();
 /// let (r, R) = get_keypair();
+/// let sig = RistrettoSchnorr::sign::<Blake256>(k, r, b"example.domain", b"Small Gods").unwrap();
+/// assert!(sig.verify_with_domain::<Blake256>(&P, b"example.domain", b"Small Gods"));
+/// ```
+///
+/// A challenge that was computed elsewhere, e.g. by another protocol, is signed as-is with `sign_raw`:
+///
+/// ```rust
+/// # use tari_crypto::ristretto::*;
+/// # use tari_crypto::keys::*;
+/// # use tari_crypto::common::*;
+/// # use digest::Digest;
+/// let mut rng = rand::thread_rng();
+/// let (k, r) = (
+///     RistrettoSecretKey::random(&mut rng),
+///     RistrettoSecretKey::random(&mut rng),
+/// );
 /// let e = Blake256::digest(b"Small Gods");
-/// let sig = RistrettoSchnorr::sign(k, r, &e);
+/// let sig = RistrettoSchnorr::sign_raw(k, r, &e).unwrap();
 /// ```
 ///
 /// # Verifying signatures
@@ -98,12 +125,233 @@ use crate::{
 /// ```
 pub type RistrettoSchnorr = SchnorrSignature<RistrettoPublicKey, RistrettoSecretKey>;
 
+/// A `RistrettoSchnorr` signature whose message challenge and deterministic nonce use BIP-340 tagged hashes. See
+/// [Bip340Challenge] for details.
+pub type RistrettoBip340Schnorr = SchnorrSignature<RistrettoPublicKey, RistrettoSecretKey, Bip340Challenge>;
+
+const RECOVERABLE_CHALLENGE_LABEL: &[u8] = b"com.tari.schnorr.recoverable_challenge";
+
+/// # Public key recovery
+///
+/// A recoverable signature uses the challenge _e = H(label || R || m)_, which does _not_ commit to the public key.
+/// Given the message, the signer's key can then be recovered from the signature alone as _P = e^{-1}(s.G - R)_, so
+/// compact formats can omit it.
+///
+/// Because the challenge isn't bound to the key, these signatures should only be used with independently generated
+/// keys; don't use them with keys that are publicly related to each other (e.g. non-hardened derivation), and never
+/// mix them with the key-prefixed challenges used elsewhere for the same key.
+impl RistrettoSchnorr {
+    /// Sign `message` with a challenge from which the public key can later be recovered
+    pub fn sign_recoverable<D: Digest>(
+        secret: RistrettoSecretKey,
+        nonce: RistrettoSecretKey,
+        message: &[u8],
+    ) -> Result<Self, SchnorrSignatureError> {
+        let public_nonce = RistrettoPublicKey::from_secret_key(&nonce);
+        let challenge = recoverable_challenge::<D>(&public_nonce, message);
+        Self::sign_raw(secret, nonce, &challenge)
+    }
+
+    /// Recover the public key that produced this signature on `message`. Returns `None` if the challenge can't be
+    /// calculated or is zero. Any signature recovers _some_ key, so the result must be checked against the expected
+    /// signer (e.g. by matching an address or key hash).
+    pub fn recover_public_key<D: Digest>(&self, message: &[u8]) -> Option<RistrettoPublicKey> {
+        let challenge = recoverable_challenge::<D>(self.get_public_nonce(), message);
+        let e = RistrettoSecretKey::from_bytes(&challenge).ok()?;
+        let e_inv = e.invert()?;
+        let p = (self.calc_signature_verifier() - self.get_public_nonce()) * &e_inv;
+        Some(p)
+    }
+
+    /// Verify a signature made with [sign_recoverable](#method.sign_recoverable) against a known public key
+    pub fn verify_recoverable<D: Digest>(&self, public_key: &RistrettoPublicKey, message: &[u8]) -> bool {
+        let challenge = recoverable_challenge::<D>(self.get_public_nonce(), message);
+        self.verify_challenge(public_key, &challenge)
+    }
+}
+
+fn recoverable_challenge<D: Digest>(public_nonce: &RistrettoPublicKey, message: &[u8]) -> Vec<u8> {
+    D::new()
+        .chain(RECOVERABLE_CHALLENGE_LABEL)
+        .chain(public_nonce.as_bytes())
+        .chain(message)
+        .finalize()
+        .to_vec()
+}
+
+const SCRIPT_CONTEXT_DOMAIN: &[u8] = b"com.tari.schnorr.script_context";
+
+/// # Context-bound signatures
+///
+/// The challenge of a context-bound signature commits to a [ScriptContext] (block height, previous block hash and
+/// commitment) as well as the message, _e = H(domain || R || P || context || m)_. A signature made for one chain tip
+/// therefore won't verify for another, which protects against replay across reorgs.
+impl RistrettoSchnorr {
+    /// Sign `message` in the given script context
+    pub fn sign_with_script_context<D: Digest>(
+        secret: RistrettoSecretKey,
+        nonce: RistrettoSecretKey,
+        context: &ScriptContext,
+        message: &[u8],
+    ) -> Result<Self, SchnorrSignatureError> {
+        let public_key = RistrettoPublicKey::from_secret_key(&secret);
+        let public_nonce = RistrettoPublicKey::from_secret_key(&nonce);
+        let challenge = script_context_challenge::<D>(&public_nonce, &public_key, context, message);
+        Self::sign_raw(secret, nonce, &challenge)
+    }
+
+    /// Verify a signature made with [sign_with_script_context](#method.sign_with_script_context). The context must
+    /// match the one used for signing exactly.
+    pub fn verify_with_script_context<D: Digest>(
+        &self,
+        public_key: &RistrettoPublicKey,
+        context: &ScriptContext,
+        message: &[u8],
+    ) -> bool {
+        let challenge = script_context_challenge::<D>(self.get_public_nonce(), public_key, context, message);
+        self.verify_challenge(public_key, &challenge)
+    }
+}
+
+fn script_context_challenge<D: Digest>(
+    public_nonce: &RistrettoPublicKey,
+    public_key: &RistrettoPublicKey,
+    context: &ScriptContext,
+    message: &[u8],
+) -> Vec<u8> {
+    SignatureChallengeBuilder::<D>::new(SCRIPT_CONTEXT_DOMAIN, public_nonce, public_key)
+        .chain_message(context.to_bytes())
+        .chain_message(message)
+        .finalize()
+}
+
+/// The length of a Ristretto Schnorr signature in its fixed-size encoding, _R || s_
+pub const SIGNATURE_LENGTH: usize = 64;
+
+/// # Fixed-size encoding
+///
+/// A Ristretto Schnorr signature always encodes to exactly [SIGNATURE_LENGTH] bytes: the compressed public nonce
+/// followed by the little-endian signature scalar. Use [signature_bytes] to serialize signatures in this form with
+/// serde.
+impl<H> SchnorrSignature<RistrettoPublicKey, RistrettoSecretKey, H> {
+    /// Encode the signature as _R || s_
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        let mut bytes = [0u8; SIGNATURE_LENGTH];
+        bytes[..32].copy_from_slice(self.get_public_nonce().as_bytes());
+        bytes[32..].copy_from_slice(self.get_signature().as_bytes());
+        bytes
+    }
+
+    /// Decode a signature from _R || s_. The encoding must be canonical, as for
+    /// [from_canonical_bytes](#method.from_canonical_bytes).
+    pub fn from_bytes(bytes: &[u8; SIGNATURE_LENGTH]) -> Result<Self, SchnorrSignatureError> {
+        Self::from_canonical_bytes(bytes)
+    }
+}
+
+/// Serde support for the fixed-size signature encoding. Binary formats get the 64 raw bytes, and human-readable
+/// formats a hex string.
+///
+/// ```nocompile
+///   #[derive(Serialize, Deserialize)]
+///   pub struct Kernel {
+///       #[serde(with = "tari_crypto::ristretto::ristretto_sig::signature_bytes")]
+///       pub signature: RistrettoSchnorr,
+///   }
+/// ```
+pub mod signature_bytes {
+    use super::SIGNATURE_LENGTH;
+    use crate::{
+        ristretto::{RistrettoPublicKey, RistrettoSecretKey},
+        signatures::SchnorrSignature,
+    };
+    use serde::{
+        de::{self, Visitor},
+        Deserialize,
+        Deserializer,
+        Serialize,
+        Serializer,
+    };
+    use std::{convert::TryInto, fmt, marker::PhantomData};
+    use tari_utilities::hex::{from_hex, to_hex};
+
+    type Signature<H> = SchnorrSignature<RistrettoPublicKey, RistrettoSecretKey, H>;
+
+    pub fn serialize<S, H>(signature: &Signature<H>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let bytes = signature.to_bytes();
+        if serializer.is_human_readable() {
+            to_hex(&bytes).serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D, H>(deserializer: D) -> Result<Signature<H>, D::Error>
+    where D: Deserializer<'de> {
+        struct SignatureVisitor<H>(PhantomData<H>);
+
+        impl<'de, H> Visitor<'de> for SignatureVisitor<H> {
+            type Value = Signature<H>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a {} byte signature", SIGNATURE_LENGTH)
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Signature<H>, E>
+            where E: de::Error {
+                from_slice(v).map_err(E::custom)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let bytes = from_hex(&s).map_err(de::Error::custom)?;
+            from_slice(&bytes).map_err(de::Error::custom)
+        } else {
+            deserializer.deserialize_bytes(SignatureVisitor(PhantomData))
+        }
+    }
+
+    fn from_slice<H>(bytes: &[u8]) -> Result<Signature<H>, String> {
+        let bytes: &[u8; SIGNATURE_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| format!("expected {} bytes, got {}", SIGNATURE_LENGTH, bytes.len()))?;
+        Signature::from_bytes(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// A half-aggregate of many `RistrettoSchnorr` signatures. See [HalfAggregateSignature] for details.
+pub type RistrettoHalfAggregateSignature = HalfAggregateSignature<RistrettoPublicKey, RistrettoSecretKey>;
+
+/// A `RistrettoSchnorr` signature bundled with its public key, encoded in 96 bytes. See [SignedMessage] for details.
+pub type RistrettoSignedMessage = SignedMessage<RistrettoPublicKey>;
+
 #[cfg(test)]
 mod test {
     use crate::{
+        commitment::HomomorphicCommitmentFactory,
         common::Blake256,
         keys::{PublicKey, SecretKey},
-        ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+        ristretto::{
+            pedersen::PedersenCommitmentFactory,
+            ristretto_sig::{RistrettoBip340Schnorr, RistrettoHalfAggregateSignature, RistrettoSignedMessage},
+            test_common::signature_batch,
+            RistrettoPublicKey,
+            RistrettoSchnorr,
+            RistrettoSecretKey,
+        },
+        script::ScriptContext,
+        signatures::{
+            sign_blinded,
+            Bip340Challenge,
+            BlindSignatureError,
+            BlindingSession,
+            ChallengeStrategy,
+            HalfAggregationError,
+            SchnorrSignatureError,
+            SignatureChallengeBuilder,
+        },
     };
     use digest::Digest;
     use tari_utilities::{hex::from_hex, ByteArray};
@@ -129,7 +377,7 @@ mod test {
             .finalize();
         let e_key = RistrettoSecretKey::from_bytes(&e).unwrap();
         let s = &r + &e_key * &k;
-        let sig = RistrettoSchnorr::sign(k, r, &e).unwrap();
+        let sig = RistrettoSchnorr::sign_raw(k, r, &e).unwrap();
         let R_calc = sig.get_public_nonce();
         assert_eq!(R, *R_calc);
         assert_eq!(sig.get_signature(), &s);
@@ -161,9 +409,9 @@ mod test {
             .chain(b"Moving Pictures")
             .finalize();
         // Calculate Alice's signature
-        let s1 = RistrettoSchnorr::sign(k1, r1, &e).unwrap();
+        let s1 = RistrettoSchnorr::sign_raw(k1, r1, &e).unwrap();
         // Calculate Bob's signature
-        let s2 = RistrettoSchnorr::sign(k2, r2, &e).unwrap();
+        let s2 = RistrettoSchnorr::sign_raw(k2, r2, &e).unwrap();
         // Now add the two signatures together
         let s_agg = &s1 + &s2;
         // Check that the multi-sig verifies
@@ -178,6 +426,425 @@ mod test {
         let m = from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap();
         let k = RistrettoSecretKey::random(&mut rng);
         let r = RistrettoSecretKey::random(&mut rng);
-        assert!(RistrettoSchnorr::sign(k, r, &m).is_ok());
+        assert!(RistrettoSchnorr::sign_raw(k, r, &m).is_ok());
+    }
+
+    fn create_signature_batch(n: usize) -> (Vec<RistrettoSchnorr>, Vec<RistrettoPublicKey>, Vec<RistrettoSecretKey>) {
+        signature_batch(n, RistrettoPublicKey::random_keypair, |k, e, rng| {
+            RistrettoSchnorr::sign_raw(k, RistrettoSecretKey::random(rng), e.as_bytes()).unwrap()
+        })
+    }
+
+    #[test]
+    fn batch_verify() {
+        let mut rng = rand::thread_rng();
+        let (signatures, public_keys, mut challenges) = create_signature_batch(25);
+        assert!(RistrettoSchnorr::batch_verify(
+            &signatures,
+            &public_keys,
+            &challenges,
+            &mut rng
+        ));
+        let challenge_bytes = challenges.iter().map(|e| e.as_bytes()).collect::<Vec<_>>();
+        assert!(RistrettoSchnorr::batch_verify_challenges(
+            &signatures,
+            &public_keys,
+            &challenge_bytes,
+            &mut rng
+        ));
+        // An empty batch is trivially valid
+        assert!(RistrettoSchnorr::batch_verify(&[], &[], &[], &mut rng));
+        // Mismatched inputs are rejected
+        assert!(!RistrettoSchnorr::batch_verify(
+            &signatures[1..],
+            &public_keys,
+            &challenges,
+            &mut rng
+        ));
+        // A single bad signature invalidates the whole batch
+        challenges.swap(3, 4);
+        assert!(!RistrettoSchnorr::batch_verify(
+            &signatures,
+            &public_keys,
+            &challenges,
+            &mut rng
+        ));
+    }
+
+    #[test]
+    fn script_context_signatures() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let commitment = factory.commit_value(&RistrettoSecretKey::random(&mut rng), 100);
+        let context = ScriptContext::new(1234, &[7u8; 32], &commitment);
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let r = RistrettoSecretKey::random(&mut rng);
+        let sig = RistrettoSchnorr::sign_with_script_context::<Blake256>(k, r, &context, b"Thud!").unwrap();
+        assert!(sig.verify_with_script_context::<Blake256>(&p, &context, b"Thud!"));
+        assert!(!sig.verify_with_script_context::<Blake256>(&p, &context, b"Snuff"));
+        // A reorg changes the height or previous block hash, and the signature no longer verifies
+        let reorged = ScriptContext::new(1234, &[8u8; 32], &commitment);
+        assert!(!sig.verify_with_script_context::<Blake256>(&p, &reorged, b"Thud!"));
+        let later = ScriptContext::new(1235, &[7u8; 32], &commitment);
+        assert!(!sig.verify_with_script_context::<Blake256>(&p, &later, b"Thud!"));
+        let (_, other) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert!(!sig.verify_with_script_context::<Blake256>(&other, &context, b"Thud!"));
+    }
+
+    #[test]
+    fn batch_verify_find_invalid() {
+        let mut rng = rand::thread_rng();
+        let (signatures, public_keys, mut challenges) = create_signature_batch(25);
+        let invalid = RistrettoSchnorr::batch_verify_find_invalid(&signatures, &public_keys, &challenges, &mut rng);
+        assert_eq!(invalid, Ok(vec![]));
+        challenges.swap(3, 4);
+        challenges[24] = RistrettoSecretKey::from(24);
+        let invalid = RistrettoSchnorr::batch_verify_find_invalid(&signatures, &public_keys, &challenges, &mut rng);
+        assert_eq!(invalid, Ok(vec![3, 4, 24]));
+        assert_eq!(
+            RistrettoSchnorr::batch_verify_find_invalid(&signatures[1..], &public_keys, &challenges, &mut rng),
+            Err(SchnorrSignatureError::BatchLengthMismatch)
+        );
+        assert_eq!(
+            RistrettoSchnorr::batch_verify_find_invalid(&[], &[], &[], &mut rng),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn adaptor_signature() {
+        let mut rng = rand::thread_rng();
+        let (k, P) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (r, R) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (t, T) = RistrettoPublicKey::random_keypair(&mut rng);
+        let R_adapted = &R + &T;
+        let e = Blake256::new()
+            .chain(R_adapted.as_bytes())
+            .chain(P.as_bytes())
+            .chain(b"Small Gods")
+            .finalize();
+        let e_key = RistrettoSecretKey::from_bytes(&e).unwrap();
+        let pre_sig = RistrettoSchnorr::sign_adaptor(k, r, &T, &e).unwrap();
+        assert_eq!(pre_sig.get_public_nonce(), &R_adapted);
+        assert!(pre_sig.verify_adaptor(&P, &T, &e_key));
+        // The pre-signature is not a valid signature on its own, nor does it verify against another adaptor point
+        assert!(!pre_sig.verify(&P, &e_key));
+        let (_, T2) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert!(!pre_sig.verify_adaptor(&P, &T2, &e_key));
+        // Adapting with the secret produces a valid signature, from which the secret can be recovered
+        let sig = pre_sig.adapt(&t);
+        assert!(sig.verify(&P, &e_key));
+        assert_eq!(pre_sig.extract_adaptor_secret(&sig), Some(t));
+        assert_eq!(pre_sig.extract_adaptor_secret(&RistrettoSchnorr::default()), None);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn blind_signature() {
+        let mut rng = rand::thread_rng();
+        let (x, P) = RistrettoPublicKey::random_keypair(&mut rng);
+        // Signer sends its public nonce
+        let (k, R) = RistrettoPublicKey::random_keypair(&mut rng);
+        // Requester blinds the nonce and challenge
+        let session = BlindingSession::new::<Blake256, _>(&P, &R, b"The Truth", &mut rng).unwrap();
+        // Signer answers the blinded challenge
+        let s = sign_blinded(&x, k, session.blinded_challenge());
+        let e = session.challenge().clone();
+        let blinded_challenge = session.blinded_challenge().clone();
+        let sig = session.unblind(&s).unwrap();
+        assert!(sig.verify(&P, &e));
+        // The signer never saw the final nonce or signature
+        assert_ne!(sig.get_public_nonce(), &R);
+        assert_ne!(sig.get_signature(), &s);
+        assert_ne!(e, blinded_challenge);
+        // An incorrect response is caught before unblinding
+        let session = BlindingSession::new::<Blake256, _>(&P, &R, b"The Truth", &mut rng).unwrap();
+        assert_eq!(
+            session.unblind(&RistrettoSecretKey::random(&mut rng)).unwrap_err(),
+            BlindSignatureError::InvalidBlindSignature
+        );
+    }
+
+    #[test]
+    fn deterministic_signature() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig1 = RistrettoSchnorr::sign_deterministic::<Blake256>(k.clone(), b"Eric").unwrap();
+        let sig2 = RistrettoSchnorr::sign_deterministic::<Blake256>(k.clone(), b"Eric").unwrap();
+        assert_eq!(sig1, sig2);
+        assert!(sig1.verify_message::<Blake256>(&p, b"Eric"));
+        assert!(!sig1.verify_message::<Blake256>(&p, b"Faust"));
+        // A different message uses a different nonce
+        let sig3 = RistrettoSchnorr::sign_deterministic::<Blake256>(k.clone(), b"Faust").unwrap();
+        assert_ne!(sig1.get_public_nonce(), sig3.get_public_nonce());
+        assert!(sig3.verify_message::<Blake256>(&p, b"Faust"));
+        // The hash output must fit a secret key
+        assert_eq!(
+            RistrettoSchnorr::sign_deterministic::<sha2::Sha512>(k, b"Eric").unwrap_err(),
+            SchnorrSignatureError::IncompatibleHashFunction
+        );
+    }
+
+    #[test]
+    fn bip340_challenge_strategy() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = RistrettoBip340Schnorr::sign_deterministic::<sha2::Sha256>(k.clone(), b"Mort").unwrap();
+        assert_eq!(
+            sig,
+            RistrettoBip340Schnorr::sign_deterministic::<sha2::Sha256>(k.clone(), b"Mort").unwrap()
+        );
+        assert!(sig.verify_message::<sha2::Sha256>(&p, b"Mort"));
+        assert!(!sig.verify_message::<sha2::Sha256>(&p, b"Reaper Man"));
+        // The challenge is a tagged hash of R || P || m
+        let r = sig.get_public_nonce();
+        let tag = sha2::Sha256::digest(b"BIP0340/challenge");
+        let expected = sha2::Sha256::new()
+            .chain(&tag)
+            .chain(&tag)
+            .chain(r.as_bytes())
+            .chain(p.as_bytes())
+            .chain(b"Mort")
+            .finalize();
+        let e = Bip340Challenge::challenge::<sha2::Sha256>(r.as_bytes(), p.as_bytes(), b"Mort");
+        assert_eq!(e, expected.to_vec());
+        assert!(sig.verify_challenge(&p, &e));
+        // Signatures don't verify under the other strategy
+        let default_sig = RistrettoSchnorr::sign_deterministic::<sha2::Sha256>(k, b"Mort").unwrap();
+        assert_ne!(default_sig.get_public_nonce(), r);
+        let converted = RistrettoSchnorr::new(r.clone(), sig.get_signature().clone());
+        assert!(!converted.verify_message::<sha2::Sha256>(&p, b"Mort"));
+    }
+
+    #[test]
+    fn domain_separated_challenges() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (r, nonce) = RistrettoPublicKey::random_keypair(&mut rng);
+        let e = SignatureChallengeBuilder::<Blake256>::new(b"test.domain", &nonce, &p)
+            .chain_message(b"Lords and Ladies")
+            .finalize();
+        let sig = RistrettoSchnorr::sign::<Blake256>(k, r, b"test.domain", b"Lords and Ladies").unwrap();
+        assert!(sig.verify_challenge(&p, &e));
+        assert!(sig.verify_with_domain::<Blake256>(&p, b"test.domain", b"Lords and Ladies"));
+        assert!(!sig.verify_with_domain::<Blake256>(&p, b"test.domaiN", b"Lords and Ladies"));
+        assert!(!sig.verify_message::<Blake256>(&p, b"Lords and Ladies"));
+        // Chunks are length-prefixed, so the split of the message matters
+        let split = SignatureChallengeBuilder::<Blake256>::new(b"test.domain", &nonce, &p)
+            .chain_message(b"Lords and ")
+            .chain_message(b"Ladies")
+            .finalize();
+        assert_ne!(e, split);
+        // Moving bytes between the domain and the message changes the challenge
+        let shifted = SignatureChallengeBuilder::<Blake256>::new(b"test.domainL", &nonce, &p)
+            .chain_message(b"ords and Ladies")
+            .finalize();
+        assert_ne!(e, shifted);
+        let scalar: RistrettoSecretKey = SignatureChallengeBuilder::<Blake256>::new(b"test.domain", &nonce, &p)
+            .chain_message(b"Lords and Ladies")
+            .build()
+            .unwrap();
+        assert!(sig.verify(&p, &scalar));
+        assert_eq!(
+            SignatureChallengeBuilder::<sha2::Sha512>::new(b"test.domain", &nonce, &p)
+                .build::<RistrettoSecretKey>()
+                .unwrap_err(),
+            SchnorrSignatureError::IncompatibleHashFunction
+        );
+    }
+
+    #[test]
+    fn canonical_bytes() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, b"Carpe Jugulum").unwrap();
+        let mut bytes = sig.get_public_nonce().as_bytes().to_vec();
+        bytes.extend_from_slice(sig.get_signature().as_bytes());
+        let decoded = RistrettoSchnorr::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded, sig);
+        assert!(decoded.verify_message::<Blake256>(&p, b"Carpe Jugulum"));
+        assert_eq!(
+            RistrettoSchnorr::from_canonical_bytes(&bytes[..63]).unwrap_err(),
+            SchnorrSignatureError::InvalidEncoding
+        );
+        let mut bad_point = bytes.clone();
+        bad_point[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            RistrettoSchnorr::from_canonical_bytes(&bad_point).unwrap_err(),
+            SchnorrSignatureError::InvalidEncoding
+        );
+        // s + l reduces to the same scalar, but isn't its canonical encoding
+        let order = curve25519_dalek::constants::BASEPOINT_ORDER;
+        let mut unreduced = bytes.clone();
+        let mut carry = 0u16;
+        for (b, l) in unreduced[32..].iter_mut().zip(order.as_bytes()) {
+            let sum = *b as u16 + *l as u16 + carry;
+            *b = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(carry, 0);
+        assert_eq!(
+            RistrettoSecretKey::from_bytes(&unreduced[32..]).unwrap(),
+            *sig.get_signature()
+        );
+        assert_eq!(
+            RistrettoSchnorr::from_canonical_bytes(&unreduced).unwrap_err(),
+            SchnorrSignatureError::NonCanonicalEncoding
+        );
+        let mut identity = bytes;
+        identity[..32].copy_from_slice(&[0u8; 32]);
+        assert_eq!(
+            RistrettoSchnorr::from_canonical_bytes(&identity).unwrap_err(),
+            SchnorrSignatureError::IdentityNonce
+        );
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn strict_deserialization() {
+        let mut rng = rand::thread_rng();
+        let (k, _) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, b"Carpe Jugulum").unwrap();
+        let json = serde_json::to_string(&sig).unwrap();
+        assert_eq!(serde_json::from_str::<RistrettoSchnorr>(&json).unwrap(), sig);
+        let encoded = bincode::serialize(&sig).unwrap();
+        assert_eq!(bincode::deserialize::<RistrettoSchnorr>(&encoded).unwrap(), sig);
+        let identity = RistrettoSchnorr::new(RistrettoPublicKey::default(), sig.get_signature().clone());
+        let json = serde_json::to_string(&identity).unwrap();
+        assert!(serde_json::from_str::<RistrettoSchnorr>(&json).is_err());
+    }
+
+    #[test]
+    fn fixed_size_encoding() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Wrapper {
+            #[serde(with = "super::signature_bytes")]
+            signature: RistrettoSchnorr,
+        }
+
+        let mut rng = rand::thread_rng();
+        let (k, _) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, b"The Last Continent").unwrap();
+        let bytes = sig.to_bytes();
+        assert_eq!(&bytes[..32], sig.get_public_nonce().as_bytes());
+        assert_eq!(&bytes[32..], sig.get_signature().as_bytes());
+        assert_eq!(RistrettoSchnorr::from_bytes(&bytes).unwrap(), sig);
+
+        let wrapper = Wrapper { signature: sig.clone() };
+        let encoded = bincode::serialize(&wrapper).unwrap();
+        // An 8-byte length prefix, then the signature
+        assert_eq!(encoded.len(), 8 + 64);
+        assert_eq!(&encoded[8..], &bytes[..]);
+        let decoded: Wrapper = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded.signature, sig);
+        let json = serde_json::to_string(&wrapper).unwrap();
+        let decoded: Wrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.signature, sig);
+        assert!(serde_json::from_str::<Wrapper>(r#"{"signature":"00"}"#).is_err());
+    }
+
+    #[test]
+    fn sign_raw_challenge() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (r, _) = RistrettoPublicKey::random_keypair(&mut rng);
+        // An externally built challenge is used exactly as given
+        let challenge = Blake256::new()
+            .chain(b"consensus.kernel")
+            .chain(b"Men at Arms")
+            .finalize();
+        let sig = RistrettoSchnorr::sign_raw(k.clone(), r.clone(), &challenge).unwrap();
+        assert_eq!(sig, RistrettoSchnorr::sign_raw(k, r, &challenge).unwrap());
+        assert!(sig.verify_challenge(&p, &challenge));
+        assert!(sig.verify(&p, &RistrettoSecretKey::from_bytes(&challenge).unwrap()));
+        assert!(!sig.verify_challenge(&p, &Blake256::digest(&challenge)));
+        assert!(!sig.verify_challenge(&p, &challenge[..31]));
+    }
+
+    #[test]
+    fn half_aggregation() {
+        let (sigs, keys, mut challenges) = create_signature_batch(20);
+        let agg = RistrettoHalfAggregateSignature::aggregate::<Blake256>(&sigs, &keys, &challenges).unwrap();
+        assert_eq!(agg.len(), 20);
+        assert!(agg.verify::<Blake256>(&keys, &challenges));
+        // The order of the inputs matters
+        let mut shuffled_keys = keys.clone();
+        shuffled_keys.swap(0, 1);
+        assert!(!agg.verify::<Blake256>(&shuffled_keys, &challenges));
+        assert!(!agg.verify::<Blake256>(&keys[..19], &challenges[..19]));
+        // An invalid input signature produces an invalid aggregate
+        challenges.swap(3, 4);
+        let bad = RistrettoHalfAggregateSignature::aggregate::<Blake256>(&sigs, &keys, &challenges).unwrap();
+        assert!(!bad.verify::<Blake256>(&keys, &challenges));
+        // Empty aggregates are trivially valid
+        let empty = RistrettoHalfAggregateSignature::aggregate::<Blake256>(&[], &[], &[]).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.verify::<Blake256>(&[], &[]));
+        assert_eq!(
+            RistrettoHalfAggregateSignature::aggregate::<Blake256>(&sigs, &keys[..1], &challenges).unwrap_err(),
+            HalfAggregationError::MismatchedLengths
+        );
+    }
+
+    #[test]
+    fn recover_public_key() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let r = RistrettoSecretKey::random(&mut rng);
+        let sig = RistrettoSchnorr::sign_recoverable::<Blake256>(k, r, b"Witches Abroad").unwrap();
+        assert!(sig.verify_recoverable::<Blake256>(&p, b"Witches Abroad"));
+        assert_eq!(sig.recover_public_key::<Blake256>(b"Witches Abroad"), Some(p.clone()));
+        // A different message recovers a different key
+        assert_ne!(sig.recover_public_key::<Blake256>(b"Lords and Ladies"), Some(p.clone()));
+        assert!(!sig.verify_recoverable::<Blake256>(&p, b"Lords and Ladies"));
+        // Incompatible hash functions cannot produce a challenge
+        assert_eq!(sig.recover_public_key::<sha2::Sha512>(b"Witches Abroad"), None);
+    }
+
+    #[test]
+    fn signed_message() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let r = RistrettoSecretKey::random(&mut rng);
+        let msg = RistrettoSignedMessage::sign::<Blake256>(k, r, b"Pyramids").unwrap();
+        assert_eq!(msg.public_key(), &p);
+        assert!(msg.verify::<Blake256>(b"Pyramids"));
+        assert!(!msg.verify::<Blake256>(b"Eric"));
+        // Compatible with verify_message on the bare signature
+        assert!(msg.signature().verify_message::<Blake256>(&p, b"Pyramids"));
+        // 96-byte encoding: P || R || s
+        let bytes = msg.to_vec();
+        assert_eq!(bytes.len(), 96);
+        assert_eq!(RistrettoSignedMessage::encoded_length(), 96);
+        assert_eq!(&bytes[..32], p.as_bytes());
+        assert_eq!(&bytes[32..64], msg.signature().get_public_nonce().as_bytes());
+        assert_eq!(&bytes[64..], msg.signature().get_signature().as_bytes());
+        assert_eq!(RistrettoSignedMessage::from_bytes(&bytes).unwrap(), msg);
+        assert!(RistrettoSignedMessage::from_bytes(&bytes[..95]).is_err());
+        // s + l decodes to the same scalar, but isn't the canonical encoding
+        let mut unreduced = bytes.clone();
+        let mut carry = 0u16;
+        for (b, l) in unreduced[64..]
+            .iter_mut()
+            .zip(curve25519_dalek::constants::BASEPOINT_ORDER.as_bytes())
+        {
+            let sum = u16::from(*b) + u16::from(*l) + carry;
+            *b = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(carry, 0);
+        assert!(RistrettoSignedMessage::from_bytes(&unreduced).is_err());
+        // As is an identity nonce
+        let mut identity_nonce = bytes.clone();
+        identity_nonce[32..64].copy_from_slice(&[0u8; 32]);
+        assert!(RistrettoSignedMessage::from_bytes(&identity_nonce).is_err());
+        // serde round trips, in both binary and human readable formats
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(json, format!("\"{}\"", tari_utilities::hex::to_hex(&bytes)));
+        assert_eq!(serde_json::from_str::<RistrettoSignedMessage>(&json).unwrap(), msg);
+        let bin = bincode::serialize(&msg).unwrap();
+        assert_eq!(bin.len(), 8 + 96);
+        assert_eq!(bincode::deserialize::<RistrettoSignedMessage>(&bin).unwrap(), msg);
     }
 }
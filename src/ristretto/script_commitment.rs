@@ -167,7 +167,10 @@ impl ScriptCommitmentFactory {
         commitment: &PedersenCommitment,
     ) -> bool {
         match self.commit_script::<D>(k, v, script) {
-            Ok(sc) => commitment == &self.script_to_pedersen(&sc),
+            Ok(sc) => {
+                let v = RistrettoSecretKey::from(sc.value);
+                self.factory.open_ct(&sc.adj_blinding_factor, &v, commitment).into()
+            },
             _ => false,
         }
     }
@@ -40,8 +40,21 @@
 //!       digest_type: PhantomData<D>,
 //!   }
 //! ```
+//!
+//! In binary formats, keys and commitments are serialized as byte strings, which most formats (bincode included)
+//! prefix with a length. Where a canonical, fixed-length encoding is needed, e.g. for consensus, use the
+//! [fixed_32_bytes] helper on 32-byte types such as `PedersenCommitment`, which writes exactly 32 bytes:
+//!
+//! ```nocompile
+//!   #[derive(Serialize, Deserialize)]
+//!   pub struct Output {
+//!       #[serde(with = "tari_crypto::ristretto::serialize::fixed_32_bytes")]
+//!       pub commitment: PedersenCommitment,
+//!   }
+//! ```
 
 use crate::ristretto::{RistrettoPublicKey, RistrettoSecretKey};
+use clear_on_drop::clear::Clear;
 use serde::{
     de::{self, Visitor},
     Deserialize,
@@ -50,7 +63,10 @@ use serde::{
     Serializer,
 };
 use std::fmt;
-use tari_utilities::{byte_array::ByteArray, hex::Hex};
+use tari_utilities::{
+    byte_array::ByteArray,
+    hex::{from_hex, Hex},
+};
 
 impl<'de> Deserialize<'de> for RistrettoPublicKey {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -104,13 +120,15 @@ impl<'de> Deserialize<'de> for RistrettoSecretKey {
 
             fn visit_bytes<E>(self, v: &[u8]) -> Result<RistrettoSecretKey, E>
             where E: de::Error {
-                RistrettoSecretKey::from_bytes(v).map_err(E::custom)
+                RistrettoSecretKey::from_canonical_bytes(v).map_err(E::custom)
             }
         }
 
+        // Only canonical encodings are accepted, so that each key has exactly one serialized form
         if deserializer.is_human_readable() {
             let s = String::deserialize(deserializer)?;
-            RistrettoSecretKey::from_hex(&s).map_err(de::Error::custom)
+            let bytes = from_hex(&s).map_err(de::Error::custom)?;
+            RistrettoSecretKey::from_canonical_bytes(&bytes).map_err(de::Error::custom)
         } else {
             deserializer.deserialize_bytes(RistrettoVisitor)
         }
@@ -121,9 +139,113 @@ impl Serialize for RistrettoSecretKey {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where S: Serializer {
         if serializer.is_human_readable() {
-            self.to_hex().serialize(serializer)
+            // Don't leave a hex copy of the key lying around once it has been written out
+            let mut hex = self.to_hex();
+            let result = hex.serialize(serializer);
+            hex.as_mut_str().clear();
+            result
         } else {
             serializer.serialize_bytes(self.as_bytes())
         }
     }
 }
+
+/// A `serde(with = "...")` helper that (de)serializes a 32-byte [ByteArray] type, such as a `PedersenCommitment` or
+/// `RistrettoPublicKey`, as a fixed-length array of 32 bytes in binary formats, with no length prefix. Human readable
+/// formats use hex, as usual.
+pub mod fixed_32_bytes {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use tari_utilities::{byte_array::ByteArray, hex::Hex};
+
+    const LENGTH: usize = 32;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ByteArray,
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            return value.to_hex().serialize(serializer);
+        }
+        let bytes = value.as_bytes();
+        if bytes.len() != LENGTH {
+            return Err(serde::ser::Error::custom("expected a 32 byte value"));
+        }
+        let mut buf = [0u8; LENGTH];
+        buf.copy_from_slice(bytes);
+        buf.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: ByteArray,
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            T::from_hex(&s).map_err(de::Error::custom)
+        } else {
+            let buf = <[u8; LENGTH]>::deserialize(deserializer)?;
+            T::from_bytes(&buf).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        commitment::HomomorphicCommitmentFactory,
+        keys::SecretKey,
+        ristretto::{
+            pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+            RistrettoSecretKey,
+        },
+    };
+    use serde::{Deserialize, Serialize};
+    use tari_utilities::{
+        hex::{from_hex, Hex},
+        ByteArray,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Output {
+        #[serde(with = "super::fixed_32_bytes")]
+        commitment: PedersenCommitment,
+    }
+
+    #[test]
+    fn fixed_length_commitments() {
+        let k = RistrettoSecretKey::random(&mut rand::thread_rng());
+        let commitment = PedersenCommitmentFactory::default().commit_value(&k, 100);
+        let output = Output {
+            commitment: commitment.clone(),
+        };
+        let encoded = bincode::serialize(&output).unwrap();
+        assert_eq!(encoded, commitment.as_bytes());
+        // The default encoding carries a length prefix
+        assert_eq!(bincode::serialize(&commitment).unwrap().len(), 40);
+        assert_eq!(bincode::deserialize::<Output>(&encoded).unwrap(), output);
+        assert!(bincode::deserialize::<Output>(&encoded[..31]).is_err());
+        // Human readable formats still use hex
+        let json = serde_json::to_string(&output).unwrap();
+        assert_eq!(json, format!("{{\"commitment\":\"{}\"}}", commitment.to_hex()));
+        assert_eq!(serde_json::from_str::<Output>(&json).unwrap(), output);
+    }
+
+    #[test]
+    fn non_canonical_secret_keys_are_rejected() {
+        let k = RistrettoSecretKey::random(&mut rand::thread_rng());
+        let json = serde_json::to_string(&k).unwrap();
+        assert_eq!(serde_json::from_str::<RistrettoSecretKey>(&json).unwrap(), k);
+        assert_eq!(
+            bincode::deserialize::<RistrettoSecretKey>(&bincode::serialize(&k).unwrap()).unwrap(),
+            k
+        );
+        // l + 1, which from_bytes would reduce to 1
+        let l_plus_one = "eed3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+        assert!(serde_json::from_str::<RistrettoSecretKey>(&format!("\"{}\"", l_plus_one)).is_err());
+        // bincode writes a byte string as a length-prefixed sequence, just like a Vec<u8>
+        let encoded = bincode::serialize(&from_hex(l_plus_one).unwrap()).unwrap();
+        assert!(bincode::deserialize::<RistrettoSecretKey>(&encoded).is_err());
+    }
+}
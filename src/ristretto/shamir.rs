@@ -0,0 +1,427 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Shamir secret sharing of Ristretto secret keys.
+//!
+//! [split] hides a secret as the constant term of a random polynomial, _f_, of degree _t - 1_, and hands out the
+//! shares _(i, f(i))_ for _i = 1..n_. Any _t_ shares recover the secret with [combine], by Lagrange interpolation at
+//! zero; fewer than _t_ reveal nothing about it.
+//!
+//! [combine] can't tell how many shares are needed, so combining fewer than the threshold returns a key, just the
+//! wrong one. Check the result against something public, such as the public key, before relying on it.
+//!
+//! ## Verifiable secret sharing
+//! Plain Shamir shares have to be taken on trust. [split_verifiable] also returns a [FeldmanCommitment] to the
+//! polynomial, _(a_0.G, ..., a_{t-1}.G)_, which can be published. Each shareholder checks their share with
+//! [FeldmanCommitment::verify_share], i.e. that _f(i).G = \sum i^j.(a_j.G)_, without learning anything about the
+//! secret beyond its public key, _a_0.G_.
+//!
+//! ## Re-sharing
+//! For proactive security, shares can be refreshed without changing the secret (or its public key), so that shares
+//! stolen before a refresh are useless once it's done. [refresh] adds the evaluations of a random polynomial with a
+//! zero constant term to each Shamir share. Groups that need all _n_ parties to sign can use [split_additive]
+//! instead, which splits a key into _n_ random keys that sum to it, and refresh these with [refresh_additive].
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{
+        poly_commit::Polynomial,
+        threshold_com_sig::{lagrange_coefficient, ShareIndex},
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+    },
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, convert::TryFrom};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+/// The length of a serialized [Share]: an 8-byte index followed by a 32-byte value
+pub const SHARE_LENGTH: usize = 40;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ShamirError {
+    #[error("The threshold must be between 1 and the number of shares ({0}), but is {1}")]
+    InvalidThreshold(usize, usize),
+    #[error("At least one share is needed to recover a secret")]
+    NoShares,
+    #[error("Share index {0} appears more than once")]
+    DuplicateIndex(ShareIndex),
+    #[error("Share indices start at 1")]
+    ZeroIndex,
+    #[error("A serialized share must be exactly {} bytes", SHARE_LENGTH)]
+    InvalidShareEncoding,
+    #[error("A Feldman commitment needs at least one coefficient commitment")]
+    EmptyCommitment,
+}
+
+/// One share of a secret: the evaluation of the sharing polynomial at `index`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    index: ShareIndex,
+    value: RistrettoSecretKey,
+}
+
+impl Share {
+    pub fn new(index: ShareIndex, value: RistrettoSecretKey) -> Self {
+        Share { index, value }
+    }
+
+    pub fn index(&self) -> ShareIndex {
+        self.index
+    }
+
+    pub fn value(&self) -> &RistrettoSecretKey {
+        &self.value
+    }
+
+    /// The little-endian index followed by the value
+    pub fn to_bytes(&self) -> [u8; SHARE_LENGTH] {
+        let mut bytes = [0u8; SHARE_LENGTH];
+        bytes[..8].copy_from_slice(&self.index.to_le_bytes());
+        bytes[8..].copy_from_slice(self.value.as_bytes());
+        bytes
+    }
+
+    /// Read a share written by [to_bytes](#method.to_bytes). The value must be a canonical scalar.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ShamirError> {
+        if bytes.len() != SHARE_LENGTH {
+            return Err(ShamirError::InvalidShareEncoding);
+        }
+        let index =
+            u64::from_le_bytes(<[u8; 8]>::try_from(&bytes[..8]).map_err(|_| ShamirError::InvalidShareEncoding)?);
+        if index == 0 {
+            return Err(ShamirError::ZeroIndex);
+        }
+        let value = <[u8; 32]>::try_from(&bytes[8..]).map_err(|_| ShamirError::InvalidShareEncoding)?;
+        let value = Scalar::from_canonical_bytes(value).ok_or(ShamirError::InvalidShareEncoding)?;
+        Ok(Share::new(index, RistrettoSecretKey(value)))
+    }
+}
+
+/// Split `secret` into `n` shares, any `threshold` of which recover it. The shares have indices `1..=n`.
+pub fn split<R: Rng + CryptoRng>(
+    secret: &RistrettoSecretKey,
+    threshold: usize,
+    n: usize,
+    rng: &mut R,
+) -> Result<Vec<Share>, ShamirError> {
+    sharing_polynomial(secret, threshold, n, rng).map(|polynomial| evaluate_shares(&polynomial, n))
+}
+
+/// Split `secret` as in [split], and also return the Feldman commitment that the shares can be verified against
+pub fn split_verifiable<R: Rng + CryptoRng>(
+    secret: &RistrettoSecretKey,
+    threshold: usize,
+    n: usize,
+    rng: &mut R,
+) -> Result<(Vec<Share>, FeldmanCommitment), ShamirError> {
+    let polynomial = sharing_polynomial(secret, threshold, n, rng)?;
+    let commitment = FeldmanCommitment::from(&polynomial);
+    Ok((evaluate_shares(&polynomial, n), commitment))
+}
+
+fn sharing_polynomial<R: Rng + CryptoRng>(
+    secret: &RistrettoSecretKey,
+    threshold: usize,
+    n: usize,
+    rng: &mut R,
+) -> Result<Polynomial, ShamirError> {
+    if threshold == 0 || threshold > n {
+        return Err(ShamirError::InvalidThreshold(n, threshold));
+    }
+    Ok(Polynomial::random_with_secret(secret, threshold - 1, rng))
+}
+
+fn evaluate_shares(polynomial: &Polynomial, n: usize) -> Vec<Share> {
+    (1..=n as u64)
+        .map(|i| Share::new(i, polynomial.evaluate(&RistrettoSecretKey::from(i))))
+        .collect()
+}
+
+/// Public commitments to the coefficients of a sharing polynomial, _a_j.G_, lowest degree first
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<RistrettoPublicKey>", into = "Vec<RistrettoPublicKey>")]
+pub struct FeldmanCommitment {
+    commitments: Vec<RistrettoPublicKey>,
+}
+
+impl FeldmanCommitment {
+    /// The public key of the shared secret, _a_0.G_
+    pub fn public_key(&self) -> &RistrettoPublicKey {
+        &self.commitments[0]
+    }
+
+    /// The number of shares needed to recover the secret
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn commitments(&self) -> &[RistrettoPublicKey] {
+        &self.commitments
+    }
+
+    /// The public key that the share at `index` should have, _f(index).G_
+    pub fn verification_key(&self, index: ShareIndex) -> RistrettoPublicKey {
+        let x = Scalar::from(index);
+        let point = self
+            .commitments
+            .iter()
+            .rev()
+            .fold(RistrettoPoint::default(), |acc, c| acc * x + c.point);
+        RistrettoPublicKey::new_from_pk(point)
+    }
+
+    /// Check that `share` is the evaluation of the committed polynomial at the share's index
+    pub fn verify_share(&self, share: &Share) -> bool {
+        share.index != 0 && RistrettoPublicKey::from_secret_key(&share.value) == self.verification_key(share.index)
+    }
+}
+
+impl From<&Polynomial> for FeldmanCommitment {
+    fn from(polynomial: &Polynomial) -> Self {
+        let commitments = polynomial
+            .coefficients()
+            .iter()
+            .map(RistrettoPublicKey::from_secret_key)
+            .collect();
+        FeldmanCommitment { commitments }
+    }
+}
+
+impl TryFrom<Vec<RistrettoPublicKey>> for FeldmanCommitment {
+    type Error = ShamirError;
+
+    fn try_from(commitments: Vec<RistrettoPublicKey>) -> Result<Self, Self::Error> {
+        if commitments.is_empty() {
+            return Err(ShamirError::EmptyCommitment);
+        }
+        Ok(FeldmanCommitment { commitments })
+    }
+}
+
+impl From<FeldmanCommitment> for Vec<RistrettoPublicKey> {
+    fn from(commitment: FeldmanCommitment) -> Self {
+        commitment.commitments
+    }
+}
+
+/// Recover a secret from at least `threshold` of its shares
+pub fn combine(shares: &[Share]) -> Result<RistrettoSecretKey, ShamirError> {
+    let indices = check_indices(shares)?;
+    let mut secret = Scalar::zero();
+    for share in shares {
+        let lambda = lagrange_coefficient(share.index, &indices).expect("every index is in the set");
+        secret += lambda.0 * share.value.0;
+    }
+    Ok(RistrettoSecretKey(secret))
+}
+
+/// Re-randomise a complete set of shares with the given threshold. The refreshed shares recover the same secret, but
+/// can't be combined with any of the old ones. All the old shares must be replaced.
+pub fn refresh<R: Rng + CryptoRng>(shares: &[Share], threshold: usize, rng: &mut R) -> Result<Vec<Share>, ShamirError> {
+    check_indices(shares)?;
+    let zero = sharing_polynomial(&RistrettoSecretKey::default(), threshold, shares.len(), rng)?;
+    let refreshed = shares
+        .iter()
+        .map(|s| Share::new(s.index, &s.value + &zero.evaluate(&RistrettoSecretKey::from(s.index))))
+        .collect();
+    Ok(refreshed)
+}
+
+/// Split `secret` into `n` random keys that sum to it. All `n` are needed to recover the secret, and the sum of their
+/// public keys is the secret's public key.
+pub fn split_additive<R: Rng + CryptoRng>(
+    secret: &RistrettoSecretKey,
+    n: usize,
+    rng: &mut R,
+) -> Result<Vec<RistrettoSecretKey>, ShamirError> {
+    if n == 0 {
+        return Err(ShamirError::NoShares);
+    }
+    let mut shares: Vec<RistrettoSecretKey> = (1..n).map(|_| RistrettoSecretKey::random(rng)).collect();
+    let sum: RistrettoSecretKey = shares.iter().sum();
+    shares.push(secret - &sum);
+    Ok(shares)
+}
+
+/// Re-randomise additive shares in place by adding random offsets that sum to zero, so their sum is unchanged
+pub fn refresh_additive<R: Rng + CryptoRng>(shares: &mut [RistrettoSecretKey], rng: &mut R) -> Result<(), ShamirError> {
+    let offsets = split_additive(&RistrettoSecretKey::default(), shares.len(), rng)?;
+    for (share, offset) in shares.iter_mut().zip(offsets) {
+        *share += offset;
+    }
+    Ok(())
+}
+
+fn check_indices(shares: &[Share]) -> Result<Vec<ShareIndex>, ShamirError> {
+    if shares.is_empty() {
+        return Err(ShamirError::NoShares);
+    }
+    let mut seen = HashSet::with_capacity(shares.len());
+    for share in shares {
+        if share.index == 0 {
+            return Err(ShamirError::ZeroIndex);
+        }
+        if !seen.insert(share.index) {
+            return Err(ShamirError::DuplicateIndex(share.index));
+        }
+    }
+    Ok(shares.iter().map(|s| s.index).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_and_combine() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let shares = split(&secret, 3, 5, &mut rng).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert_eq!(shares.iter().map(Share::index).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        // Any three shares will do
+        assert_eq!(combine(&shares[..3]).unwrap(), secret);
+        assert_eq!(combine(&shares[2..]).unwrap(), secret);
+        assert_eq!(
+            combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap(),
+            secret
+        );
+        assert_eq!(combine(&shares).unwrap(), secret);
+        // Two shares give the wrong answer
+        assert_ne!(combine(&shares[..2]).unwrap(), secret);
+        // 1-of-n shares are copies of the secret
+        let copies = split(&secret, 1, 3, &mut rng).unwrap();
+        assert!(copies.iter().all(|s| s.value() == &secret));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        assert_eq!(split(&secret, 0, 3, &mut rng), Err(ShamirError::InvalidThreshold(3, 0)));
+        assert_eq!(split(&secret, 4, 3, &mut rng), Err(ShamirError::InvalidThreshold(3, 4)));
+        let shares = split(&secret, 2, 3, &mut rng).unwrap();
+        assert_eq!(combine(&[]), Err(ShamirError::NoShares));
+        assert_eq!(
+            combine(&[shares[0].clone(), shares[0].clone()]),
+            Err(ShamirError::DuplicateIndex(1))
+        );
+        let zero = Share::new(0, secret.clone());
+        assert_eq!(combine(&[zero, shares[1].clone()]), Err(ShamirError::ZeroIndex));
+    }
+
+    #[test]
+    fn share_serialization() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let shares = split(&secret, 2, 3, &mut rng).unwrap();
+        let restored = shares
+            .iter()
+            .map(|s| Share::from_bytes(&s.to_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(restored, shares);
+        let bytes = shares[1].to_bytes();
+        assert_eq!(&bytes[..8], &2u64.to_le_bytes());
+        assert_eq!(Share::from_bytes(&bytes[..39]), Err(ShamirError::InvalidShareEncoding));
+        let mut zero_index = bytes;
+        zero_index[..8].copy_from_slice(&[0; 8]);
+        assert_eq!(Share::from_bytes(&zero_index), Err(ShamirError::ZeroIndex));
+        let mut non_canonical = bytes;
+        non_canonical[8..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            Share::from_bytes(&non_canonical),
+            Err(ShamirError::InvalidShareEncoding)
+        );
+        let json = serde_json::to_string(&shares[0]).unwrap();
+        assert_eq!(serde_json::from_str::<Share>(&json).unwrap(), shares[0]);
+    }
+
+    #[test]
+    fn verifiable_shares() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let (shares, commitment) = split_verifiable(&secret, 3, 6, &mut rng).unwrap();
+        assert_eq!(commitment.threshold(), 3);
+        assert_eq!(commitment.public_key(), &RistrettoPublicKey::from_secret_key(&secret));
+        assert!(shares.iter().all(|s| commitment.verify_share(s)));
+        for s in &shares {
+            assert_eq!(
+                commitment.verification_key(s.index()),
+                RistrettoPublicKey::from_secret_key(s.value())
+            );
+        }
+        assert_eq!(combine(&shares[1..4]).unwrap(), secret);
+        // A tampered share, or a share presented at the wrong index, is rejected
+        let tampered = Share::new(2, shares[1].value() + &RistrettoSecretKey::from(1));
+        assert!(!commitment.verify_share(&tampered));
+        assert!(!commitment.verify_share(&Share::new(3, shares[1].value().clone())));
+        assert!(!commitment.verify_share(&Share::new(0, secret.clone())));
+        // Shares from another sharing don't verify
+        let (other_shares, _) = split_verifiable(&secret, 3, 6, &mut rng).unwrap();
+        assert!(!commitment.verify_share(&other_shares[0]));
+        // Serialization round trip, rejecting empty commitments
+        let json = serde_json::to_string(&commitment).unwrap();
+        assert_eq!(serde_json::from_str::<FeldmanCommitment>(&json).unwrap(), commitment);
+        assert!(serde_json::from_str::<FeldmanCommitment>("[]").is_err());
+    }
+
+    #[test]
+    fn refreshed_shares() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let shares = split(&secret, 3, 5, &mut rng).unwrap();
+        let refreshed = refresh(&shares, 3, &mut rng).unwrap();
+        assert_eq!(combine(&refreshed[..3]).unwrap(), secret);
+        assert_eq!(combine(&refreshed[2..]).unwrap(), secret);
+        assert!(shares
+            .iter()
+            .zip(&refreshed)
+            .all(|(a, b)| a.index() == b.index() && a.value() != b.value()));
+        // Old and new shares don't mix
+        let mixed = vec![shares[0].clone(), refreshed[1].clone(), refreshed[2].clone()];
+        assert_ne!(combine(&mixed).unwrap(), secret);
+        assert_eq!(refresh(&shares, 6, &mut rng), Err(ShamirError::InvalidThreshold(5, 6)));
+        assert_eq!(refresh(&[], 1, &mut rng), Err(ShamirError::NoShares));
+    }
+
+    #[test]
+    fn additive_shares() {
+        let mut rng = rand::thread_rng();
+        let secret = RistrettoSecretKey::random(&mut rng);
+        let public_key = RistrettoPublicKey::from_secret_key(&secret);
+        let mut shares = split_additive(&secret, 4, &mut rng).unwrap();
+        assert_eq!(shares.len(), 4);
+        assert_eq!(shares.iter().sum::<RistrettoSecretKey>(), secret);
+        let public_keys: Vec<RistrettoPublicKey> = shares.iter().map(RistrettoPublicKey::from_secret_key).collect();
+        assert_eq!(public_keys.iter().sum::<RistrettoPublicKey>(), public_key);
+        let old = shares.clone();
+        refresh_additive(&mut shares, &mut rng).unwrap();
+        assert_eq!(shares.iter().sum::<RistrettoSecretKey>(), secret);
+        assert!(old.iter().zip(&shares).all(|(a, b)| a != b));
+        assert_eq!(split_additive(&secret, 1, &mut rng).unwrap(), vec![secret.clone()]);
+        assert_eq!(split_additive(&secret, 0, &mut rng), Err(ShamirError::NoShares));
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A round-based 2-of-2 aggregated signing session.
+//!
+//! [SigningSession] drives one party through the three rounds of a two-party MuSig signature, exchanging
+//! serializable messages with the other party at each step:
+//!
+//! 1. Both parties send a [NonceCommitmentMessage], a hash of their public nonce.
+//! 2. On receiving the peer's commitment, each party reveals its nonce with a [NonceRevealMessage].
+//! 3. On receiving the peer's nonce (which is checked against its commitment), each party calculates its partial
+//!    signature, _s_i = r_i + e.a_i.k_i_, and sends it as a [PartialSignatureMessage].
+//!
+//! On receiving the peer's partial signature, which is checked before use, the session produces the aggregate
+//! `RistrettoSchnorr` signature. It verifies against the MuSig joint public key with challenge _e = H(R || X || m)_,
+//! where _m_ is the digest of the message.
+//!
+//! Any error aborts the session; a failed session must be discarded and a new one, with a fresh nonce, started.
+
+use crate::{
+    keys::PublicKey,
+    musig::{JointKey, JointKeyBuilder, MuSigError},
+    ristretto::{
+        nonce_commitment::{CommittedNonce, NonceCommitment},
+        RistrettoPublicKey,
+        RistrettoSchnorr,
+        RistrettoSecretKey,
+    },
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use tari_utilities::ByteArray;
+
+type JointPubKey = JointKey<RistrettoPublicKey, RistrettoSecretKey>;
+
+/// Round 1: a commitment to the sender's public nonce
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceCommitmentMessage {
+    commitment: NonceCommitment,
+}
+
+/// Round 2: the sender's public nonce
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceRevealMessage {
+    public_nonce: RistrettoPublicKey,
+}
+
+/// Round 3: the sender's partial signature
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignatureMessage {
+    partial_signature: RistrettoSecretKey,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SessionState {
+    AwaitingNonceCommitment,
+    AwaitingNonce,
+    AwaitingPartialSignature,
+    Finalized,
+    Failed,
+}
+
+/// One party's side of a 2-of-2 aggregated signing session.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{signing_session::SigningSession, RistrettoPublicKey};
+/// # use tari_crypto::keys::PublicKey;
+/// # use sha2::Sha256;
+/// let mut rng = rand::thread_rng();
+/// let (k_a, p_a) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let (k_b, p_b) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let (mut alice, commit_a) = SigningSession::<Sha256>::new(k_a, &p_b, b"Interesting Times", &mut rng).unwrap();
+/// let (mut bob, commit_b) = SigningSession::<Sha256>::new(k_b, &p_a, b"Interesting Times", &mut rng).unwrap();
+/// let nonce_a = alice.receive_nonce_commitment(&commit_b).unwrap();
+/// let nonce_b = bob.receive_nonce_commitment(&commit_a).unwrap();
+/// let partial_a = alice.receive_nonce(&nonce_b).unwrap();
+/// let partial_b = bob.receive_nonce(&nonce_a).unwrap();
+/// let sig = alice.receive_partial_signature(&partial_b).unwrap();
+/// assert_eq!(sig, bob.receive_partial_signature(&partial_a).unwrap());
+/// assert_eq!(alice.joint_public_key(), bob.joint_public_key());
+/// ```
+pub struct SigningSession<D: Digest> {
+    secret_key: RistrettoSecretKey,
+    joint_key: JointPubKey,
+    index: usize,
+    message: Vec<u8>,
+    secret_nonce: Option<CommittedNonce>,
+    public_nonce: RistrettoPublicKey,
+    peer_commitment: Option<NonceCommitment>,
+    peer_nonce: Option<RistrettoPublicKey>,
+    aggregate_nonce: Option<RistrettoPublicKey>,
+    challenge: Option<RistrettoSecretKey>,
+    partial_signature: Option<RistrettoSecretKey>,
+    state: SessionState,
+    _hasher: PhantomData<D>,
+}
+
+impl<D: Digest> SigningSession<D> {
+    /// Start a session to sign `message` with `peer_public_key`. A fresh secret nonce is drawn from `rng`. Returns the
+    /// session and the nonce commitment to send to the peer.
+    pub fn new<R: Rng + CryptoRng>(
+        secret_key: RistrettoSecretKey,
+        peer_public_key: &RistrettoPublicKey,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<(Self, NonceCommitmentMessage), MuSigError> {
+        if D::output_size() != 32 {
+            return Err(MuSigError::IncompatibleHashFunction);
+        }
+        let public_key = RistrettoPublicKey::from_secret_key(&secret_key);
+        let mut builder = JointKeyBuilder::<RistrettoPublicKey, RistrettoSecretKey>::new(2)?;
+        builder.add_keys(vec![public_key.clone(), peer_public_key.clone()])?;
+        let joint_key = builder.build::<D>()?;
+        let index = joint_key.index_of(&public_key)?;
+        let secret_nonce = CommittedNonce::random::<D, _>(rng);
+        let public_nonce = secret_nonce.public_nonce().clone();
+        let commitment = NonceCommitmentMessage {
+            commitment: secret_nonce.commitment().clone(),
+        };
+        let session = SigningSession {
+            secret_key,
+            joint_key,
+            index,
+            message: D::digest(message).to_vec(),
+            secret_nonce: Some(secret_nonce),
+            public_nonce,
+            peer_commitment: None,
+            peer_nonce: None,
+            aggregate_nonce: None,
+            challenge: None,
+            partial_signature: None,
+            state: SessionState::AwaitingNonceCommitment,
+            _hasher: PhantomData,
+        };
+        Ok((session, commitment))
+    }
+
+    /// Round 1: store the peer's nonce commitment and reveal our public nonce
+    pub fn receive_nonce_commitment(&mut self, msg: &NonceCommitmentMessage) -> Result<NonceRevealMessage, MuSigError> {
+        self.transition(SessionState::AwaitingNonceCommitment, |session| {
+            session.peer_commitment = Some(msg.commitment.clone());
+            let reveal = NonceRevealMessage {
+                public_nonce: session.public_nonce.clone(),
+            };
+            Ok((reveal, SessionState::AwaitingNonce))
+        })
+    }
+
+    /// Round 2: check the peer's nonce against its commitment, and calculate our partial signature. The secret nonce
+    /// is consumed.
+    pub fn receive_nonce(&mut self, msg: &NonceRevealMessage) -> Result<PartialSignatureMessage, MuSigError> {
+        self.transition(SessionState::AwaitingNonce, |session| {
+            let expected = session.peer_commitment.as_ref().ok_or(MuSigError::MissingHash)?;
+            if !expected.verify::<D>(&msg.public_nonce) {
+                return Err(MuSigError::MismatchedNonces);
+            }
+            let aggregate_nonce = &session.public_nonce + &msg.public_nonce;
+            let e = D::new()
+                .chain(aggregate_nonce.as_bytes())
+                .chain(session.joint_key.get_joint_pubkey().as_bytes())
+                .chain(&session.message)
+                .finalize();
+            let challenge =
+                RistrettoSecretKey::from_bytes(&e).expect("Found a u256 that does not map to a valid Ristretto scalar");
+            let nonce = session
+                .secret_nonce
+                .take()
+                .ok_or(MuSigError::InvalidStateTransition)?
+                .into_secret();
+            let a = session.joint_key.get_musig_scalar(session.index);
+            let s = &nonce + &(&(&challenge * a) * &session.secret_key);
+            session.peer_nonce = Some(msg.public_nonce.clone());
+            session.aggregate_nonce = Some(aggregate_nonce);
+            session.challenge = Some(challenge);
+            session.partial_signature = Some(s.clone());
+            Ok((
+                PartialSignatureMessage { partial_signature: s },
+                SessionState::AwaitingPartialSignature,
+            ))
+        })
+    }
+
+    /// Round 3: check the peer's partial signature and aggregate it with ours into the final signature
+    pub fn receive_partial_signature(&mut self, msg: &PartialSignatureMessage) -> Result<RistrettoSchnorr, MuSigError> {
+        self.transition(SessionState::AwaitingPartialSignature, |session| {
+            let (peer_nonce, aggregate_nonce, challenge, own) = match (
+                &session.peer_nonce,
+                &session.aggregate_nonce,
+                &session.challenge,
+                &session.partial_signature,
+            ) {
+                (Some(r), Some(agg), Some(e), Some(s)) => (r, agg, e, s),
+                _ => return Err(MuSigError::InvalidStateTransition),
+            };
+            // s_j.G = R_j + e.a_j.P_j
+            let peer = 1 - session.index;
+            let a = session.joint_key.get_musig_scalar(peer);
+            let expected = peer_nonce + &(&(challenge * a) * session.joint_key.get_pub_keys(peer));
+            if RistrettoPublicKey::from_secret_key(&msg.partial_signature) != expected {
+                return Err(MuSigError::InvalidPartialSignature(peer));
+            }
+            let signature = RistrettoSchnorr::new(aggregate_nonce.clone(), own + &msg.partial_signature);
+            if !signature.verify(session.joint_key.get_joint_pubkey(), challenge) {
+                return Err(MuSigError::InvalidAggregateSignature);
+            }
+            Ok((signature, SessionState::Finalized))
+        })
+    }
+
+    /// The MuSig joint public key that the final signature verifies against
+    pub fn joint_public_key(&self) -> &RistrettoPublicKey {
+        self.joint_key.get_joint_pubkey()
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.state == SessionState::Finalized
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.state == SessionState::Failed
+    }
+
+    /// Run a round if the session is in the `expected` state, moving to the next state on success. Any error,
+    /// including an out-of-order message, fails the session.
+    fn transition<T, F>(&mut self, expected: SessionState, round: F) -> Result<T, MuSigError>
+    where F: FnOnce(&mut Self) -> Result<(T, SessionState), MuSigError> {
+        if self.state != expected {
+            self.state = SessionState::Failed;
+            return Err(MuSigError::InvalidStateTransition);
+        }
+        match round(self) {
+            Ok((result, next)) => {
+                self.state = next;
+                Ok(result)
+            },
+            Err(e) => {
+                self.state = SessionState::Failed;
+                Err(e)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::SecretKey;
+    use sha2::Sha256;
+
+    type Session = SigningSession<Sha256>;
+
+    fn sessions(message: &[u8]) -> (Session, NonceCommitmentMessage, Session, NonceCommitmentMessage) {
+        let mut rng = rand::thread_rng();
+        let (k_a, p_a) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (k_b, p_b) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (alice, commit_a) = Session::new(k_a, &p_b, message, &mut rng).unwrap();
+        let (bob, commit_b) = Session::new(k_b, &p_a, message, &mut rng).unwrap();
+        (alice, commit_a, bob, commit_b)
+    }
+
+    #[test]
+    fn two_party_signature() {
+        let (mut alice, commit_a, mut bob, commit_b) = sessions(b"Maskerade");
+        let nonce_a = alice.receive_nonce_commitment(&commit_b).unwrap();
+        let nonce_b = bob.receive_nonce_commitment(&commit_a).unwrap();
+        let partial_a = alice.receive_nonce(&nonce_b).unwrap();
+        let partial_b = bob.receive_nonce(&nonce_a).unwrap();
+        let sig = alice.receive_partial_signature(&partial_b).unwrap();
+        assert_eq!(sig, bob.receive_partial_signature(&partial_a).unwrap());
+        assert!(alice.is_finalized() && bob.is_finalized());
+        let e = Sha256::new()
+            .chain(sig.get_public_nonce().as_bytes())
+            .chain(alice.joint_public_key().as_bytes())
+            .chain(Sha256::digest(b"Maskerade"))
+            .finalize();
+        assert!(sig.verify_challenge(alice.joint_public_key(), &e));
+    }
+
+    #[test]
+    fn messages_serialize() {
+        let (mut alice, commit_a, mut bob, commit_b) = sessions(b"Maskerade");
+        let commit_b: NonceCommitmentMessage =
+            serde_json::from_str(&serde_json::to_string(&commit_b).unwrap()).unwrap();
+        let nonce_a = alice.receive_nonce_commitment(&commit_b).unwrap();
+        let nonce_b = bob.receive_nonce_commitment(&commit_a).unwrap();
+        let nonce_b: NonceRevealMessage = serde_json::from_str(&serde_json::to_string(&nonce_b).unwrap()).unwrap();
+        let partial_a = alice.receive_nonce(&nonce_b).unwrap();
+        let partial_b = bob.receive_nonce(&nonce_a).unwrap();
+        let partial_b: PartialSignatureMessage =
+            serde_json::from_str(&serde_json::to_string(&partial_b).unwrap()).unwrap();
+        assert!(alice.receive_partial_signature(&partial_b).is_ok());
+        assert!(bob.receive_partial_signature(&partial_a).is_ok());
+    }
+
+    #[test]
+    fn mismatched_nonce_fails_session() {
+        let mut rng = rand::thread_rng();
+        let (mut alice, _, mut bob, commit_b) = sessions(b"Maskerade");
+        alice.receive_nonce_commitment(&commit_b).unwrap();
+        let reveal = NonceRevealMessage {
+            public_nonce: RistrettoPublicKey::random_keypair(&mut rng).1,
+        };
+        assert_eq!(alice.receive_nonce(&reveal).unwrap_err(), MuSigError::MismatchedNonces);
+        assert!(alice.has_failed());
+        // A failed session refuses to continue
+        let (_, other_commit, _, _) = sessions(b"Maskerade");
+        let nonce_b = bob.receive_nonce_commitment(&other_commit).unwrap();
+        assert_eq!(
+            alice.receive_nonce(&nonce_b).unwrap_err(),
+            MuSigError::InvalidStateTransition
+        );
+    }
+
+    #[test]
+    fn invalid_partial_signature() {
+        let mut rng = rand::thread_rng();
+        let (mut alice, commit_a, mut bob, commit_b) = sessions(b"Maskerade");
+        let nonce_a = alice.receive_nonce_commitment(&commit_b).unwrap();
+        let nonce_b = bob.receive_nonce_commitment(&commit_a).unwrap();
+        alice.receive_nonce(&nonce_b).unwrap();
+        bob.receive_nonce(&nonce_a).unwrap();
+        let bad = PartialSignatureMessage {
+            partial_signature: RistrettoSecretKey::random(&mut rng),
+        };
+        let peer = 1 - alice.index;
+        assert_eq!(
+            alice.receive_partial_signature(&bad).unwrap_err(),
+            MuSigError::InvalidPartialSignature(peer)
+        );
+        assert!(alice.has_failed());
+    }
+
+    #[test]
+    fn out_of_order_messages() {
+        let (mut alice, commit_a, _, _) = sessions(b"Maskerade");
+        let reveal = NonceRevealMessage {
+            public_nonce: alice.public_nonce.clone(),
+        };
+        assert_eq!(
+            alice.receive_nonce(&reveal).unwrap_err(),
+            MuSigError::InvalidStateTransition
+        );
+        assert_eq!(
+            alice.receive_nonce_commitment(&commit_a).unwrap_err(),
+            MuSigError::InvalidStateTransition
+        );
+    }
+}
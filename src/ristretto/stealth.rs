@@ -0,0 +1,320 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Dual-key stealth addresses.
+//!
+//! A receiver publishes a [StealthAddress] holding two public keys: a scan key, _A = a.G_, and a spend key,
+//! _B = b.G_. To pay them, a sender picks a random _r_, publishes _R = r.G_, and sends to the one-time key
+//!
+//! _P = H(r.A || i).G + B_
+//!
+//! where _i_ is the index of the output, so that one _R_ can serve several outputs. Nobody but the receiver can link
+//! _P_ to the address. The receiver finds the shared secret from the other side of the Diffie-Hellman exchange,
+//! _a.R = r.A_, which is all that is needed to recognise their outputs, so the scan secret can be given to a
+//! watch-only service. Only the holder of the spend secret can calculate the one-time secret key, _H(a.R || i) + b_.
+//!
+//! ## View keys
+//! The full key material is a [StealthKeys]; a [ViewKey] holds the scan secret and the public spend key, so a
+//! watch-only wallet can find outputs and nothing else. Wallets that only want to back up one secret can use
+//! [StealthKeys::from_spend_key], which derives the scan secret from the spend secret with [derive_view_key].
+//!
+//! ## Keys held elsewhere
+//! The Diffie-Hellman exchanges go through [Signer::shared_secret], so the ephemeral secret of a sender
+//! ([StealthAddress::derive_output_with_signer]) and the scan secret of a receiver ([output_belongs_to_signer]) can
+//! live on a hardware wallet or in an HSM.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{RistrettoKeypair, RistrettoPublicKey, RistrettoSecretKey},
+    signatures::Signer,
+};
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tari_utilities::ByteArray;
+
+const ONE_TIME_KEY_DOMAIN: &[u8] = b"com.tari.stealth.one_time_key";
+const VIEW_KEY_DOMAIN: &[u8] = b"com.tari.stealth.view_key";
+
+/// The standard scan (view) secret for a spend secret. Knowing the view key reveals nothing about the spend key.
+pub fn derive_view_key(spend_secret: &RistrettoSecretKey) -> RistrettoSecretKey {
+    RistrettoSecretKey::from_domain_hash(VIEW_KEY_DOMAIN, spend_secret.as_bytes())
+}
+
+/// The public half of a receiver's stealth keys, which senders pay to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StealthAddress {
+    scan_key: RistrettoPublicKey,
+    spend_key: RistrettoPublicKey,
+}
+
+impl StealthAddress {
+    pub fn new(scan_key: RistrettoPublicKey, spend_key: RistrettoPublicKey) -> Self {
+        StealthAddress { scan_key, spend_key }
+    }
+
+    pub fn scan_key(&self) -> &RistrettoPublicKey {
+        &self.scan_key
+    }
+
+    pub fn spend_key(&self) -> &RistrettoPublicKey {
+        &self.spend_key
+    }
+
+    /// Create a one-time output key for this address with a fresh ephemeral key
+    pub fn derive_output<R: Rng + CryptoRng>(&self, index: u64, rng: &mut R) -> StealthOutput {
+        self.derive_output_with_nonce(&RistrettoSecretKey::random(rng), index)
+    }
+
+    /// Create the one-time key for output `index` using the ephemeral secret `r`. The same `r` can be used for
+    /// several outputs in one transaction, as long as their indices differ.
+    pub fn derive_output_with_nonce(&self, r: &RistrettoSecretKey, index: u64) -> StealthOutput {
+        self.derive_output_with_signer(r, index)
+            .expect("An in-memory secret key can always do Diffie-Hellman")
+    }
+
+    /// As [derive_output_with_nonce](StealthAddress::derive_output_with_nonce), with the ephemeral secret held by
+    /// `ephemeral`
+    pub fn derive_output_with_signer<S: Signer<PK = RistrettoPublicKey>>(
+        &self,
+        ephemeral: &S,
+        index: u64,
+    ) -> Result<StealthOutput, S::Error> {
+        let shared_secret = ephemeral.shared_secret(&self.scan_key)?;
+        let one_time_key =
+            &RistrettoPublicKey::from_secret_key(&one_time_offset(&shared_secret, index)) + &self.spend_key;
+        Ok(StealthOutput {
+            ephemeral_key: ephemeral.public_key()?,
+            one_time_key,
+            index,
+        })
+    }
+}
+
+/// What a sender publishes: the ephemeral key, _R_, the one-time key, _P_, and the output index
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StealthOutput {
+    pub ephemeral_key: RistrettoPublicKey,
+    pub one_time_key: RistrettoPublicKey,
+    pub index: u64,
+}
+
+/// A receiver's scan and spend keypairs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StealthKeys {
+    scan: RistrettoKeypair,
+    spend: RistrettoKeypair,
+}
+
+impl StealthKeys {
+    pub fn new(scan: RistrettoKeypair, spend: RistrettoKeypair) -> Self {
+        StealthKeys { scan, spend }
+    }
+
+    /// Generate independent random scan and spend keys
+    pub fn generate<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        StealthKeys::new(RistrettoKeypair::generate(rng), RistrettoKeypair::generate(rng))
+    }
+
+    /// Build the keys from a spend secret alone, deriving the scan secret with [derive_view_key]
+    pub fn from_spend_key(spend_secret: RistrettoSecretKey) -> Self {
+        let scan = RistrettoKeypair::from_secret_key(derive_view_key(&spend_secret));
+        StealthKeys::new(scan, RistrettoKeypair::from_secret_key(spend_secret))
+    }
+
+    pub fn scan(&self) -> &RistrettoKeypair {
+        &self.scan
+    }
+
+    pub fn spend(&self) -> &RistrettoKeypair {
+        &self.spend
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        StealthAddress::new(self.scan.public().clone(), self.spend.public().clone())
+    }
+
+    /// The view-only part of these keys, for a watch-only wallet
+    pub fn view_key(&self) -> ViewKey {
+        ViewKey::new(self.scan.secret().clone(), self.spend.public().clone())
+    }
+
+    /// Whether `output` was sent to this receiver. Only the scan secret is used.
+    pub fn owns(&self, output: &StealthOutput) -> bool {
+        output_belongs_to(self.scan.secret(), self.spend.public(), output)
+    }
+
+    /// The secret key for `output`'s one-time key, if the output was sent to this receiver
+    pub fn one_time_secret_key(&self, output: &StealthOutput) -> Option<RistrettoSecretKey> {
+        let shared_secret = self.scan.shared_secret(&output.ephemeral_key).ok()?;
+        let secret = &one_time_offset(&shared_secret, output.index) + self.spend.secret();
+        if RistrettoPublicKey::from_secret_key(&secret) == output.one_time_key {
+            Some(secret)
+        } else {
+            None
+        }
+    }
+}
+
+/// View-only key material: the scan secret and the public spend key. It can recognise a receiver's outputs, but
+/// can't spend them. `Debug` doesn't show the scan secret.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewKey {
+    scan_secret: RistrettoSecretKey,
+    spend_key: RistrettoPublicKey,
+}
+
+impl ViewKey {
+    pub fn new(scan_secret: RistrettoSecretKey, spend_key: RistrettoPublicKey) -> Self {
+        ViewKey { scan_secret, spend_key }
+    }
+
+    pub fn scan_secret(&self) -> &RistrettoSecretKey {
+        &self.scan_secret
+    }
+
+    pub fn spend_key(&self) -> &RistrettoPublicKey {
+        &self.spend_key
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        StealthAddress::new(
+            RistrettoPublicKey::from_secret_key(&self.scan_secret),
+            self.spend_key.clone(),
+        )
+    }
+
+    /// Whether `output` was sent to this address
+    pub fn owns(&self, output: &StealthOutput) -> bool {
+        output_belongs_to(&self.scan_secret, &self.spend_key, output)
+    }
+}
+
+impl fmt::Debug for ViewKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ViewKey").field("spend_key", &self.spend_key).finish()
+    }
+}
+
+/// Check whether `output` pays the address with the given spend key, using only the scan secret
+pub fn output_belongs_to(
+    scan_secret: &RistrettoSecretKey,
+    spend_key: &RistrettoPublicKey,
+    output: &StealthOutput,
+) -> bool {
+    output_belongs_to_signer(scan_secret, spend_key, output)
+        .expect("An in-memory secret key can always do Diffie-Hellman")
+}
+
+/// As [output_belongs_to], with the scan secret held by `scanner`
+pub fn output_belongs_to_signer<S: Signer<PK = RistrettoPublicKey>>(
+    scanner: &S,
+    spend_key: &RistrettoPublicKey,
+    output: &StealthOutput,
+) -> Result<bool, S::Error> {
+    let shared_secret = scanner.shared_secret(&output.ephemeral_key)?;
+    let expected = &RistrettoPublicKey::from_secret_key(&one_time_offset(&shared_secret, output.index)) + spend_key;
+    Ok(expected == output.one_time_key)
+}
+
+fn one_time_offset(shared_secret: &RistrettoPublicKey, index: u64) -> RistrettoSecretKey {
+    let mut data = Vec::with_capacity(40);
+    data.extend_from_slice(shared_secret.as_bytes());
+    data.extend_from_slice(&index.to_le_bytes());
+    RistrettoSecretKey::from_domain_hash(ONE_TIME_KEY_DOMAIN, &data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn night_watch() {
+        let mut rng = rand::thread_rng();
+        let keys = StealthKeys::generate(&mut rng);
+        let address = keys.address();
+        let r = RistrettoSecretKey::random(&mut rng);
+        let outputs: Vec<StealthOutput> = (0..3).map(|i| address.derive_output_with_nonce(&r, i)).collect();
+        // Outputs sharing an ephemeral key still get unrelated one-time keys
+        assert_eq!(outputs[0].ephemeral_key, outputs[1].ephemeral_key);
+        assert_ne!(outputs[0].one_time_key, outputs[1].one_time_key);
+        for output in &outputs {
+            assert!(keys.owns(output));
+            assert!(output_belongs_to(keys.scan().secret(), address.spend_key(), output));
+            let secret = keys.one_time_secret_key(output).unwrap();
+            assert_eq!(RistrettoPublicKey::from_secret_key(&secret), output.one_time_key);
+        }
+        // The wrong index doesn't match
+        let mut moved = outputs[0].clone();
+        moved.index = 7;
+        assert!(!keys.owns(&moved));
+        assert!(keys.one_time_secret_key(&moved).is_none());
+    }
+
+    #[test]
+    fn other_receivers() {
+        let mut rng = rand::thread_rng();
+        let alice = StealthKeys::generate(&mut rng);
+        let bob = StealthKeys::generate(&mut rng);
+        let output = alice.address().derive_output(0, &mut rng);
+        assert!(alice.owns(&output));
+        assert!(!bob.owns(&output));
+        assert!(bob.one_time_secret_key(&output).is_none());
+        assert_ne!(&output.one_time_key, alice.address().spend_key());
+        let restored: StealthOutput = serde_json::from_str(&serde_json::to_string(&output).unwrap()).unwrap();
+        assert_eq!(restored, output);
+    }
+
+    #[test]
+    fn watch_only_wallets() {
+        let mut rng = rand::thread_rng();
+        let spend_secret = RistrettoSecretKey::random(&mut rng);
+        let keys = StealthKeys::from_spend_key(spend_secret.clone());
+        assert_eq!(keys.spend().secret(), &spend_secret);
+        assert_eq!(keys.scan().secret(), &derive_view_key(&spend_secret));
+        assert_ne!(keys.scan().secret(), &spend_secret);
+        // Restoring from the spend key gives the same address
+        assert_eq!(StealthKeys::from_spend_key(spend_secret).address(), keys.address());
+        let view_key = keys.view_key();
+        assert_eq!(view_key.address(), keys.address());
+        let output = keys.address().derive_output(3, &mut rng);
+        assert!(view_key.owns(&output));
+        let other = StealthKeys::generate(&mut rng).address().derive_output(3, &mut rng);
+        assert!(!view_key.owns(&other));
+        let restored: ViewKey = serde_json::from_str(&serde_json::to_string(&view_key).unwrap()).unwrap();
+        assert_eq!(restored, view_key);
+        assert!(!format!("{:?}", view_key).contains("scan_secret"));
+    }
+
+    #[test]
+    fn external_signers() {
+        let mut rng = rand::thread_rng();
+        let keys = StealthKeys::generate(&mut rng);
+        let address = keys.address();
+        let ephemeral = RistrettoKeypair::generate(&mut rng);
+        let output = address.derive_output_with_signer(&ephemeral, 2).unwrap();
+        assert_eq!(output, address.derive_output_with_nonce(ephemeral.secret(), 2));
+        assert!(output_belongs_to_signer(keys.scan(), address.spend_key(), &output).unwrap());
+        let other = StealthKeys::generate(&mut rng);
+        assert!(!output_belongs_to_signer(other.scan(), address.spend_key(), &output).unwrap());
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2020. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Switch commitments, as described in [Switch Commitments: A Safety Switch for Confidential
+//! Transactions](https://eprint.iacr.org/2017/237).
+//!
+//! A Pedersen commitment is only computationally binding; an attacker who can solve discrete logs (e.g. with a quantum
+//! computer) could open it to any value. A switch commitment is an ordinary Pedersen commitment whose blinding factor
+//! is tweaked by a hash of an ElGamal commitment to the same value:
+//!
+//! _C = (x + H(E || S)).G + v.H_, where the ElGamal commitment is _(E, S) = (x.G + v.H, x.J)_ and _H_ hashes to a
+//! scalar under its own domain label.
+//!
+//! On chain, _C_ looks like any other commitment. The ElGamal commitment is perfectly binding, so if it ever becomes
+//! necessary, owners can "switch" by revealing _(E, S)_ along with a [SwitchProof] that the pair is well formed and
+//! that _C_ was derived from it. Validators then rely on the ElGamal commitment instead of _C_.
+
+use crate::{
+    commitment::HomomorphicCommitmentFactory,
+    keys::SecretKey,
+    ristretto::{
+        constants::RISTRETTO_NUMS_POINTS,
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        transcript::Transcript,
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+    },
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+
+const SWITCH_HASH_LABEL: &[u8] = b"com.tari.switch_commitment.switch_hash";
+const SWITCH_PROOF_LABEL: &[u8] = b"com.tari.switch_commitment.proof";
+
+/// The terms of a switch commitment. To get the commitment itself, use [SwitchCommitmentFactory::switch_to_pedersen].
+pub struct SwitchCommitment {
+    blinding_factor: RistrettoSecretKey,
+    value: u64,
+    elgamal_commitment: PedersenCommitment,
+    switch_point: RistrettoPublicKey,
+    adj_blinding_factor: RistrettoSecretKey,
+}
+
+impl SwitchCommitment {
+    /// The blinding factor, _x_, of the ElGamal commitment
+    pub fn blinding_factor(&self) -> &RistrettoSecretKey {
+        &self.blinding_factor
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The commitment part of the ElGamal commitment, _E = x.G + v.H_
+    pub fn elgamal_commitment(&self) -> &PedersenCommitment {
+        &self.elgamal_commitment
+    }
+
+    /// The switch point of the ElGamal commitment, _S = x.J_
+    pub fn switch_point(&self) -> &RistrettoPublicKey {
+        &self.switch_point
+    }
+
+    /// The blinding factor of the switch commitment, _x + H(E || S)_
+    pub fn adj_blinding_factor(&self) -> &RistrettoSecretKey {
+        &self.adj_blinding_factor
+    }
+}
+
+/// A proof that an ElGamal commitment _(E, S)_ is well formed, i.e. that the same _x_ is used in _E = x.G + v.H_ and
+/// _S = x.J_, and that a switch commitment was derived from it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchProof {
+    elgamal_commitment: PedersenCommitment,
+    switch_point: RistrettoPublicKey,
+    nonce_commitment: PedersenCommitment,
+    nonce_switch_point: RistrettoPublicKey,
+    blinding_response: RistrettoSecretKey,
+    value_response: RistrettoSecretKey,
+}
+
+impl SwitchProof {
+    pub fn elgamal_commitment(&self) -> &PedersenCommitment {
+        &self.elgamal_commitment
+    }
+
+    pub fn switch_point(&self) -> &RistrettoPublicKey {
+        &self.switch_point
+    }
+}
+
+/// A factory for switch commitments. It uses the generators of a [PedersenCommitmentFactory], and an independent
+/// switch generator, _J_.
+#[allow(non_snake_case)]
+pub struct SwitchCommitmentFactory {
+    factory: PedersenCommitmentFactory,
+    J: RistrettoPublicKey,
+}
+
+/// Uses the default Pedersen generators, and the second NUMS point for _J_
+impl Default for SwitchCommitmentFactory {
+    fn default() -> Self {
+        SwitchCommitmentFactory::new(
+            PedersenCommitmentFactory::default(),
+            RistrettoPublicKey::new_from_pk(RISTRETTO_NUMS_POINTS[1]),
+        )
+    }
+}
+
+impl SwitchCommitmentFactory {
+    /// Create a factory with the given generators. The discrete log of _J_ with respect to _G_ and _H_ must be
+    /// unknown.
+    #[allow(non_snake_case)]
+    pub fn new(factory: PedersenCommitmentFactory, J: RistrettoPublicKey) -> Self {
+        SwitchCommitmentFactory { factory, J }
+    }
+
+    /// Create a switch commitment to `value` with the blinding factor `key`
+    pub fn commit_switch<D: Digest + Clone>(&self, key: &RistrettoSecretKey, value: u64) -> SwitchCommitment {
+        let elgamal_commitment = self.factory.commit_value(key, value);
+        let switch_point = key * &self.J;
+        let h = switch_hash::<D>(&elgamal_commitment, &switch_point);
+        SwitchCommitment {
+            blinding_factor: key.clone(),
+            value,
+            elgamal_commitment,
+            switch_point,
+            adj_blinding_factor: key + &h,
+        }
+    }
+
+    /// Return the switch commitment, _C = (x + H(E || S)).G + v.H_
+    pub fn switch_to_pedersen(&self, sc: &SwitchCommitment) -> PedersenCommitment {
+        self.factory.commit_value(&sc.adj_blinding_factor, sc.value)
+    }
+
+    /// Test whether the given blinding factor and value open the given switch commitment
+    pub fn open_switch<D: Digest + Clone>(
+        &self,
+        k: &RistrettoSecretKey,
+        v: u64,
+        commitment: &PedersenCommitment,
+    ) -> bool {
+        let sc = self.commit_switch::<D>(k, v);
+        let v = RistrettoSecretKey::from(sc.value);
+        self.factory.open_ct(&sc.adj_blinding_factor, &v, commitment).into()
+    }
+
+    /// Prove that the switch commitment was correctly derived from its ElGamal commitment
+    pub fn prove_switch<D: Digest + Clone, R: Rng + CryptoRng>(
+        &self,
+        sc: &SwitchCommitment,
+        rng: &mut R,
+    ) -> SwitchProof {
+        let a = RistrettoSecretKey::random(rng);
+        let b = RistrettoSecretKey::random(rng);
+        let nonce_commitment = self.factory.commit(&a, &b);
+        let nonce_switch_point = &a * &self.J;
+        let commitment = self.switch_to_pedersen(sc);
+        let e = self.proof_challenge::<D>(
+            &commitment,
+            &sc.elgamal_commitment,
+            &sc.switch_point,
+            &nonce_commitment,
+            &nonce_switch_point,
+        );
+        let v = RistrettoSecretKey::from(sc.value);
+        SwitchProof {
+            elgamal_commitment: sc.elgamal_commitment.clone(),
+            switch_point: sc.switch_point.clone(),
+            nonce_commitment,
+            nonce_switch_point,
+            blinding_response: &a + &(&e * &sc.blinding_factor),
+            value_response: &b + &(&e * &v),
+        }
+    }
+
+    /// Verify that `proof` shows that `commitment` was derived from the (well formed) ElGamal commitment in the proof
+    pub fn verify_switch<D: Digest + Clone>(&self, commitment: &PedersenCommitment, proof: &SwitchProof) -> bool {
+        // C = E + H(E || S).G
+        let h = switch_hash::<D>(&proof.elgamal_commitment, &proof.switch_point);
+        let derived = &proof.elgamal_commitment + &self.factory.commit_value(&h, 0);
+        if &derived != commitment {
+            return false;
+        }
+        let e = self.proof_challenge::<D>(
+            commitment,
+            &proof.elgamal_commitment,
+            &proof.switch_point,
+            &proof.nonce_commitment,
+            &proof.nonce_switch_point,
+        );
+        // z_x.G + z_v.H = A + e.E and z_x.J = B + e.S
+        let lhs = self.factory.commit(&proof.blinding_response, &proof.value_response);
+        let rhs = &proof.nonce_commitment + &(&proof.elgamal_commitment * &e);
+        let switch_lhs = &proof.blinding_response * &self.J;
+        let switch_rhs = &proof.nonce_switch_point + &(&e * &proof.switch_point);
+        lhs == rhs && switch_lhs == switch_rhs
+    }
+
+    fn proof_challenge<D: Digest + Clone>(
+        &self,
+        commitment: &PedersenCommitment,
+        elgamal_commitment: &PedersenCommitment,
+        switch_point: &RistrettoPublicKey,
+        nonce_commitment: &PedersenCommitment,
+        nonce_switch_point: &RistrettoPublicKey,
+    ) -> RistrettoSecretKey {
+        let mut transcript = Transcript::<D>::new(SWITCH_PROOF_LABEL);
+        transcript.append_point(b"J", &self.J);
+        transcript.append_message(b"C", commitment.as_bytes());
+        transcript.append_message(b"E", elgamal_commitment.as_bytes());
+        transcript.append_point(b"S", switch_point);
+        transcript.append_message(b"A", nonce_commitment.as_bytes());
+        transcript.append_point(b"B", nonce_switch_point);
+        transcript.challenge_scalar(b"e")
+    }
+}
+
+/// H(E || S), the tweak applied to the blinding factor. The hash is domain separated with `SWITCH_HASH_LABEL`, which
+/// the transcript length-prefixes, so it can't collide with a challenge or any other hash of the same points.
+fn switch_hash<D: Digest + Clone>(
+    elgamal_commitment: &PedersenCommitment,
+    switch_point: &RistrettoPublicKey,
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(SWITCH_HASH_LABEL);
+    transcript.append_message(b"E", elgamal_commitment.as_bytes());
+    transcript.append_point(b"S", switch_point);
+    transcript.challenge_scalar(b"h")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::Blake256, keys::PublicKey};
+    use blake2::Blake2b;
+
+    #[test]
+    fn switch_commitment() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let scf = SwitchCommitmentFactory::default();
+        let sc = scf.commit_switch::<Blake256>(&k, 1_000);
+        let c = scf.switch_to_pedersen(&sc);
+        assert!(scf.open_switch::<Blake256>(&k, 1_000, &c));
+        assert!(!scf.open_switch::<Blake256>(&k, 1_001, &c));
+        // The switch commitment is an ordinary Pedersen commitment with the adjusted blinding factor
+        let factory = PedersenCommitmentFactory::default();
+        assert!(factory.open_value(sc.adj_blinding_factor(), 1_000, &c));
+        assert_ne!(&c, sc.elgamal_commitment());
+    }
+
+    #[test]
+    fn switch_proof() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let scf = SwitchCommitmentFactory::default();
+        let sc = scf.commit_switch::<Blake256>(&k, 42);
+        let c = scf.switch_to_pedersen(&sc);
+        let proof = scf.prove_switch::<Blake256, _>(&sc, &mut rng);
+        assert!(scf.verify_switch::<Blake256>(&c, &proof));
+        // The proof is for this commitment only
+        let other = scf.commit_switch::<Blake256>(&k, 43);
+        assert!(!scf.verify_switch::<Blake256>(&scf.switch_to_pedersen(&other), &proof));
+        // A malformed switch point is rejected
+        let mut bad = proof.clone();
+        bad.switch_point = RistrettoPublicKey::from_secret_key(&k);
+        assert!(!scf.verify_switch::<Blake256>(&c, &bad));
+    }
+
+    #[test]
+    fn labelled_switch_hash() {
+        let mut rng = rand::thread_rng();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let scf = SwitchCommitmentFactory::default();
+        let sc = scf.commit_switch::<Blake256>(&k, 1);
+        // The tweak is not the bare hash of the ElGamal commitment
+        let bare = Blake256::new()
+            .chain(sc.elgamal_commitment().as_bytes())
+            .chain(sc.switch_point().as_bytes())
+            .finalize();
+        let tweak = sc.adj_blinding_factor() - sc.blinding_factor();
+        assert_ne!(tweak, RistrettoSecretKey::from_bytes(&bare).unwrap());
+        // Any digest can be used
+        let wide = scf.commit_switch::<Blake2b>(&k, 1);
+        assert!(scf.open_switch::<Blake2b>(&k, 1, &scf.switch_to_pedersen(&wide)));
+        assert!(!scf.open_switch::<Blake256>(&k, 1, &scf.switch_to_pedersen(&wide)));
+    }
+}
@@ -21,12 +21,44 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use crate::{
+    common::Blake256,
     keys::{PublicKey, SecretKey},
     ristretto::{RistrettoPublicKey, RistrettoSecretKey},
 };
+use digest::Digest;
+use rand::rngs::ThreadRng;
+use tari_utilities::ByteArray;
+
 pub fn get_keypair() -> (RistrettoSecretKey, RistrettoPublicKey) {
     let mut rng = rand::thread_rng();
     let k = RistrettoSecretKey::random(&mut rng);
     let pk = RistrettoPublicKey::from_secret_key(&k);
     (k, pk)
 }
+
+/// Create `n` random signatures, each over a different challenge, along with the public values and challenges needed
+/// to verify them. `keygen` makes a secret and the public value it is verified against, and `sign` signs the
+/// challenge with the secret.
+pub fn signature_batch<T, P, S, G, F>(n: usize, mut keygen: G, mut sign: F) -> (Vec<S>, Vec<P>, Vec<RistrettoSecretKey>)
+where
+    P: ByteArray,
+    G: FnMut(&mut ThreadRng) -> (T, P),
+    F: FnMut(T, &RistrettoSecretKey, &mut ThreadRng) -> S,
+{
+    let mut rng = rand::thread_rng();
+    let mut signatures = Vec::with_capacity(n);
+    let mut publics = Vec::with_capacity(n);
+    let mut challenges = Vec::with_capacity(n);
+    for i in 0..n {
+        let (secret, public) = keygen(&mut rng);
+        let e = Blake256::new()
+            .chain(public.as_bytes())
+            .chain(&i.to_le_bytes())
+            .finalize();
+        let e = RistrettoSecretKey::from_bytes(&e).unwrap();
+        signatures.push(sign(secret, &e, &mut rng));
+        publics.push(public);
+        challenges.push(e);
+    }
+    (signatures, publics, challenges)
+}
@@ -0,0 +1,359 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Threshold commitment signatures.
+//!
+//! A commitment _C = a.H + x.G_ whose blinding factor, _x_, is Shamir-shared among _n_ parties with threshold _m_
+//! can be signed by any _m_ of them, producing an ordinary [RistrettoComSig] that verifies against _C_ with
+//! [verify_message](crate::signatures::CommitmentSignature::verify_message).
+//!
+//! Party _i_ holds the share _x_i_, and the value, _a_, is known to every signer. The _share commitment_
+//! _C_i = a.H + x_i.G_ is public, and because the Lagrange coefficients over any signing set sum to one,
+//! _C = \sum \lambda_i C_i_.
+//!
+//! 1. Every signer picks a [ThresholdNonce] and shares its public nonce, _R_i = k_{a,i}.H + k_{x,i}.G_.
+//! 2. Given all the public nonces, every party builds the same [ThresholdSigningSession], which fixes the signing set,
+//!    the aggregate nonce _R = \sum R_i_ and the challenge _e = H(R || C || m)_.
+//! 3. Signer _i_ responds with the partial signature
+//!    _(R_i, u_i, v_i) = (R_i, k_{x,i} + e.\lambda_i.x_i, k_{a,i} + e.\lambda_i.a)_, which can be checked against its
+//!    share commitment: _v_i.H + u_i.G = R_i + e.\lambda_i.C_i_.
+//! 4. The partial signatures are summed into _(R, \sum u_i, \sum v_i)_.
+
+use crate::{
+    commitment::{HomomorphicCommitment, HomomorphicCommitmentFactory},
+    keys::SecretKey,
+    ristretto::{pedersen::PedersenCommitment, RistrettoComSig, RistrettoPublicKey, RistrettoSecretKey},
+};
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+/// The index of a share; the x-coordinate at which the sharing polynomial was evaluated. It must not be zero.
+pub type ShareIndex = u64;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ThresholdSignatureError {
+    #[error("Share indices must be non-zero")]
+    InvalidShareIndex,
+    #[error("Signer {0} appears more than once")]
+    DuplicateSigner(ShareIndex),
+    #[error("Signer {0} is not part of the signing set")]
+    UnknownSigner(ShareIndex),
+    #[error("At least {0} signers are required")]
+    NotEnoughSigners(usize),
+    #[error("The partial signature from signer {0} is invalid")]
+    InvalidPartialSignature(ShareIndex),
+    #[error("A partial signature is required from every signer")]
+    MissingPartialSignature,
+    #[error("The aggregate signature does not verify")]
+    InvalidAggregateSignature,
+    #[error("Threshold signatures require a hash function with a 32 byte digest")]
+    IncompatibleHashFunction,
+}
+
+/// A signer's secret nonces for one session. This type is deliberately not `Clone` and is consumed by
+/// [ThresholdSigningSession::sign]; reusing a nonce leaks the signer's share.
+pub struct ThresholdNonce {
+    k_x: RistrettoSecretKey,
+    k_a: RistrettoSecretKey,
+}
+
+impl ThresholdNonce {
+    pub fn random<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        ThresholdNonce {
+            k_x: RistrettoSecretKey::random(rng),
+            k_a: RistrettoSecretKey::random(rng),
+        }
+    }
+
+    /// The public nonce, _R_i = k_a.H + k_x.G_, to share with the other signers
+    pub fn public_nonce<C>(&self, factory: &C) -> PedersenCommitment
+    where C: HomomorphicCommitmentFactory<P = RistrettoPublicKey> {
+        factory.commit(&self.k_x, &self.k_a)
+    }
+}
+
+/// The Lagrange coefficient at zero for `index` over the set of share indices `signers`
+pub fn lagrange_coefficient(
+    index: ShareIndex,
+    signers: &[ShareIndex],
+) -> Result<RistrettoSecretKey, ThresholdSignatureError> {
+    if !signers.contains(&index) {
+        return Err(ThresholdSignatureError::UnknownSigner(index));
+    }
+    let x_i = Scalar::from(index);
+    let mut num = Scalar::one();
+    let mut den = Scalar::one();
+    for &j in signers.iter().filter(|&&j| j != index) {
+        let x_j = Scalar::from(j);
+        num *= x_j;
+        den *= x_j - x_i;
+    }
+    Ok(RistrettoSecretKey(num * den.invert()))
+}
+
+/// The public state of one threshold signing session, identical for every participant
+pub struct ThresholdSigningSession {
+    commitment: PedersenCommitment,
+    signers: Vec<ShareIndex>,
+    public_nonces: Vec<PedersenCommitment>,
+    aggregate_nonce: PedersenCommitment,
+    challenge: RistrettoSecretKey,
+}
+
+impl ThresholdSigningSession {
+    /// Start a session to sign `message` for `commitment` with the given signers and their public nonces. There must
+    /// be at least `threshold` distinct, non-zero signer indices.
+    pub fn new<D: Digest>(
+        commitment: &PedersenCommitment,
+        threshold: usize,
+        public_nonces: &[(ShareIndex, PedersenCommitment)],
+        message: &[u8],
+    ) -> Result<Self, ThresholdSignatureError> {
+        let mut nonces = public_nonces.to_vec();
+        nonces.sort_by_key(|(i, _)| *i);
+        for pair in nonces.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                return Err(ThresholdSignatureError::DuplicateSigner(pair[0].0));
+            }
+        }
+        if nonces.iter().any(|(i, _)| *i == 0) {
+            return Err(ThresholdSignatureError::InvalidShareIndex);
+        }
+        if nonces.len() < threshold {
+            return Err(ThresholdSignatureError::NotEnoughSigners(threshold));
+        }
+        let (signers, public_nonces): (Vec<_>, Vec<_>) = nonces.into_iter().unzip();
+        let mut aggregate_nonce = HomomorphicCommitment::from_public_key(&RistrettoPublicKey::default());
+        for r in &public_nonces {
+            aggregate_nonce = &aggregate_nonce + r;
+        }
+        let e = D::new()
+            .chain(aggregate_nonce.as_bytes())
+            .chain(commitment.as_bytes())
+            .chain(message)
+            .finalize();
+        let challenge =
+            RistrettoSecretKey::from_bytes(&e).map_err(|_| ThresholdSignatureError::IncompatibleHashFunction)?;
+        Ok(ThresholdSigningSession {
+            commitment: commitment.clone(),
+            signers,
+            public_nonces,
+            aggregate_nonce,
+            challenge,
+        })
+    }
+
+    /// The sorted share indices of the signing set
+    pub fn signers(&self) -> &[ShareIndex] {
+        &self.signers
+    }
+
+    pub fn challenge(&self) -> &RistrettoSecretKey {
+        &self.challenge
+    }
+
+    pub fn aggregate_nonce(&self) -> &PedersenCommitment {
+        &self.aggregate_nonce
+    }
+
+    /// Calculate the partial signature of signer `index`, who holds blinding factor share `share` of a commitment to
+    /// `value`. The nonce is consumed, and must be the one whose public nonce was given to the session.
+    pub fn sign<C>(
+        &self,
+        index: ShareIndex,
+        share: &RistrettoSecretKey,
+        value: &RistrettoSecretKey,
+        nonce: ThresholdNonce,
+        factory: &C,
+    ) -> Result<RistrettoComSig, ThresholdSignatureError>
+    where
+        C: HomomorphicCommitmentFactory<P = RistrettoPublicKey>,
+    {
+        let position = self.position(index)?;
+        if nonce.public_nonce(factory) != self.public_nonces[position] {
+            return Err(ThresholdSignatureError::InvalidPartialSignature(index));
+        }
+        let e_lambda = &self.challenge * &lagrange_coefficient(index, &self.signers)?;
+        let u = &nonce.k_x + &(&e_lambda * share);
+        let v = &nonce.k_a + &(&e_lambda * value);
+        Ok(RistrettoComSig::new(self.public_nonces[position].clone(), u, v))
+    }
+
+    /// Check the partial signature of signer `index` against its share commitment, _C_i = a.H + x_i.G_
+    pub fn verify_partial_signature<C>(
+        &self,
+        index: ShareIndex,
+        partial: &RistrettoComSig,
+        share_commitment: &PedersenCommitment,
+        factory: &C,
+    ) -> bool
+    where
+        C: HomomorphicCommitmentFactory<P = RistrettoPublicKey>,
+    {
+        let position = match self.position(index) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        if partial.public_nonce() != &self.public_nonces[position] {
+            return false;
+        }
+        let lambda = match lagrange_coefficient(index, &self.signers) {
+            Ok(l) => l,
+            Err(_) => return false,
+        };
+        partial.verify(share_commitment, &(&self.challenge * &lambda), factory)
+    }
+
+    /// Sum the partial signatures of every signer into the final signature, and check it against the commitment
+    pub fn aggregate<C>(
+        &self,
+        partials: &[(ShareIndex, RistrettoComSig)],
+        factory: &C,
+    ) -> Result<RistrettoComSig, ThresholdSignatureError>
+    where
+        C: HomomorphicCommitmentFactory<P = RistrettoPublicKey>,
+    {
+        if partials.len() != self.signers.len() {
+            return Err(ThresholdSignatureError::MissingPartialSignature);
+        }
+        let mut u = RistrettoSecretKey::default();
+        let mut v = RistrettoSecretKey::default();
+        for signer in &self.signers {
+            let partial = partials
+                .iter()
+                .find(|(i, _)| i == signer)
+                .map(|(_, p)| p)
+                .ok_or(ThresholdSignatureError::MissingPartialSignature)?;
+            u = &u + partial.u();
+            v = &v + partial.v();
+        }
+        let signature = RistrettoComSig::new(self.aggregate_nonce.clone(), u, v);
+        if !signature.verify(&self.commitment, &self.challenge, factory) {
+            return Err(ThresholdSignatureError::InvalidAggregateSignature);
+        }
+        Ok(signature)
+    }
+
+    fn position(&self, index: ShareIndex) -> Result<usize, ThresholdSignatureError> {
+        self.signers
+            .binary_search(&index)
+            .map_err(|_| ThresholdSignatureError::UnknownSigner(index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::Blake256, ristretto::pedersen::PedersenCommitmentFactory};
+
+    /// Shamir-share `secret` with threshold `t` among parties 1..=n
+    fn share(secret: &RistrettoSecretKey, t: usize, n: u64) -> Vec<RistrettoSecretKey> {
+        let mut rng = rand::thread_rng();
+        let coefficients: Vec<_> = (1..t).map(|_| RistrettoSecretKey::random(&mut rng)).collect();
+        (1..=n)
+            .map(|i| {
+                let x = RistrettoSecretKey::from(i);
+                // Horner's rule
+                let mut y = RistrettoSecretKey::default();
+                for c in coefficients.iter().rev() {
+                    y = &(&y + c) * &x;
+                }
+                &y + secret
+            })
+            .collect()
+    }
+
+    fn sign(signers: &[ShareIndex], tamper: bool) -> Result<RistrettoComSig, ThresholdSignatureError> {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let x = RistrettoSecretKey::random(&mut rng);
+        let a = RistrettoSecretKey::from(1_000);
+        let commitment = factory.commit(&x, &a);
+        let shares = share(&x, 3, 5);
+        let nonces: Vec<_> = signers.iter().map(|_| ThresholdNonce::random(&mut rng)).collect();
+        let public_nonces: Vec<_> = signers
+            .iter()
+            .zip(&nonces)
+            .map(|(&i, n)| (i, n.public_nonce(&factory)))
+            .collect();
+        let session = ThresholdSigningSession::new::<Blake256>(&commitment, 3, &public_nonces, b"Night Watch")?;
+        let mut partials = Vec::new();
+        for (&i, nonce) in signers.iter().zip(nonces) {
+            let share = &shares[i as usize - 1];
+            let mut partial = session.sign(i, share, &a, nonce, &factory)?;
+            let share_commitment = factory.commit(share, &a);
+            assert!(session.verify_partial_signature(i, &partial, &share_commitment, &factory));
+            if tamper {
+                partial = RistrettoComSig::new(
+                    partial.public_nonce().clone(),
+                    partial.u() + &RistrettoSecretKey::from(1),
+                    partial.v().clone(),
+                );
+                assert!(!session.verify_partial_signature(i, &partial, &share_commitment, &factory));
+            }
+            partials.push((i, partial));
+        }
+        let sig = session.aggregate(&partials, &factory)?;
+        assert!(sig.verify_message::<Blake256, _>(&commitment, b"Night Watch", &factory));
+        Ok(sig)
+    }
+
+    #[test]
+    fn any_threshold_subset_can_sign() {
+        assert!(sign(&[1, 2, 3], false).is_ok());
+        assert!(sign(&[5, 2, 4], false).is_ok());
+        assert!(sign(&[1, 2, 3, 4, 5], false).is_ok());
+    }
+
+    #[test]
+    fn invalid_signing_sets() {
+        assert_eq!(
+            sign(&[1, 2], false).unwrap_err(),
+            ThresholdSignatureError::NotEnoughSigners(3)
+        );
+        assert_eq!(
+            sign(&[1, 2, 2], false).unwrap_err(),
+            ThresholdSignatureError::DuplicateSigner(2)
+        );
+        assert_eq!(
+            sign(&[1, 2, 3], true).unwrap_err(),
+            ThresholdSignatureError::InvalidAggregateSignature
+        );
+    }
+
+    #[test]
+    fn lagrange_coefficients_sum_to_one() {
+        let signers = [2, 3, 7];
+        let sum = signers
+            .iter()
+            .map(|&i| lagrange_coefficient(i, &signers).unwrap())
+            .fold(RistrettoSecretKey::default(), |acc, l| &acc + &l);
+        assert_eq!(sum, RistrettoSecretKey::from(1));
+        assert_eq!(
+            lagrange_coefficient(4, &signers).unwrap_err(),
+            ThresholdSignatureError::UnknownSigner(4)
+        );
+    }
+}
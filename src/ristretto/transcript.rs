@@ -0,0 +1,180 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A Fiat-Shamir transcript for interactive proofs made non-interactive.
+//!
+//! The prover and verifier each append the same sequence of labelled messages, points and scalars, then draw
+//! challenges from the transcript. Every challenge depends on the transcript's domain and on everything appended
+//! before it, so a proof can't be lifted into another protocol or reordered within one.
+//!
+//! Each operation is framed as _tag || len(label) || label || len(data) || data_ and absorbed into a running hash.
+//! A challenge request is absorbed like any other operation, then output is squeezed in counter mode from a copy of
+//! the hash. Repeating a request with the same label therefore gives a fresh challenge.
+//!
+//! ```edition2018
+//! # use tari_crypto::ristretto::transcript::Transcript;
+//! # use tari_crypto::ristretto::RistrettoPublicKey;
+//! # use tari_crypto::keys::PublicKey;
+//! # use sha2::Sha512;
+//! let mut rng = rand::thread_rng();
+//! let (_, p) = RistrettoPublicKey::random_keypair(&mut rng);
+//! let mut prover = Transcript::<Sha512>::new(b"com.example.proof");
+//! let mut verifier = prover.clone();
+//! prover.append_point(b"P", &p);
+//! verifier.append_point(b"P", &p);
+//! assert_eq!(prover.challenge_scalar(b"e"), verifier.challenge_scalar(b"e"));
+//! ```
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::RistrettoSecretKey,
+};
+use clear_on_drop::clear::Clear;
+use digest::Digest;
+use tari_utilities::ByteArray;
+
+/// Label that separates transcript hashes from every other hash in the crate
+const TRANSCRIPT_LABEL: &[u8] = b"com.tari.transcript";
+const APPEND_TAG: u8 = 0x00;
+const CHALLENGE_TAG: u8 = 0x01;
+
+#[derive(Clone, Debug)]
+pub struct Transcript<D> {
+    hasher: D,
+}
+
+impl<D: Digest + Clone> Transcript<D> {
+    /// Start a transcript for the protocol identified by `domain`
+    pub fn new(domain: &[u8]) -> Self {
+        let hasher = D::new()
+            .chain(TRANSCRIPT_LABEL)
+            .chain((domain.len() as u64).to_le_bytes())
+            .chain(domain);
+        Transcript { hasher }
+    }
+
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.absorb(APPEND_TAG, label, message);
+    }
+
+    pub fn append_u64(&mut self, label: &[u8], value: u64) {
+        self.append_message(label, &value.to_le_bytes());
+    }
+
+    pub fn append_point<P: PublicKey>(&mut self, label: &[u8], point: &P) {
+        self.append_message(label, point.as_bytes());
+    }
+
+    pub fn append_scalar<K: SecretKey>(&mut self, label: &[u8], scalar: &K) {
+        self.append_message(label, scalar.as_bytes());
+    }
+
+    /// Fill `out` with challenge bytes bound to everything appended so far
+    pub fn challenge_bytes(&mut self, label: &[u8], out: &mut [u8]) {
+        self.absorb(CHALLENGE_TAG, label, &(out.len() as u64).to_le_bytes());
+        let block_size = <D as Digest>::output_size();
+        for (i, chunk) in out.chunks_mut(block_size).enumerate() {
+            let mut block = self.hasher.clone().chain((i as u64).to_le_bytes()).finalize();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            block.as_mut_slice().clear();
+        }
+    }
+
+    /// A uniformly distributed challenge scalar, reduced from 64 challenge bytes
+    pub fn challenge_scalar(&mut self, label: &[u8]) -> RistrettoSecretKey {
+        let mut bytes = [0u8; 64];
+        self.challenge_bytes(label, &mut bytes);
+        let e = RistrettoSecretKey::from_uniform_bytes(&bytes).expect("64 bytes is the correct length");
+        bytes.clear();
+        e
+    }
+
+    fn absorb(&mut self, tag: u8, label: &[u8], data: &[u8]) {
+        self.hasher.update([tag]);
+        self.hasher.update((label.len() as u64).to_le_bytes());
+        self.hasher.update(label);
+        self.hasher.update((data.len() as u64).to_le_bytes());
+        self.hasher.update(data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hash::blake2::Blake256, ristretto::RistrettoPublicKey};
+    use sha2::Sha512;
+
+    fn transcript() -> Transcript<Sha512> {
+        let mut t = Transcript::new(b"com.tari.test.transcript");
+        t.append_message(b"message", b"Hex");
+        t
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let mut a = transcript();
+        let mut b = transcript();
+        for t in [&mut a, &mut b].iter_mut() {
+            t.append_point(b"P", &p);
+            t.append_scalar(b"k", &k);
+            t.append_u64(b"n", 42);
+        }
+        assert_eq!(a.challenge_scalar(b"e"), b.challenge_scalar(b"e"));
+        // Later challenges differ from earlier ones, even with the same label
+        let e1 = a.challenge_scalar(b"e");
+        let e2 = a.challenge_scalar(b"e");
+        assert_ne!(e1, e2);
+        assert_eq!(e1, b.challenge_scalar(b"e"));
+    }
+
+    #[test]
+    fn binding() {
+        let reference = transcript().challenge_scalar(b"e");
+        let mut other_domain = Transcript::<Sha512>::new(b"com.tari.test.other");
+        other_domain.append_message(b"message", b"Hex");
+        assert_ne!(other_domain.challenge_scalar(b"e"), reference);
+        // Moving bytes between label and message changes the challenge
+        let mut shifted = Transcript::<Sha512>::new(b"com.tari.test.transcript");
+        shifted.append_message(b"messageH", b"ex");
+        assert_ne!(shifted.challenge_scalar(b"e"), reference);
+        // As does the challenge label
+        assert_ne!(transcript().challenge_scalar(b"f"), reference);
+        // And appending anything at all
+        let mut extended = transcript();
+        extended.append_message(b"", b"");
+        assert_ne!(extended.challenge_scalar(b"e"), reference);
+    }
+
+    #[test]
+    fn long_challenges() {
+        let mut a = Transcript::<Blake256>::new(b"com.tari.test.transcript");
+        let mut long = [0u8; 100];
+        a.clone().challenge_bytes(b"c", &mut long);
+        let mut short = [0u8; 40];
+        a.challenge_bytes(b"c", &mut short);
+        // The requested length is bound into the output
+        assert_ne!(long[..40], short[..]);
+        assert_ne!(long[..32], long[32..64]);
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Verifiably encrypted Schnorr signatures, for optimistic fair exchange.
+//!
+//! The signer encrypts the `s` component of a [RistrettoSchnorr] signature to an arbiter's public key, _A_, and
+//! attaches a proof that the ciphertext decrypts to a valid signature on a given message. The recipient can check the
+//! proof without learning the signature; if the signer later refuses to hand over the signature itself, the arbiter
+//! can decrypt it.
+//!
+//! Scalars are encrypted with hashed ElGamal, _Enc(x; ρ) = (ρ.G, x + H(ρ.A))_, and the proof is a Fiat-Shamir
+//! cut-and-choose over 128 rounds. In each round the signer picks a random _r_ and encrypts both _r_ and _r - s_;
+//! the challenge opens exactly one of the pair, and the other is kept for the arbiter. Cheating in a round is caught
+//! with probability one half, so an invalid encryption passes with probability \\( 2^{-128} \\). The proof is about
+//! 16kB.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+const ROUNDS: usize = 128;
+const ENCRYPTION_KEY_LABEL: &[u8] = b"com.tari.verifiable_encryption.encryption_key";
+const CHALLENGE_LABEL: &[u8] = b"com.tari.verifiable_encryption.challenge";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum VerifiableEncryptionError {
+    #[error("The signature is not valid for the given public key and message")]
+    InvalidSignature,
+    #[error("The encryption proof is not valid")]
+    InvalidProof,
+    #[error("No ciphertext decrypted to a valid signature")]
+    DecryptionFailed,
+    #[error("Verifiable encryption requires a hash function with a 32 byte digest")]
+    IncompatibleHashFunction,
+}
+
+/// A hashed ElGamal encryption of a scalar, _(U, v) = (ρ.G, x + H(ρ.A))_
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct ScalarCiphertext {
+    ephemeral_key: RistrettoPublicKey,
+    value: RistrettoSecretKey,
+}
+
+impl ScalarCiphertext {
+    fn encrypt<D: Digest>(
+        x: &RistrettoSecretKey,
+        randomness: &RistrettoSecretKey,
+        arbiter: &RistrettoPublicKey,
+    ) -> Self {
+        let ephemeral_key = RistrettoPublicKey::from_secret_key(randomness);
+        let value = x + &encryption_key::<D>(&(randomness * arbiter));
+        ScalarCiphertext { ephemeral_key, value }
+    }
+
+    fn decrypt<D: Digest>(&self, arbiter_secret: &RistrettoSecretKey) -> RistrettoSecretKey {
+        &self.value - &encryption_key::<D>(&(arbiter_secret * &self.ephemeral_key))
+    }
+}
+
+/// One cut-and-choose round: the opening of the challenged ciphertext, and the other ciphertext as it was committed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Round {
+    opened_value: RistrettoSecretKey,
+    opened_randomness: RistrettoSecretKey,
+    unopened: ScalarCiphertext,
+}
+
+/// A [RistrettoSchnorr] signature on a message, encrypted to an arbiter, with a proof of its validity.
+///
+/// The challenge is the message challenge used by [RistrettoSchnorr::verify_message], _e = H(R || P || m)_.
+///
+/// ```edition2018
+/// # use tari_crypto::ristretto::{RistrettoSchnorr, RistrettoPublicKey};
+/// # use tari_crypto::ristretto::verifiable_encryption::VerifiablyEncryptedSignature;
+/// # use tari_crypto::keys::PublicKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let (a, arbiter) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, b"Making Money").unwrap();
+/// let ves =
+///     VerifiablyEncryptedSignature::encrypt::<Blake256, _>(&sig, &p, b"Making Money", &arbiter, &mut rng).unwrap();
+/// assert!(ves.verify::<Blake256>(&p, b"Making Money", &arbiter));
+/// assert_eq!(ves.decrypt::<Blake256>(&a, &p, b"Making Money").unwrap(), sig);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiablyEncryptedSignature {
+    public_nonce: RistrettoPublicKey,
+    challenge: Vec<u8>,
+    rounds: Vec<Round>,
+}
+
+impl VerifiablyEncryptedSignature {
+    /// Encrypt `signature`, made by `public_key` on `message`, to `arbiter`. The signature is checked first, so an
+    /// invalid signature can't be encrypted.
+    pub fn encrypt<D: Digest, R: Rng + CryptoRng>(
+        signature: &RistrettoSchnorr,
+        public_key: &RistrettoPublicKey,
+        message: &[u8],
+        arbiter: &RistrettoPublicKey,
+        rng: &mut R,
+    ) -> Result<Self, VerifiableEncryptionError> {
+        if D::output_size() != 32 {
+            return Err(VerifiableEncryptionError::IncompatibleHashFunction);
+        }
+        if !signature.verify_message::<D>(public_key, message) {
+            return Err(VerifiableEncryptionError::InvalidSignature);
+        }
+        let public_nonce = signature.get_public_nonce().clone();
+        let s = signature.get_signature();
+        let mut hasher = challenge_prefix::<D>(&public_nonce, public_key, message, arbiter);
+        let mut secrets = Vec::with_capacity(ROUNDS);
+        for _ in 0..ROUNDS {
+            let r = RistrettoSecretKey::random(rng);
+            let rho = RistrettoSecretKey::random(rng);
+            let sigma = RistrettoSecretKey::random(rng);
+            let r_minus_s = &r - s;
+            let w = RistrettoPublicKey::from_secret_key(&r);
+            let c0 = ScalarCiphertext::encrypt::<D>(&r, &rho, arbiter);
+            let c1 = ScalarCiphertext::encrypt::<D>(&r_minus_s, &sigma, arbiter);
+            hasher = round_commitment(hasher, &w, &c0, &c1);
+            secrets.push((r, rho, r_minus_s, sigma, c0, c1));
+        }
+        let challenge = hasher.finalize().to_vec();
+        let rounds = secrets
+            .into_iter()
+            .enumerate()
+            .map(|(i, (r, rho, r_minus_s, sigma, c0, c1))| {
+                if challenge_bit(&challenge, i) {
+                    Round {
+                        opened_value: r_minus_s,
+                        opened_randomness: sigma,
+                        unopened: c0,
+                    }
+                } else {
+                    Round {
+                        opened_value: r,
+                        opened_randomness: rho,
+                        unopened: c1,
+                    }
+                }
+            })
+            .collect();
+        Ok(VerifiablyEncryptedSignature {
+            public_nonce,
+            challenge,
+            rounds,
+        })
+    }
+
+    /// Check that this is an encryption, to `arbiter`, of a valid signature by `public_key` on `message`
+    pub fn verify<D: Digest>(
+        &self,
+        public_key: &RistrettoPublicKey,
+        message: &[u8],
+        arbiter: &RistrettoPublicKey,
+    ) -> bool {
+        if D::output_size() != 32 || self.challenge.len() != 32 || self.rounds.len() != ROUNDS {
+            return false;
+        }
+        let verifier = match self.signature_verifier::<D>(public_key, message) {
+            Some(v) => v,
+            None => return false,
+        };
+        let mut hasher = challenge_prefix::<D>(&self.public_nonce, public_key, message, arbiter);
+        for (i, round) in self.rounds.iter().enumerate() {
+            let opened = ScalarCiphertext::encrypt::<D>(&round.opened_value, &round.opened_randomness, arbiter);
+            let w = RistrettoPublicKey::from_secret_key(&round.opened_value);
+            hasher = if challenge_bit(&self.challenge, i) {
+                // Opened r - s, so W = (r - s).G + s.G
+                round_commitment(hasher, &(&w + &verifier), &round.unopened, &opened)
+            } else {
+                round_commitment(hasher, &w, &opened, &round.unopened)
+            };
+        }
+        hasher.finalize().as_slice() == self.challenge.as_slice()
+    }
+
+    /// Decrypt the signature with the arbiter's secret key. The proof is verified first.
+    pub fn decrypt<D: Digest>(
+        &self,
+        arbiter_secret: &RistrettoSecretKey,
+        public_key: &RistrettoPublicKey,
+        message: &[u8],
+    ) -> Result<RistrettoSchnorr, VerifiableEncryptionError> {
+        let arbiter = RistrettoPublicKey::from_secret_key(arbiter_secret);
+        if !self.verify::<D>(public_key, message, &arbiter) {
+            return Err(VerifiableEncryptionError::InvalidProof);
+        }
+        let verifier = self
+            .signature_verifier::<D>(public_key, message)
+            .ok_or(VerifiableEncryptionError::InvalidProof)?;
+        // A valid proof means at least one unopened ciphertext is honest, with overwhelming probability
+        for (i, round) in self.rounds.iter().enumerate() {
+            let x = round.unopened.decrypt::<D>(arbiter_secret);
+            let s = if challenge_bit(&self.challenge, i) {
+                &x - &round.opened_value
+            } else {
+                &round.opened_value - &x
+            };
+            if RistrettoPublicKey::from_secret_key(&s) == verifier {
+                return Ok(RistrettoSchnorr::new(self.public_nonce.clone(), s));
+            }
+        }
+        Err(VerifiableEncryptionError::DecryptionFailed)
+    }
+
+    /// The public nonce, _R_, of the encrypted signature
+    pub fn public_nonce(&self) -> &RistrettoPublicKey {
+        &self.public_nonce
+    }
+
+    /// _s.G = R + e.P_ for the encrypted signature
+    fn signature_verifier<D: Digest>(
+        &self,
+        public_key: &RistrettoPublicKey,
+        message: &[u8],
+    ) -> Option<RistrettoPublicKey> {
+        let challenge = RistrettoSchnorr::message_challenge::<D>(&self.public_nonce, public_key, message);
+        let e = RistrettoSecretKey::from_bytes(&challenge).ok()?;
+        Some(&self.public_nonce + &(&e * public_key))
+    }
+}
+
+fn encryption_key<D: Digest>(shared_secret: &RistrettoPublicKey) -> RistrettoSecretKey {
+    let hash = D::new()
+        .chain(ENCRYPTION_KEY_LABEL)
+        .chain(shared_secret.as_bytes())
+        .finalize();
+    RistrettoSecretKey::from_bytes(&hash).expect("Found a u256 that does not map to a valid Ristretto scalar")
+}
+
+fn challenge_prefix<D: Digest>(
+    public_nonce: &RistrettoPublicKey,
+    public_key: &RistrettoPublicKey,
+    message: &[u8],
+    arbiter: &RistrettoPublicKey,
+) -> D {
+    D::new()
+        .chain(CHALLENGE_LABEL)
+        .chain(public_nonce.as_bytes())
+        .chain(public_key.as_bytes())
+        .chain(arbiter.as_bytes())
+        .chain(message)
+}
+
+fn round_commitment<D: Digest>(hasher: D, w: &RistrettoPublicKey, c0: &ScalarCiphertext, c1: &ScalarCiphertext) -> D {
+    hasher
+        .chain(w.as_bytes())
+        .chain(c0.ephemeral_key.as_bytes())
+        .chain(c0.value.as_bytes())
+        .chain(c1.ephemeral_key.as_bytes())
+        .chain(c1.value.as_bytes())
+}
+
+fn challenge_bit(challenge: &[u8], i: usize) -> bool {
+    (challenge[i / 8] >> (i % 8)) & 1 == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+
+    fn encrypted_signature(
+        message: &[u8],
+    ) -> (
+        VerifiablyEncryptedSignature,
+        RistrettoSchnorr,
+        RistrettoPublicKey,
+        RistrettoSecretKey,
+        RistrettoPublicKey,
+    ) {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (a, arbiter) = RistrettoPublicKey::random_keypair(&mut rng);
+        let sig = RistrettoSchnorr::sign_deterministic::<Blake256>(k, message).unwrap();
+        let ves = VerifiablyEncryptedSignature::encrypt::<Blake256, _>(&sig, &p, message, &arbiter, &mut rng).unwrap();
+        (ves, sig, p, a, arbiter)
+    }
+
+    #[test]
+    fn encrypt_verify_and_decrypt() {
+        let (ves, sig, p, a, arbiter) = encrypted_signature(b"Small Gods");
+        assert!(ves.verify::<Blake256>(&p, b"Small Gods", &arbiter));
+        assert_eq!(ves.public_nonce(), sig.get_public_nonce());
+        let decrypted = ves.decrypt::<Blake256>(&a, &p, b"Small Gods").unwrap();
+        assert_eq!(decrypted, sig);
+        assert!(decrypted.verify_message::<Blake256>(&p, b"Small Gods"));
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        let mut rng = rand::thread_rng();
+        let (ves, _, p, a, arbiter) = encrypted_signature(b"Small Gods");
+        let (other, other_arbiter) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert!(!ves.verify::<Blake256>(&p, b"Pyramids", &arbiter));
+        assert!(!ves.verify::<Blake256>(&arbiter, b"Small Gods", &arbiter));
+        assert!(!ves.verify::<Blake256>(&p, b"Small Gods", &other_arbiter));
+        assert_eq!(
+            ves.decrypt::<Blake256>(&other, &p, b"Small Gods").unwrap_err(),
+            VerifiableEncryptionError::InvalidProof
+        );
+        // Tampering with any ciphertext breaks the proof
+        let mut tampered = ves.clone();
+        tampered.rounds[7].unopened.value = &tampered.rounds[7].unopened.value + &RistrettoSecretKey::from(1);
+        assert!(!tampered.verify::<Blake256>(&p, b"Small Gods", &arbiter));
+        let mut truncated = ves.clone();
+        truncated.rounds.pop();
+        assert!(!truncated.verify::<Blake256>(&p, b"Small Gods", &arbiter));
+        assert!(ves.decrypt::<Blake256>(&a, &p, b"Small Gods").is_ok());
+    }
+
+    #[test]
+    fn invalid_signatures_are_not_encrypted() {
+        let mut rng = rand::thread_rng();
+        let (_, sig, p, _, arbiter) = encrypted_signature(b"Small Gods");
+        assert_eq!(
+            VerifiablyEncryptedSignature::encrypt::<Blake256, _>(&sig, &p, b"Eric", &arbiter, &mut rng).unwrap_err(),
+            VerifiableEncryptionError::InvalidSignature
+        );
+        assert_eq!(
+            VerifiablyEncryptedSignature::encrypt::<sha2::Sha512, _>(&sig, &p, b"Small Gods", &arbiter, &mut rng)
+                .unwrap_err(),
+            VerifiableEncryptionError::IncompatibleHashFunction
+        );
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An elliptic curve verifiable random function (ECVRF) over Ristretto, following the structure of
+//! [RFC 9381](https://www.rfc-editor.org/rfc/rfc9381).
+//!
+//! The holder of a secret key, _x_, can evaluate a pseudo-random function on any input, _alpha_, and prove that the
+//! output is correct for the public key _P = x.G_. Nobody else can predict the output, and the key holder can't bias
+//! it: there is exactly one valid output per key and input. This makes the outputs suitable for leader election.
+//!
+//! With _H = H_p(P || alpha)_ hashed to the curve, the proof is _(Gamma, c, s)_ where _Gamma = x.H_,
+//! _c_ is drawn from a [Transcript] over _P, H, Gamma, k.G, k.H_ and _s = k + c.x_ for a deterministic nonce _k_. The
+//! output is _beta = H(Gamma)_.
+//!
+//! ```edition2018
+//! # use tari_crypto::ristretto::{vrf::{prove, verify}, RistrettoPublicKey};
+//! # use tari_crypto::keys::PublicKey;
+//! # use tari_crypto::common::Blake256;
+//! let mut rng = rand::thread_rng();
+//! let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+//! let (output, proof) = prove::<Blake256>(&k, b"round 12");
+//! assert_eq!(verify::<Blake256>(&p, b"round 12", &proof), Ok(output));
+//! ```
+
+use crate::{
+    keys::PublicKey,
+    ristretto::{transcript::Transcript, RistrettoPublicKey, RistrettoSecretKey},
+};
+use clear_on_drop::clear::Clear;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+const HASH_TO_POINT_LABEL: &[u8] = b"com.tari.vrf.hash_to_point";
+const NONCE_LABEL: &[u8] = b"com.tari.vrf.nonce";
+const CHALLENGE_LABEL: &[u8] = b"com.tari.vrf.challenge";
+const OUTPUT_LABEL: &[u8] = b"com.tari.vrf.output";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum VrfError {
+    #[error("The VRF proof is invalid")]
+    InvalidProof,
+}
+
+/// The pseudo-random output, _beta_, of the VRF. It is as long as the digest used to produce it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VrfOutput(Vec<u8>);
+
+impl VrfOutput {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A proof that a [VrfOutput] was correctly evaluated for a public key and input
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VrfProof {
+    gamma: RistrettoPublicKey,
+    challenge: RistrettoSecretKey,
+    response: RistrettoSecretKey,
+}
+
+impl VrfProof {
+    /// The VRF point, _Gamma = x.H_, from which the output is derived
+    pub fn gamma(&self) -> &RistrettoPublicKey {
+        &self.gamma
+    }
+
+    pub fn challenge(&self) -> &RistrettoSecretKey {
+        &self.challenge
+    }
+
+    pub fn response(&self) -> &RistrettoSecretKey {
+        &self.response
+    }
+}
+
+/// Evaluate the VRF on `alpha` with `secret`, returning the output and a proof of its correctness
+pub fn prove<D: Digest + Clone>(secret: &RistrettoSecretKey, alpha: &[u8]) -> (VrfOutput, VrfProof) {
+    let public_key = RistrettoPublicKey::from_secret_key(secret);
+    let h = hash_to_point(&public_key, alpha);
+    let gamma = RistrettoPublicKey::new_from_pk(secret.0 * h.point);
+    let nonce = deterministic_nonce(secret, &h);
+    let k_g = RistrettoPublicKey::from_secret_key(&nonce);
+    let k_h = RistrettoPublicKey::new_from_pk(nonce.0 * h.point);
+    let challenge = challenge::<D>(&public_key, &h, &gamma, &k_g, &k_h);
+    let response = &nonce + &(&challenge * secret);
+    let output = output::<D>(&gamma);
+    (output, VrfProof {
+        gamma,
+        challenge,
+        response,
+    })
+}
+
+/// Verify that `proof` is a valid VRF proof for `public_key` and `alpha`, and if so, return the VRF output
+pub fn verify<D: Digest + Clone>(
+    public_key: &RistrettoPublicKey,
+    alpha: &[u8],
+    proof: &VrfProof,
+) -> Result<VrfOutput, VrfError> {
+    let h = hash_to_point(public_key, alpha);
+    // U = s.G - c.P and V = s.H - c.Gamma
+    let u = RistrettoPublicKey::from_secret_key(&proof.response) - &proof.challenge * public_key;
+    let v = RistrettoPublicKey::new_from_pk(proof.response.0 * h.point - proof.challenge.0 * proof.gamma.point);
+    let challenge = challenge::<D>(public_key, &h, &proof.gamma, &u, &v);
+    if challenge != proof.challenge {
+        return Err(VrfError::InvalidProof);
+    }
+    Ok(output::<D>(&proof.gamma))
+}
+
+fn hash_to_point(public_key: &RistrettoPublicKey, alpha: &[u8]) -> RistrettoPublicKey {
+    let hasher = Sha512::new()
+        .chain(HASH_TO_POINT_LABEL)
+        .chain(public_key.as_bytes())
+        .chain(alpha);
+    RistrettoPublicKey::new_from_pk(RistrettoPoint::from_hash(hasher))
+}
+
+/// Derive the nonce from the secret key and the hashed input, so that proving is deterministic and needs no RNG
+fn deterministic_nonce(secret: &RistrettoSecretKey, h: &RistrettoPublicKey) -> RistrettoSecretKey {
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(
+        &Sha512::new()
+            .chain(NONCE_LABEL)
+            .chain(secret.as_bytes())
+            .chain(h.as_bytes())
+            .finalize(),
+    );
+    let nonce = RistrettoSecretKey(Scalar::from_bytes_mod_order_wide(&hash));
+    hash.clear();
+    nonce
+}
+
+fn challenge<D: Digest + Clone>(
+    public_key: &RistrettoPublicKey,
+    h: &RistrettoPublicKey,
+    gamma: &RistrettoPublicKey,
+    u: &RistrettoPublicKey,
+    v: &RistrettoPublicKey,
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_point(b"P", public_key);
+    transcript.append_point(b"H", h);
+    transcript.append_point(b"Gamma", gamma);
+    transcript.append_point(b"U", u);
+    transcript.append_point(b"V", v);
+    transcript.challenge_scalar(b"c")
+}
+
+fn output<D: Digest>(gamma: &RistrettoPublicKey) -> VrfOutput {
+    VrfOutput(D::new().chain(OUTPUT_LABEL).chain(gamma.as_bytes()).finalize().to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{common::Blake256, keys::SecretKey};
+
+    #[test]
+    fn prove_and_verify() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (output, proof) = prove::<Blake256>(&k, b"Wyrd Sisters");
+        assert_eq!(verify::<Blake256>(&p, b"Wyrd Sisters", &proof), Ok(output.clone()));
+        assert_eq!(output.as_bytes().len(), 32);
+        // Proving is deterministic, so the key holder can't grind for a better output
+        assert_eq!(prove::<Blake256>(&k, b"Wyrd Sisters"), (output.clone(), proof.clone()));
+        let (other_output, _) = prove::<Blake256>(&k, b"Witches Abroad");
+        assert_ne!(output, other_output);
+        // Any digest works, and the output is as long as its digest
+        let (wide_output, wide_proof) = prove::<Sha512>(&k, b"Wyrd Sisters");
+        assert_eq!(
+            verify::<Sha512>(&p, b"Wyrd Sisters", &wide_proof),
+            Ok(wide_output.clone())
+        );
+        assert_eq!(wide_output.as_bytes().len(), 64);
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        let mut rng = rand::thread_rng();
+        let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+        let (_, proof) = prove::<Blake256>(&k, b"Lords and Ladies");
+        assert_eq!(
+            verify::<Blake256>(&p, b"Maskerade", &proof),
+            Err(VrfError::InvalidProof)
+        );
+        let (_, other) = RistrettoPublicKey::random_keypair(&mut rng);
+        assert_eq!(
+            verify::<Blake256>(&other, b"Lords and Ladies", &proof),
+            Err(VrfError::InvalidProof)
+        );
+        let forged = VrfProof {
+            gamma: RistrettoPublicKey::from_secret_key(&RistrettoSecretKey::random(&mut rng)),
+            ..proof.clone()
+        };
+        assert_eq!(
+            verify::<Blake256>(&p, b"Lords and Ladies", &forged),
+            Err(VrfError::InvalidProof)
+        );
+        let tampered = VrfProof {
+            response: &proof.response + &RistrettoSecretKey::from(1),
+            ..proof
+        };
+        assert_eq!(
+            verify::<Blake256>(&p, b"Lords and Ladies", &tampered),
+            Err(VrfError::InvalidProof)
+        );
+    }
+}
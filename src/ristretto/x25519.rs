@@ -0,0 +1,199 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Conversions between Ristretto keys and their Ed25519 and X25519 ([RFC 7748](https://www.rfc-editor.org/rfc/rfc7748))
+//! forms, available with the `x25519` feature, so that Ristretto keys can do Diffie-Hellman with X25519 peers.
+//!
+//! ## Which conversions are valid
+//! Secret keys are scalars in all three schemes, so they convert freely:
+//! * [RistrettoSecretKey] to X25519: use the key directly with [diffie_hellman]. Its X25519 public key is
+//!   [X25519PublicKey::from_secret_key].
+//! * X25519 or Ed25519 secrets to Ristretto: [secret_key_from_x25519] clamps the X25519 secret, and
+//!   [secret_key_from_ed25519_seed] expands an RFC 8032 seed, and both reduce the result into a [RistrettoSecretKey].
+//!   The Ristretto public key of the result is a _different_ encoding of a different group element to the peer's
+//!   Ed25519 or X25519 public key, but it belongs to the same scalar.
+//!
+//! Public keys only convert from Edwards to Montgomery form: [X25519PublicKey::from] an [Ed25519PublicKey] is the
+//! standard birational map. There is no conversion from a [RistrettoPublicKey](crate::ristretto::RistrettoPublicKey),
+//! because a Ristretto element stands for a coset of four Edwards points and curve25519-dalek doesn't expose a
+//! canonical representative.
+//!
+//! ## Caveats
+//! * The scalars used here are not clamped, may not be multiples of the cofactor, and so don't clear the small-order
+//!   component of a peer's key the way X25519 does. A peer key with such a component gives a shared secret that the
+//!   peer can't reproduce. Honest X25519 implementations never produce such keys.
+//! * [diffie_hellman] rejects an all-zero shared secret, which is what a small-order peer key produces. Other protocols
+//!   built on X25519 should do the same check.
+//! * A Ristretto secret key derived from an Ed25519 seed will sign with different nonces to RFC 8032 signing, so the
+//!   signatures differ, although both verify.
+
+use crate::ristretto::{ed25519::Ed25519PublicKey, RistrettoSecretKey};
+use clear_on_drop::clear::Clear;
+use curve25519_dalek::{constants::ED25519_BASEPOINT_TABLE, montgomery::MontgomeryPoint, scalar::Scalar};
+use digest::Digest;
+use sha2::Sha512;
+use std::convert::TryInto;
+use thiserror::Error;
+
+/// The length of an X25519 public key, secret key or shared secret
+pub const X25519_KEY_LENGTH: usize = 32;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum X25519Error {
+    #[error("X25519 keys must be {} bytes long", X25519_KEY_LENGTH)]
+    InvalidLength,
+    #[error("The peer's public key has small order")]
+    SmallOrderKey,
+}
+
+/// An X25519 public key: the u-coordinate of a point on Curve25519
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct X25519PublicKey(MontgomeryPoint);
+
+impl X25519PublicKey {
+    /// Read a u-coordinate. As in RFC 7748, every 32-byte string is accepted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, X25519Error> {
+        let bytes: [u8; X25519_KEY_LENGTH] = bytes.try_into().map_err(|_| X25519Error::InvalidLength)?;
+        Ok(X25519PublicKey(MontgomeryPoint(bytes)))
+    }
+
+    /// The X25519 public key, _k.B_, for a Ristretto secret key
+    pub fn from_secret_key(secret: &RistrettoSecretKey) -> Self {
+        X25519PublicKey((&secret.0 * &ED25519_BASEPOINT_TABLE).to_montgomery())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; X25519_KEY_LENGTH] {
+        self.0.as_bytes()
+    }
+}
+
+impl From<&Ed25519PublicKey> for X25519PublicKey {
+    fn from(key: &Ed25519PublicKey) -> Self {
+        X25519PublicKey(key.point.to_montgomery())
+    }
+}
+
+/// The output of [diffie_hellman]. It should be hashed before use as a key; it is cleared when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct X25519SharedSecret([u8; X25519_KEY_LENGTH]);
+
+impl X25519SharedSecret {
+    pub fn as_bytes(&self) -> &[u8; X25519_KEY_LENGTH] {
+        &self.0
+    }
+}
+
+impl Drop for X25519SharedSecret {
+    fn drop(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// X25519 Diffie-Hellman between a Ristretto secret key and a peer's X25519 public key
+pub fn diffie_hellman(secret: &RistrettoSecretKey, peer: &X25519PublicKey) -> Result<X25519SharedSecret, X25519Error> {
+    let shared = &secret.0 * &peer.0;
+    if shared.as_bytes() == &[0u8; X25519_KEY_LENGTH] {
+        return Err(X25519Error::SmallOrderKey);
+    }
+    Ok(X25519SharedSecret(shared.to_bytes()))
+}
+
+/// The Ristretto secret key with the same scalar as an X25519 secret key, after RFC 7748 clamping
+pub fn secret_key_from_x25519(secret: &[u8]) -> Result<RistrettoSecretKey, X25519Error> {
+    let mut bytes: [u8; X25519_KEY_LENGTH] = secret.try_into().map_err(|_| X25519Error::InvalidLength)?;
+    let key = RistrettoSecretKey(Scalar::from_bytes_mod_order(clamp(bytes)));
+    bytes.clear();
+    Ok(key)
+}
+
+/// The Ristretto secret key with the same scalar as an RFC 8032 Ed25519 seed: the clamped first half of its SHA-512
+/// hash
+pub fn secret_key_from_ed25519_seed(seed: &[u8]) -> Result<RistrettoSecretKey, X25519Error> {
+    if seed.len() != X25519_KEY_LENGTH {
+        return Err(X25519Error::InvalidLength);
+    }
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(&Sha512::digest(seed));
+    let mut bytes = [0u8; X25519_KEY_LENGTH];
+    bytes.copy_from_slice(&hash[..X25519_KEY_LENGTH]);
+    let key = RistrettoSecretKey(Scalar::from_bytes_mod_order(clamp(bytes)));
+    hash.clear();
+    bytes.clear();
+    Ok(key)
+}
+
+fn clamp(mut bytes: [u8; X25519_KEY_LENGTH]) -> [u8; X25519_KEY_LENGTH] {
+    bytes[0] &= 248;
+    bytes[31] &= 127;
+    bytes[31] |= 64;
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::SecretKey;
+    use tari_utilities::hex::from_hex;
+
+    #[test]
+    fn diffie_hellman_agrees() {
+        let mut rng = rand::thread_rng();
+        let a = RistrettoSecretKey::random(&mut rng);
+        let b = RistrettoSecretKey::random(&mut rng);
+        let ab = diffie_hellman(&a, &X25519PublicKey::from_secret_key(&b)).unwrap();
+        let ba = diffie_hellman(&b, &X25519PublicKey::from_secret_key(&a)).unwrap();
+        assert_eq!(ab.as_bytes(), ba.as_bytes());
+        let identity = X25519PublicKey::from_bytes(&[0u8; 32]).unwrap();
+        assert_eq!(diffie_hellman(&a, &identity).err(), Some(X25519Error::SmallOrderKey));
+        assert_eq!(X25519PublicKey::from_bytes(&[0u8; 31]), Err(X25519Error::InvalidLength));
+    }
+
+    #[test]
+    fn rfc7748_keys() {
+        // RFC 7748, section 6.1
+        let alice_secret = from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a").unwrap();
+        let alice_public = from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a").unwrap();
+        let bob_public = from_hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f").unwrap();
+        let shared = from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742").unwrap();
+        let alice = secret_key_from_x25519(&alice_secret).unwrap();
+        assert_eq!(
+            X25519PublicKey::from_secret_key(&alice).as_bytes().to_vec(),
+            alice_public
+        );
+        let bob = X25519PublicKey::from_bytes(&bob_public).unwrap();
+        assert_eq!(diffie_hellman(&alice, &bob).unwrap().as_bytes().to_vec(), shared);
+    }
+
+    #[test]
+    fn ed25519_keys() {
+        // RFC 8032, section 7.1, test 1
+        let seed = from_hex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+        let public = from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a").unwrap();
+        let secret = secret_key_from_ed25519_seed(&seed).unwrap();
+        let ed_public = Ed25519PublicKey::from_bytes(&public).unwrap();
+        assert_eq!(Ed25519PublicKey::from_secret_key(&secret), ed_public);
+        assert_eq!(
+            X25519PublicKey::from(&ed_public),
+            X25519PublicKey::from_secret_key(&secret)
+        );
+    }
+}
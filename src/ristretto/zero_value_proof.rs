@@ -0,0 +1,170 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Proofs that a Pedersen commitment is to a value of zero.
+//!
+//! A commitment to zero has no _H_ component, _C = k.G_, so knowing its opening is just knowing the discrete log of
+//! _C_ with respect to _G_. A [ZeroValueProof] is a Schnorr proof of exactly that. This is the heart of balance
+//! checks: the sum of a transaction's outputs minus its inputs is a commitment to zero iff the values balance, and a
+//! proof for the excess shows that it is.
+//!
+//! The prover picks a nonce, _r_, and calculates _A = r.G_, draws the challenge _e_ from a [Transcript] over
+//! _G, H, C, A_ and the context, and calculates the response _s = r + e.k_. The proof is _(e, s)_. The proofs are
+//! created and checked with [PedersenCommitmentFactory::prove_zero_value] and
+//! [PedersenCommitmentFactory::verify_zero_value].
+
+use crate::{
+    commitment::HomomorphicCommitmentFactory,
+    keys::SecretKey,
+    ristretto::{
+        pedersen::{PedersenCommitment, PedersenCommitmentFactory},
+        transcript::Transcript,
+        RistrettoSecretKey,
+    },
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, traits::VartimeMultiscalarMul};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use tari_utilities::ByteArray;
+
+const CHALLENGE_LABEL: &[u8] = b"com.tari.zero_value_proof.challenge";
+
+/// A proof that a commitment is of the form _k.G_, i.e. that it commits to zero, without revealing _k_.
+///
+/// ```edition2018
+/// # use tari_crypto::commitment::HomomorphicCommitmentFactory;
+/// # use tari_crypto::common::Blake256;
+/// # use tari_crypto::keys::SecretKey;
+/// # use tari_crypto::ristretto::{pedersen::PedersenCommitmentFactory, RistrettoSecretKey};
+/// let mut rng = rand::thread_rng();
+/// let factory = PedersenCommitmentFactory::default();
+/// let k = RistrettoSecretKey::random(&mut rng);
+/// let excess = factory.commit_value(&k, 0);
+/// let proof = factory.prove_zero_value::<Blake256, _>(&k, b"example", &mut rng);
+/// assert!(factory.verify_zero_value::<Blake256>(&excess, &proof, b"example"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZeroValueProof {
+    challenge: RistrettoSecretKey,
+    response: RistrettoSecretKey,
+}
+
+impl ZeroValueProof {
+    pub fn challenge(&self) -> &RistrettoSecretKey {
+        &self.challenge
+    }
+
+    pub fn response(&self) -> &RistrettoSecretKey {
+        &self.response
+    }
+}
+
+impl PedersenCommitmentFactory {
+    /// Prove that the commitment `blinding_factor.G` (i.e. `commit_value(blinding_factor, 0)`) has a value of zero.
+    /// The proof is bound to this factory's generators and to `context`.
+    #[allow(non_snake_case)]
+    pub fn prove_zero_value<D: Digest + Clone, R: Rng + CryptoRng>(
+        &self,
+        blinding_factor: &RistrettoSecretKey,
+        context: &[u8],
+        rng: &mut R,
+    ) -> ZeroValueProof {
+        let commitment = self.commit_value(blinding_factor, 0);
+        let r = RistrettoSecretKey::random(rng);
+        let A = r.0 * self.G;
+        let challenge = zero_value_challenge::<D>(self, &commitment, &A, context);
+        let response = &r + &(&challenge * blinding_factor);
+        ZeroValueProof { challenge, response }
+    }
+
+    /// Check that `commitment` is a commitment to zero under this factory, for the context the proof was made with
+    #[allow(non_snake_case)]
+    pub fn verify_zero_value<D: Digest + Clone>(
+        &self,
+        commitment: &PedersenCommitment,
+        proof: &ZeroValueProof,
+        context: &[u8],
+    ) -> bool {
+        // A = s.G - e.C
+        let A = RistrettoPoint::vartime_multiscalar_mul(&[proof.response.0, -proof.challenge.0], &[
+            self.G,
+            commitment.0.point,
+        ]);
+        zero_value_challenge::<D>(self, commitment, &A, context) == proof.challenge
+    }
+}
+
+#[allow(non_snake_case)]
+fn zero_value_challenge<D: Digest + Clone>(
+    factory: &PedersenCommitmentFactory,
+    commitment: &PedersenCommitment,
+    A: &RistrettoPoint,
+    context: &[u8],
+) -> RistrettoSecretKey {
+    let mut transcript = Transcript::<D>::new(CHALLENGE_LABEL);
+    transcript.append_message(b"G", factory.G.compress().as_bytes());
+    transcript.append_message(b"H", factory.H.compress().as_bytes());
+    transcript.append_message(b"C", commitment.as_bytes());
+    transcript.append_message(b"A", A.compress().as_bytes());
+    transcript.append_message(b"context", context);
+    transcript.challenge_scalar(b"e")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::Blake256;
+
+    #[test]
+    fn balanced_transaction() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let (k_in, k_out1, k_out2) = (
+            RistrettoSecretKey::random(&mut rng),
+            RistrettoSecretKey::random(&mut rng),
+            RistrettoSecretKey::random(&mut rng),
+        );
+        let input = factory.commit_value(&k_in, 100);
+        let outputs = factory.commit_value(&k_out1, 60) + factory.commit_value(&k_out2, 40);
+        let excess = &outputs - &input;
+        let k_excess = &(&k_out1 + &k_out2) - &k_in;
+        let proof = factory.prove_zero_value::<Blake256, _>(&k_excess, b"Going Postal", &mut rng);
+        assert!(factory.verify_zero_value::<Blake256>(&excess, &proof, b"Going Postal"));
+        // Bound to the context and generators
+        assert!(!factory.verify_zero_value::<Blake256>(&excess, &proof, b"Making Money"));
+        let other = PedersenCommitmentFactory::new_with_label(b"Raising Steam");
+        assert!(!other.verify_zero_value::<Blake256>(&excess, &proof, b"Going Postal"));
+    }
+
+    #[test]
+    fn nonzero_value_fails() {
+        let mut rng = rand::thread_rng();
+        let factory = PedersenCommitmentFactory::default();
+        let k = RistrettoSecretKey::random(&mut rng);
+        let proof = factory.prove_zero_value::<Blake256, _>(&k, b"", &mut rng);
+        assert!(factory.verify_zero_value::<Blake256>(&factory.commit_value(&k, 0), &proof, b""));
+        assert!(!factory.verify_zero_value::<Blake256>(&factory.commit_value(&k, 1), &proof, b""));
+        let proof = factory.prove_zero_value::<sha2::Sha512, _>(&k, b"", &mut rng);
+        assert!(factory.verify_zero_value::<sha2::Sha512>(&factory.commit_value(&k, 0), &proof, b""));
+    }
+}
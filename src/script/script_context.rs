@@ -16,6 +16,7 @@
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use crate::{ristretto::pedersen::PedersenCommitment, script::op_codes::HashValue};
+use tari_utilities::ByteArray;
 
 /// Contextual data for use in Tari scripts. The context will typically be unambiguously and deterministically
 /// populated by nodes that are executing the script.
@@ -50,4 +51,14 @@ impl ScriptContext {
     pub fn commitment(&self) -> &PedersenCommitment {
         &self.commitment
     }
+
+    /// The canonical serialization of the context, as committed to by context-bound signatures: the little-endian
+    /// block height, followed by the previous block hash and the commitment
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + 32 + 32);
+        bytes.extend_from_slice(&self.block_height.to_le_bytes());
+        bytes.extend_from_slice(&self.prev_block_hash);
+        bytes.extend_from_slice(self.commitment.as_bytes());
+        bytes
+    }
 }
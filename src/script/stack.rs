@@ -366,7 +366,7 @@ mod test {
             RistrettoSecretKey::from_hex("193ee873f3de511eda8ae387db6498f3d194d31a130a94cdf13dc5890ec1ad0f").unwrap();
         let p = RistrettoPublicKey::from_secret_key(&k);
         let m = Blake256::digest(b"Hello Tari Script");
-        let sig = RistrettoSchnorr::sign(k, r, m.as_slice()).unwrap();
+        let sig = RistrettoSchnorr::sign_raw(k, r, m.as_slice()).unwrap();
         let inputs = inputs!(sig, p);
         assert_eq!(inputs.to_hex(), "0500f7c695528c858cde76dab3076908e01228b6dbdd5f671bed1b03b89e170c316db1023d5c46d78a97da8eb6c5a37e00d5f2fee182dcb38c1b6c65e90a43c1090456c0fa32558d6edc0916baa26b48e745de834571534ca253ea82435f08ebbc7c");
     }
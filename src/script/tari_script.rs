@@ -822,7 +822,7 @@ mod test {
         let (pvt_key, pub_key) = RistrettoPublicKey::random_keypair(&mut rng);
         let nonce = RistrettoSecretKey::random(&mut rng);
         let m_key = RistrettoSecretKey::random(&mut rng);
-        let sig = RistrettoSchnorr::sign(pvt_key, nonce, m_key.as_bytes()).unwrap();
+        let sig = RistrettoSchnorr::sign_raw(pvt_key, nonce, m_key.as_bytes()).unwrap();
         let msg = slice_to_boxed_message(m_key.as_bytes());
         let script = script!(CheckSig(msg));
         let inputs = inputs!(sig.clone(), pub_key.clone());
@@ -844,7 +844,7 @@ mod test {
         let (pvt_key, pub_key) = RistrettoPublicKey::random_keypair(&mut rng);
         let nonce = RistrettoSecretKey::random(&mut rng);
         let m_key = RistrettoSecretKey::random(&mut rng);
-        let sig = RistrettoSchnorr::sign(pvt_key, nonce, m_key.as_bytes()).unwrap();
+        let sig = RistrettoSchnorr::sign_raw(pvt_key, nonce, m_key.as_bytes()).unwrap();
         let msg = slice_to_boxed_message(m_key.as_bytes());
         let script = script!(CheckSigVerify(msg) PushOne);
         let inputs = inputs!(sig.clone(), pub_key.clone());
@@ -872,8 +872,8 @@ mod test {
         let msg = slice_to_boxed_message(m.as_bytes());
         let script = script!(Add RevRot Add CheckSigVerify(msg) PushOne);
 
-        let s1 = RistrettoSchnorr::sign(k1, r1, m.as_bytes()).unwrap();
-        let s2 = RistrettoSchnorr::sign(k2, r2, m.as_bytes()).unwrap();
+        let s1 = RistrettoSchnorr::sign_raw(k1, r1, m.as_bytes()).unwrap();
+        let s2 = RistrettoSchnorr::sign_raw(k2, r2, m.as_bytes()).unwrap();
         let inputs = inputs!(p1, p2, s1, s2);
         let result = script.execute(&inputs).unwrap();
         assert_eq!(result, Number(1));
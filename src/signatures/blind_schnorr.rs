@@ -0,0 +1,127 @@
+// Copyright 2021. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Blind Schnorr signatures
+//! A requester obtains a Schnorr signature on a message of their choosing without the signer learning the message or
+//! the final signature.
+//!
+//! 1. The signer picks a nonce, _k_, and sends _R = k.G_ to the requester.
+//! 2. The requester picks blinding factors _a_ and _b_, and calculates the blinded nonce _R' = R + a.G + b.P_, the
+//!    challenge _e' = H(R' || P || m)_ and the blinded challenge _e = e' + b_, which is sent to the signer. See
+//!    [BlindingSession].
+//! 3. The signer responds with _s = k + e.x_ (see [sign_blinded]).
+//! 4. The requester checks the response and unblinds it, giving the signature _(R', s + a)_, which verifies against
+//!    _P_ with challenge _e'_.
+//!
+//! **NB:** A signer must not run blind signing sessions concurrently. Answering many open sessions at once allows
+//! the requesters to forge an extra signature (the ROS attack); complete each session before starting the next.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    signatures::SchnorrSignature,
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul};
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlindSignatureError {
+    #[error("The challenge could not be converted to a secret key")]
+    InvalidChallenge,
+    #[error("The signer's response does not match the blinded challenge")]
+    InvalidBlindSignature,
+}
+
+/// The signer's side of the protocol: respond to the blinded challenge with _s = k + e.x_. The nonce is consumed and
+/// must never be used again.
+pub fn sign_blinded<K>(secret: &K, nonce: K, blinded_challenge: &K) -> K
+where
+    K: SecretKey,
+    for<'b> &'b K: Mul<&'b K, Output = K>,
+{
+    nonce + blinded_challenge * secret
+}
+
+/// The requester's state for a single blind signing session
+pub struct BlindingSession<P, K> {
+    public_key: P,
+    signer_nonce: P,
+    blinded_nonce: P,
+    nonce_blinding: K,
+    challenge: K,
+    blinded_challenge: K,
+}
+
+impl<P, K> BlindingSession<P, K>
+where
+    P: PublicKey<K = K>,
+    K: SecretKey,
+    for<'b> &'b K: Mul<&'b P, Output = P>,
+    for<'b> &'b P: Add<P, Output = P>,
+{
+    /// Blind the signer's public nonce and calculate the blinded challenge for `message`
+    pub fn new<D: Digest, R: Rng + CryptoRng>(
+        public_key: &P,
+        signer_nonce: &P,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, BlindSignatureError> {
+        let a = K::random(rng);
+        let b = K::random(rng);
+        // R' = R + a.G + b.P
+        let blinded_nonce = signer_nonce.clone() + P::from_secret_key(&a) + &b * public_key;
+        let e = D::new()
+            .chain(blinded_nonce.as_bytes())
+            .chain(public_key.as_bytes())
+            .chain(message)
+            .finalize();
+        let challenge = K::from_bytes(&e).map_err(|_| BlindSignatureError::InvalidChallenge)?;
+        let blinded_challenge = challenge.clone() + b;
+        Ok(BlindingSession {
+            public_key: public_key.clone(),
+            signer_nonce: signer_nonce.clone(),
+            blinded_nonce,
+            nonce_blinding: a,
+            challenge,
+            blinded_challenge,
+        })
+    }
+
+    /// The blinded challenge, _e = e' + b_, to send to the signer
+    pub fn blinded_challenge(&self) -> &K {
+        &self.blinded_challenge
+    }
+
+    /// The unblinded challenge, _e'_, that the final signature verifies with
+    pub fn challenge(&self) -> &K {
+        &self.challenge
+    }
+
+    /// Check the signer's response, _s.G = R + e.P_, and unblind it into the final signature, _(R', s + a)_
+    pub fn unblind(self, blinded_signature: &K) -> Result<SchnorrSignature<P, K>, BlindSignatureError> {
+        let lhs = P::from_secret_key(blinded_signature);
+        let rhs = &self.signer_nonce + &self.blinded_challenge * &self.public_key;
+        if lhs != rhs {
+            return Err(BlindSignatureError::InvalidBlindSignature);
+        }
+        let s = blinded_signature.clone() + self.nonce_blinding;
+        Ok(SchnorrSignature::new(self.blinded_nonce, s))
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2021. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Challenge strategies
+//! A [ChallengeStrategy] fixes how [SchnorrSignature](crate::signatures::SchnorrSignature) derives its message
+//! challenge and deterministic nonce. It is selected with the signature's third type parameter, which defaults to
+//! [DefaultChallenge].
+
+use digest::Digest;
+
+/// Domain label for deterministic nonce derivation with [DefaultChallenge]
+const DETERMINISTIC_NONCE_LABEL: &[u8] = b"com.tari.schnorr.deterministic_nonce";
+
+const BIP340_CHALLENGE_TAG: &[u8] = b"BIP0340/challenge";
+const BIP340_NONCE_TAG: &[u8] = b"BIP0340/nonce";
+const BIP340_AUX_TAG: &[u8] = b"BIP0340/aux";
+
+/// Derives the message challenge and the deterministic nonce for a Schnorr signature from the byte encodings of its
+/// inputs
+pub trait ChallengeStrategy {
+    /// The challenge for a signature with public nonce _R_ by key _P_ on message _m_
+    fn challenge<D: Digest>(public_nonce: &[u8], public_key: &[u8], message: &[u8]) -> Vec<u8>;
+
+    /// The deterministic nonce for a signature by secret key _k_ (with public key _P_) on message _m_
+    fn nonce<D: Digest>(secret_key: &[u8], public_key: &[u8], message: &[u8]) -> Vec<u8>;
+}
+
+/// The crate's native scheme: _e = H(R || P || m)_ and _r = H(label || k || m)_
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DefaultChallenge;
+
+impl ChallengeStrategy for DefaultChallenge {
+    fn challenge<D: Digest>(public_nonce: &[u8], public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        D::new()
+            .chain(public_nonce)
+            .chain(public_key)
+            .chain(message)
+            .finalize()
+            .to_vec()
+    }
+
+    fn nonce<D: Digest>(secret_key: &[u8], _public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        D::new()
+            .chain(DETERMINISTIC_NONCE_LABEL)
+            .chain(secret_key)
+            .chain(message)
+            .finalize()
+            .to_vec()
+    }
+}
+
+/// [BIP-340](https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki) tagged hashes,
+/// _hash_tag(x) = H(H(tag) || H(tag) || x)_, giving _e = hash_BIP0340/challenge(R || P || m)_ and, with all-zero
+/// auxiliary randomness, _r = hash_BIP0340/nonce((k xor hash_BIP0340/aux(0)) || P || m)_.
+///
+/// This only reproduces BIP-340 signatures when paired with a secp256k1 backend whose keys encode as 32-byte x-only
+/// points and big-endian scalars, and `H` is SHA-256. With Ristretto keys it is simply a differently domain-separated
+/// challenge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bip340Challenge;
+
+impl Bip340Challenge {
+    /// A BIP-340 tagged hasher, _H(H(tag) || H(tag))_, ready for the data to be chained on
+    pub fn tagged_hasher<D: Digest>(tag: &[u8]) -> D {
+        let tag_hash = D::digest(tag);
+        D::new().chain(&tag_hash).chain(&tag_hash)
+    }
+}
+
+impl ChallengeStrategy for Bip340Challenge {
+    fn challenge<D: Digest>(public_nonce: &[u8], public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        Self::tagged_hasher::<D>(BIP340_CHALLENGE_TAG)
+            .chain(public_nonce)
+            .chain(public_key)
+            .chain(message)
+            .finalize()
+            .to_vec()
+    }
+
+    fn nonce<D: Digest>(secret_key: &[u8], public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        let aux = Self::tagged_hasher::<D>(BIP340_AUX_TAG).chain(&[0u8; 32]).finalize();
+        let t: Vec<u8> = secret_key.iter().zip(aux.iter().cycle()).map(|(k, a)| k ^ a).collect();
+        Self::tagged_hasher::<D>(BIP340_NONCE_TAG)
+            .chain(&t)
+            .chain(public_key)
+            .chain(message)
+            .finalize()
+            .to_vec()
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2021. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Domain-separated signature challenges
+//! [SignatureChallengeBuilder] builds the challenge for a signature from its public nonce, public key and message.
+//! Every challenge is bound to an application domain, and every input is length-prefixed, so challenges built for
+//! different purposes, or from differently split messages, can never collide.
+
+use crate::{keys::SecretKey, signatures::SchnorrSignatureError};
+use digest::Digest;
+use tari_utilities::ByteArray;
+
+/// Label that separates builder challenges from every other hash in the crate
+const CHALLENGE_BUILDER_LABEL: &[u8] = b"com.tari.signature_challenge";
+
+/// Builds a signature challenge,
+/// _e = H(label || len(domain) || domain || R || P || len(m_1) || m_1 || ... || len(m_n) || m_n)_.
+///
+/// The domain, nonce and public key are required up front, so a challenge can't be built without them; message
+/// chunks are added with [chain_message](#method.chain_message).
+///
+/// ```edition2018
+/// # use tari_crypto::signatures::SignatureChallengeBuilder;
+/// # use tari_crypto::ristretto::{RistrettoPublicKey, RistrettoSchnorr, RistrettoSecretKey};
+/// # use tari_crypto::keys::PublicKey;
+/// # use tari_crypto::common::Blake256;
+/// let mut rng = rand::thread_rng();
+/// let (k, p) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let (r, nonce) = RistrettoPublicKey::random_keypair(&mut rng);
+/// let e = SignatureChallengeBuilder::<Blake256>::new(b"example.transfer", &nonce, &p)
+///     .chain_message(b"amount=10")
+///     .chain_message(b"to=bob")
+///     .finalize();
+/// let sig = RistrettoSchnorr::sign_raw(k, r, &e).unwrap();
+/// assert!(sig.verify_challenge(&p, &e));
+/// ```
+pub struct SignatureChallengeBuilder<D> {
+    hasher: D,
+}
+
+impl<D: Digest> SignatureChallengeBuilder<D> {
+    /// Start a challenge for the signature with `public_nonce` by `public_key`, in the given application `domain`
+    pub fn new<P: ByteArray>(domain: &[u8], public_nonce: &P, public_key: &P) -> Self {
+        let hasher = D::new()
+            .chain(CHALLENGE_BUILDER_LABEL)
+            .chain((domain.len() as u64).to_le_bytes())
+            .chain(domain)
+            .chain(public_nonce.as_bytes())
+            .chain(public_key.as_bytes());
+        SignatureChallengeBuilder { hasher }
+    }
+
+    /// Add a chunk of the message to the challenge
+    pub fn chain_message<T: AsRef<[u8]>>(mut self, chunk: T) -> Self {
+        let chunk = chunk.as_ref();
+        self.hasher = self.hasher.chain((chunk.len() as u64).to_le_bytes()).chain(chunk);
+        self
+    }
+
+    /// The challenge as bytes, suitable for [SchnorrSignature::sign_raw](crate::signatures::SchnorrSignature::sign_raw)
+    /// and `verify_challenge`
+    pub fn finalize(self) -> Vec<u8> {
+        self.hasher.finalize().to_vec()
+    }
+
+    /// The hasher with the challenge absorbed, e.g. for an unbiased challenge from a 64-byte digest with
+    /// [RistrettoSecretKey::from_hasher](crate::ristretto::RistrettoSecretKey::from_hasher)
+    pub fn into_hasher(self) -> D {
+        self.hasher
+    }
+
+    /// The challenge as a scalar. `D` must produce digests of the secret key length.
+    pub fn build<K: SecretKey>(self) -> Result<K, SchnorrSignatureError> {
+        K::from_bytes(&self.finalize()).map_err(|_| SchnorrSignatureError::IncompatibleHashFunction)
+    }
+}
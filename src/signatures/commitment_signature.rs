@@ -27,7 +27,10 @@
 use crate::{
     commitment::{HomomorphicCommitment, HomomorphicCommitmentFactory},
     keys::{PublicKey, SecretKey},
+    signatures::schnorr::find_invalid,
 };
+use digest::Digest;
+use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
@@ -41,8 +44,16 @@ use thiserror::Error;
 pub enum CommitmentSignatureError {
     #[error("An invalid challenge was provided")]
     InvalidChallenge,
+    #[error("The hash function output cannot be converted to a secret key")]
+    IncompatibleHashFunction,
+    #[error("The batch has different numbers of signatures, commitments and challenges")]
+    BatchLengthMismatch,
 }
 
+/// Domain labels for deterministic nonce derivation in [CommitmentSignature::sign_deterministic]
+const DETERMINISTIC_NONCE_A_LABEL: &[u8] = b"com.tari.commitment_signature.deterministic_nonce_a";
+const DETERMINISTIC_NONCE_X_LABEL: &[u8] = b"com.tari.commitment_signature.deterministic_nonce_x";
+
 /// # Commitment Signatures
 ///
 /// Find out more about Commitment signatures [here](https://eprint.iacr.org/2020/061.pdf) and
@@ -97,6 +108,26 @@ where
         challenge: &[u8],
         factory: &C,
     ) -> Result<Self, CommitmentSignatureError>
+    where
+        K: Mul<P, Output = P>,
+        for<'a> &'a K: Add<&'a K, Output = K>,
+        for<'a> &'a K: Mul<&'a K, Output = K>,
+        C: HomomorphicCommitmentFactory<P = P>,
+    {
+        Self::sign_raw(secret_a, secret_x, nonce_a, nonce_x, challenge, factory)
+    }
+
+    /// Sign an externally-computed challenge. The challenge bytes are converted to a secret key as-is and are never
+    /// re-hashed, so the signature can be checked with [verify_challenge](#method.verify_challenge) using the same
+    /// bytes.
+    pub fn sign_raw<C>(
+        secret_a: K,
+        secret_x: K,
+        nonce_a: K,
+        nonce_x: K,
+        challenge: &[u8],
+        factory: &C,
+    ) -> Result<Self, CommitmentSignatureError>
     where
         K: Mul<P, Output = P>,
         for<'a> &'a K: Add<&'a K, Output = K>,
@@ -118,6 +149,73 @@ where
         Ok(Self::new(public_commitment_nonce, u, v))
     }
 
+    /// Sign a message with the commitment's value and blinding factor, using nonces derived deterministically from
+    /// the secrets and the message instead of an RNG.
+    ///
+    /// The nonces are _k_2 = H(label_a || a || x || m)_ and _k_1 = H(label_x || a || x || m)_, and the challenge is
+    /// _e = H(R || C || m)_. Signatures made this way are checked with [verify_message](#method.verify_message). `D`
+    /// must produce digests of the secret key length.
+    pub fn sign_deterministic<D, C>(
+        secret_a: K,
+        secret_x: K,
+        message: &[u8],
+        factory: &C,
+    ) -> Result<Self, CommitmentSignatureError>
+    where
+        D: Digest,
+        K: Mul<P, Output = P>,
+        for<'a> &'a K: Add<&'a K, Output = K>,
+        for<'a> &'a K: Mul<&'a K, Output = K>,
+        C: HomomorphicCommitmentFactory<P = P>,
+    {
+        let derive_nonce = |label: &[u8]| {
+            let r = D::new()
+                .chain(label)
+                .chain(secret_a.as_bytes())
+                .chain(secret_x.as_bytes())
+                .chain(message)
+                .finalize();
+            K::from_bytes(&r).map_err(|_| CommitmentSignatureError::IncompatibleHashFunction)
+        };
+        let nonce_a = derive_nonce(DETERMINISTIC_NONCE_A_LABEL)?;
+        let nonce_x = derive_nonce(DETERMINISTIC_NONCE_X_LABEL)?;
+        let commitment = factory.commit(&secret_x, &secret_a);
+        let public_nonce = factory.commit(&nonce_x, &nonce_a);
+        let challenge = Self::message_challenge::<D>(&public_nonce, &commitment, message);
+        Self::sign(secret_a, secret_x, nonce_a, nonce_x, &challenge, factory)
+    }
+
+    /// Verify a signature on `message` whose challenge is _e = H(R || C || m)_, as produced by
+    /// [sign_deterministic](#method.sign_deterministic)
+    pub fn verify_message<'a, D, C>(
+        &self,
+        public_commitment: &'a HomomorphicCommitment<P>,
+        message: &[u8],
+        factory: &C,
+    ) -> bool
+    where
+        D: Digest,
+        for<'b> &'a HomomorphicCommitment<P>: Mul<&'b K, Output = HomomorphicCommitment<P>>,
+        for<'b> &'b HomomorphicCommitment<P>: Add<&'b HomomorphicCommitment<P>, Output = HomomorphicCommitment<P>>,
+        C: HomomorphicCommitmentFactory<P = P>,
+    {
+        let challenge = Self::message_challenge::<D>(&self.public_nonce, public_commitment, message);
+        self.verify_challenge(public_commitment, &challenge, factory)
+    }
+
+    fn message_challenge<D: Digest>(
+        public_nonce: &HomomorphicCommitment<P>,
+        public_commitment: &HomomorphicCommitment<P>,
+        message: &[u8],
+    ) -> Vec<u8> {
+        D::new()
+            .chain(public_nonce.as_bytes())
+            .chain(public_commitment.as_bytes())
+            .chain(message)
+            .finalize()
+            .to_vec()
+    }
+
     /// Verify if the commitment signature signed the commitment using the specified challenge (as bytes). If the
     /// provided challenge n bytes cannot be converted to a secret key, this function also returns false.
     pub fn verify_challenge<'a, C>(
@@ -155,6 +253,106 @@ where
         lhs == rhs
     }
 
+    /// Verify a batch of commitment signatures against their respective commitments and challenges (as bytes). If
+    /// any of the challenges cannot be converted to a secret key, this function returns false.
+    ///
+    /// See [batch_verify](#method.batch_verify) for details.
+    pub fn batch_verify_challenges<C, R>(
+        signatures: &[Self],
+        public_commitments: &[HomomorphicCommitment<P>],
+        challenges: &[&[u8]],
+        factory: &C,
+        rng: &mut R,
+    ) -> bool
+    where
+        C: HomomorphicCommitmentFactory<P = P>,
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let challenges = match challenges
+            .iter()
+            .map(|e| K::from_bytes(e))
+            .collect::<Result<Vec<K>, _>>()
+        {
+            Ok(e) => e,
+            Err(_) => return false,
+        };
+        Self::batch_verify(signatures, public_commitments, &challenges, factory, rng)
+    }
+
+    /// Verify a batch of commitment signatures against their respective commitments and challenges using a single
+    /// multiscalar multiplication.
+    ///
+    /// Each verification equation, _v_i.H + u_i.G = R_i + e_i.C_i_, is weighted by a random scalar _w_i_ drawn from
+    /// `rng`, and the weighted equations are summed:
+    ///   (\sum w_i.v_i).H + (\sum w_i.u_i).G = \sum w_i.R_i + \sum (w_i.e_i).C_i
+    ///
+    /// Returns false if the slices have different lengths, or if any signature in the batch is invalid. An empty batch
+    /// is valid.
+    pub fn batch_verify<C, R>(
+        signatures: &[Self],
+        public_commitments: &[HomomorphicCommitment<P>],
+        challenges: &[K],
+        factory: &C,
+        rng: &mut R,
+    ) -> bool
+    where
+        C: HomomorphicCommitmentFactory<P = P>,
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let n = signatures.len();
+        if public_commitments.len() != n || challenges.len() != n {
+            return false;
+        }
+        let mut u_sum = K::default();
+        let mut v_sum = K::default();
+        let mut scalars = Vec::with_capacity(2 * n);
+        let mut points = Vec::with_capacity(2 * n);
+        for ((sig, commitment), e) in signatures.iter().zip(public_commitments).zip(challenges) {
+            let w = K::random(rng);
+            u_sum = u_sum + &w * &sig.u;
+            v_sum = v_sum + &w * &sig.v;
+            scalars.push(&w * e);
+            points.push(commitment.as_public_key().clone());
+            scalars.push(w);
+            points.push(sig.public_nonce.as_public_key().clone());
+        }
+        let lhs = factory.commit(&u_sum, &v_sum);
+        // Implementors should make this a constant time comparison
+        lhs.as_public_key() == &P::batch_mul_vartime(&scalars, &points)
+    }
+
+    /// Batch verify the signatures, and if the batch fails, bisect it to find the signatures that are invalid.
+    ///
+    /// Returns the (ascending) indices of the invalid signatures; an empty vector means that the whole batch is valid.
+    pub fn batch_verify_find_invalid<C, R>(
+        signatures: &[Self],
+        public_commitments: &[HomomorphicCommitment<P>],
+        challenges: &[K],
+        factory: &C,
+        rng: &mut R,
+    ) -> Result<Vec<usize>, CommitmentSignatureError>
+    where
+        C: HomomorphicCommitmentFactory<P = P>,
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let n = signatures.len();
+        if public_commitments.len() != n || challenges.len() != n {
+            return Err(CommitmentSignatureError::BatchLengthMismatch);
+        }
+        Ok(find_invalid(n, |range| {
+            Self::batch_verify(
+                &signatures[range.clone()],
+                &public_commitments[range.clone()],
+                &challenges[range],
+                factory,
+                rng,
+            )
+        }))
+    }
+
     /// This function returns the complete signature tuple (R, u, v)
     #[inline]
     pub fn complete_signature_tuple(&self) -> (&HomomorphicCommitment<P>, &K, &K) {
@@ -0,0 +1,152 @@
+// Copyright 2021. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Half-aggregation of Schnorr signatures
+//! Compresses _n_ independent Schnorr signatures, made by different keys over different messages, into a single
+//! object holding the _n_ public nonces and one scalar. This is non-interactive; anyone can aggregate.
+//!
+//! The aggregate scalar is _s = \sum z_i.s_i_, where the weights _z_i_ are derived by hashing every nonce, public
+//! key and challenge in the set. Verification checks _s.G = \sum z_i.(R_i + e_i.P_i)_. See
+//! [Chalkias et al.](https://eprint.iacr.org/2021/350) for the security proof.
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    signatures::SchnorrSignature,
+};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+use tari_utilities::ByteArray;
+use thiserror::Error;
+
+const AGGREGATION_WEIGHT_LABEL: &[u8] = b"com.tari.schnorr.half_aggregation";
+
+#[derive(Clone, Debug, Error, PartialEq, Eq, Deserialize, Serialize)]
+pub enum HalfAggregationError {
+    #[error("The number of signatures, public keys and challenges must match")]
+    MismatchedLengths,
+    #[error("The hash function output cannot be converted to a secret key")]
+    IncompatibleHashFunction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HalfAggregateSignature<P, K> {
+    public_nonces: Vec<P>,
+    signature: K,
+}
+
+impl<P, K> HalfAggregateSignature<P, K>
+where
+    P: PublicKey<K = K>,
+    K: SecretKey,
+    for<'b> &'b K: Mul<&'b K, Output = K>,
+{
+    /// Aggregate the signatures, where `signatures[i]` is a signature by `public_keys[i]` with challenge
+    /// `challenges[i]`. The signatures are not checked; an invalid input produces an aggregate that fails to verify.
+    pub fn aggregate<D: Digest>(
+        signatures: &[SchnorrSignature<P, K>],
+        public_keys: &[P],
+        challenges: &[K],
+    ) -> Result<Self, HalfAggregationError> {
+        let n = signatures.len();
+        if public_keys.len() != n || challenges.len() != n {
+            return Err(HalfAggregationError::MismatchedLengths);
+        }
+        let public_nonces: Vec<P> = signatures.iter().map(|s| s.get_public_nonce().clone()).collect();
+        let weights = aggregation_weights::<D, P, K>(&public_nonces, public_keys, challenges)?;
+        let signature = signatures
+            .iter()
+            .zip(weights.iter())
+            .fold(K::default(), |acc, (sig, z)| acc + z * sig.get_signature());
+        Ok(HalfAggregateSignature {
+            public_nonces,
+            signature,
+        })
+    }
+
+    /// Verify the aggregate against the public keys and challenges of the original signatures, in the same order as
+    /// they were aggregated
+    pub fn verify<D: Digest>(&self, public_keys: &[P], challenges: &[K]) -> bool {
+        let n = self.public_nonces.len();
+        if public_keys.len() != n || challenges.len() != n {
+            return false;
+        }
+        let weights = match aggregation_weights::<D, P, K>(&self.public_nonces, public_keys, challenges) {
+            Ok(w) => w,
+            Err(_) => return false,
+        };
+        let mut scalars = Vec::with_capacity(2 * n);
+        let mut points = Vec::with_capacity(2 * n);
+        for (((r, p), e), z) in self.public_nonces.iter().zip(public_keys).zip(challenges).zip(weights) {
+            scalars.push(&z * e);
+            points.push(p.clone());
+            scalars.push(z);
+            points.push(r.clone());
+        }
+        // Implementors should make this a constant time comparison
+        P::from_secret_key(&self.signature) == P::batch_mul_vartime(&scalars, &points)
+    }
+
+    /// The number of signatures in the aggregate
+    pub fn len(&self) -> usize {
+        self.public_nonces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.public_nonces.is_empty()
+    }
+
+    #[inline]
+    pub fn get_public_nonces(&self) -> &[P] {
+        &self.public_nonces
+    }
+
+    #[inline]
+    pub fn get_signature(&self) -> &K {
+        &self.signature
+    }
+}
+
+/// Calculate _z_i = H(label || R_1..R_n || P_1..P_n || e_1..e_n || i)_
+fn aggregation_weights<D, P, K>(
+    public_nonces: &[P],
+    public_keys: &[P],
+    challenges: &[K],
+) -> Result<Vec<K>, HalfAggregationError>
+where
+    D: Digest,
+    P: PublicKey<K = K>,
+    K: SecretKey,
+{
+    let mut hasher = D::new().chain(AGGREGATION_WEIGHT_LABEL);
+    for r in public_nonces {
+        hasher = hasher.chain(r.as_bytes());
+    }
+    for p in public_keys {
+        hasher = hasher.chain(p.as_bytes());
+    }
+    for e in challenges {
+        hasher = hasher.chain(e.as_bytes());
+    }
+    let prefix = hasher.finalize();
+    (0..public_nonces.len() as u64)
+        .map(|i| {
+            let z = D::new().chain(&prefix).chain(&i.to_le_bytes()).finalize();
+            K::from_bytes(&z).map_err(|_| HalfAggregationError::IncompatibleHashFunction)
+        })
+        .collect()
+}
@@ -15,8 +15,20 @@
 // WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
 // USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+mod blind_schnorr;
+mod challenge;
+mod challenge_builder;
 mod commitment_signature;
+mod half_aggregate;
 mod schnorr;
+mod signed_message;
+mod signer;
 
+pub use blind_schnorr::*;
+pub use challenge::*;
+pub use challenge_builder::*;
 pub use commitment_signature::*;
+pub use half_aggregate::*;
 pub use schnorr::*;
+pub use signed_message::*;
+pub use signer::*;
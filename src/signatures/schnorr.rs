@@ -2,11 +2,17 @@
 //! This module defines generic traits for handling the digital signature operations, agnostic
 //! of the underlying elliptic curve implementation
 
-use crate::keys::{PublicKey, SecretKey};
+use crate::{
+    keys::{PublicKey, SecretKey},
+    signatures::{ChallengeStrategy, DefaultChallenge, SignatureChallengeBuilder},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    ops::{Add, Mul},
+    marker::PhantomData,
+    ops::{Add, Mul, Range, Sub},
 };
 use tari_utilities::ByteArray;
 use thiserror::Error;
@@ -15,16 +21,39 @@ use thiserror::Error;
 pub enum SchnorrSignatureError {
     #[error("An invalid challenge was provided")]
     InvalidChallenge,
+    #[error("The hash function output cannot be converted to a secret key")]
+    IncompatibleHashFunction,
+    #[error("The signature bytes have the wrong length or could not be decoded")]
+    InvalidEncoding,
+    #[error("The signature is not canonically encoded")]
+    NonCanonicalEncoding,
+    #[error("The signature's public nonce is the identity")]
+    IdentityNonce,
+    #[error("The batch has different numbers of signatures, public keys and challenges")]
+    BatchLengthMismatch,
 }
 
+/// A Schnorr signature, _(R, s)_. The challenge strategy, `H`, determines how [sign_deterministic] and
+/// [verify_message] derive the challenge from a message; see [ChallengeStrategy]. Methods that take an explicit
+/// challenge are unaffected by it.
+///
+/// With the `strict` feature, deserialization goes through [from_canonical_bytes] checks and rejects non-canonical
+/// encodings and identity nonces.
+///
+/// [sign_deterministic]: SchnorrSignature::sign_deterministic
+/// [verify_message]: SchnorrSignature::verify_message
+/// [from_canonical_bytes]: SchnorrSignature::from_canonical_bytes
 #[allow(non_snake_case)]
-#[derive(PartialEq, Eq, Copy, Debug, Clone, Serialize, Deserialize, Hash)]
-pub struct SchnorrSignature<P, K> {
+#[derive(PartialEq, Eq, Copy, Debug, Clone, Serialize, Hash)]
+#[cfg_attr(not(feature = "strict"), derive(Deserialize))]
+pub struct SchnorrSignature<P, K, H = DefaultChallenge> {
     public_nonce: P,
     signature: K,
+    #[serde(skip)]
+    challenge_strategy: PhantomData<H>,
 }
 
-impl<P, K> SchnorrSignature<P, K>
+impl<P, K, H> SchnorrSignature<P, K, H>
 where
     P: PublicKey<K = K>,
     K: SecretKey,
@@ -33,6 +62,7 @@ where
         SchnorrSignature {
             public_nonce,
             signature,
+            challenge_strategy: PhantomData,
         }
     }
 
@@ -40,7 +70,27 @@ where
         P::from_secret_key(&self.signature)
     }
 
-    pub fn sign(secret: K, nonce: K, challenge: &[u8]) -> Result<Self, SchnorrSignatureError>
+    /// Sign `message` in the given application `domain` with the given secret key and nonce. The challenge is always
+    /// built by [SignatureChallengeBuilder] from the public nonce, the public key and the message, so callers can't
+    /// assemble it inconsistently by hand, and it can't collide with a challenge from another domain. Check the
+    /// result with [verify_with_domain](#method.verify_with_domain).
+    ///
+    /// Protocols that define their own challenge (e.g. a script's message hash, or an FFI caller's challenge) sign
+    /// it with [sign_raw](#method.sign_raw) instead.
+    pub fn sign<D: Digest>(secret: K, nonce: K, domain: &[u8], message: &[u8]) -> Result<Self, SchnorrSignatureError>
+    where K: Add<Output = K> + Mul<P, Output = P> + Mul<Output = K> {
+        let public_nonce = P::from_secret_key(&nonce);
+        let public_key = P::from_secret_key(&secret);
+        let challenge = SignatureChallengeBuilder::<D>::new(domain, &public_nonce, &public_key)
+            .chain_message(message)
+            .finalize();
+        Self::sign_raw(secret, nonce, &challenge)
+    }
+
+    /// Sign an externally-computed challenge, _s = r + e.k_. The challenge bytes are converted to a secret key as-is
+    /// (`K::from_bytes`) and are never re-hashed, so callers that construct their own domain-separated challenges
+    /// can check the result with [verify_challenge](#method.verify_challenge) using the same bytes.
+    pub fn sign_raw(secret: K, nonce: K, challenge: &[u8]) -> Result<Self, SchnorrSignatureError>
     where K: Add<Output = K> + Mul<P, Output = P> + Mul<Output = K> {
         // s = r + e.k
         let e = match K::from_bytes(challenge) {
@@ -53,6 +103,58 @@ where
         Ok(Self::new(public_nonce, s))
     }
 
+    /// Sign a message using a nonce derived deterministically from the secret key and the message, rather than from
+    /// an RNG, in the spirit of RFC 6979. The same key and message always produce the same signature, and a faulty
+    /// RNG can no longer leak the secret key through nonce reuse.
+    ///
+    /// The nonce and challenge are derived by the challenge strategy, `H`; with [DefaultChallenge] they are
+    /// _r = H(label || k || m)_ and _e = H(R || P || m)_. Signatures made this way are checked with
+    /// [verify_message](#method.verify_message). `D` must produce digests of the secret key length.
+    pub fn sign_deterministic<D: Digest>(secret: K, message: &[u8]) -> Result<Self, SchnorrSignatureError>
+    where
+        K: Add<Output = K> + Mul<P, Output = P> + Mul<Output = K>,
+        H: ChallengeStrategy,
+    {
+        let public_key = P::from_secret_key(&secret);
+        let r = H::nonce::<D>(secret.as_bytes(), public_key.as_bytes(), message);
+        let nonce = K::from_bytes(&r).map_err(|_| SchnorrSignatureError::IncompatibleHashFunction)?;
+        let public_nonce = P::from_secret_key(&nonce);
+        let challenge = Self::message_challenge::<D>(&public_nonce, &public_key, message);
+        Self::sign_raw(secret, nonce, &challenge)
+    }
+
+    /// Verify a signature on `message` whose challenge is derived by the challenge strategy (_e = H(R || P || m)_
+    /// for [DefaultChallenge]), as produced by [sign_deterministic](#method.sign_deterministic)
+    pub fn verify_message<'a, D: Digest>(&self, public_key: &'a P, message: &[u8]) -> bool
+    where
+        for<'b> &'b K: Mul<&'a P, Output = P>,
+        for<'b> &'b P: Add<P, Output = P>,
+        H: ChallengeStrategy,
+    {
+        let challenge = Self::message_challenge::<D>(&self.public_nonce, public_key, message);
+        self.verify_challenge(public_key, &challenge)
+    }
+
+    /// Verify a signature made with [sign](#method.sign)
+    pub fn verify_with_domain<'a, D: Digest>(&self, public_key: &'a P, domain: &[u8], message: &[u8]) -> bool
+    where
+        for<'b> &'b K: Mul<&'a P, Output = P>,
+        for<'b> &'b P: Add<P, Output = P>,
+    {
+        let challenge = SignatureChallengeBuilder::<D>::new(domain, &self.public_nonce, public_key)
+            .chain_message(message)
+            .finalize();
+        self.verify_challenge(public_key, &challenge)
+    }
+
+    pub(crate) fn message_challenge<D: Digest>(public_nonce: &P, public_key: &P, message: &[u8]) -> Vec<u8>
+    where H: ChallengeStrategy {
+        H::challenge::<D>(public_nonce.as_bytes(), public_key.as_bytes(), message)
+    }
+
+    /// Verify the signature against an externally-computed challenge, given as bytes. This is the counterpart of
+    /// [sign_raw](#method.sign_raw); the challenge is not re-hashed. Returns false if the bytes cannot be converted to
+    /// a secret key.
     pub fn verify_challenge<'a>(&self, public_key: &'a P, challenge: &[u8]) -> bool
     where
         for<'b> &'b K: Mul<&'a P, Output = P>,
@@ -76,6 +178,147 @@ where
         lhs == rhs
     }
 
+    /// Verify a batch of signatures against their respective public keys and challenges (as bytes). If any of the
+    /// challenges cannot be converted to a secret key, this function returns false.
+    ///
+    /// See [batch_verify](#method.batch_verify) for details.
+    pub fn batch_verify_challenges<R>(signatures: &[Self], public_keys: &[P], challenges: &[&[u8]], rng: &mut R) -> bool
+    where
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let challenges = match challenges
+            .iter()
+            .map(|e| K::from_bytes(e))
+            .collect::<Result<Vec<K>, _>>()
+        {
+            Ok(e) => e,
+            Err(_) => return false,
+        };
+        Self::batch_verify(signatures, public_keys, &challenges, rng)
+    }
+
+    /// Verify a batch of signatures against their respective public keys and challenges using a single multiscalar
+    /// multiplication.
+    ///
+    /// Each verification equation, _s_i.G = R_i + e_i.P_i_, is weighted by a random scalar _w_i_ drawn from `rng`, and
+    /// the weighted equations are summed:
+    ///   (\sum w_i.s_i).G = \sum w_i.R_i + \sum (w_i.e_i).P_i
+    /// The random weights prevent an attacker from crafting invalid signatures whose errors cancel each other out.
+    ///
+    /// Returns false if the slices have different lengths, or if any signature in the batch is invalid. An empty batch
+    /// is valid. The result doesn't tell you _which_ signature failed; use
+    /// [batch_verify_find_invalid](#method.batch_verify_find_invalid) if you need to know.
+    pub fn batch_verify<R>(signatures: &[Self], public_keys: &[P], challenges: &[K], rng: &mut R) -> bool
+    where
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let n = signatures.len();
+        if public_keys.len() != n || challenges.len() != n {
+            return false;
+        }
+        let mut s_sum = K::default();
+        let mut scalars = Vec::with_capacity(2 * n);
+        let mut points = Vec::with_capacity(2 * n);
+        for ((sig, public_key), e) in signatures.iter().zip(public_keys).zip(challenges) {
+            let w = K::random(rng);
+            s_sum = s_sum + &w * &sig.signature;
+            scalars.push(&w * e);
+            points.push(public_key.clone());
+            scalars.push(w);
+            points.push(sig.public_nonce.clone());
+        }
+        // Implementors should make this a constant time comparison
+        P::from_secret_key(&s_sum) == P::batch_mul_vartime(&scalars, &points)
+    }
+
+    /// Batch verify the signatures, and if the batch fails, bisect it to find the signatures that are invalid.
+    ///
+    /// Returns the (ascending) indices of the invalid signatures, so an empty vector means that the whole batch is
+    /// valid. The common case of a valid batch costs a single [batch_verify](#method.batch_verify); each invalid
+    /// signature costs roughly _2 log n_ further batch verifications of decreasing size.
+    pub fn batch_verify_find_invalid<R>(
+        signatures: &[Self],
+        public_keys: &[P],
+        challenges: &[K],
+        rng: &mut R,
+    ) -> Result<Vec<usize>, SchnorrSignatureError>
+    where
+        R: Rng + CryptoRng,
+        for<'b> &'b K: Mul<&'b K, Output = K>,
+    {
+        let n = signatures.len();
+        if public_keys.len() != n || challenges.len() != n {
+            return Err(SchnorrSignatureError::BatchLengthMismatch);
+        }
+        Ok(find_invalid(n, |range| {
+            Self::batch_verify(
+                &signatures[range.clone()],
+                &public_keys[range.clone()],
+                &challenges[range],
+                rng,
+            )
+        }))
+    }
+
+    /// Create an adaptor pre-signature that can only be completed by someone who knows the discrete log, _t_, of the
+    /// adaptor point _T = t.G_.
+    ///
+    /// The public nonce of the pre-signature is the _adapted_ nonce, _R = r.G + T_, and the scalar is
+    /// _s' = r + e.k_, so the challenge should be calculated over _R_ exactly as for an ordinary signature. The
+    /// pre-signature does not satisfy [verify](#method.verify); use [verify_adaptor](#method.verify_adaptor) instead,
+    /// and [adapt](#method.adapt) to complete it once _t_ is known.
+    pub fn sign_adaptor(
+        secret: K,
+        nonce: K,
+        adaptor_point: &P,
+        challenge: &[u8],
+    ) -> Result<Self, SchnorrSignatureError>
+    where
+        K: Add<Output = K> + Mul<P, Output = P> + Mul<Output = K>,
+    {
+        // s' = r + e.k
+        let e = match K::from_bytes(challenge) {
+            Ok(e) => e,
+            Err(_) => return Err(SchnorrSignatureError::InvalidChallenge),
+        };
+        let public_nonce = P::from_secret_key(&nonce) + adaptor_point.clone();
+        let s = e * secret + nonce;
+        Ok(Self::new(public_nonce, s))
+    }
+
+    /// Verify an adaptor pre-signature against the signer's public key and the adaptor point, i.e. check that
+    /// _s'.G + T = R + e.P_
+    pub fn verify_adaptor<'a>(&self, public_key: &'a P, adaptor_point: &P, challenge: &K) -> bool
+    where
+        for<'b> &'b K: Mul<&'a P, Output = P>,
+        for<'b> &'b P: Add<P, Output = P>,
+    {
+        let lhs = self.calc_signature_verifier() + adaptor_point.clone();
+        let rhs = &self.public_nonce + challenge * public_key;
+        // Implementors should make this a constant time comparison
+        lhs == rhs
+    }
+
+    /// Complete an adaptor pre-signature using the adaptor secret, _t_, producing the valid signature _(R, s' + t)_
+    pub fn adapt(&self, adaptor_secret: &K) -> Self {
+        Self::new(
+            self.public_nonce.clone(),
+            self.signature.clone() + adaptor_secret.clone(),
+        )
+    }
+
+    /// Recover the adaptor secret, _t = s - s'_, from this pre-signature and the completed signature. Returns `None`
+    /// if the signatures do not share the same public nonce, in which case one cannot be an adaptation of the other.
+    pub fn extract_adaptor_secret(&self, signature: &Self) -> Option<K>
+    where for<'b> &'b K: Sub<&'b K, Output = K> {
+        if self.public_nonce != signature.public_nonce {
+            return None;
+        }
+        Some(&signature.signature - &self.signature)
+    }
+
     #[inline]
     pub fn get_signature(&self) -> &K {
         &self.signature
@@ -85,41 +328,126 @@ where
     pub fn get_public_nonce(&self) -> &P {
         &self.public_nonce
     }
+
+    /// Decode a signature from its public nonce followed by its signature scalar, _R || s_, rejecting anything that
+    /// isn't the canonical encoding of a usable signature:
+    /// * `InvalidEncoding` if the length is wrong or either part can't be decoded,
+    /// * `NonCanonicalEncoding` if either part doesn't re-encode to the same bytes (e.g. an unreduced scalar),
+    /// * `IdentityNonce` if the public nonce is the identity.
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, SchnorrSignatureError> {
+        let n = P::key_length();
+        if bytes.len() != n + K::key_length() {
+            return Err(SchnorrSignatureError::InvalidEncoding);
+        }
+        Self::from_canonical_parts(&bytes[..n], &bytes[n..])
+    }
+
+    fn from_canonical_parts(public_nonce: &[u8], signature: &[u8]) -> Result<Self, SchnorrSignatureError> {
+        let r = P::from_bytes(public_nonce).map_err(|_| SchnorrSignatureError::InvalidEncoding)?;
+        let s = K::from_bytes(signature).map_err(|_| SchnorrSignatureError::InvalidEncoding)?;
+        if r.as_bytes() != public_nonce || s.as_bytes() != signature {
+            return Err(SchnorrSignatureError::NonCanonicalEncoding);
+        }
+        if r == P::default() {
+            return Err(SchnorrSignatureError::IdentityNonce);
+        }
+        Ok(Self::new(r, s))
+    }
+}
+
+#[cfg(feature = "strict")]
+impl<'de, P, K, H> Deserialize<'de> for SchnorrSignature<P, K, H>
+where
+    P: PublicKey<K = K>,
+    K: SecretKey,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: serde::Deserializer<'de> {
+        #[derive(Deserialize)]
+        struct Encoded {
+            public_nonce: strict::EncodedBytes,
+            signature: strict::EncodedBytes,
+        }
+        let encoded = Encoded::deserialize(deserializer)?;
+        Self::from_canonical_parts(&encoded.public_nonce.0, &encoded.signature.0).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "strict")]
+mod strict {
+    use serde::{
+        de::{self, Visitor},
+        Deserialize,
+        Deserializer,
+    };
+    use std::fmt;
+    use tari_utilities::hex::from_hex;
+
+    /// The raw bytes of a key, in the same format the key types serialize to: hex for human-readable formats, and
+    /// bytes otherwise. Keeping the bytes lets the signature check that they are canonical.
+    pub(super) struct EncodedBytes(pub(super) Vec<u8>);
+
+    impl<'de> Deserialize<'de> for EncodedBytes {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            struct BytesVisitor;
+
+            impl<'de> Visitor<'de> for BytesVisitor {
+                type Value = EncodedBytes;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a key in binary format")
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<EncodedBytes, E>
+                where E: de::Error {
+                    Ok(EncodedBytes(v.to_vec()))
+                }
+            }
+
+            if deserializer.is_human_readable() {
+                let s = String::deserialize(deserializer)?;
+                from_hex(&s).map(EncodedBytes).map_err(de::Error::custom)
+            } else {
+                deserializer.deserialize_bytes(BytesVisitor)
+            }
+        }
+    }
 }
 
-impl<'a, 'b, P, K> Add<&'b SchnorrSignature<P, K>> for &'a SchnorrSignature<P, K>
+impl<'a, 'b, P, K, H> Add<&'b SchnorrSignature<P, K, H>> for &'a SchnorrSignature<P, K, H>
 where
     P: PublicKey<K = K>,
     &'a P: Add<&'b P, Output = P>,
     K: SecretKey,
     &'a K: Add<&'b K, Output = K>,
 {
-    type Output = SchnorrSignature<P, K>;
+    type Output = SchnorrSignature<P, K, H>;
 
-    fn add(self, rhs: &'b SchnorrSignature<P, K>) -> SchnorrSignature<P, K> {
+    fn add(self, rhs: &'b SchnorrSignature<P, K, H>) -> SchnorrSignature<P, K, H> {
         let r_sum = self.get_public_nonce() + rhs.get_public_nonce();
         let s_sum = self.get_signature() + rhs.get_signature();
         SchnorrSignature::new(r_sum, s_sum)
     }
 }
 
-impl<'a, P, K> Add<SchnorrSignature<P, K>> for &'a SchnorrSignature<P, K>
+impl<'a, P, K, H> Add<SchnorrSignature<P, K, H>> for &'a SchnorrSignature<P, K, H>
 where
     P: PublicKey<K = K>,
     for<'b> &'a P: Add<&'b P, Output = P>,
     K: SecretKey,
     for<'b> &'a K: Add<&'b K, Output = K>,
 {
-    type Output = SchnorrSignature<P, K>;
+    type Output = SchnorrSignature<P, K, H>;
 
-    fn add(self, rhs: SchnorrSignature<P, K>) -> SchnorrSignature<P, K> {
+    fn add(self, rhs: SchnorrSignature<P, K, H>) -> SchnorrSignature<P, K, H> {
         let r_sum = self.get_public_nonce() + rhs.get_public_nonce();
         let s_sum = self.get_signature() + rhs.get_signature();
         SchnorrSignature::new(r_sum, s_sum)
     }
 }
 
-impl<P, K> Default for SchnorrSignature<P, K>
+impl<P, K, H> Default for SchnorrSignature<P, K, H>
 where
     P: PublicKey<K = K>,
     K: SecretKey,
@@ -133,10 +461,11 @@ where
 /// for secret keys, but in this instance, the signature is publicly known and is simply a scalar, so we use the byte
 /// representation of the scalar as the canonical ordering metric. This conversion is done if and only if the public
 /// nonces are already equal, otherwise the public nonce ordering determines the SchnorrSignature order.
-impl<P, K> Ord for SchnorrSignature<P, K>
+impl<P, K, H> Ord for SchnorrSignature<P, K, H>
 where
     P: Eq + Ord,
     K: Eq + ByteArray,
+    H: Eq,
 {
     fn cmp(&self, other: &Self) -> Ordering {
         match self.public_nonce.cmp(&other.public_nonce) {
@@ -146,12 +475,35 @@ where
     }
 }
 
-impl<P, K> PartialOrd for SchnorrSignature<P, K>
+impl<P, K, H> PartialOrd for SchnorrSignature<P, K, H>
 where
     P: Eq + Ord,
     K: Eq + ByteArray,
+    H: Eq,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
+
+/// Bisect a batch of `n` signatures to find the invalid ones, given a function that batch verifies a sub-range.
+/// Returns the invalid indices in ascending order.
+pub(crate) fn find_invalid<F>(n: usize, mut batch_verify: F) -> Vec<usize>
+where F: FnMut(Range<usize>) -> bool {
+    let mut invalid = Vec::new();
+    let mut pending = vec![(0, n)];
+    // Depth-first, lower half first, so the indices come out in ascending order
+    while let Some((start, end)) = pending.pop() {
+        if batch_verify(start..end) {
+            continue;
+        }
+        if end - start == 1 {
+            invalid.push(start);
+            continue;
+        }
+        let mid = start + (end - start) / 2;
+        pending.push((mid, end));
+        pending.push((start, mid));
+    }
+    invalid
+}
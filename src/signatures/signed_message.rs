@@ -0,0 +1,159 @@
+// Copyright 2021. The Tari Project
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Signed messages
+//! A [SignedMessage] bundles a Schnorr signature with the public key that made it, and has a canonical, fixed-length
+//! byte encoding: _P || R || s_ (96 bytes for Ristretto). The challenge is _e = H(R || P || m)_, the same as
+//! [SchnorrSignature::verify_message].
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    signatures::{SchnorrSignature, SchnorrSignatureError},
+};
+use digest::Digest;
+use serde::{
+    de::{self, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Add, Mul},
+};
+use tari_utilities::{
+    hex::{from_hex, to_hex},
+    ByteArray,
+    ByteArrayError,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedMessage<P: PublicKey> {
+    public_key: P,
+    signature: SchnorrSignature<P, P::K>,
+}
+
+impl<P> SignedMessage<P>
+where P: PublicKey
+{
+    pub fn new(public_key: P, signature: SchnorrSignature<P, P::K>) -> Self {
+        SignedMessage { public_key, signature }
+    }
+
+    /// Sign `message` with the given secret key and nonce. The nonce must be random and never reused.
+    pub fn sign<D: Digest>(secret: P::K, nonce: P::K, message: &[u8]) -> Result<Self, SchnorrSignatureError>
+    where P::K: Mul<P, Output = P> + Mul<Output = P::K> {
+        let public_key = P::from_secret_key(&secret);
+        let public_nonce = P::from_secret_key(&nonce);
+        let challenge = SchnorrSignature::<P, P::K>::message_challenge::<D>(&public_nonce, &public_key, message);
+        let signature = SchnorrSignature::sign_raw(secret, nonce, &challenge)?;
+        Ok(SignedMessage { public_key, signature })
+    }
+
+    /// Check that the signature is valid for `message` under the bundled public key
+    pub fn verify<D: Digest>(&self, message: &[u8]) -> bool
+    where
+        for<'a, 'b> &'b P::K: Mul<&'a P, Output = P>,
+        for<'b> &'b P: Add<P, Output = P>,
+    {
+        self.signature.verify_message::<D>(&self.public_key, message)
+    }
+
+    #[inline]
+    pub fn public_key(&self) -> &P {
+        &self.public_key
+    }
+
+    #[inline]
+    pub fn signature(&self) -> &SchnorrSignature<P, P::K> {
+        &self.signature
+    }
+
+    /// The length of the byte encoding, _|P| + |R| + |s|_
+    pub fn encoded_length() -> usize {
+        2 * P::key_length() + P::K::key_length()
+    }
+
+    /// Returns the canonical byte encoding, _P || R || s_
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::encoded_length());
+        buf.extend_from_slice(self.public_key.as_bytes());
+        buf.extend_from_slice(self.signature.get_public_nonce().as_bytes());
+        buf.extend_from_slice(self.signature.get_signature().as_bytes());
+        buf
+    }
+
+    /// Reads the canonical byte encoding, _P || R || s_. The slice must be exactly
+    /// [encoded_length](#method.encoded_length) bytes long. The signature half is decoded with
+    /// [SchnorrSignature::from_canonical_bytes], so an unreduced `s` or an identity nonce is rejected and every
+    /// signed message has exactly one encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ByteArrayError> {
+        if bytes.len() != Self::encoded_length() {
+            return Err(ByteArrayError::IncorrectLength);
+        }
+        let n = P::key_length();
+        let public_key = P::from_bytes(&bytes[..n])?;
+        let signature = SchnorrSignature::from_canonical_bytes(&bytes[n..])
+            .map_err(|e| ByteArrayError::ConversionError(e.to_string()))?;
+        Ok(SignedMessage::new(public_key, signature))
+    }
+}
+
+impl<P> Serialize for SignedMessage<P>
+where P: PublicKey
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        if serializer.is_human_readable() {
+            to_hex(&self.to_vec()).serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.to_vec())
+        }
+    }
+}
+
+impl<'de, P> Deserialize<'de> for SignedMessage<P>
+where P: PublicKey
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        struct SignedMessageVisitor<P>(PhantomData<P>);
+
+        impl<'de, P: PublicKey> Visitor<'de> for SignedMessageVisitor<P> {
+            type Value = SignedMessage<P>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a signed message in binary format")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<SignedMessage<P>, E>
+            where E: de::Error {
+                SignedMessage::from_bytes(v).map_err(E::custom)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let bytes = from_hex(&s).map_err(de::Error::custom)?;
+            SignedMessage::from_bytes(&bytes).map_err(de::Error::custom)
+        } else {
+            deserializer.deserialize_bytes(SignedMessageVisitor(PhantomData))
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2021 The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Signing with keys that may not be in memory.
+//!
+//! A [Signer] holds a secret key somewhere (in memory, on a hardware wallet or in an HSM) and exposes only the
+//! operations that need it: producing Schnorr signatures and Diffie-Hellman shared secrets. The signing methods on
+//! [SchnorrSignature] that end in `_with_signer` build the same challenges as their in-memory counterparts, so the
+//! signatures verify in exactly the same way.
+//!
+//! The signer chooses its own nonce, _r_, and is handed a function that turns _(R, P)_ into the challenge bytes. A
+//! signer must never reuse a nonce, and should draw it from the RNG it is given (or its own secure source).
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    signatures::{ChallengeStrategy, SchnorrSignature, SchnorrSignatureError, SignatureChallengeBuilder},
+};
+use digest::Digest;
+use rand::{CryptoRng, Rng};
+
+/// Something that can sign and do Diffie-Hellman with a secret key it keeps to itself
+pub trait Signer {
+    type PK: PublicKey;
+    type Error: From<SchnorrSignatureError>;
+
+    /// The public key of the stored secret key, _P = k.G_
+    fn public_key(&self) -> Result<Self::PK, Self::Error>;
+
+    /// Choose a fresh nonce _r_, compute the challenge bytes, _e = challenge(R, P)_, and return the signature
+    /// _(R, r + e.k)_
+    fn sign_with<R: Rng + CryptoRng>(
+        &self,
+        challenge: &dyn Fn(&Self::PK, &Self::PK) -> Vec<u8>,
+        rng: &mut R,
+    ) -> Result<SchnorrSignature<Self::PK, <Self::PK as PublicKey>::K>, Self::Error>;
+
+    /// The Diffie-Hellman shared secret between the stored secret key and `public_key`, _k.Q_
+    fn shared_secret(&self, public_key: &Self::PK) -> Result<Self::PK, Self::Error>;
+}
+
+impl<P, K, H> SchnorrSignature<P, K, H>
+where
+    P: PublicKey<K = K>,
+    K: SecretKey,
+{
+    /// Sign `message` with `signer`, using the challenge strategy's challenge. The result verifies with
+    /// [verify_message](#method.verify_message).
+    pub fn sign_message_with_signer<D, S, R>(signer: &S, message: &[u8], rng: &mut R) -> Result<Self, S::Error>
+    where
+        D: Digest,
+        S: Signer<PK = P>,
+        R: Rng + CryptoRng,
+        H: ChallengeStrategy,
+    {
+        let sig = signer.sign_with(&|r, p| Self::message_challenge::<D>(r, p, message), rng)?;
+        Ok(Self::new(sig.get_public_nonce().clone(), sig.get_signature().clone()))
+    }
+
+    /// Sign `message` in `domain` with `signer`. The result verifies with
+    /// [verify_with_domain](#method.verify_with_domain).
+    pub fn sign_with_domain_with_signer<D, S, R>(
+        signer: &S,
+        domain: &[u8],
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Self, S::Error>
+    where
+        D: Digest,
+        S: Signer<PK = P>,
+        R: Rng + CryptoRng,
+    {
+        let challenge = |r: &P, p: &P| {
+            SignatureChallengeBuilder::<D>::new(domain, r, p)
+                .chain_message(message)
+                .finalize()
+        };
+        let sig = signer.sign_with(&challenge, rng)?;
+        Ok(Self::new(sig.get_public_nonce().clone(), sig.get_signature().clone()))
+    }
+}
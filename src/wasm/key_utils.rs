@@ -154,7 +154,7 @@ pub(super) fn sign_with_key(k: &RistrettoSecretKey, e: &[u8], r: Option<&Ristret
         None => RistrettoPublicKey::random_keypair(&mut OsRng),
     };
 
-    let sig = match RistrettoSchnorr::sign(k.clone(), r, e) {
+    let sig = match RistrettoSchnorr::sign_raw(k.clone(), r, e) {
         Ok(s) => s,
         Err(e) => {
             result.error = format!("Could not create signature. {}", e.to_string());
@@ -444,7 +444,7 @@ mod test {
     use super::*;
     use crate::{
         commitment::HomomorphicCommitmentFactory,
-        signatures::{CommitmentSignature, SchnorrSignature},
+        signatures::CommitmentSignature,
         tari_utilities::{hex, ByteArray},
     };
     use blake2::digest::Output;
@@ -468,7 +468,7 @@ mod test {
     fn create_signature(msg: &str) -> (RistrettoSchnorr, RistrettoPublicKey, RistrettoSecretKey) {
         let (sk, pk) = random_keypair();
         let (nonce, _) = random_keypair();
-        let sig = SchnorrSignature::sign(sk.clone(), nonce, &hash(msg)).unwrap();
+        let sig = RistrettoSchnorr::sign_raw(sk.clone(), nonce, &hash(msg)).unwrap();
 
         (sig, pk, sk)
     }
@@ -561,7 +561,7 @@ mod test {
             assert!(result.error.is_empty());
             let p_nonce = RistrettoPublicKey::from_hex(&result.public_nonce.unwrap()).unwrap();
             let s = RistrettoSecretKey::from_hex(&result.signature.unwrap()).unwrap();
-            assert!(SchnorrSignature::new(p_nonce, s).verify_challenge(&pk, &hash(SAMPLE_CHALLENGE)));
+            assert!(RistrettoSchnorr::new(p_nonce, s).verify_challenge(&pk, &hash(SAMPLE_CHALLENGE)));
         }
 
         #[wasm_bindgen_test]
@@ -619,7 +619,7 @@ mod test {
             let p_nonce = RistrettoPublicKey::from_hex(&result.public_nonce.unwrap()).unwrap();
             assert_eq!(p_nonce, expected_pr);
             let s = RistrettoSecretKey::from_hex(&result.signature.unwrap()).unwrap();
-            assert!(SchnorrSignature::new(p_nonce, s).verify_challenge(&pk, &hash(SAMPLE_CHALLENGE)));
+            assert!(RistrettoSchnorr::new(p_nonce, s).verify_challenge(&pk, &hash(SAMPLE_CHALLENGE)));
         }
     }
 